@@ -3,11 +3,25 @@ use quote::quote;
 
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
-	let CallDef { pallet_struct, methods } = def;
+	let CallDef { pallet_type, generics, caller_type, methods, errors } = def;
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	let type_params = generics.type_params().map(|param| &param.ident).collect::<Vec<_>>();
 
 	// This is a vector of all the callable function names.
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
 
+	// This is a vector of the index of each callable function in the `Call` enum.
+	let fn_index = methods.iter().map(|method| method.index).collect::<Vec<_>>();
+
+	// This is a vector of the weight of each callable function, which is zero when not given.
+	let fn_weight = methods
+		.iter()
+		.map(|method| match &method.weight {
+			Some(weight) => quote!(#weight),
+			None => quote!(0),
+		})
+		.collect::<Vec<_>>();
+
 	// This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
 	// not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
 	// first two parameters to these calls.
@@ -23,36 +37,119 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
+	// The bounds of the (de)serialization of `Call`, one for each argument type. Serde only infers
+	// them for the associated types of the generics, like `T::AccountId`, and not for the aliases
+	// of a pallet, like `BalanceOf<T>`.
+	let serialize_bound = args_type
+		.iter()
+		.flatten()
+		.map(|type_| quote!(#type_: serde::Serialize).to_string())
+		.collect::<Vec<_>>()
+		.join(", ");
+	let deserialize_bound = args_type
+		.iter()
+		.flatten()
+		.map(|type_| quote!(#type_: serde::Deserialize<'de>).to_string())
+		.collect::<Vec<_>>()
+		.join(", ");
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
 	// and the `Dispatch` trait logic to route a `caller` to access those functions.
 	let dispatch_impl = quote! {
 		// The callable functions exposed by this pallet.
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
+		//
+		// Calls can be cloned, compared and (de)serialized, so they can be stored in blocks and
+		// submitted over RPC. They are also described in the runtime metadata, and SCALE encoded
+		// with the index of the variant followed by the arguments, which is what the `dynamic`
+		// clients produce from the metadata.
 		#[allow(non_camel_case_types)]
-		pub enum Call<T: Config> {
+		#[derive(
+			macros::CloneNoBound,
+			macros::DebugNoBound,
+			macros::PartialEqNoBound,
+			macros::EqNoBound,
+			serde::Serialize,
+			serde::Deserialize,
+			macros::TypeInfo,
+			codec::Encode,
+			codec::Decode,
+		)]
+		#[serde(bound(serialize = #serialize_bound, deserialize = #deserialize_bound))]
+		pub enum Call #impl_generics #where_clause {
 			#(
+				#[codec(index = #fn_index)]
 				#fn_name { #( #args_name: #args_type),* },
 			)*
+			// A variant which can never be built, holding the generic parameters not used by the
+			// arguments, like the instance of the pallet.
+			#[doc(hidden)]
+			#[codec(skip)]
+			#[serde(skip)]
+			__Ignore(::core::marker::PhantomData<( #( #type_params, )* )>, crate::support::Never),
+		}
+
+		impl #impl_generics Call #ty_generics #where_clause {
+			// The errors which the callable functions can return, for the runtime metadata.
+			pub const ERRORS: &'static [&'static str] = &[ #( #errors ),* ];
+		}
+
+		// The weight of each call, as given by the `#[weight(..)]` attribute of its function, which
+		// can use the arguments of the call.
+		impl #impl_generics crate::support::GetWeight for Call #ty_generics #where_clause {
+			#[allow(unused_variables)]
+			fn weight(&self) -> crate::support::Weight {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => #fn_weight,
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		// The name of each call, which is the name of its function, so the runtime can filter the
+		// calls by name.
+		impl #impl_generics crate::support::GetCallName for Call #ty_generics #where_clause {
+			fn call_name(&self) -> &'static str {
+				match self {
+					#(
+						Call::#fn_name { .. } => stringify!(#fn_name),
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
 		}
 
 		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
 		// appropriate function call with all arguments, including the `caller`.
-		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
-			type Call = Call<T>;
+		impl #impl_generics crate::support::Dispatch for #pallet_type #where_clause {
+			type Caller = #caller_type;
+			type Call = Call #ty_generics;
 
 			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
 				match call {
 					#(
 						Call::#fn_name { #( #args_name ),* } => {
-							self.#fn_name(
+							let result = self.#fn_name(
 								// Note that we assume the first argument of every call is the `caller`.
 								caller,
 								#( #args_name ),*
-							)?;
+							);
+							// The tests of the crate check that every error is declared.
+							if let (true, Err(error)) = (cfg!(test), &result) {
+								assert!(
+									<Call #ty_generics>::ERRORS.contains(error),
+									"`{}` returned the undeclared error {:?}",
+									stringify!(#fn_name),
+									error,
+								);
+							}
+							result?;
 						},
 					)*
+					Call::__Ignore(_, never) => match never {},
 				}
 				Ok(())
 			}
@@ -6,20 +6,32 @@ pub fn call(
 	_attr: proc_macro::TokenStream,
 	item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-	// The final expanded code will be placed here.
-	// Since our macro only adds new code, our final product will contain all of our old code too,
-	// hence we clone `item`.
-	let mut finished = item.clone();
-	let item_mod = syn::parse_macro_input!(item as syn::Item);
+	let mut item = syn::parse_macro_input!(item as syn::Item);
 
-	// First we parse the call functions implemented for the pallet...
-	let generated: proc_macro::TokenStream = match parse::CallDef::try_from(item_mod.clone()) {
-		// ..then we generate our new code.
-		Ok(def) => expand::expand_call(def).into(),
-		Err(e) => e.to_compile_error().into(),
+	// First we parse the call functions implemented for the pallet, which also removes the
+	// attributes only meant for our macro, like `#[call_index(n)]`...
+	let def = match parse::CallDef::try_from(&mut item) {
+		Ok(def) => def,
+		Err(e) => {
+			let error = e.to_compile_error();
+			return quote::quote!(#item #error).into()
+		},
 	};
 
-	// Add our generated code to the end, and return the final result.
-	finished.extend(generated);
-	return finished;
+	// ..then we generate our new code. Since our macro otherwise only adds new code, our final
+	// product contains all of our old code too.
+	let generated = expand::expand_call(def);
+	match item {
+		// The generated code is placed in the module, so it can use the same imports as the calls,
+		// and the `Call` enum is exported from it.
+		syn::Item::Mod(mut item_mod) => {
+			let ident = &item_mod.ident;
+			let export = quote::quote!(pub use #ident::Call;);
+			if let Some((_, items)) = &mut item_mod.content {
+				items.push(syn::Item::Verbatim(generated));
+			}
+			quote::quote!(#item_mod #export).into()
+		},
+		item => quote::quote!(#item #generated).into(),
+	}
 }
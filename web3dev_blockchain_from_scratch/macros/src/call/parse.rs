@@ -1,21 +1,23 @@
 use quote::ToTokens;
 use syn::spanned::Spanned;
 
-// Custom keywords we match to when parsing the calls in a pallet.
-mod keyword {
-	syn::custom_keyword!(T);
-	syn::custom_keyword!(AccountId);
-}
-
 /// This object will collect all the information we need to keep while parsing the callable
 /// functions.
 #[derive(Debug)]
 pub struct CallDef {
-	/// This is the name of the pallet struct where the callable functions are implemented. We
-	/// mostly assume it is `Pallet`.
-	pub pallet_struct: syn::Ident,
+	/// This is the type of the pallet where the callable functions are implemented, like
+	/// `Pallet<T>`.
+	pub pallet_type: syn::Type,
+	/// The generics of the `impl` blocks, along with their where clause. They are also the
+	/// generics of the generated `Call` enum.
+	pub generics: syn::Generics,
+	/// The type of the `caller` argument of every callable function, like `T::AccountId`.
+	pub caller_type: syn::Type,
 	/// This is a list of the callable functions exposed by this pallet. See `CallVariantDef`.
 	pub methods: Vec<CallVariantDef>,
+	/// The errors returned by the callable functions, declared with `#[errors(..)]` on their
+	/// `impl` blocks.
+	pub errors: Vec<syn::Expr>,
 }
 
 /// This is the metadata we keep about each callable function in our pallet.
@@ -25,100 +27,197 @@ pub struct CallVariantDef {
 	pub name: syn::Ident,
 	/// Information on args of the function: `(name, type)`.
 	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+	/// The index of the call in the `Call` enum, given with `#[call_index(n)]` or else its
+	/// position among the callable functions.
+	pub index: u8,
+	/// The weight of the call, given with `#[weight(..)]`.
+	pub weight: Option<syn::Expr>,
 }
 
 impl CallDef {
-	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
-		// First we check that we are parsing an `impl`.
-		let item_impl = if let syn::Item::Impl(item) = item {
-			item
-		} else {
-			return Err(syn::Error::new(item.span(), "Invalid pallet::call, expected item impl"))
+	/// Parse the callable functions of `item`, which is either a single `impl` block or a module
+	/// whose `impl` blocks marked with `#[call]` are all callable.
+	///
+	/// The attributes understood by the macro, like `#[call_index(n)]`, are removed from `item`.
+	pub fn try_from(item: &mut syn::Item) -> syn::Result<Self> {
+		let item_span = item.span();
+		let mut impls = match item {
+			syn::Item::Impl(item_impl) => vec![item_impl],
+			syn::Item::Mod(item_mod) => {
+				let Some((_, items)) = &mut item_mod.content else {
+					let msg = "Invalid call, expected a module with a body";
+					return Err(syn::Error::new(item_span, msg))
+				};
+				items
+					.iter_mut()
+					.filter_map(|item| match item {
+						syn::Item::Impl(item_impl) => {
+							take_attr(&mut item_impl.attrs, "call").then_some(item_impl)
+						},
+						_ => None,
+					})
+					.collect()
+			},
+			_ => {
+				let msg = "Invalid call, expected item impl or mod";
+				return Err(syn::Error::new(item_span, msg))
+			},
 		};
 
-		// Extract the name of the struct. We mostly assume it is `Pallet`, but we can handle it
-		// when it isn't.
-		let pallet_struct = match &*item_impl.self_ty {
-			syn::Type::Path(tp) => tp.path.segments.first().unwrap().ident.clone(),
-			_ => panic!("not supported tokens"),
+		// All the `impl` blocks must be for the same pallet type, with the same generics.
+		let Some(first) = impls.first() else {
+			let msg = "Invalid call, expected at least one `impl` block marked with `#[call]`";
+			return Err(syn::Error::new(item_span, msg))
 		};
+		let pallet_type = (*first.self_ty).clone();
+		let generics = first.generics.clone();
+		for item_impl in &impls {
+			if let Some((_, trait_, _)) = &item_impl.trait_ {
+				let msg = "Invalid call, expected an inherent impl";
+				return Err(syn::Error::new(trait_.span(), msg))
+			}
+			if !same_tokens(&*item_impl.self_ty, &pallet_type)
+				|| !same_tokens(&item_impl.generics, &generics)
+				|| !same_tokens(&item_impl.generics.where_clause, &generics.where_clause)
+			{
+				let msg =
+					"Invalid call, all the call blocks must be for the same type and generics";
+				return Err(syn::Error::new(item_impl.self_ty.span(), msg))
+			}
+		}
+
+		// The errors of all the `impl` blocks, which are not real attributes so they are removed.
+		let mut errors = vec![];
+		for item_impl in impls.iter_mut() {
+			let attrs = &mut item_impl.attrs;
+			while let Some(position) = attrs.iter().position(|attr| attr.path().is_ident("errors"))
+			{
+				let declared = attrs.remove(position).parse_args_with(
+					syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+				)?;
+				errors.extend(declared);
+			}
+		}
 
 		// Here is where we will store all the callable functions.
-		let mut methods = vec![];
-		for item in item_impl.items {
-			if let syn::ImplItem::Fn(method) = item {
-				// Here is where we will store all the args for each callable functions.
-				let mut args = vec![];
-
-				// First argument should be some variant of `self`.
-				match method.sig.inputs.first() {
-					Some(syn::FnArg::Receiver(_)) => {},
-					_ => {
-						let msg = "Invalid call, first argument must be a variant of self";
-						return Err(syn::Error::new(method.sig.span(), msg))
-					},
-				}
-
-				// The second argument should be the `caller: T::AccountId` argument.
-				match method.sig.inputs.iter().skip(1).next() {
-					Some(syn::FnArg::Typed(arg)) => {
-						// Here we specifically check that this argument is as we expect for
-						// `caller: T::AccountId`.
-						check_caller_arg(arg)?;
-					},
-					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
-						return Err(syn::Error::new(method.sig.span(), msg))
-					},
-				}
-
-				let fn_name = method.sig.ident.clone();
-
-				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
-				for arg in method.sig.inputs.iter().skip(2) {
-					// All arguments should be typed.
-					let arg = if let syn::FnArg::Typed(arg) = arg {
-						arg
-					} else {
-						unreachable!("All args should be typed.");
-					};
-
-					// Extract the name of the argument.
-					let arg_ident = if let syn::Pat::Ident(pat) = &*arg.pat {
-						pat.ident.clone()
-					} else {
-						let msg = "Invalid pallet::call, argument must be ident";
-						return Err(syn::Error::new(arg.pat.span(), msg))
-					};
-
-					// Store the argument name and the argument type for generating code.
-					args.push((arg_ident, arg.ty.clone()));
-				}
-
-				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+		let mut caller_type: Option<syn::Type> = None;
+		let mut methods: Vec<CallVariantDef> = vec![];
+		for item in impls.into_iter().flat_map(|item_impl| item_impl.items.iter_mut()) {
+			let syn::ImplItem::Fn(method) = item else { continue };
+
+			// First argument should be some variant of `self`.
+			match method.sig.inputs.first() {
+				Some(syn::FnArg::Receiver(_)) => {},
+				_ => {
+					let msg = "Invalid call, first argument must be a variant of self";
+					return Err(syn::Error::new(method.sig.span(), msg))
+				},
+			}
+
+			// The second argument should be the `caller: T::AccountId` argument, with the same type
+			// for every callable function.
+			match method.sig.inputs.iter().nth(1) {
+				Some(syn::FnArg::Typed(arg)) => {
+					check_caller_arg(arg, &generics)?;
+					match &caller_type {
+						Some(ty) if !same_tokens(ty, &arg.ty) => {
+							let msg =
+								"Invalid call, every `caller` argument must have the same type";
+							return Err(syn::Error::new(arg.ty.span(), msg))
+						},
+						Some(_) => {},
+						None => caller_type = Some((*arg.ty).clone()),
+					}
+				},
+				_ => {
+					let msg = "Invalid call, second argument should be `caller: T::AccountId`";
+					return Err(syn::Error::new(method.sig.span(), msg))
+				},
+			}
+
+			let fn_name = method.sig.ident.clone();
+
+			// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
+			let mut args = vec![];
+			for arg in method.sig.inputs.iter().skip(2) {
+				// All arguments should be typed.
+				let syn::FnArg::Typed(arg) = arg else {
+					let msg = "Invalid call, only the first argument can be a variant of self";
+					return Err(syn::Error::new(arg.span(), msg))
+				};
+
+				// Extract the name of the argument.
+				let arg_ident = if let syn::Pat::Ident(pat) = &*arg.pat {
+					pat.ident.clone()
+				} else {
+					let msg = "Invalid pallet::call, argument must be ident";
+					return Err(syn::Error::new(arg.pat.span(), msg))
+				};
+
+				// Store the argument name and the argument type for generating code.
+				args.push((arg_ident, arg.ty.clone()));
+			}
+
+			// The index and weight of the call, which are not real attributes so they are removed
+			// from the function.
+			let index = match parse_attr::<syn::LitInt>(&mut method.attrs, "call_index")? {
+				Some(lit) => lit.base10_parse::<u8>()?,
+				None => u8::try_from(methods.len()).map_err(|_| {
+					syn::Error::new(fn_name.span(), "Invalid call, too many callable functions")
+				})?,
+			};
+			if let Some(other) = methods.iter().find(|method| method.index == index) {
+				let msg =
+					format!("Invalid call, call index {index} is already used by `{}`", other.name);
+				return Err(syn::Error::new(fn_name.span(), msg))
 			}
+			let weight = parse_attr::<syn::Expr>(&mut method.attrs, "weight")?;
+
+			// Store all the function name and the arg data for the function.
+			methods.push(CallVariantDef { name: fn_name, args, index, weight });
 		}
 
+		// Without any callable function, the caller type does not matter.
+		let caller_type = caller_type.unwrap_or_else(|| syn::parse_quote!(()));
+
 		// Return all callable functions for this pallet.
-		Ok(Self { pallet_struct, methods })
+		Ok(Self { pallet_type, generics, caller_type, methods, errors })
 	}
 }
 
-/// Check caller arg is exactly: `caller: T::AccountId`.
-///
-/// This is kept strict to keep the code simple.
-pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
-	pub struct CheckDispatchableFirstArg;
-	impl syn::parse::Parse for CheckDispatchableFirstArg {
-		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-			input.parse::<keyword::T>()?;
-			input.parse::<syn::Token![::]>()?;
-			input.parse::<keyword::AccountId>()?;
-			Ok(Self)
-		}
+/// Whether `a` and `b` are written with the same tokens.
+fn same_tokens(a: &impl ToTokens, b: &impl ToTokens) -> bool {
+	a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
+/// Remove the attribute `#[name]` from `attrs`, returning whether it was there.
+fn take_attr(attrs: &mut Vec<syn::Attribute>, name: &str) -> bool {
+	let len = attrs.len();
+	attrs.retain(|attr| !attr.path().is_ident(name));
+	attrs.len() != len
+}
+
+/// Remove the attribute `#[name(..)]` from `attrs`, returning its parsed argument.
+fn parse_attr<T: syn::parse::Parse>(
+	attrs: &mut Vec<syn::Attribute>,
+	name: &str,
+) -> syn::Result<Option<T>> {
+	let Some(position) = attrs.iter().position(|attr| attr.path().is_ident(name)) else {
+		return Ok(None)
+	};
+	let attr = attrs.remove(position);
+	if let Some(duplicate) = attrs.iter().find(|attr| attr.path().is_ident(name)) {
+		let msg = format!("Invalid call, duplicate `#[{name}]` attribute");
+		return Err(syn::Error::new(duplicate.span(), msg))
 	}
+	attr.parse_args().map(Some)
+}
 
+/// Check caller arg is `caller: T::AccountId`, where `T` is any generic type parameter of the
+/// `impl` block.
+///
+/// This is kept strict to keep the code simple.
+pub fn check_caller_arg(arg: &syn::PatType, generics: &syn::Generics) -> syn::Result<()> {
 	// This checks the arg name is `caller` or `_caller`.
 	if let syn::Pat::Ident(ident) = &*arg.pat {
 		// We also support the name as `_caller` for when the variable is unused.
@@ -128,14 +227,26 @@ pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
 		}
 	}
 
-	// This checks the type is `T::AccountId` with `CheckDispatchableFirstArg`
+	// This checks the type is `T::AccountId`.
 	let ty = &arg.ty;
-	syn::parse2::<CheckDispatchableFirstArg>(ty.to_token_stream()).map_err(|e| {
+	let is_account_id = match &**ty {
+		syn::Type::Path(syn::TypePath { qself: None, path }) => {
+			let segments = path.segments.iter().collect::<Vec<_>>();
+			matches!(
+				segments.as_slice(),
+				[generic, account_id]
+					if generics.type_params().any(|param| param.ident == generic.ident) &&
+						generic.arguments.is_none() &&
+						account_id.ident == "AccountId" &&
+						account_id.arguments.is_none()
+			)
+		},
+		_ => false,
+	};
+	if !is_account_id {
 		let msg = "Invalid type for second parameter: expected `caller: T::AccountId`";
-		let mut err = syn::Error::new(ty.span(), msg);
-		err.combine(e);
-		err
-	})?;
+		return Err(syn::Error::new(ty.span(), msg))
+	}
 
 	Ok(())
 }
@@ -1,6 +1,35 @@
 mod call;
+mod no_bound;
 mod runtime;
+mod storage;
+mod type_info;
 
+/// Expose the functions of an `impl` block of a pallet as callable functions.
+///
+/// Every function takes `&mut self`, then `caller: T::AccountId`, where `T` can be any generic
+/// parameter of the `impl` block, and then the arguments of the call. The `impl` block can have any
+/// generics and where clause, which are also the ones of the generated `Call` enum.
+///
+/// The callable functions can be split in multiple `impl` blocks, by applying the macro to a module
+/// in which they are marked with `#[call]`. All their functions are merged in a single `Call` enum,
+/// which is generated in the module and exported from it.
+///
+/// Every `impl` block can declare the errors its functions return with `#[errors(..)]`, a list of
+/// `&'static str` expressions like literals or constants. They include the errors of the functions
+/// called by the callable functions, like `system::NOT_ROOT`. When the crate is tested, dispatching
+/// a call which fails with an undeclared error panics, so the declaration is kept up to date.
+///
+/// Every function can have the attributes:
+/// - `#[call_index(n)]` - the index of the call in the SCALE encoding of `Call`, which otherwise is
+///   the position of the function. Giving it keeps the encoding stable when calls are reordered.
+/// - `#[weight(..)]` - an expression computing the weight of the call, which can use its arguments
+///   by reference. Calls without it have a weight of zero.
+///
+/// This generates:
+/// - `enum Call` - with a variant for every callable function, holding its arguments.
+/// - `Call::ERRORS` - the errors declared with `#[errors(..)]`, for the metadata.
+/// - implements the traits `support::GetWeight` and `support::GetCallName` for `Call`.
+/// - implements the trait `support::Dispatch` for the pallet, dispatching a `Call` to its function.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -9,22 +38,108 @@ pub fn call(
 	call::call(attr, item)
 }
 
+/// Declare the storage items of a pallet struct.
+///
+/// The fields of type `StorageValue`, `StorageMap` or `StorageDoubleMap` (see `support::storage`)
+/// are the storage items of the pallet. The value they return when nothing is stored can be given
+/// with a `#[default(..)]` attribute. Items without a default value can only be read with
+/// `try_get`.
+///
+/// This generates:
+/// - `fn new()` - which creates the pallet with empty storage items, whose keys are prefixed by
+///   the name of the module of the pallet and the names of the items. The other fields are
+///   initialized with `Default::default()`.
+/// - `fn with_pallet_name()` - like `fn new()`, but the keys are prefixed by the given name instead
+///   of the name of the module. The runtime uses it, so the instances of a pallet included more
+///   than once do not share their keys.
+/// - implements the trait `support::storage::Storage`, giving the runtime access to the storage
+///   items for its state root and its transactions.
+/// - implements the trait `metadata::StorageInfo`, describing the storage items in the metadata.
+#[proc_macro_attribute]
+pub fn storage(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	storage::storage(attr, item)
+}
+
 /// Expand the `Runtime` definition.
 ///
+/// Every field is a pallet, named by the field, and the first one must be the `system` pallet. A
+/// pallet can be included more than once, with different instances, like
+/// `balances::Pallet<Runtime, support::Instance1>`. The index of every pallet, used in the metadata
+/// and in the encoding of `RuntimeCall`, is its position in the struct unless it is given with
+/// `#[pallet_index = n]`. Giving it keeps the encoding stable when the pallets are reordered. The
+/// system pallet always has the index `0`.
+///
+/// A pallet whose calls need another pallet, like the balances pallet to hold deposits, is given it
+/// with `#[lend(balances)]`: the other pallet is lent to it while its calls are dispatched, so the
+/// pallet must implement `support::Borrower` for the type of the lent pallet, with any role, like
+/// `AsCurrency` for the `T::Currency` of its config. Several pallets, including `system`, can be
+/// lent with `#[lend(system, balances)]`.
+///
+/// A pallet whose calls make other calls of the runtime, like the proxy pallet, is marked with
+/// `#[forward_calls]`: it must implement `support::ForwardCalls`, and the call it forwards is
+/// dispatched right after its own, if allowed by the filter it came with. A call can be forwarded
+/// by at most `support::MAX_CALL_DEPTH` calls, deeper ones fail with `support::CALL_TOO_DEEP`.
+///
 /// This generates function implementations on `Runtime`:
 /// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
-///   included in the runtime.
+///   included in the runtime. The storage keys of every pallet are prefixed by its name. The
+///   `support::OnRuntimeUpgrade::on_genesis` hooks of all the pallets run on the new state.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
 ///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   valid block number, that the runtime is recent enough after a `set_code` of the system pallet,
+///   and that the extrinsics weigh at most `support::MAX_BLOCK_WEIGHT` together, as given by
+///   `support::ValidateTransaction::weight`. Every extrinsic goes through the
+///   `support::ValidateTransaction` checks implemented by the runtime before being dispatched, and
+///   through their post-dispatch step after. The `on_initialize` and `on_finalize` hooks of all the
+///   pallets, which must implement `support::Hooks`, run before and after the extrinsics, and are
+///   lent the pallets they borrow like their calls. A failing hook makes the block invalid. The
+///   events emitted by the pallets are recorded in the system pallet. It returns a
+///   `types::BlockExecutionReport` with the outcome of every extrinsic and the resulting state
+///   root, so the runtime is expected to define this alias of `support::BlockExecutionReport`.
+///
+/// - `fn execute_block_parallel()` - which executes a block like `execute_block`, with the same
+///   outcome, after dispatching its calls on several threads. A call is dispatched speculatively on
+///   a snapshot of the state shared by the threads, and its recorded changes are reused in order
+///   unless the storage values it read were changed by an earlier extrinsic. The calls and the
+///   events must be `Send`. Only the dispatch of the calls runs in parallel: the
+///   `support::ValidateTransaction` checks, checking the values read and applying the changes stay
+///   sequential. So it only pays off on several cores, for calls whose dispatch is heavy compared
+///   to these checks. For a block of plain transfers, the sequential part alone costs about as much
+///   as `execute_block`, as measured by `cargo run --release --bin benchmark -- --block 10000`.
+///
+/// - `fn apply_runtime_upgrade()` - which runs the `support::OnRuntimeUpgrade` hooks of all the
+///   pallets, to migrate their storage. This happens in `execute_block` when the state was last
+///   upgraded by another `spec_version` than the one of the `VERSION` constant of the runtime.
+///
+/// - implements the trait `support::storage::Storage` with the storage items of all the pallets,
+///   which must implement it as well (see `fn storage`). Every call is dispatched in a storage
+///   transaction, which is rolled back if it fails.
+/// - `fn metadata()` - which describes the pallets of the runtime for client tooling, see the
+///   `metadata` module. Every pallet is expected to implement `metadata::StorageInfo`, and its
+///   `Call` and `Event` types to implement `metadata::TypeInfo`.
+///
+/// This also generates `enum RuntimeEvent`, an "outer"-enum of the events of all pallets, including
+/// the events of the system pallet. Every pallet is expected to expose an `Event` type with the
+/// same generics as the pallet, and a `take_events()` function returning the events it emitted
+/// during the last dispatch.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
-/// - Note: For simplicity, we assume that the system pallet is not callable.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
-/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+///   all pallets, including the system pallet.
+/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. The raw
+///   storage changes made by the root calls of the system pallet are applied to the pallets they
+///   belong to.
+/// - `fn dispatch_filtered()` - which dispatches a call only if it is allowed by the base call
+///   filter of the runtime, its implementation of `support::CallFilter<RuntimeCall>`, and by the
+///   given filters of its origin, like the filters of the calls which forwarded it. A filtered call
+///   fails with `support::CALL_FILTERED`, and is recorded with
+///   `system::Pallet::note_filtered_call`.
+/// - implements the traits `support::GetWeight` and `support::GetCallName` for `RuntimeCall`,
+///   `From` the `Call` of every pallet, and `metadata::TypeInfo` as `metadata::Type::RuntimeCall`.
+/// - `fn pallet_name()` on `RuntimeCall` - the name of the pallet of the call in the runtime.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
@@ -32,3 +147,40 @@ pub fn runtime(
 ) -> proc_macro::TokenStream {
 	runtime::runtime(attr, item)
 }
+
+/// Derive `Clone`, but do not bound any generic.
+///
+/// This is useful for types like `Call<T>` or `Event<T>`, where `T` is a `Config` and only its
+/// associated types are actually stored.
+#[proc_macro_derive(CloneNoBound)]
+pub fn derive_clone_no_bound(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	no_bound::clone::derive_clone_no_bound(input)
+}
+
+/// Derive `Debug`, but do not bound any generic. See `CloneNoBound`.
+#[proc_macro_derive(DebugNoBound)]
+pub fn derive_debug_no_bound(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	no_bound::debug::derive_debug_no_bound(input)
+}
+
+/// Derive `PartialEq`, but do not bound any generic. See `CloneNoBound`.
+#[proc_macro_derive(PartialEqNoBound)]
+pub fn derive_partial_eq_no_bound(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	no_bound::partial_eq::derive_partial_eq_no_bound(input)
+}
+
+/// Derive `Eq`, but do not bound any generic. See `CloneNoBound`.
+#[proc_macro_derive(EqNoBound)]
+pub fn derive_eq_no_bound(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	no_bound::partial_eq::derive_eq_no_bound(input)
+}
+
+/// Derive `metadata::TypeInfo`, so the type can be described in the runtime metadata.
+///
+/// Every field of the type must implement `TypeInfo` as well. Like in the SCALE encoding, the
+/// index of a variant can be given with `#[codec(index = n)]`, and a variant marked with
+/// `#[codec(skip)]` is left out.
+#[proc_macro_derive(TypeInfo, attributes(codec))]
+pub fn derive_type_info(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	type_info::derive_type_info(input)
+}
@@ -0,0 +1,53 @@
+use quote::quote;
+
+use super::{fields_pattern, impl_no_bound, union_error};
+
+/// Derive `Clone` without adding any bound on the generic parameters.
+pub fn derive_clone_no_bound(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	impl_no_bound(input, quote!(::core::clone::Clone), |input| {
+		let body = match &input.data {
+			syn::Data::Struct(data) => {
+				let (pattern, bindings) = fields_pattern(&data.fields, "_");
+				let cloned = cloned_fields(&data.fields, &bindings);
+				quote! {
+					let Self #pattern = self;
+					Self #cloned
+				}
+			},
+			syn::Data::Enum(data) => {
+				let variants = data.variants.iter().map(|variant| {
+					let ident = &variant.ident;
+					let (pattern, bindings) = fields_pattern(&variant.fields, "_");
+					let cloned = cloned_fields(&variant.fields, &bindings);
+					quote!( Self::#ident #pattern => Self::#ident #cloned )
+				});
+				quote! {
+					match self {
+						#( #variants, )*
+					}
+				}
+			},
+			syn::Data::Union(_) => return Err(union_error(input)),
+		};
+
+		Ok(quote! {
+			fn clone(&self) -> Self {
+				#body
+			}
+		})
+	})
+}
+
+/// Builds the fields of the new value, cloning each of the `bindings` of the original value.
+fn cloned_fields(fields: &syn::Fields, bindings: &[syn::Ident]) -> proc_macro2::TokenStream {
+	match fields {
+		syn::Fields::Named(named) => {
+			let names = named.named.iter().map(|f| &f.ident);
+			quote!( { #( #names: ::core::clone::Clone::clone(#bindings) ),* } )
+		},
+		syn::Fields::Unnamed(_) => {
+			quote!( ( #( ::core::clone::Clone::clone(#bindings) ),* ) )
+		},
+		syn::Fields::Unit => quote!(),
+	}
+}
@@ -0,0 +1,64 @@
+use quote::quote;
+
+use super::{fields_pattern, impl_no_bound, union_error};
+
+/// Derive `Debug` without adding any bound on the generic parameters.
+pub fn derive_debug_no_bound(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	impl_no_bound(input, quote!(::core::fmt::Debug), |input| {
+		let name = input.ident.to_string();
+		let body = match &input.data {
+			syn::Data::Struct(data) => {
+				let (pattern, bindings) = fields_pattern(&data.fields, "_");
+				let debug = debug_fields(&name, &data.fields, &bindings);
+				quote! {
+					let Self #pattern = self;
+					#debug
+				}
+			},
+			syn::Data::Enum(data) => {
+				let variants = data.variants.iter().map(|variant| {
+					let ident = &variant.ident;
+					let (pattern, bindings) = fields_pattern(&variant.fields, "_");
+					let debug = debug_fields(&ident.to_string(), &variant.fields, &bindings);
+					quote!( Self::#ident #pattern => { #debug } )
+				});
+				quote! {
+					match self {
+						#( #variants, )*
+					}
+				}
+			},
+			syn::Data::Union(_) => return Err(union_error(input)),
+		};
+
+		Ok(quote! {
+			fn fmt(&self, fmt: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+				#body
+			}
+		})
+	})
+}
+
+/// Formats the `bindings` the same way `#[derive(Debug)]` would for a type named `name`.
+fn debug_fields(
+	name: &str,
+	fields: &syn::Fields,
+	bindings: &[syn::Ident],
+) -> proc_macro2::TokenStream {
+	match fields {
+		syn::Fields::Named(named) => {
+			let names = named.named.iter().map(|f| f.ident.as_ref().unwrap().to_string());
+			quote! {
+				fmt.debug_struct(#name)
+					#( .field(#names, #bindings) )*
+					.finish()
+			}
+		},
+		syn::Fields::Unnamed(_) => quote! {
+			fmt.debug_tuple(#name)
+				#( .field(#bindings) )*
+				.finish()
+		},
+		syn::Fields::Unit => quote!(fmt.write_str(#name)),
+	}
+}
@@ -0,0 +1,66 @@
+pub mod clone;
+pub mod debug;
+pub mod partial_eq;
+
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+
+/// The derives in this module generate the same code as their `std` counterparts, except that they
+/// do not add any bounds on the generic parameters of the type.
+///
+/// The `std` derives require `T: Clone` (for example) when deriving `Clone` for `Call<T>`, even
+/// though `T` is only ever used through its associated types like `T::AccountId`. Since `T` is our
+/// `Runtime` or some `TestConfig`, we would rather rely on the bounds already declared on the
+/// associated types in the `Config` traits.
+pub fn impl_no_bound(
+	input: proc_macro::TokenStream,
+	trait_path: proc_macro2::TokenStream,
+	body: impl FnOnce(&syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream>,
+) -> proc_macro::TokenStream {
+	let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+	let name = &input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+	let generated = match body(&input) {
+		Ok(body) => quote! {
+			const _: () = {
+				impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+					#body
+				}
+			};
+		},
+		Err(e) => e.to_compile_error(),
+	};
+
+	generated.into()
+}
+
+/// Returns an error for the unsupported `union` types.
+pub fn union_error(input: &syn::DeriveInput) -> syn::Error {
+	syn::Error::new(input.span(), "Union type not supported by the `NoBound` derives")
+}
+
+/// Generates the pattern binding all the fields of a struct or enum variant, along with the
+/// identifiers used in that pattern. Every binding is prefixed with `prefix` so two patterns can be
+/// used side by side, for example when comparing `self` and `other`.
+pub fn fields_pattern(
+	fields: &syn::Fields,
+	prefix: &str,
+) -> (proc_macro2::TokenStream, Vec<syn::Ident>) {
+	match fields {
+		syn::Fields::Named(named) => {
+			let names = named.named.iter().map(|f| f.ident.clone().unwrap()).collect::<Vec<_>>();
+			let bindings =
+				names.iter().map(|name| format_ident!("{}{}", prefix, name)).collect::<Vec<_>>();
+			(quote!( { #( #names: #bindings ),* } ), bindings)
+		},
+		syn::Fields::Unnamed(unnamed) => {
+			let bindings = (0..unnamed.unnamed.len())
+				.map(|i| format_ident!("{}{}", prefix, i))
+				.collect::<Vec<_>>();
+			(quote!( ( #( #bindings ),* ) ), bindings)
+		},
+		syn::Fields::Unit => (quote!(), vec![]),
+	}
+}
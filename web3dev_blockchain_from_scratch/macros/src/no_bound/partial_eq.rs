@@ -0,0 +1,51 @@
+use quote::quote;
+
+use super::{fields_pattern, impl_no_bound, union_error};
+
+/// Derive `PartialEq` without adding any bound on the generic parameters.
+pub fn derive_partial_eq_no_bound(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	impl_no_bound(input, quote!(::core::cmp::PartialEq), |input| {
+		let body = match &input.data {
+			syn::Data::Struct(data) => {
+				let (self_pattern, self_bindings) = fields_pattern(&data.fields, "self_");
+				let (other_pattern, other_bindings) = fields_pattern(&data.fields, "other_");
+				quote! {
+					let Self #self_pattern = self;
+					let Self #other_pattern = other;
+					true #( && #self_bindings == #other_bindings )*
+				}
+			},
+			syn::Data::Enum(data) => {
+				let variants = data.variants.iter().map(|variant| {
+					let ident = &variant.ident;
+					let (self_pattern, self_bindings) = fields_pattern(&variant.fields, "self_");
+					let (other_pattern, other_bindings) = fields_pattern(&variant.fields, "other_");
+					quote! {
+						(Self::#ident #self_pattern, Self::#ident #other_pattern) => {
+							true #( && #self_bindings == #other_bindings )*
+						}
+					}
+				});
+				quote! {
+					match (self, other) {
+						#( #variants, )*
+						#[allow(unreachable_patterns)]
+						_ => false,
+					}
+				}
+			},
+			syn::Data::Union(_) => return Err(union_error(input)),
+		};
+
+		Ok(quote! {
+			fn eq(&self, other: &Self) -> bool {
+				#body
+			}
+		})
+	})
+}
+
+/// Derive `Eq` without adding any bound on the generic parameters.
+pub fn derive_eq_no_bound(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	impl_no_bound(input, quote!(::core::cmp::Eq), |_| Ok(quote!()))
+}
@@ -3,17 +3,38 @@ use syn::spanned::Spanned;
 /// This object will collect all the information we need to keep while parsing the `Runtime` struct.
 #[derive(Debug)]
 pub struct RuntimeDef {
-	/// This is the name of the struct used by the user. We mostly assume it is `Runtime`.
-	pub runtime_struct: syn::Ident,
+	/// This is the `Runtime` struct, without the `#[pallet_index = n]`, `#[lend(..)]` and
+	/// `#[forward_calls]` attributes of its fields.
+	pub item_struct: syn::ItemStruct,
 	/// This is the list of pallets included in the `Runtime` struct. We omit `system` from this
 	/// list, but during parsing we check that system exists.
-	pub pallets: Vec<(syn::Ident, syn::Type)>,
+	pub pallets: Vec<PalletDef>,
+}
+
+/// This is the metadata we keep about each pallet included in the runtime.
+#[derive(Debug)]
+pub struct PalletDef {
+	/// The field name, which is also the name of the pallet in the runtime.
+	pub name: syn::Ident,
+	/// The type of the pallet, like `balances::Pallet<Runtime, Instance1>`.
+	pub ty: syn::Type,
+	/// The type of the events of the pallet, like `balances::Event<Runtime, Instance1>`.
+	pub event_ty: syn::Type,
+	/// The index of the pallet in the metadata and in the encoding of `RuntimeCall`, given with
+	/// `#[pallet_index = n]` or else its position in the `Runtime` struct.
+	pub index: u8,
+	/// The pallets lent to this pallet while its calls are dispatched, given with
+	/// `#[lend(name, ..)]`, along with their types.
+	pub lend: Vec<(syn::Ident, syn::Type)>,
+	/// Whether the calls of the pallet forward other calls to the runtime, given with
+	/// `#[forward_calls]`.
+	pub forwards_calls: bool,
 }
 
 impl RuntimeDef {
 	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
 		// First we check that we are parsing a `struct`.
-		let item_struct = if let syn::Item::Struct(item) = item {
+		let mut item_struct = if let syn::Item::Struct(item) = item {
 			item
 		} else {
 			return Err(syn::Error::new(item.span(), "Invalid runtime, expected item struct"))
@@ -22,21 +43,145 @@ impl RuntimeDef {
 		// We check that the `Runtime` includes the `system` pallet as the first item.
 		check_system(&item_struct)?;
 
-		let runtime_struct = item_struct.ident;
+		// Here is where we will store a list of all the pallets, along with the names of the pallets
+		// they borrow.
+		let mut pallets: Vec<PalletDef> = vec![];
+		let mut borrowed: Vec<Vec<syn::Ident>> = vec![];
+		let mut system_ty = None;
+		for (position, field) in item_struct.fields.iter_mut().enumerate() {
+			// The index is not a real attribute, so it is removed from the field.
+			let index = match take_pallet_index(&mut field.attrs)? {
+				Some(index) => index,
+				None => u8::try_from(position).map_err(|_| {
+					syn::Error::new(field.span(), "Invalid runtime, too many pallets")
+				})?,
+			};
+			let lend = take_lend(&mut field.attrs)?;
+			let forwards_calls = take_forward_calls(&mut field.attrs);
 
-		// Here is where we will store a list of all the pallets.
-		let mut pallets = vec![];
-		// We skip `system`, which we ensure is the first field in `check_system`.
-		for field in item_struct.fields.into_iter().skip(1) {
-			if let Some(ident) = field.ident {
-				pallets.push((ident, field.ty))
+			// We skip `system`, which we ensure is the first field in `check_system`, and which
+			// always has the index `0`.
+			if position == 0 {
+				if index != 0 {
+					let msg = "Invalid runtime, the index of the system pallet must be 0";
+					return Err(syn::Error::new(field.span(), msg))
+				}
+				if !lend.is_empty() {
+					let msg = "Invalid runtime, the system pallet can't borrow another pallet";
+					return Err(syn::Error::new(field.span(), msg))
+				}
+				if forwards_calls {
+					let msg = "Invalid runtime, the system pallet can't forward calls";
+					return Err(syn::Error::new(field.span(), msg))
+				}
+				system_ty = Some(field.ty.clone());
+				continue
+			}
+
+			let Some(name) = field.ident.clone() else { continue };
+			if index == 0 {
+				let msg = "Invalid runtime, the index 0 is used by the system pallet";
+				return Err(syn::Error::new(field.span(), msg))
+			}
+			if let Some(other) = pallets.iter().find(|pallet| pallet.index == index) {
+				let msg = format!(
+					"Invalid runtime, pallet index {index} is already used by `{}`",
+					other.name
+				);
+				return Err(syn::Error::new(field.span(), msg))
 			}
+			let event_ty = sibling_type(&field.ty, "Event")?;
+			pallets.push(PalletDef {
+				name,
+				ty: field.ty.clone(),
+				event_ty,
+				index,
+				lend: vec![],
+				forwards_calls,
+			});
+			borrowed.push(lend);
 		}
 
-		Ok(Self { runtime_struct, pallets })
+		// The lent pallets are known once all the pallets are. The system pallet can be lent too.
+		for (i, names) in borrowed.into_iter().enumerate() {
+			for lend in names {
+				let ty = if lend == "system" {
+					system_ty.clone()
+				} else {
+					pallets
+						.iter()
+						.find(|pallet| pallet.name == lend)
+						.map(|pallet| pallet.ty.clone())
+				};
+				let Some(ty) = ty else {
+					let msg = format!("Invalid runtime, there is no pallet named `{lend}` to lend");
+					return Err(syn::Error::new(lend.span(), msg))
+				};
+				if lend == pallets[i].name {
+					let msg = "Invalid runtime, a pallet can't borrow itself";
+					return Err(syn::Error::new(lend.span(), msg))
+				}
+				if pallets[i].lend.iter().any(|(name, _)| name == &lend) {
+					let msg = format!("Invalid runtime, `{lend}` is lent more than once");
+					return Err(syn::Error::new(lend.span(), msg))
+				}
+				pallets[i].lend.push((lend, ty));
+			}
+		}
+
+		Ok(Self { item_struct, pallets })
 	}
 }
 
+/// Remove the `#[pallet_index = n]` attribute from `attrs`, returning `n`.
+fn take_pallet_index(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<u8>> {
+	let Some(position) = attrs.iter().position(|attr| attr.path().is_ident("pallet_index")) else {
+		return Ok(None)
+	};
+	let attr = attrs.remove(position);
+	let syn::Meta::NameValue(meta) = &attr.meta else {
+		let msg = "Invalid runtime, expected `#[pallet_index = n]`";
+		return Err(syn::Error::new(attr.span(), msg))
+	};
+	let lit = syn::parse2::<syn::LitInt>(quote::ToTokens::to_token_stream(&meta.value))?;
+	lit.base10_parse().map(Some)
+}
+
+/// Remove the `#[lend(name, ..)]` attribute from `attrs`, returning the names.
+fn take_lend(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Vec<syn::Ident>> {
+	let Some(position) = attrs.iter().position(|attr| attr.path().is_ident("lend")) else {
+		return Ok(vec![])
+	};
+	let names = attrs.remove(position).parse_args_with(
+		syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated,
+	)?;
+	Ok(names.into_iter().collect())
+}
+
+/// Remove the `#[forward_calls]` attribute from `attrs`, returning whether it was there.
+fn take_forward_calls(attrs: &mut Vec<syn::Attribute>) -> bool {
+	let len = attrs.len();
+	attrs.retain(|attr| !attr.path().is_ident("forward_calls"));
+	attrs.len() != len
+}
+
+/// The type named `name` in the module of the pallet type `ty`, with the same generic arguments.
+/// For example, the `Event` of `balances::Pallet<Runtime>` is `balances::Event<Runtime>`.
+fn sibling_type(ty: &syn::Type, name: &str) -> syn::Result<syn::Type> {
+	let mut sibling = ty.clone();
+	let last = match &mut sibling {
+		syn::Type::Path(syn::TypePath { qself: None, path }) => path.segments.last_mut(),
+		_ => None,
+	};
+	let Some(last) = last else {
+		let msg =
+			"Invalid runtime, expected the type of a pallet, like `balances::Pallet<Runtime>`";
+		return Err(syn::Error::new(ty.span(), msg))
+	};
+	last.ident = syn::Ident::new(name, last.ident.span());
+	Ok(sibling)
+}
+
 /// This function checks that the `system` pallet is the first pallet included in the `Runtime`
 /// struct. We make many assumptions about the `system` pallet in order to keep these macros simple.
 /// For example, we assume that the system pallet has no callable functions, and that it contains
@@ -0,0 +1,98 @@
+use super::parse::StorageDef;
+use quote::quote;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_storage(def: StorageDef) -> proc_macro2::TokenStream {
+	let StorageDef { item_struct, items, other_fields } = def;
+
+	let pallet_struct = &item_struct.ident;
+	let (impl_generics, ty_generics, where_clause) = item_struct.generics.split_for_impl();
+
+	let item_names = items.iter().map(|item| &item.name).collect::<Vec<_>>();
+	let item_types = items.iter().map(|item| &item.ty).collect::<Vec<_>>();
+	// The function returning the default value of each item, if any.
+	let item_defaults = items
+		.iter()
+		.map(|item| match &item.default {
+			Some(default) => quote!(Some(|| #default)),
+			None => quote!(None),
+		})
+		.collect::<Vec<_>>();
+
+	// This quote block creates the storage items of the pallet.
+	let new_impl = quote! {
+		impl #impl_generics #pallet_struct #ty_generics #where_clause {
+			// Create a new instance of the pallet, where nothing is stored yet.
+			//
+			// The keys of the storage items are prefixed by the name of the module of the pallet,
+			// and the names of the items.
+			pub fn new() -> Self {
+				Self::with_pallet_name(module_path!().rsplit("::").next().unwrap())
+			}
+
+			// Create a new instance of the pallet, whose keys are prefixed by the given name instead
+			// of the name of its module. The runtime uses the names of its fields, so that several
+			// instances of the same pallet do not share their keys.
+			pub fn with_pallet_name(pallet: &str) -> Self {
+				Self {
+					#(
+						#item_names: crate::support::storage::StorageItem::new(
+							pallet,
+							stringify!(#item_names),
+							#item_defaults,
+						),
+					)*
+					#(
+						#other_fields: ::core::default::Default::default(),
+					)*
+				}
+			}
+		}
+	};
+
+	// This quote block exposes the storage items to the runtime, for its state root and its
+	// transactions.
+	let storage_impl = quote! {
+		impl #impl_generics crate::support::storage::Storage for #pallet_struct #ty_generics #where_clause {
+			fn raw_storage(&self) -> Vec<&crate::support::storage::RawStorage> {
+				vec![ #( crate::support::storage::StorageItem::raw(&self.#item_names) ),* ]
+			}
+
+			fn raw_storage_mut(&mut self) -> Vec<&mut crate::support::storage::RawStorage> {
+				vec![ #( crate::support::storage::StorageItem::raw_mut(&mut self.#item_names) ),* ]
+			}
+		}
+	};
+
+	// This quote block describes the storage items in the runtime metadata.
+	let mut metadata_generics = item_struct.generics.clone();
+	let metadata_where = metadata_generics.make_where_clause();
+	for ty in &item_types {
+		metadata_where
+			.predicates
+			.push(syn::parse_quote!(#ty: crate::support::storage::StorageItemInfo));
+	}
+	let (_, _, metadata_where) = metadata_generics.split_for_impl();
+	let metadata_impl = quote! {
+		impl #impl_generics crate::metadata::StorageInfo for #pallet_struct #ty_generics #metadata_where {
+			fn storage_info() -> Vec<crate::metadata::StorageEntryMetadata> {
+				vec![
+					#(
+						crate::metadata::StorageEntryMetadata {
+							name: stringify!(#item_names).to_string(),
+							ty: <#item_types as crate::support::storage::StorageItemInfo>::entry_type(),
+						}
+					),*
+				]
+			}
+		}
+	};
+
+	// Return the pallet struct along with the generated code.
+	quote! {
+		#item_struct
+		#new_impl
+		#storage_impl
+		#metadata_impl
+	}
+}
@@ -0,0 +1,20 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn storage(
+	_attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let item_mod = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the storage items of the pallet struct...
+	let generated: proc_macro::TokenStream = match parse::StorageDef::try_from(item_mod) {
+		// ..then we generate our new code. Unlike the other macros, we do not return the original
+		// code as is, since the `#[default(..)]` attributes of the fields must be removed.
+		Ok(def) => expand::expand_storage(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	};
+
+	generated
+}
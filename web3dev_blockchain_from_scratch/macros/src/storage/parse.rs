@@ -0,0 +1,84 @@
+use syn::spanned::Spanned;
+
+/// The types of the storage items, as declared in `support::storage`.
+const STORAGE_TYPES: &[&str] = &["StorageValue", "StorageMap", "StorageDoubleMap"];
+
+/// This object will collect all the information we need to keep while parsing the pallet struct.
+#[derive(Debug)]
+pub struct StorageDef {
+	/// The pallet struct, without the `#[default(..)]` attributes of its fields.
+	pub item_struct: syn::ItemStruct,
+	/// The storage items of the pallet. See `StorageItemDef`.
+	pub items: Vec<StorageItemDef>,
+	/// The other fields of the pallet, which are not part of its state, like its events.
+	pub other_fields: Vec<syn::Ident>,
+}
+
+/// This is the metadata we keep about each storage item of the pallet.
+#[derive(Debug)]
+pub struct StorageItemDef {
+	/// The field name, which is also the name of the storage item.
+	pub name: syn::Ident,
+	/// The type of the storage item, like `StorageMap<T::AccountId, T::Balance>`.
+	pub ty: syn::Type,
+	/// The value returned when nothing is stored, given with `#[default(..)]`.
+	pub default: Option<syn::Expr>,
+}
+
+impl StorageDef {
+	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing a `struct`.
+		let mut item_struct = if let syn::Item::Struct(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid storage, expected item struct"))
+		};
+
+		let mut items = vec![];
+		let mut other_fields = vec![];
+		for field in item_struct.fields.iter_mut() {
+			let name = match &field.ident {
+				Some(name) => name.clone(),
+				None => {
+					let msg = "Invalid storage, expected a struct with named fields";
+					return Err(syn::Error::new(field.span(), msg))
+				},
+			};
+
+			// Take the `#[default(..)]` attribute out of the field.
+			let mut default = None;
+			let mut attrs = vec![];
+			for attr in field.attrs.drain(..) {
+				if attr.path().is_ident("default") {
+					default = Some(attr.parse_args::<syn::Expr>()?);
+				} else {
+					attrs.push(attr);
+				}
+			}
+			field.attrs = attrs;
+
+			if is_storage_type(&field.ty) {
+				items.push(StorageItemDef { name, ty: field.ty.clone(), default });
+			} else if default.is_some() {
+				let msg = "Invalid storage, `#[default(..)]` is only supported on storage items";
+				return Err(syn::Error::new(field.span(), msg))
+			} else {
+				other_fields.push(name);
+			}
+		}
+
+		Ok(Self { item_struct, items, other_fields })
+	}
+}
+
+/// Whether the type is one of the storage items, like `StorageValue<T::BlockNumber>`.
+fn is_storage_type(ty: &syn::Type) -> bool {
+	match ty {
+		syn::Type::Path(tp) => tp
+			.path
+			.segments
+			.last()
+			.map_or(false, |segment| STORAGE_TYPES.iter().any(|ty| segment.ident == ty)),
+		_ => false,
+	}
+}
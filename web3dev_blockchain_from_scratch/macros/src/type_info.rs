@@ -0,0 +1,131 @@
+use quote::quote;
+use syn::spanned::Spanned;
+
+/// Derive `metadata::TypeInfo`, describing a struct as a `Composite` and an enum as a `Variant`.
+///
+/// Like the `NoBound` derives, no bound is added on the generic parameters. Instead, the type of
+/// every field is required to implement `TypeInfo`, which for `Event<T>` means bounds like
+/// `T::AccountId: TypeInfo`.
+pub fn derive_type_info(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+	let body = match &input.data {
+		syn::Data::Struct(data) => {
+			let fields = fields_info(&data.fields);
+			quote!(crate::metadata::Type::Composite(#fields))
+		},
+		syn::Data::Enum(data) => {
+			// Like in the SCALE encoding, the index of a variant is its position unless it is given
+			// with `#[codec(index = n)]`, and variants with `#[codec(skip)]` are left out.
+			let mut variants = vec![];
+			for (position, variant) in data.variants.iter().enumerate() {
+				match codec_index(variant) {
+					Ok(CodecIndex::Position) => variants.push((position as u8, variant)),
+					Ok(CodecIndex::Index(index)) => variants.push((index, variant)),
+					Ok(CodecIndex::Skip) => {},
+					Err(e) => return e.to_compile_error().into(),
+				}
+			}
+			let variants = variants.into_iter().map(|(index, variant)| {
+				let name = variant.ident.to_string();
+				let fields = fields_info(&variant.fields);
+				quote! {
+					crate::metadata::Variant {
+						index: #index,
+						name: #name.to_string(),
+						fields: #fields,
+					}
+				}
+			});
+			quote!(crate::metadata::Type::Variant(vec![ #( #variants ),* ]))
+		},
+		syn::Data::Union(_) => {
+			let msg = "Union type not supported by the `TypeInfo` derive";
+			return syn::Error::new(input.span(), msg).to_compile_error().into()
+		},
+	};
+
+	// Every field type must describe itself.
+	let mut generics = input.generics.clone();
+	let where_clause = generics.make_where_clause();
+	for ty in field_types(&input.data) {
+		where_clause.predicates.push(syn::parse_quote!(#ty: crate::metadata::TypeInfo));
+	}
+
+	let name = &input.ident;
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	quote! {
+		const _: () = {
+			impl #impl_generics crate::metadata::TypeInfo for #name #ty_generics #where_clause {
+				fn type_info() -> crate::metadata::Type {
+					#body
+				}
+			}
+		};
+	}
+	.into()
+}
+
+/// How a variant is indexed in the SCALE encoding.
+enum CodecIndex {
+	/// By its position in the enum.
+	Position,
+	/// By the index given with `#[codec(index = n)]`.
+	Index(u8),
+	/// Not at all, because of `#[codec(skip)]`.
+	Skip,
+}
+
+/// Read the `#[codec(..)]` attributes of a variant, ignoring the ones not about its index.
+fn codec_index(variant: &syn::Variant) -> syn::Result<CodecIndex> {
+	let mut index = CodecIndex::Position;
+	for attr in variant.attrs.iter().filter(|attr| attr.path().is_ident("codec")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("index") {
+				let lit: syn::LitInt = meta.value()?.parse()?;
+				index = CodecIndex::Index(lit.base10_parse()?);
+			} else if meta.path.is_ident("skip") {
+				index = CodecIndex::Skip;
+			} else if meta.input.peek(syn::Token![=]) {
+				meta.value()?.parse::<syn::Expr>()?;
+			}
+			Ok(())
+		})?;
+	}
+	Ok(index)
+}
+
+/// Generates the `Vec<metadata::Field>` describing `fields`.
+fn fields_info(fields: &syn::Fields) -> proc_macro2::TokenStream {
+	let fields = fields.iter().map(|field| {
+		let name = match &field.ident {
+			Some(ident) => {
+				let ident = ident.to_string();
+				quote!(Some(#ident.to_string()))
+			},
+			None => quote!(None),
+		};
+		let ty = &field.ty;
+		quote! {
+			crate::metadata::Field {
+				name: #name,
+				ty: <#ty as crate::metadata::TypeInfo>::type_info(),
+			}
+		}
+	});
+	quote!(vec![ #( #fields ),* ])
+}
+
+/// All the field types of a struct or enum, except in the variants skipped by the encoding.
+fn field_types(data: &syn::Data) -> Vec<&syn::Type> {
+	match data {
+		syn::Data::Struct(data) => data.fields.iter().map(|field| &field.ty).collect(),
+		syn::Data::Enum(data) => data
+			.variants
+			.iter()
+			.filter(|variant| !matches!(codec_index(variant), Ok(CodecIndex::Skip)))
+			.flat_map(|variant| variant.fields.iter().map(|field| &field.ty))
+			.collect(),
+		syn::Data::Union(_) => vec![],
+	}
+}
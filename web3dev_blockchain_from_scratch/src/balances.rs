@@ -1,26 +1,157 @@
-use std::{
-    collections::BTreeMap,
-    ops::AddAssign,
+use core::{
+    fmt::Debug,
+    marker::PhantomData,
 };
+use std::ops::AddAssign;
 
+use codec::{
+    Decode,
+    Encode,
+};
 use num::{
     CheckedAdd,
     CheckedSub,
     One,
     Zero,
 };
+use serde::Serialize;
+
+use crate::support::{
+    self,
+    benchmarking::{
+        Benchmark,
+        Benchmarks,
+    },
+    storage::{
+        StorageMap,
+        StorageValue,
+    },
+    Currency,
+    DispatchResult,
+    GetStorageVersion,
+    Hooks,
+    Migration,
+    Never,
+    OnRuntimeUpgrade,
+    ReservableCurrency,
+    StorageVersion,
+};
+
+/// The storage version of the balances pallet.
+///
+/// - `0`: the initial layout.
+/// - `1`: accounts with a zero balance are no longer stored, see `migrations::RemoveZeroBalances`.
+pub const STORAGE_VERSION: StorageVersion = StorageVersion(1);
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+/// The errors of the balances pallet, which are the errors of its `Currency` implementation.
+pub use crate::support::{
+    INSUFFICIENT_BALANCE,
+    INSUFFICIENT_RESERVED_BALANCE,
+    OVERFLOW,
+};
 
-pub trait Config: crate::system::Config {
-    type Balance: Zero + One + CheckedAdd + CheckedSub + AddAssign + Copy;
+/// The configuration of an instance `I` of the balances pallet. The pallet can be included more
+/// than once in the runtime, like for a native token and a stablecoin, by implementing this trait
+/// for other instances than the default `()`, like `support::Instance1`.
+pub trait Config<I: 'static = ()>: crate::system::Config {
+    type Balance: Zero
+        + One
+        + CheckedAdd
+        + CheckedSub
+        + AddAssign
+        + Copy
+        + Eq
+        + Debug
+        + Encode
+        + Decode;
+    /// The weights of the calls, see the `weights` module.
+    type WeightInfo: WeightInfo;
+}
+
+/// The events emitted by the balances pallet.
+#[derive(
+    macros::CloneNoBound,
+    macros::DebugNoBound,
+    macros::PartialEqNoBound,
+    macros::EqNoBound,
+    macros::TypeInfo,
+    Serialize,
+)]
+pub enum Event<T: Config<I>, I: 'static = ()> {
+    /// `amount` was transferred from `from` to `to`.
+    Transfer {
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    },
+    /// `amount` was withdrawn from `who` and burned, like for paying a transaction fee.
+    Withdraw {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    /// `amount` of the balance of `who` was reserved, like for a deposit held by another pallet.
+    Reserved {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    /// `amount` of the reserved balance of `who` was returned to its balance.
+    Unreserved {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    /// `amount` of the reserved balance of `from` was moved to the balance of `to`.
+    ReserveRepatriated {
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    },
+    /// `amount` was minted and added to the balance of `who`, like a share of the transaction
+    /// fees.
+    Deposit {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    /// `amount` was slashed from the balance of `who` and burned, like a penalty.
+    Slashed {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    /// `amount` was slashed from the reserved balance of `who` and burned.
+    ReserveSlashed {
+        who: T::AccountId,
+        amount: T::Balance,
+    },
+    #[doc(hidden)]
+    #[codec(skip)]
+    #[serde(skip)]
+    __Ignore(PhantomData<I>, Never),
 }
 
 #[derive(Debug)]
-pub struct Pallet<T: Config> {
-    balances: BTreeMap<T::AccountId, T::Balance>,
+#[macros::storage]
+pub struct Pallet<T: Config<I>, I: 'static = ()> {
+    #[default(T::Balance::zero())]
+    balances: StorageMap<T::AccountId, T::Balance>,
+    /// The balances which can't be spent, like the deposits held by other pallets. They are not
+    /// part of the `balances` above.
+    #[default(T::Balance::zero())]
+    reserved: StorageMap<T::AccountId, T::Balance>,
+    events: Vec<Event<T, I>>,
+    /// The chains started before the storage versions were recorded have none, so it defaults to
+    /// the first version. New chains record the current version at genesis.
+    #[default(StorageVersion(0))]
+    storage_version: StorageValue<StorageVersion>,
+    instance: PhantomData<I>,
 }
 
 #[macros::call]
-impl<T: Config> Pallet<T> {
+#[errors(INSUFFICIENT_BALANCE, OVERFLOW)]
+impl<T: Config<I>, I: 'static> Pallet<T, I> {
+    #[call_index(0)]
+    #[weight(T::WeightInfo::transfer_new_account().max(T::WeightInfo::transfer_existing_account()))]
     pub fn transfer(
         &mut self,
         caller: T::AccountId,
@@ -28,57 +159,429 @@ impl<T: Config> Pallet<T> {
         amount: T::Balance,
     ) -> Result<(), &'static str> {
         let caller_balance = self.balance(caller.clone());
-        let to_balance = self.balance(to.clone());
 
         let new_caller_balance = caller_balance
             .checked_sub(&amount)
-            .ok_or("Insufficient balance")?;
+            .ok_or(INSUFFICIENT_BALANCE)?;
 
+        // On a transfer to oneself, `to` receives the amount after it was withdrawn.
+        let to_balance = if caller == to {
+            new_caller_balance
+        } else {
+            self.balance(to.clone())
+        };
         let new_to_balance = to_balance
             .checked_add(&amount)
-            .ok_or("Overflow when adding balance")?;
+            .ok_or(OVERFLOW)?;
+
+        self.set_balance(caller.clone(), new_caller_balance);
+        self.set_balance(to.clone(), new_to_balance);
 
-        self.set_balance(caller, new_caller_balance);
-        self.set_balance(to, new_to_balance);
+        self.deposit_event(Event::Transfer {
+            from: caller,
+            to,
+            amount,
+        });
 
         Ok(())
     }
 }
 
-impl<T: Config> Pallet<T> {
-    pub fn new() -> Self {
-        Self {
-            balances: BTreeMap::new(),
-        }
+impl<T: Config<I>, I: 'static> Default for Pallet<T, I> {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
+impl<T: Config<I>, I: 'static> Pallet<T, I> {
     /// set the balance of who
+    ///
+    /// Accounts with a zero balance are not stored.
     pub fn set_balance(&mut self, who: T::AccountId, amount: T::Balance) {
-        self.balances
-            .insert(who, amount);
+        if amount.is_zero() {
+            self.balances.remove(&who);
+        } else {
+            self.balances
+                .insert(&who, amount);
+        }
     }
 
     /// get the balance of who
     pub fn balance(&self, who: T::AccountId) -> T::Balance {
-        *self
-            .balances
-            .get(&who)
-            .unwrap_or(&T::Balance::zero())
+        self.balances.get(&who)
+    }
+
+    /// Withdraw `amount` from the balance of `who` and burn it. Withdrawing nothing does not emit
+    /// any event.
+    pub fn withdraw(&mut self, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        let new_balance = self
+            .balance(who.clone())
+            .checked_sub(&amount)
+            .ok_or(INSUFFICIENT_BALANCE)?;
+        self.set_balance(who.clone(), new_balance);
+        self.deposit_event(Event::Withdraw { who, amount });
+        Ok(())
+    }
+
+    /// Mint `amount` and add it to the balance of `who`. Depositing nothing does not emit any
+    /// event.
+    pub fn deposit(&mut self, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        let new_balance = self
+            .balance(who.clone())
+            .checked_add(&amount)
+            .ok_or(OVERFLOW)?;
+        self.set_balance(who.clone(), new_balance);
+        self.deposit_event(Event::Deposit { who, amount });
+        Ok(())
+    }
+
+    /// get the reserved balance of who
+    pub fn reserved_balance(&self, who: T::AccountId) -> T::Balance {
+        self.reserved.get(&who)
+    }
+
+    /// Move `amount` from the balance of `who` to its reserved balance, which can't be spent until
+    /// it is unreserved. Reserving nothing does not emit any event.
+    pub fn reserve(&mut self, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        let new_balance = self
+            .balance(who.clone())
+            .checked_sub(&amount)
+            .ok_or(INSUFFICIENT_BALANCE)?;
+        let new_reserved = self
+            .reserved_balance(who.clone())
+            .checked_add(&amount)
+            .ok_or(OVERFLOW)?;
+        self.set_balance(who.clone(), new_balance);
+        self.set_reserved(who.clone(), new_reserved);
+        self.deposit_event(Event::Reserved { who, amount });
+        Ok(())
+    }
+
+    /// Move `amount` from the reserved balance of `who` back to its balance. Unreserving nothing
+    /// does not emit any event.
+    pub fn unreserve(&mut self, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        let new_reserved = self
+            .reserved_balance(who.clone())
+            .checked_sub(&amount)
+            .ok_or(INSUFFICIENT_RESERVED_BALANCE)?;
+        let new_balance = self
+            .balance(who.clone())
+            .checked_add(&amount)
+            .ok_or(OVERFLOW)?;
+        self.set_reserved(who.clone(), new_reserved);
+        self.set_balance(who.clone(), new_balance);
+        self.deposit_event(Event::Unreserved { who, amount });
+        Ok(())
+    }
+
+    /// Move `amount` from the reserved balance of `from` to the balance of `to`, like to pay out
+    /// funds held by another pallet. Repatriating nothing does not emit any event.
+    pub fn repatriate_reserved(
+        &mut self,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        if amount.is_zero() {
+            return Ok(());
+        }
+        let new_reserved = self
+            .reserved_balance(from.clone())
+            .checked_sub(&amount)
+            .ok_or(INSUFFICIENT_RESERVED_BALANCE)?;
+        let new_balance = self
+            .balance(to.clone())
+            .checked_add(&amount)
+            .ok_or(OVERFLOW)?;
+        self.set_reserved(from.clone(), new_reserved);
+        self.set_balance(to.clone(), new_balance);
+        self.deposit_event(Event::ReserveRepatriated { from, to, amount });
+        Ok(())
+    }
+
+    /// Like `set_balance`, zero reserved balances are not stored.
+    fn set_reserved(&mut self, who: T::AccountId, amount: T::Balance) {
+        if amount.is_zero() {
+            self.reserved.remove(&who);
+        } else {
+            self.reserved
+                .insert(&who, amount);
+        }
+    }
+
+    /// The sum of the balances of all accounts, including their reserved balances.
+    pub fn total_issuance(&self) -> Result<T::Balance, &'static str> {
+        self.balances
+            .values()
+            .chain(self.reserved.values())
+            .try_fold(T::Balance::zero(), |total, balance| {
+                total.checked_add(&balance)
+            })
+            .ok_or("Overflow when computing the total issuance")
+    }
+
+    fn deposit_event(&mut self, event: Event<T, I>) {
+        self.events.push(event);
+    }
+
+    /// Take the events emitted since the last call, so the runtime can record them.
+    pub fn take_events(&mut self) -> Vec<Event<T, I>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+/// The balances of every instance are a currency, whose `transfer` is the one of the calls.
+impl<T: Config<I>, I: 'static> Currency<T::AccountId> for Pallet<T, I> {
+    type Balance = T::Balance;
+
+    fn free_balance(&self, who: T::AccountId) -> T::Balance {
+        self.balance(who)
+    }
+
+    fn total_issuance(&self) -> Result<T::Balance, &'static str> {
+        Pallet::total_issuance(self)
+    }
+
+    fn transfer(
+        &mut self,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        Pallet::transfer(self, from, to, amount)
+    }
+
+    fn deposit_creating(&mut self, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+        self.deposit(who, amount)
+    }
+
+    fn withdraw(&mut self, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+        Pallet::withdraw(self, who, amount)
+    }
+
+    fn slash(&mut self, who: T::AccountId, amount: T::Balance) -> T::Balance {
+        let balance = self.balance(who.clone());
+        let (slashed, remaining) = match balance.checked_sub(&amount) {
+            Some(new_balance) => {
+                self.set_balance(who.clone(), new_balance);
+                (amount, T::Balance::zero())
+            }
+            None => {
+                self.set_balance(who.clone(), T::Balance::zero());
+                (balance, amount - balance)
+            }
+        };
+        if !slashed.is_zero() {
+            self.deposit_event(Event::Slashed {
+                who,
+                amount: slashed,
+            });
+        }
+        remaining
+    }
+}
+
+impl<T: Config<I>, I: 'static> ReservableCurrency<T::AccountId> for Pallet<T, I> {
+    fn reserved_balance(&self, who: T::AccountId) -> T::Balance {
+        Pallet::reserved_balance(self, who)
+    }
+
+    fn reserve(&mut self, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+        Pallet::reserve(self, who, amount)
+    }
+
+    fn unreserve(&mut self, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+        Pallet::unreserve(self, who, amount)
+    }
+
+    fn repatriate_reserved(
+        &mut self,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        Pallet::repatriate_reserved(self, from, to, amount)
+    }
+
+    fn slash_reserved(&mut self, who: T::AccountId, amount: T::Balance) -> T::Balance {
+        let reserved = self.reserved_balance(who.clone());
+        let (slashed, remaining) = match reserved.checked_sub(&amount) {
+            Some(new_reserved) => {
+                self.set_reserved(who.clone(), new_reserved);
+                (amount, T::Balance::zero())
+            }
+            None => {
+                self.set_reserved(who.clone(), T::Balance::zero());
+                (reserved, amount - reserved)
+            }
+        };
+        if !slashed.is_zero() {
+            self.deposit_event(Event::ReserveSlashed {
+                who,
+                amount: slashed,
+            });
+        }
+        remaining
+    }
+}
+
+impl<T: Config<I>, I: 'static> GetStorageVersion for Pallet<T, I> {
+    const CURRENT_STORAGE_VERSION: StorageVersion = STORAGE_VERSION;
+
+    fn on_chain_storage_version(&self) -> StorageVersion {
+        self.storage_version.get()
+    }
+
+    fn set_on_chain_storage_version(&mut self, version: StorageVersion) {
+        self.storage_version
+            .set(version);
+    }
+}
+
+impl<T: Config<I>, I: 'static> Hooks<T::BlockNumber> for Pallet<T, I> {}
+
+impl<T: Config<I>, I: 'static> OnRuntimeUpgrade for Pallet<T, I> {
+    fn on_runtime_upgrade(&mut self) -> DispatchResult {
+        support::migrate::<Self, (migrations::RemoveZeroBalances,)>(self)
+    }
+
+    fn on_genesis(&mut self) {
+        self.set_on_chain_storage_version(STORAGE_VERSION);
+    }
+}
+
+/// The worst case of a transfer depends on whether it creates the account of the recipient.
+impl<T: Config<I>, I: 'static> Benchmarks for Pallet<T, I>
+where
+    T::AccountId: From<&'static str>,
+    T::Balance: From<u32>,
+{
+    /// The balance of the recipient.
+    type Setup = u32;
+
+    fn setup(recipient_balance: u32) -> Self {
+        let mut pallet = Pallet::new();
+        pallet.set_balance("caller".into(), 1_000.into());
+        pallet.set_balance("recipient".into(), recipient_balance.into());
+        pallet
+    }
+
+    fn benchmarks() -> Vec<Benchmark<Self>> {
+        vec![
+            Benchmark {
+                name: "transfer_new_account",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::transfer {
+                        to: "recipient".into(),
+                        amount: 100.into(),
+                    };
+                    (Self::setup(0), "caller".into(), call)
+                },
+                verify: |pallet, _| {
+                    if pallet.balance("recipient".into()) != 100.into() {
+                        return Err("the recipient did not receive the amount");
+                    }
+                    Ok(())
+                },
+            },
+            Benchmark {
+                name: "transfer_existing_account",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::transfer {
+                        to: "recipient".into(),
+                        amount: 100.into(),
+                    };
+                    (Self::setup(1_000), "caller".into(), call)
+                },
+                verify: |pallet, _| {
+                    if pallet.balance("recipient".into()) != 1_100.into() {
+                        return Err("the recipient did not receive the amount");
+                    }
+                    Ok(())
+                },
+            },
+        ]
+    }
+}
+
+/// The storage migrations of the balances pallet, in order.
+pub mod migrations {
+    use super::*;
+
+    /// Storage version `1` no longer stores accounts with a zero balance.
+    pub struct RemoveZeroBalances;
+
+    impl<T: Config<I>, I: 'static> Migration<Pallet<T, I>> for RemoveZeroBalances {
+        const FROM: StorageVersion = StorageVersion(0);
+        const TO: StorageVersion = StorageVersion(1);
+
+        type State = T::Balance;
+
+        fn pre_upgrade(pallet: &Pallet<T, I>) -> Result<Self::State, &'static str> {
+            pallet.total_issuance()
+        }
+
+        fn migrate(pallet: &mut Pallet<T, I>) {
+            pallet
+                .balances
+                .retain(|_, balance| !balance.is_zero());
+        }
+
+        fn post_upgrade(pallet: &Pallet<T, I>, total_issuance: Self::State) -> DispatchResult {
+            if pallet
+                .balances
+                .values()
+                .any(|balance| balance.is_zero())
+            {
+                return Err("zero balances are still stored");
+            }
+            if pallet.total_issuance()? != total_issuance {
+                return Err("the total issuance changed");
+            }
+            Ok(())
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::support::{
+        benchmarking::{
+            self,
+            BenchmarkConfig,
+        },
+        GetStorageVersion,
+        OnRuntimeUpgrade,
+        StorageVersion,
+    };
+
     struct TestConfig;
 
     impl crate::system::Config for TestConfig {
         type AccountId = String;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = ();
+        type SystemWeightInfo = ();
     }
 
     impl super::Config for TestConfig {
         type Balance = u128;
+        type WeightInfo = ();
     }
 
     #[test]
@@ -107,10 +610,206 @@ mod tests {
         );
         assert_eq!(balances.balance("alice".to_string()), 49);
         assert_eq!(balances.balance("bob".to_string()), 51);
+        assert_eq!(
+            balances.take_events(),
+            vec![super::Event::Transfer {
+                from: "alice".to_string(),
+                to: "bob".to_string(),
+                amount: 51,
+            }]
+        );
 
         assert_eq!(
             balances.transfer("alice".to_string(), "bob".to_string(), 51),
             Err("Insufficient balance")
         );
     }
+
+    #[test]
+    fn transfer_to_oneself() {
+        let mut balances = super::Pallet::<TestConfig>::new();
+
+        balances.set_balance("alice".to_string(), 100);
+        assert_eq!(
+            balances.transfer("alice".to_string(), "alice".to_string(), 60),
+            Ok(())
+        );
+        assert_eq!(balances.balance("alice".to_string()), 100);
+        assert_eq!(
+            balances.transfer("alice".to_string(), "alice".to_string(), 101),
+            Err("Insufficient balance")
+        );
+    }
+
+    #[test]
+    fn zero_balances_are_not_stored() {
+        let mut balances = super::Pallet::<TestConfig>::new();
+
+        balances.set_balance("alice".to_string(), 100);
+        balances
+            .transfer("alice".to_string(), "bob".to_string(), 100)
+            .unwrap();
+        assert_eq!(balances.balance("alice".to_string()), 0);
+        assert!(!balances
+            .balances
+            .contains_key(&"alice".to_string()));
+    }
+
+    #[test]
+    fn reserve_and_unreserve() {
+        let mut balances = super::Pallet::<TestConfig>::new();
+        let alice = || "alice".to_string();
+
+        balances.set_balance(alice(), 100);
+        assert_eq!(balances.reserve(alice(), 101), Err("Insufficient balance"));
+        assert_eq!(balances.reserve(alice(), 60), Ok(()));
+        assert_eq!(balances.balance(alice()), 40);
+        assert_eq!(balances.reserved_balance(alice()), 60);
+        assert_eq!(balances.total_issuance(), Ok(100));
+
+        // The reserved balance can't be transferred.
+        assert_eq!(
+            balances.transfer(alice(), "bob".to_string(), 50),
+            Err("Insufficient balance")
+        );
+
+        // Part of the reserved balance is paid out to bob.
+        assert_eq!(
+            balances.repatriate_reserved(alice(), "bob".to_string(), 61),
+            Err("Insufficient reserved balance")
+        );
+        assert_eq!(
+            balances.repatriate_reserved(alice(), "bob".to_string(), 10),
+            Ok(())
+        );
+        assert_eq!(balances.balance("bob".to_string()), 10);
+        assert_eq!(balances.total_issuance(), Ok(100));
+
+        assert_eq!(
+            balances.unreserve(alice(), 51),
+            Err("Insufficient reserved balance")
+        );
+        assert_eq!(balances.unreserve(alice(), 50), Ok(()));
+        assert_eq!(balances.balance(alice()), 90);
+        assert!(!balances
+            .reserved
+            .contains_key(&alice()));
+        assert_eq!(
+            balances.take_events(),
+            vec![
+                super::Event::Reserved {
+                    who: alice(),
+                    amount: 60,
+                },
+                super::Event::ReserveRepatriated {
+                    from: alice(),
+                    to: "bob".to_string(),
+                    amount: 10,
+                },
+                super::Event::Unreserved {
+                    who: alice(),
+                    amount: 50,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn slashing_burns_up_to_the_balance() {
+        use crate::support::{
+            Currency,
+            ReservableCurrency,
+        };
+
+        let mut balances = super::Pallet::<TestConfig>::new();
+        let alice = || "alice".to_string();
+
+        Currency::deposit_creating(&mut balances, alice(), 100).unwrap();
+        ReservableCurrency::reserve(&mut balances, alice(), 30).unwrap();
+        balances.take_events();
+        assert_eq!(balances.slash(alice(), 50), 0);
+        assert_eq!(balances.slash(alice(), 50), 30);
+        assert_eq!(balances.slash_reserved(alice(), 10), 0);
+        assert_eq!(balances.slash_reserved(alice(), 30), 10);
+        assert_eq!(balances.slash(alice(), 10), 10);
+        assert_eq!(balances.total_issuance(), Ok(0));
+        assert_eq!(
+            balances.take_events(),
+            vec![
+                super::Event::Slashed {
+                    who: alice(),
+                    amount: 50,
+                },
+                super::Event::Slashed {
+                    who: alice(),
+                    amount: 20,
+                },
+                super::Event::ReserveSlashed {
+                    who: alice(),
+                    amount: 10,
+                },
+                super::Event::ReserveSlashed {
+                    who: alice(),
+                    amount: 20,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn benchmarks_succeed() {
+        let config = BenchmarkConfig {
+            steps: 2,
+            repeat: 1,
+        };
+        let results = benchmarking::run::<super::Pallet<TestConfig>>(&config).unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result.name)
+                .collect::<Vec<_>>(),
+            vec!["transfer_new_account", "transfer_existing_account"]
+        );
+    }
+
+    #[test]
+    fn migrate_to_v1_removes_zero_balances() {
+        let mut balances = super::Pallet::<TestConfig>::new();
+        balances.on_genesis();
+        assert_eq!(balances.on_chain_storage_version(), StorageVersion(1));
+
+        // Storage written with the layout of version 0.
+        balances.set_on_chain_storage_version(StorageVersion(0));
+        balances
+            .balances
+            .insert(&"alice".to_string(), 0);
+        balances
+            .balances
+            .insert(&"bob".to_string(), 10);
+
+        assert_eq!(balances.on_runtime_upgrade(), Ok(()));
+        assert_eq!(balances.on_chain_storage_version(), StorageVersion(1));
+        assert_eq!(
+            balances
+                .balances
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["bob".to_string()]
+        );
+        assert_eq!(balances.total_issuance(), Ok(10));
+
+        // Running the upgrade again is a no-op.
+        assert_eq!(balances.on_runtime_upgrade(), Ok(()));
+    }
+
+    #[test]
+    fn migrate_rejects_unknown_storage_versions() {
+        let mut balances = super::Pallet::<TestConfig>::new();
+
+        balances.set_on_chain_storage_version(StorageVersion(2));
+        assert_eq!(
+            balances.on_runtime_upgrade(),
+            Err("storage version is newer than the code")
+        );
+    }
 }
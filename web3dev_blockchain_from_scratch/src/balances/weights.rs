@@ -0,0 +1,36 @@
+//! The weights of the `balances` pallet, measured by its benchmarks.
+//!
+//! Generated by `cargo run --release --bin benchmark`, do not edit by hand. The unit of weight is
+//! one nanosecond of execution on the machine which ran the benchmarks.
+
+use crate::support::Weight;
+
+/// The weights of the callable functions of the pallet.
+pub trait WeightInfo {
+    fn transfer_new_account() -> Weight;
+    fn transfer_existing_account() -> Weight;
+}
+
+/// The weights measured by the benchmarks.
+pub struct BenchmarkedWeights;
+
+impl WeightInfo for BenchmarkedWeights {
+    fn transfer_new_account() -> Weight {
+        3105
+    }
+
+    fn transfer_existing_account() -> Weight {
+        2579
+    }
+}
+
+/// No weights, for tests.
+impl WeightInfo for () {
+    fn transfer_new_account() -> Weight {
+        0
+    }
+
+    fn transfer_existing_account() -> Weight {
+        0
+    }
+}
@@ -0,0 +1,260 @@
+//! Run the benchmarks of the pallets, and regenerate their weights files.
+//!
+//! Usage: `cargo run --release --bin benchmark -- [--pallet <name>] [--steps <n>] [--repeat <n>]
+//! [--output <directory>]`
+//!
+//! The weights of a pallet are written to `<directory>/<pallet>/weights.rs`, where `<directory>`
+//! is the `src` directory of the crate by default. Without `--pallet`, all the pallets are
+//! benchmarked.
+//!
+//! With `--block <n>`, the pallets are not benchmarked. Instead, a block of `n` transfers between
+//! distinct accounts is executed with `Runtime::execute_block` and with
+//! `Runtime::execute_block_parallel`, to compare their execution times.
+//!
+//! The benchmarks measure execution time, so they should run in release mode on an otherwise idle
+//! machine.
+
+use std::{
+    path::PathBuf,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use web3dev_blockchain_from_scratch::{
+    balances,
+    contracts,
+    escrow,
+    evm,
+    nfts,
+    proof_of_existence,
+    proxy,
+    runtime::{
+        types,
+        Runtime,
+        RuntimeCall,
+        VERSION,
+    },
+    support::{
+        self,
+        benchmarking::{
+            self,
+            BenchmarkConfig,
+            BenchmarkResult,
+            Benchmarks,
+        },
+    },
+    system,
+    treasury,
+};
+
+/// Run the benchmarks of a pallet.
+type RunBenchmarks = fn(&BenchmarkConfig) -> Result<Vec<BenchmarkResult>, String>;
+
+/// The benchmarked pallets, by the name of their module.
+const PALLETS: &[(&str, RunBenchmarks)] = &[
+    ("system", run::<system::Pallet<Runtime>>),
+    ("balances", run::<balances::Pallet<Runtime>>),
+    (
+        "proof_of_existence",
+        run::<proof_of_existence::Pallet<Runtime>>,
+    ),
+    ("nfts", run::<nfts::Pallet<Runtime>>),
+    ("escrow", run::<escrow::Pallet<Runtime>>),
+    ("proxy", run::<proxy::Pallet<Runtime>>),
+    ("treasury", run::<treasury::Pallet<Runtime>>),
+    ("contracts", run::<contracts::Pallet<Runtime>>),
+    ("evm", run::<evm::Pallet<Runtime>>),
+];
+
+/// The command line options of the benchmarks.
+struct Options {
+    pallet: Option<String>,
+    /// The number of transfers of the benchmarked block, if benchmarking the block execution.
+    block: Option<u32>,
+    config: BenchmarkConfig,
+    output: PathBuf,
+}
+
+fn main() {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!(
+                "{e}\nUsage: benchmark [--pallet <name>] [--steps <n>] [--repeat <n>] [--output \
+                 <directory>] [--block <n>]"
+            );
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(transfers) = options.block {
+        if let Err(e) = benchmark_block(transfers, options.config.repeat) {
+            eprintln!("Benchmarking the block execution failed: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let pallets = PALLETS
+        .iter()
+        .filter(|(name, _)| {
+            options
+                .pallet
+                .as_ref()
+                .is_none_or(|pallet| pallet == name)
+        })
+        .collect::<Vec<_>>();
+    if pallets.is_empty() {
+        eprintln!("Unknown pallet: {:?}", options.pallet);
+        std::process::exit(1);
+    }
+
+    for (name, run) in pallets {
+        let results = match run(&options.config) {
+            Ok(results) => results,
+            Err(e) => {
+                eprintln!("Benchmarking {name} failed: {e}");
+                std::process::exit(1);
+            }
+        };
+        for result in &results {
+            println!(
+                "{name}::{}: {} + {:?}",
+                result.name, result.base, result.slopes
+            );
+        }
+
+        let path = options
+            .output
+            .join(name)
+            .join("weights.rs");
+        if let Err(e) = std::fs::write(&path, benchmarking::weights_file(name, &results)) {
+            eprintln!("Writing {} failed: {e}", path.display());
+            std::process::exit(1);
+        }
+        println!("Wrote {}", path.display());
+    }
+}
+
+fn run<P: Benchmarks>(config: &BenchmarkConfig) -> Result<Vec<BenchmarkResult>, String> {
+    benchmarking::run::<P>(config)
+}
+
+/// Time the sequential and parallel execution of a block of `transfers` transfers, each from its
+/// own account to its own account, keeping the fastest of `repeat` runs.
+fn benchmark_block(transfers: u32, repeat: u32) -> Result<(), String> {
+    let (mut sequential, mut parallel) = (Duration::MAX, Duration::MAX);
+    for _ in 0..repeat.max(1) {
+        let (mut runtime, block) = transfers_block(transfers);
+        let start = Instant::now();
+        let expected = runtime.execute_block(block.clone())?;
+        sequential = sequential.min(start.elapsed());
+
+        let (mut runtime, _) = transfers_block(transfers);
+        let start = Instant::now();
+        let report = runtime.execute_block_parallel(block)?;
+        parallel = parallel.min(start.elapsed());
+
+        if report != expected {
+            return Err("the parallel execution has another outcome".to_string());
+        }
+        let failed = report.failed().next();
+        if let Some((_, error)) = failed {
+            return Err(format!("a transfer failed: {}", error.message()));
+        }
+    }
+    println!("execute_block: {transfers} transfers in {sequential:?}");
+    println!(
+        "execute_block_parallel: {transfers} transfers in {parallel:?}, {:.2}x on {} threads",
+        sequential.as_secs_f64() / parallel.as_secs_f64(),
+        std::thread::available_parallelism().map_or(1, usize::from),
+    );
+    Ok(())
+}
+
+/// A development runtime whose senders are funded, and the first block transferring from each of
+/// them to its receiver.
+fn transfers_block(transfers: u32) -> (Runtime, types::Block) {
+    let mut runtime = Runtime::new();
+    runtime
+        .system
+        .allow_keyless_accounts();
+    let extrinsics = (0..transfers)
+        .map(|i| {
+            let sender = format!("sender-{i}");
+            runtime
+                .balances
+                .set_balance(sender.clone(), 1_000_000);
+            support::Extrinsic {
+                caller: sender,
+                signature: None,
+                nonce: 0,
+                tip: 0,
+                era: support::Era::Immortal,
+                spec_version: VERSION.spec_version,
+                genesis_hash: [0; 32],
+                call: RuntimeCall::balances(balances::Call::transfer {
+                    to: format!("receiver-{i}"),
+                    amount: 1000,
+                }),
+            }
+        })
+        .collect::<Vec<_>>();
+    let block = types::Block {
+        header: support::Header {
+            block_number: 1,
+            extrinsics_root: support::extrinsics_root(&extrinsics),
+            ..Default::default()
+        },
+        extrinsics,
+    };
+    (runtime, block)
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        pallet: None,
+        block: None,
+        config: BenchmarkConfig::default(),
+        output: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src"),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pallet" => {
+                options.pallet = Some(
+                    args.next()
+                        .ok_or("--pallet expects the name of a pallet")?,
+                );
+            }
+            "--steps" => {
+                options.config.steps = args
+                    .next()
+                    .and_then(|steps| steps.parse().ok())
+                    .ok_or("--steps expects a number")?;
+            }
+            "--repeat" => {
+                options.config.repeat = args
+                    .next()
+                    .and_then(|repeat| repeat.parse().ok())
+                    .ok_or("--repeat expects a number")?;
+            }
+            "--block" => {
+                options.block = Some(
+                    args.next()
+                        .and_then(|transfers| transfers.parse().ok())
+                        .ok_or("--block expects a number of transfers")?,
+                );
+            }
+            "--output" => {
+                options.output = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or("--output expects a directory")?;
+            }
+            _ => return Err(format!("unknown argument {arg}")),
+        }
+    }
+    Ok(options)
+}
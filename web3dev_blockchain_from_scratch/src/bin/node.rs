@@ -0,0 +1,276 @@
+//! A development node running our `Runtime` behind a local JSON-RPC server.
+//!
+//! Usage: `cargo run --bin node -- [--dev] [--root <account>] [--port <port>] [--block-time
+//! <milliseconds>] [--import <file>] [--export <file>]`
+//!
+//! The server listens on `127.0.0.1` (port `9944` by default) for both HTTP and WebSocket
+//! connections.
+//!
+//! The root account, allowed to make the root calls of the system pallet, is given with `--root`.
+//! It must be backed by a key, like the accounts of the wallet, and is funded at genesis. With
+//! `--dev`, the chain is a development chain: the accounts `alice`, `bob` and `charlie`, which have
+//! no key, are funded at genesis and make unsigned extrinsics, so anyone can spend their funds. The
+//! root account is then by default the account of the sr25519 key of the `DEV_ROOT_PHRASE`, which
+//! the wallet can import.
+//!
+//! A new block is produced from the transaction queue every block time (`6000` ms by default).
+//!
+//! With `--export`, the chain is written to the given file, replacing its content, and every
+//! produced block is then appended to it. With `--import`, the blocks of such a file are replayed
+//! on top of the genesis state before the node starts, verifying every one of them: the node stops
+//! at the first block which diverges, and prints why, along with the outcome of its extrinsics when
+//! it executed differently.
+
+use std::{
+    fs::{
+        self,
+        File,
+    },
+    io::Write,
+    net::TcpListener,
+    path::PathBuf,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use codec::Encode;
+use web3dev_blockchain_from_scratch::{
+    crypto::{
+        self,
+        Pair,
+        Scheme,
+    },
+    node::{
+        self,
+        ImportError,
+        Node,
+    },
+    rpc::RpcServer,
+    runtime::{
+        types,
+        Runtime,
+    },
+};
+
+const DEFAULT_PORT: u16 = 9944;
+const DEFAULT_BLOCK_TIME: Duration = Duration::from_millis(6000);
+
+const DEV_ACCOUNTS: &[&str] = &["alice", "bob", "charlie"];
+const DEV_BALANCE: types::Balance = 1_000_000;
+
+/// The mnemonic phrase of the default root key of the development chains. It is public, so the
+/// root of a chain which must be kept safe is given with `--root`.
+const DEV_ROOT_PHRASE: &str =
+    "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+
+const USAGE: &str = "Usage: node [--dev] [--root <account>] [--port <port>] [--block-time \
+                     <milliseconds>] [--import <file>] [--export <file>]";
+
+/// The command line options of the node.
+struct Options {
+    /// Whether to run a development chain, with funded accounts without a key.
+    dev: bool,
+    /// The root account, backed by a key.
+    root: Option<types::AccountId>,
+    port: u16,
+    block_time: Duration,
+    /// The chain to replay before starting.
+    import: Option<PathBuf>,
+    /// Where to write the chain, and append the produced blocks.
+    export: Option<PathBuf>,
+}
+
+fn main() -> std::io::Result<()> {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    let mut runtime = Runtime::new();
+    let mut endowed = Vec::new();
+    if options.dev {
+        runtime
+            .system
+            .allow_keyless_accounts();
+        endowed.extend(
+            DEV_ACCOUNTS
+                .iter()
+                .map(|account| account.to_string()),
+        );
+    }
+    let root = options
+        .root
+        .clone()
+        .or_else(|| {
+            options.dev.then(|| {
+                Pair::from_phrase(Scheme::Sr25519, DEV_ROOT_PHRASE, "")
+                    .expect("the development phrase is valid")
+                    .account_id()
+            })
+        });
+    if let Some(root) = root {
+        println!("The root account is {root}");
+        runtime
+            .system
+            .set_root_key(root.clone());
+        endowed.push(root);
+    }
+    for account in endowed {
+        runtime
+            .balances
+            .set_balance(account, DEV_BALANCE);
+    }
+
+    let mut node = match Node::new(runtime) {
+        Ok(node) => node,
+        Err(e) => {
+            eprintln!("Runtime upgrade failed: {e}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(path) = &options.import {
+        import_chain(&mut node, path);
+    }
+    let export = match &options.export {
+        Some(path) => Some(export_chain(&node, path)?),
+        None => None,
+    };
+
+    let listener = TcpListener::bind(("127.0.0.1", options.port))?;
+    println!(
+        "JSON-RPC server listening on http://{}",
+        listener.local_addr()?
+    );
+    let server = Arc::new(RpcServer::new(node));
+
+    let producer = server.clone();
+    thread::spawn(move || {
+        node::run_block_producer(producer.node(), options.block_time, |imported| {
+            println!(
+                "Produced block #{} with {} extrinsic(s)",
+                imported
+                    .block
+                    .header
+                    .block_number,
+                imported
+                    .block
+                    .extrinsics
+                    .len()
+            );
+            for (extrinsic, error) in imported.report.failed() {
+                println!("  extrinsic #{} failed: {error:?}", extrinsic.index);
+            }
+            if let Some(mut file) = export.as_ref() {
+                if let Err(e) = file.write_all(&imported.block.encode()) {
+                    eprintln!("Block export failed: {e}");
+                }
+            }
+            producer.notify(imported);
+        })
+    });
+
+    server.serve(listener)
+}
+
+/// Write the chain of the node to `path`, replacing its content, and return the file to append the
+/// next blocks to.
+fn export_chain(node: &Node, path: &PathBuf) -> std::io::Result<File> {
+    let mut file = File::create(path)?;
+    file.write_all(&node.export_chain())?;
+    Ok(file)
+}
+
+/// Replay the chain exported to `path` on top of the state of the node, or exit at the first block
+/// which can't be imported.
+fn import_chain(node: &mut Node, path: &PathBuf) {
+    let blocks = fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| node::decode_chain(&bytes));
+    let blocks = match blocks {
+        Ok(blocks) => blocks,
+        Err(e) => {
+            eprintln!("Cannot read the chain {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    };
+    match node.import_chain(blocks) {
+        Ok(imported) => println!(
+            "Imported {imported} block(s), the best block is #{}",
+            node.best_number()
+        ),
+        Err((number, e)) => {
+            eprintln!("Block #{number} diverges from the chain: {e}");
+            if let ImportError::StateRoot { report, .. } = &e {
+                for extrinsic in &report.extrinsics {
+                    eprintln!(
+                        "  extrinsic #{} of {}: {:?}, fee {}, events {:?}",
+                        extrinsic.index,
+                        extrinsic.caller,
+                        extrinsic.result,
+                        extrinsic.fee,
+                        extrinsic.events
+                    );
+                }
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        dev: false,
+        root: None,
+        port: DEFAULT_PORT,
+        block_time: DEFAULT_BLOCK_TIME,
+        import: None,
+        export: None,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dev" => options.dev = true,
+            "--root" => {
+                let root = args
+                    .next()
+                    .ok_or("--root expects an account")?;
+                if crypto::account_public_key(&root).is_none() {
+                    return Err(format!("the root account {root} is not backed by a key"));
+                }
+                options.root = Some(root);
+            }
+            "--port" => {
+                options.port = args
+                    .next()
+                    .and_then(|port| port.parse().ok())
+                    .ok_or("--port expects a port number")?;
+            }
+            "--block-time" => {
+                options.block_time = args
+                    .next()
+                    .and_then(|millis| millis.parse().ok())
+                    .map(Duration::from_millis)
+                    .ok_or("--block-time expects a number of milliseconds")?;
+            }
+            "--import" => {
+                options.import = Some(
+                    args.next()
+                        .ok_or("--import expects a file")?
+                        .into(),
+                );
+            }
+            "--export" => {
+                options.export = Some(
+                    args.next()
+                        .ok_or("--export expects a file")?
+                        .into(),
+                );
+            }
+            _ => return Err(format!("unknown argument {arg}")),
+        }
+    }
+    Ok(options)
+}
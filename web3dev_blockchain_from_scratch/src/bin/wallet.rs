@@ -0,0 +1,298 @@
+//! A command line wallet: manages the keys of the user, and signs and submits their extrinsics.
+//!
+//! Usage: `cargo run --bin wallet -- [--keystore <directory>] [--password <password>] <command>`
+//!
+//! Commands:
+//! - `generate <name> [--scheme sr25519|ed25519]` - generate a new key, and print its mnemonic
+//!   phrase and account.
+//! - `import <name> <phrase> [--scheme sr25519|ed25519]` - add the key of an existing phrase.
+//! - `list` - print the name, scheme and account of every key.
+//! - `export <name>` - print the mnemonic phrase of a key.
+//! - `transfer <key> <to> <amount>`, `create-claim <key> <claim>` and `revoke-claim <key> <claim>`
+//!   - sign an extrinsic of the account of the key, and print it both as JSON and SCALE encoded.
+//!
+//! The keys are stored in `~/.web3dev/keystore` by default, encrypted with the password given by
+//! `--password` or the `WALLET_PASSWORD` environment variable (empty by default).
+//!
+//! The extrinsics accept the options:
+//! - `--node <url>` - the node to query and submit to, `http://127.0.0.1:9944` by default.
+//! - `--submit` - submit the extrinsic to the node instead of printing it.
+//! - `--tip <amount>`, and `--mortal <period>` to make it valid only for the next `period` blocks.
+//! - `--nonce <nonce>`, `--spec-version <version>` and `--genesis-hash <hex>`, which are otherwise
+//!   queried from the node. Giving all of them signs the extrinsic offline.
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+};
+
+use codec::Encode;
+use serde_json::{
+    json,
+    Value,
+};
+use web3dev_blockchain_from_scratch::{
+    balances,
+    crypto::{
+        self,
+        Scheme,
+    },
+    proof_of_existence,
+    rpc::client::RpcClient,
+    runtime::{
+        types,
+        RuntimeCall,
+    },
+    support,
+    wallet::{
+        self,
+        Keystore,
+    },
+};
+
+const USAGE: &str = "Usage: wallet [--keystore <directory>] [--password <password>] <command>
+
+Commands:
+  generate <name> [--scheme sr25519|ed25519]
+  import <name> <phrase> [--scheme sr25519|ed25519]
+  list
+  export <name>
+  transfer <key> <to> <amount> [options]
+  create-claim <key> <claim> [options]
+  revoke-claim <key> <claim> [options]
+
+Options of the extrinsics:
+  --node <url> --submit --tip <amount> --mortal <period>
+  --nonce <nonce> --spec-version <version> --genesis-hash <hex>";
+
+const DEFAULT_NODE: &str = "http://127.0.0.1:9944";
+
+/// The options taking a value.
+const OPTIONS: &[&str] = &[
+    "--keystore",
+    "--password",
+    "--scheme",
+    "--node",
+    "--tip",
+    "--mortal",
+    "--nonce",
+    "--spec-version",
+    "--genesis-hash",
+];
+
+/// The options without a value.
+const FLAGS: &[&str] = &["--submit"];
+
+/// The command line: the positional arguments, followed by the options.
+struct Args {
+    positional: Vec<String>,
+    options: BTreeMap<String, String>,
+}
+
+impl Args {
+    fn option<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.options
+            .get(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid value for {name}: {value}"))
+            })
+            .transpose()
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options
+            .contains_key(name)
+    }
+}
+
+fn main() {
+    let result = parse_args(std::env::args().skip(1)).and_then(|args| run(&args));
+    match result {
+        Ok(output) => println!("{output}"),
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<String, String> {
+    let keystore = Keystore::open(
+        args.option::<PathBuf>("--keystore")?
+            .unwrap_or_else(default_keystore),
+    )?;
+    let password = match args.options.get("--password") {
+        Some(password) => password.clone(),
+        None => std::env::var("WALLET_PASSWORD").unwrap_or_default(),
+    };
+    let scheme = args
+        .option::<Scheme>("--scheme")?
+        .unwrap_or(Scheme::Sr25519);
+
+    let positional = args
+        .positional
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    match positional.as_slice() {
+        ["generate", name] => {
+            let phrase = crypto::generate_phrase();
+            let key = keystore.add(name, scheme, &phrase, &password)?;
+            Ok(format!(
+                "Generated the {} key {name} of the account {}\nMnemonic phrase, to keep secret: {phrase}",
+                key.scheme, key.account
+            ))
+        }
+        ["import", name, phrase] => {
+            let key = keystore.add(name, scheme, phrase, &password)?;
+            Ok(format!(
+                "Imported the {} key {name} of the account {}",
+                key.scheme, key.account
+            ))
+        }
+        ["list"] => Ok(keystore
+            .list()?
+            .iter()
+            .map(|key| format!("{}\t{}\t{}", key.name, key.scheme, key.account))
+            .collect::<Vec<_>>()
+            .join("\n")),
+        ["export", name] => keystore.phrase(name, &password),
+        ["transfer", key, to, amount] => {
+            let call = RuntimeCall::balances(balances::Call::transfer {
+                to: to.to_string(),
+                amount: amount
+                    .parse()
+                    .map_err(|_| format!("invalid amount {amount}"))?,
+            });
+            sign_and_send(args, &keystore, key, &password, call)
+        }
+        ["create-claim", key, claim] => {
+            let call = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                claim: claim.to_string(),
+            });
+            sign_and_send(args, &keystore, key, &password, call)
+        }
+        ["revoke-claim", key, claim] => {
+            let call = RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
+                claim: claim.to_string(),
+            });
+            sign_and_send(args, &keystore, key, &password, call)
+        }
+        _ => Err("unknown command".to_string()),
+    }
+}
+
+/// Sign an extrinsic of the account of `key` making `call`, and print or submit it.
+///
+/// What the extrinsic needs from the chain, and is not given on the command line, is queried from
+/// the node.
+fn sign_and_send(
+    args: &Args,
+    keystore: &Keystore,
+    key: &str,
+    password: &str,
+    call: RuntimeCall,
+) -> Result<String, String> {
+    let pair = keystore.unlock(key, password)?;
+    let client = RpcClient::new(
+        args.options
+            .get("--node")
+            .map_or(DEFAULT_NODE, String::as_str),
+    );
+
+    let nonce = match args.option("--nonce")? {
+        Some(nonce) => nonce,
+        None => client.call("system_accountNextIndex", json!([pair.account_id()]))?,
+    };
+    let spec_version = match args.option("--spec-version")? {
+        Some(spec_version) => spec_version,
+        None => {
+            let version: Value = client.call("state_getRuntimeVersion", json!([]))?;
+            serde_json::from_value(version["spec_version"].clone()).map_err(|e| e.to_string())?
+        }
+    };
+    let genesis_hash = match args
+        .options
+        .get("--genesis-hash")
+    {
+        Some(hash) => hash.clone(),
+        None => client.call("chain_getGenesisHash", json!([]))?,
+    };
+    let mut genesis = [0; 32];
+    hex::decode_to_slice(genesis_hash.trim_start_matches("0x"), &mut genesis)
+        .map_err(|_| format!("invalid genesis hash {genesis_hash}"))?;
+    let era = match args.option::<types::BlockNumber>("--mortal")? {
+        Some(period) => {
+            let header: Value = client.call("chain_getHeader", json!([]))?;
+            let best: types::BlockNumber = serde_json::from_value(header["block_number"].clone())
+                .map_err(|e| e.to_string())?;
+            support::Era::Mortal {
+                birth: best + 1,
+                period,
+            }
+        }
+        None => support::Era::Immortal,
+    };
+
+    let extrinsic = wallet::sign(
+        &pair,
+        support::Extrinsic {
+            caller: pair.account_id(),
+            signature: None,
+            nonce,
+            tip: args
+                .option("--tip")?
+                .unwrap_or(0),
+            era,
+            spec_version,
+            genesis_hash: genesis,
+            call,
+        },
+    );
+    let encoded = format!("0x{}", hex::encode(extrinsic.encode()));
+
+    if args.flag("--submit") {
+        let status: Value = client.call("author_submitExtrinsic", json!([encoded]))?;
+        return Ok(format!(
+            "Submitted the extrinsic {nonce} of {key}: {status}"
+        ));
+    }
+    let json = serde_json::to_string_pretty(&extrinsic).map_err(|e| e.to_string())?;
+    Ok(format!("{json}\n{encoded}"))
+}
+
+/// `~/.web3dev/keystore`, or `keystore` in the current directory without a home directory.
+fn default_keystore() -> PathBuf {
+    match std::env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".web3dev/keystore"),
+        None => PathBuf::from("keystore"),
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        positional: Vec::new(),
+        options: BTreeMap::new(),
+    };
+    while let Some(arg) = args.next() {
+        if OPTIONS.contains(&arg.as_str()) {
+            let value = args
+                .next()
+                .ok_or(format!("{arg} expects a value"))?;
+            parsed
+                .options
+                .insert(arg, value);
+        } else if FLAGS.contains(&arg.as_str()) {
+            parsed
+                .options
+                .insert(arg, String::new());
+        } else if arg.starts_with("--") {
+            return Err(format!("unknown argument {arg}"));
+        } else {
+            parsed.positional.push(arg);
+        }
+    }
+    Ok(parsed)
+}
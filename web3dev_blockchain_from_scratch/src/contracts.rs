@@ -0,0 +1,806 @@
+//! The contracts pallet: accounts running user-deployed WebAssembly code.
+//!
+//! Anyone can upload WebAssembly code, which is stored by its BLAKE2b-256 hash, and instantiate
+//! contracts from it. A contract is an account, derived from its deployer, its code and a salt, so
+//! it has a balance in the currency of the config like any other account, and a storage of its
+//! own. Its code runs in the `wasmi` interpreter when it is instantiated and when it is called, see
+//! the `wasm` module for the functions it exports and the host functions it can import.
+//!
+//! The execution is metered with gas, in units of weight: every call is given a `gas_limit`,
+//! which is added to its weight so the caller pays for it in the fee, and fails once it is used
+//! up. The gas limit of a call is bounded by `MAX_GAS_LIMIT`, and the gas limits of the calls of a
+//! block by `support::MAX_BLOCK_WEIGHT`, like any other weight.
+//!
+//! The runtime lends the currency, for the balances of the contracts, like the balances pallet with
+//! `#[lend(balances)]`.
+
+use codec::Encode;
+use num::Zero;
+use serde::Serialize;
+
+use crate::{
+    support::{
+        benchmarking::{
+            ensure,
+            Benchmark,
+            Benchmarks,
+            Component,
+        },
+        storage::{
+            blake2_256,
+            StorageDoubleMap,
+            StorageMap,
+        },
+        AsCurrency,
+        Borrower,
+        Currency,
+        DispatchResult,
+        Hooks,
+        Lent,
+        OnRuntimeUpgrade,
+        Weight,
+    },
+    system,
+};
+
+pub mod wasm;
+pub mod weights;
+pub use weights::WeightInfo;
+
+/// The hash of some uploaded code.
+pub type CodeHash = [u8; 32];
+
+/// The longest storage key of a contract, in bytes.
+pub const MAX_KEY_LEN: u32 = 128;
+
+/// The longest storage value of a contract, in bytes.
+pub const MAX_VALUE_LEN: u32 = 16 * 1024;
+
+/// The address of a contract. It is the hash of its deployer, its code and a salt, so it has no
+/// secret key and only the contract can move its funds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContractAddress(pub [u8; 32]);
+
+/// The runtime is `'static`, as the interpreter only takes `'static` host functions.
+pub trait Config: system::Config<AccountId: From<ContractAddress>> + 'static {
+    /// The currency holding the balances of the contracts.
+    type Currency: Currency<Self::AccountId>;
+    /// The longest code which can be uploaded, in bytes.
+    const MAX_CODE_LEN: u32;
+    /// The most gas a call can be given, which bounds the share of a block a contract can use.
+    const MAX_GAS_LIMIT: Weight;
+    /// The weights of the calls, see the `weights` module.
+    type WeightInfo: WeightInfo;
+}
+
+/// The balances of the contracts and their callers, in the currency of the runtime `T`.
+pub type BalanceOf<T> =
+    <<T as Config>::Currency as Currency<<T as system::Config>::AccountId>>::Balance;
+
+/// The events emitted by the contracts pallet.
+#[derive(
+    macros::CloneNoBound,
+    macros::DebugNoBound,
+    macros::PartialEqNoBound,
+    macros::EqNoBound,
+    macros::TypeInfo,
+    Serialize,
+)]
+pub enum Event<T: Config> {
+    /// `uploader` stored the code `code_hash`.
+    CodeStored {
+        #[serde(with = "hex::serde")]
+        code_hash: CodeHash,
+        uploader: T::AccountId,
+    },
+    /// `deployer` instantiated the contract `contract`.
+    Instantiated {
+        deployer: T::AccountId,
+        contract: T::AccountId,
+    },
+    /// `caller` called the contract `contract`.
+    Called {
+        caller: T::AccountId,
+        contract: T::AccountId,
+    },
+    /// The contract `contract` deposited an event, whose encoding is up to the contract.
+    ContractEmitted {
+        contract: T::AccountId,
+        data: Vec<u8>,
+    },
+}
+
+#[derive(Debug)]
+#[macros::storage]
+pub struct Pallet<T: Config> {
+    /// The uploaded code, by its hash.
+    code: StorageMap<CodeHash, Vec<u8>>,
+    /// The hash of the code of every contract.
+    contracts: StorageMap<T::AccountId, CodeHash>,
+    /// The storage of every contract, by key.
+    contract_storage: StorageDoubleMap<T::AccountId, Vec<u8>, Vec<u8>>,
+    /// The currency, holding the balances of the contracts.
+    currency: Lent<T::Currency>,
+    /// The events emitted since the runtime last collected them.
+    events: Vec<Event<T>>,
+}
+
+#[macros::call]
+#[errors(
+    "the code is too long",
+    "the code is already stored",
+    "unknown code",
+    "the contract already exists",
+    "unknown contract",
+    "the gas limit is too high",
+    wasm::INVALID_CODE,
+    wasm::UNKNOWN_IMPORTS,
+    wasm::NO_ENTRY_POINTS,
+    wasm::NO_MEMORY,
+    wasm::NOT_INSTANTIABLE,
+    wasm::OUT_OF_GAS,
+    wasm::TRAPPED,
+    wasm::OUT_OF_BOUNDS,
+    wasm::KEY_TOO_LONG,
+    wasm::VALUE_TOO_LONG,
+    wasm::INVALID_ACCOUNT,
+    wasm::INVALID_BALANCE,
+    crate::support::NOT_LENT,
+    crate::support::INSUFFICIENT_BALANCE,
+    crate::support::INSUFFICIENT_RESERVED_BALANCE,
+    crate::support::OVERFLOW
+)]
+impl<T: Config> Pallet<T> {
+    /// Store `code`, so contracts can be instantiated from it. It must be a valid contract, see the
+    /// `wasm` module.
+    #[call_index(0)]
+    #[weight(<T as Config>::WeightInfo::upload_code(code.len() as u32))]
+    pub fn upload_code(&mut self, caller: T::AccountId, code: Vec<u8>) -> DispatchResult {
+        if code.len() > T::MAX_CODE_LEN as usize {
+            return Err("the code is too long");
+        }
+        let code_hash = blake2_256(&code);
+        if self
+            .code
+            .contains_key(&code_hash)
+        {
+            return Err("the code is already stored");
+        }
+        let ext = wasm::Ext::new(self, caller.clone(), caller.clone(), Zero::zero(), vec![]);
+        wasm::validate(ext, &code)?;
+        self.code
+            .insert(&code_hash, code);
+        self.deposit_event(Event::CodeStored {
+            code_hash,
+            uploader: caller,
+        });
+        Ok(())
+    }
+
+    /// Create a contract running the code `code_hash`, give it `value` from the balance of the
+    /// `caller`, and run its `deploy` function with the input `data`. Instantiating the same code
+    /// more than once takes different `salt`s.
+    #[call_index(1)]
+    #[weight(<T as Config>::WeightInfo::instantiate().saturating_add(*gas_limit))]
+    pub fn instantiate(
+        &mut self,
+        caller: T::AccountId,
+        code_hash: CodeHash,
+        value: BalanceOf<T>,
+        gas_limit: Weight,
+        data: Vec<u8>,
+        salt: Vec<u8>,
+    ) -> DispatchResult {
+        Self::ensure_gas_limit(gas_limit)?;
+        let code = self
+            .code
+            .try_get(&code_hash)
+            .ok_or("unknown code")?;
+        let contract = Self::contract_address(&caller, &code_hash, &salt);
+        if self
+            .contracts
+            .contains_key(&contract)
+        {
+            return Err("the contract already exists");
+        }
+        self.contracts
+            .insert(&contract, code_hash);
+        self.transfer_value(&caller, &contract, value)?;
+        self.deposit_event(Event::Instantiated {
+            deployer: caller.clone(),
+            contract: contract.clone(),
+        });
+        self.execute(
+            &code,
+            wasm::EntryPoint::Deploy,
+            caller,
+            contract,
+            value,
+            data,
+            gas_limit,
+        )
+    }
+
+    /// Give `value` from the balance of the `caller` to the contract `contract`, and run its `call`
+    /// function with the input `data`.
+    #[call_index(2)]
+    #[weight(<T as Config>::WeightInfo::call().saturating_add(*gas_limit))]
+    pub fn call(
+        &mut self,
+        caller: T::AccountId,
+        contract: T::AccountId,
+        value: BalanceOf<T>,
+        gas_limit: Weight,
+        data: Vec<u8>,
+    ) -> DispatchResult {
+        Self::ensure_gas_limit(gas_limit)?;
+        let code_hash = self
+            .contracts
+            .try_get(&contract)
+            .ok_or("unknown contract")?;
+        let code = self
+            .code
+            .try_get(&code_hash)
+            .ok_or("unknown code")?;
+        self.transfer_value(&caller, &contract, value)?;
+        self.deposit_event(Event::Called {
+            caller: caller.clone(),
+            contract: contract.clone(),
+        });
+        self.execute(
+            &code,
+            wasm::EntryPoint::Call,
+            caller,
+            contract,
+            value,
+            data,
+            gas_limit,
+        )
+    }
+}
+
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// The address of the contract instantiated by `deployer` from the code `code_hash` with
+    /// `salt`.
+    pub fn contract_address(
+        deployer: &T::AccountId,
+        code_hash: &CodeHash,
+        salt: &[u8],
+    ) -> T::AccountId {
+        let preimage = (b"contract", deployer, code_hash, salt).encode();
+        ContractAddress(blake2_256(&preimage)).into()
+    }
+
+    pub fn code(&self, code_hash: &CodeHash) -> Option<Vec<u8>> {
+        self.code.try_get(code_hash)
+    }
+
+    /// The hash of the code of `contract`, if it is a contract.
+    pub fn code_hash(&self, contract: &T::AccountId) -> Option<CodeHash> {
+        self.contracts
+            .try_get(contract)
+    }
+
+    /// The value stored by `contract` under `key`.
+    pub fn contract_storage(&self, contract: &T::AccountId, key: &[u8]) -> Option<Vec<u8>> {
+        self.contract_storage
+            .try_get(contract, &key.to_vec())
+    }
+
+    fn ensure_gas_limit(gas_limit: Weight) -> DispatchResult {
+        match gas_limit > T::MAX_GAS_LIMIT {
+            true => Err("the gas limit is too high"),
+            false => Ok(()),
+        }
+    }
+
+    fn transfer_value(
+        &mut self,
+        caller: &T::AccountId,
+        contract: &T::AccountId,
+        value: BalanceOf<T>,
+    ) -> DispatchResult {
+        if value.is_zero() {
+            return Ok(());
+        }
+        self.currency()?
+            .transfer(caller.clone(), contract.clone(), value)
+    }
+
+    /// Run the `entry_point` of `code` for `contract`. Loading the code costs as much gas as
+    /// uploading it, which compiles it as well.
+    #[allow(clippy::too_many_arguments)]
+    fn execute(
+        &mut self,
+        code: &[u8],
+        entry_point: wasm::EntryPoint,
+        caller: T::AccountId,
+        contract: T::AccountId,
+        value: BalanceOf<T>,
+        input: Vec<u8>,
+        gas_limit: Weight,
+    ) -> DispatchResult {
+        let gas = gas_limit
+            .checked_sub(<T as Config>::WeightInfo::upload_code(code.len() as u32))
+            .ok_or(wasm::OUT_OF_GAS)?;
+        let ext = wasm::Ext::new(self, contract, caller, value, input);
+        wasm::run(ext, code, entry_point, gas)
+    }
+
+    /// The currency lent by the runtime.
+    fn currency(&mut self) -> Result<&mut T::Currency, &'static str> {
+        self.currency.get_mut()
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    /// Take the events emitted since the last call, so the runtime can record them.
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<T: Config> Borrower<T::Currency, AsCurrency> for Pallet<T> {
+    fn lent(&mut self) -> &mut Lent<T::Currency> {
+        &mut self.currency
+    }
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+/// The uploaded code has `c` bytes, of constants pushed and dropped by its `deploy` function. The
+/// contracts are instantiated and called with a minimal code, whose load is part of the weight.
+impl<T: Config> Benchmarks for Pallet<T>
+where
+    T::AccountId: From<&'static str>,
+    T::Currency: Default,
+    BalanceOf<T>: From<u32>,
+{
+    /// Whether the minimal code is instantiated.
+    type Setup = bool;
+
+    /// A pallet in which the deployer can pay, with the minimal code uploaded and instantiated
+    /// if `instantiate`.
+    fn setup(instantiate: bool) -> Self {
+        let mut currency = T::Currency::default();
+        currency
+            .deposit_creating("deployer".into(), u32::MAX.into())
+            .expect("the account is empty");
+        let mut pallet = Pallet::new();
+        pallet.currency.lend(currency);
+        let code = wasm::benchmark_code(0);
+        let code_hash = blake2_256(&code);
+        pallet
+            .upload_code("deployer".into(), code)
+            .expect("the code is valid");
+        if instantiate {
+            pallet
+                .instantiate(
+                    "deployer".into(),
+                    code_hash,
+                    1000u32.into(),
+                    T::MAX_GAS_LIMIT,
+                    vec![],
+                    vec![],
+                )
+                .expect("the deployer can pay the value");
+        }
+        pallet
+    }
+
+    fn benchmarks() -> Vec<Benchmark<Self>> {
+        let code = Component {
+            name: "c",
+            min: 0,
+            max: T::MAX_CODE_LEN / 2,
+        };
+        vec![
+            Benchmark {
+                name: "upload_code",
+                components: vec![code],
+                setup: |c| {
+                    let call = Call::upload_code {
+                        code: wasm::benchmark_code(c[0]),
+                    };
+                    (Pallet::new(), "deployer".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.code.iter().count() == 1),
+            },
+            Benchmark {
+                name: "instantiate",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::instantiate {
+                        code_hash: blake2_256(&wasm::benchmark_code(0)),
+                        value: 1000u32.into(),
+                        gas_limit: T::MAX_GAS_LIMIT,
+                        data: vec![],
+                        salt: vec![],
+                    };
+                    (Self::setup(false), "deployer".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(
+                        pallet
+                            .contracts
+                            .iter()
+                            .count()
+                            == 1,
+                    )
+                },
+            },
+            Benchmark {
+                name: "call",
+                components: vec![],
+                setup: |_| {
+                    let contract = Pallet::<T>::contract_address(
+                        &"deployer".into(),
+                        &blake2_256(&wasm::benchmark_code(0)),
+                        &[],
+                    );
+                    let call = Call::call {
+                        contract,
+                        value: 1000u32.into(),
+                        gas_limit: T::MAX_GAS_LIMIT,
+                        data: vec![],
+                    };
+                    (Self::setup(true), "deployer".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(matches!(pallet.events.last(), Some(Event::Called { .. })))
+                },
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances,
+        support::benchmarking::{
+            self,
+            BenchmarkConfig,
+        },
+    };
+
+    struct TestConfig;
+
+    impl system::Config for TestConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type SystemWeightInfo = ();
+    }
+
+    impl balances::Config for TestConfig {
+        type Balance = u128;
+        type WeightInfo = ();
+    }
+
+    impl Config for TestConfig {
+        type Currency = balances::Pallet<TestConfig>;
+        const MAX_CODE_LEN: u32 = 1024;
+        const MAX_GAS_LIMIT: Weight = 1_000_000;
+        type WeightInfo = ();
+    }
+
+    /// A contract which stores its input under the key `last` when deployed and called, and emits
+    /// it as an event when called, after paying 10 to bob.
+    const STORE_AND_PAY: &str = r#"
+        (module
+            (import "env" "input" (func $input (param i32 i32) (result i32)))
+            (import "env" "storage_set" (func $storage_set (param i32 i32 i32 i32)))
+            (import "env" "transfer" (func $transfer (param i32 i32 i32 i32)))
+            (import "env" "deposit_event" (func $deposit_event (param i32 i32)))
+            (memory (export "memory") 1)
+            ;; The key, then "bob" and 10 encoded.
+            (data (i32.const 0) "last")
+            (data (i32.const 16) "\0cbob")
+            (data (i32.const 32) "\0a\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00")
+            (func $store (result i32)
+                (local $len i32)
+                (local.set $len (call $input (i32.const 64) (i32.const 64)))
+                (call $storage_set (i32.const 0) (i32.const 4) (i32.const 64) (local.get $len))
+                (local.get $len))
+            (func (export "deploy")
+                (drop (call $store)))
+            (func (export "call")
+                (local $len i32)
+                (local.set $len (call $store))
+                (call $transfer (i32.const 16) (i32.const 4) (i32.const 32) (i32.const 16))
+                (call $deposit_event (i32.const 64) (local.get $len))))
+    "#;
+
+    /// A contract which loops forever when called.
+    const LOOP: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "deploy"))
+            (func (export "call")
+                (loop $forever (br $forever))))
+    "#;
+
+    fn alice() -> String {
+        "alice".to_string()
+    }
+
+    fn bob() -> String {
+        "bob".to_string()
+    }
+
+    fn code(wat: &str) -> Vec<u8> {
+        wat::parse_str(wat).unwrap()
+    }
+
+    /// The pallet lent a balances pallet in which alice has 100, with the code of `wat` uploaded.
+    fn new_pallet(wat: &str) -> (Pallet<TestConfig>, CodeHash) {
+        let mut currency = balances::Pallet::new();
+        currency.set_balance(alice(), 100);
+        let mut pallet = Pallet::new();
+        pallet.currency.lend(currency);
+        let code = code(wat);
+        let code_hash = blake2_256(&code);
+        pallet
+            .upload_code(alice(), code)
+            .unwrap();
+        pallet.take_events();
+        (pallet, code_hash)
+    }
+
+    fn balance(pallet: &mut Pallet<TestConfig>, who: String) -> u128 {
+        pallet
+            .currency()
+            .unwrap()
+            .balance(who)
+    }
+
+    #[test]
+    fn code_is_stored_by_hash() {
+        let (mut pallet, code_hash) = new_pallet(LOOP);
+        assert_eq!(pallet.code(&code_hash), Some(code(LOOP)));
+        assert_eq!(
+            pallet.upload_code(alice(), code(LOOP)),
+            Err("the code is already stored")
+        );
+
+        let invalid = [
+            (vec![0; 8], "invalid code"),
+            (vec![0; 2000], "the code is too long"),
+            (
+                code(r#"(module (memory (export "memory") 1) (func (export "call")))"#),
+                "the code doesn't export its entry points",
+            ),
+            (
+                code(r#"(module (func (export "deploy")) (func (export "call")))"#),
+                "the code doesn't export its memory",
+            ),
+            (
+                code(
+                    r#"(module
+                        (import "env" "exit" (func))
+                        (memory (export "memory") 1)
+                        (func (export "deploy"))
+                        (func (export "call")))"#,
+                ),
+                "the code imports unknown functions",
+            ),
+            (
+                code(
+                    r#"(module
+                        (import "env" "transfer" (func))
+                        (memory (export "memory") 1)
+                        (func (export "deploy"))
+                        (func (export "call")))"#,
+                ),
+                "the code can't be instantiated",
+            ),
+            (
+                code(
+                    r#"(module
+                        (memory (export "memory") 17)
+                        (func (export "deploy"))
+                        (func (export "call")))"#,
+                ),
+                "the code can't be instantiated",
+            ),
+            (
+                code(
+                    r#"(module
+                        (memory (export "memory") 1)
+                        (func (export "deploy") (drop (f32.const 1)))
+                        (func (export "call")))"#,
+                ),
+                "invalid code",
+            ),
+        ];
+        for (code, error) in invalid {
+            assert_eq!(pallet.upload_code(alice(), code), Err(error));
+        }
+        assert!(pallet
+            .take_events()
+            .is_empty());
+    }
+
+    #[test]
+    fn contracts_have_their_own_storage_and_balance() {
+        let (mut pallet, code_hash) = new_pallet(STORE_AND_PAY);
+        assert_eq!(
+            pallet.call(alice(), bob(), 0, 100_000, vec![]),
+            Err("unknown contract")
+        );
+        assert_eq!(
+            pallet.instantiate(alice(), [0; 32], 0, 100_000, vec![], vec![]),
+            Err("unknown code")
+        );
+
+        let first = Pallet::<TestConfig>::contract_address(&alice(), &code_hash, b"first");
+        let second = Pallet::<TestConfig>::contract_address(&alice(), &code_hash, b"second");
+        assert_ne!(first, second);
+        for (salt, value) in [(&b"first"[..], 30), (b"second", 20)] {
+            assert_eq!(
+                pallet.instantiate(
+                    alice(),
+                    code_hash,
+                    value,
+                    100_000,
+                    salt.to_vec(),
+                    salt.to_vec()
+                ),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            pallet.instantiate(alice(), code_hash, 0, 100_000, vec![], b"first".to_vec()),
+            Err("the contract already exists")
+        );
+        assert_eq!(pallet.code_hash(&first), Some(code_hash));
+        assert_eq!(
+            pallet.contract_storage(&first, b"last"),
+            Some(b"first".to_vec())
+        );
+        assert_eq!(
+            pallet.contract_storage(&second, b"last"),
+            Some(b"second".to_vec())
+        );
+        assert_eq!(balance(&mut pallet, first.clone()), 30);
+        assert_eq!(balance(&mut pallet, alice()), 50);
+        pallet.take_events();
+
+        assert_eq!(
+            pallet.call(alice(), first.clone(), 5, 100_000, b"hello".to_vec()),
+            Ok(())
+        );
+        assert_eq!(
+            pallet.contract_storage(&first, b"last"),
+            Some(b"hello".to_vec())
+        );
+        assert_eq!(
+            pallet.contract_storage(&second, b"last"),
+            Some(b"second".to_vec())
+        );
+        assert_eq!(balance(&mut pallet, first.clone()), 25);
+        assert_eq!(balance(&mut pallet, bob()), 10);
+        assert_eq!(
+            pallet.take_events(),
+            vec![
+                Event::Called {
+                    caller: alice(),
+                    contract: first.clone(),
+                },
+                Event::ContractEmitted {
+                    contract: first,
+                    data: b"hello".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn the_execution_is_metered() {
+        let (mut pallet, code_hash) = new_pallet(LOOP);
+        assert_eq!(
+            pallet.instantiate(alice(), code_hash, 0, 1_000_001, vec![], vec![]),
+            Err("the gas limit is too high")
+        );
+        assert_eq!(
+            pallet.instantiate(alice(), code_hash, 0, 100_000, vec![], vec![]),
+            Ok(())
+        );
+        let contract = Pallet::<TestConfig>::contract_address(&alice(), &code_hash, &[]);
+        assert_eq!(
+            pallet.call(alice(), contract, 0, 1_000_000, vec![]),
+            Err(wasm::OUT_OF_GAS)
+        );
+
+        // The host functions use gas too.
+        let (mut pallet, code_hash) = new_pallet(STORE_AND_PAY);
+        assert_eq!(
+            pallet.instantiate(
+                alice(),
+                code_hash,
+                0,
+                wasm::HOST_FUNCTION_GAS,
+                vec![],
+                vec![]
+            ),
+            Err(wasm::OUT_OF_GAS)
+        );
+    }
+
+    #[test]
+    fn failing_host_functions_trap_the_contract() {
+        let (mut pallet, code_hash) = new_pallet(STORE_AND_PAY);
+        pallet
+            .instantiate(alice(), code_hash, 5, 100_000, vec![], vec![])
+            .unwrap();
+        let contract = Pallet::<TestConfig>::contract_address(&alice(), &code_hash, &[]);
+        assert_eq!(
+            pallet.call(alice(), contract, 0, 100_000, vec![]),
+            Err("Insufficient balance")
+        );
+
+        let (mut pallet, code_hash) = new_pallet(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "deploy") unreachable)
+                (func (export "call")))"#,
+        );
+        assert_eq!(
+            pallet.instantiate(alice(), code_hash, 0, 100_000, vec![], vec![]),
+            Err("the contract trapped")
+        );
+
+        // The data of an event is read from the memory, whose end it can't go past.
+        for (ptr, len) in [(65_532, 8), (0, -1)] {
+            let (mut pallet, code_hash) = new_pallet(&format!(
+                r#"(module
+                    (import "env" "deposit_event" (func $deposit_event (param i32 i32)))
+                    (memory (export "memory") 1)
+                    (func (export "deploy")
+                        (call $deposit_event (i32.const {ptr}) (i32.const {len})))
+                    (func (export "call")))"#
+            ));
+            assert_eq!(
+                pallet.instantiate(alice(), code_hash, 0, 100_000, vec![], vec![]),
+                Err(wasm::OUT_OF_BOUNDS)
+            );
+        }
+    }
+
+    #[test]
+    fn calls_fail_without_the_lent_pallets() {
+        let mut pallet = Pallet::<TestConfig>::new();
+        let code = wasm::benchmark_code(0);
+        let code_hash = blake2_256(&code);
+        pallet
+            .upload_code(alice(), code)
+            .unwrap();
+        assert_eq!(
+            pallet.instantiate(alice(), code_hash, 10, 100_000, vec![], vec![]),
+            Err("the pallet was not lent by the runtime")
+        );
+    }
+
+    #[test]
+    fn benchmarks_succeed() {
+        let config = BenchmarkConfig {
+            steps: 2,
+            repeat: 1,
+        };
+        let results = benchmarking::run::<Pallet<TestConfig>>(&config).unwrap();
+        assert_eq!(results.len(), 3);
+    }
+}
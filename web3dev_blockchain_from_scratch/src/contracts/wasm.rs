@@ -0,0 +1,516 @@
+//! The execution of the contracts, in the `wasmi` interpreter.
+//!
+//! A contract is a WebAssembly module, without floating point instructions, which exports its
+//! `memory` and the functions `deploy`, run when it is instantiated, and `call`, run when it is
+//! called. Both take no parameters and return nothing: a contract reads its input with a host
+//! function, and fails by trapping, like with `unreachable`, which discards all its changes.
+//!
+//! The contract can import these host functions from the `env` module, whose parameters are `i32`
+//! pointers into its memory and lengths in bytes:
+//! - `input(out_ptr, out_len) -> len` - the input of the call.
+//! - `caller(out_ptr, out_len) -> len` - the account which called the contract.
+//! - `value_transferred(out_ptr, out_len) -> len` - the value given to the contract by the call.
+//! - `balance(out_ptr, out_len) -> len` - the balance of the contract.
+//! - `storage_get(key_ptr, key_len, out_ptr, out_len) -> len` - the value stored under a key, or
+//!   `ABSENT` if there is none.
+//! - `storage_set(key_ptr, key_len, value_ptr, value_len)` - store a value under a key, of at most
+//!   `MAX_KEY_LEN` and `MAX_VALUE_LEN` bytes.
+//! - `storage_clear(key_ptr, key_len)` - remove the value stored under a key.
+//! - `transfer(to_ptr, to_len, amount_ptr, amount_len)` - transfer some of the balance of the
+//!   contract to an account, which traps if the contract can't pay it.
+//! - `deposit_event(data_ptr, data_len)` - emit a `ContractEmitted` event with the data.
+//!
+//! Accounts and balances are SCALE-encoded. The functions returning a `len` copy as much of their
+//! output as fits in the `out_len` bytes at `out_ptr`, and return its full length.
+//!
+//! Every instruction costs a unit of gas, and every call of a host function `HOST_FUNCTION_GAS`,
+//! plus `GAS_PER_BYTE` for every byte it reads from or writes to the memory of the contract.
+
+use std::fmt;
+
+use codec::{
+    Decode,
+    Encode,
+};
+use wasmi::{
+    core::{
+        HostError,
+        TrapCode,
+    },
+    Caller,
+    Engine,
+    Extern,
+    ExternType,
+    Linker,
+    Module,
+    Store,
+    StoreLimits,
+    StoreLimitsBuilder,
+};
+
+use super::{
+    BalanceOf,
+    Config,
+    Event,
+    Pallet,
+    MAX_KEY_LEN,
+    MAX_VALUE_LEN,
+};
+use crate::support::{
+    Currency,
+    DispatchResult,
+    Weight,
+};
+
+/// The gas of a call of a host function.
+pub const HOST_FUNCTION_GAS: Weight = 1_000;
+
+/// The gas of every byte a host function reads from or writes to the memory of a contract.
+pub const GAS_PER_BYTE: Weight = 1;
+
+/// The length returned by `storage_get` when there is no value under the key.
+pub const ABSENT: u32 = u32::MAX;
+
+/// The largest memory of a contract, in bytes.
+pub const MAX_MEMORY: usize = 16 * 64 * 1024;
+
+/// The error of an execution which used up its gas limit.
+pub const OUT_OF_GAS: &str = "out of gas";
+
+/// The errors of code which is not a valid contract.
+pub const INVALID_CODE: &str = "invalid code";
+pub const UNKNOWN_IMPORTS: &str = "the code imports unknown functions";
+pub const NO_ENTRY_POINTS: &str = "the code doesn't export its entry points";
+pub const NO_MEMORY: &str = "the code doesn't export its memory";
+pub const NOT_INSTANTIABLE: &str = "the code can't be instantiated";
+
+/// The errors which stop a contract while it runs.
+pub const TRAPPED: &str = "the contract trapped";
+pub const OUT_OF_BOUNDS: &str = "the contract accessed memory out of bounds";
+pub const KEY_TOO_LONG: &str = "the storage key is too long";
+pub const VALUE_TOO_LONG: &str = "the storage value is too long";
+pub const INVALID_ACCOUNT: &str = "invalid account";
+pub const INVALID_BALANCE: &str = "invalid balance";
+
+/// The host functions which a contract can import from the `env` module.
+const HOST_FUNCTIONS: &[&str] = &[
+    "input",
+    "caller",
+    "value_transferred",
+    "balance",
+    "storage_get",
+    "storage_set",
+    "storage_clear",
+    "transfer",
+    "deposit_event",
+];
+
+/// A function exported by a contract.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryPoint {
+    /// Run when the contract is instantiated.
+    Deploy,
+    /// Run when the contract is called.
+    Call,
+}
+
+impl EntryPoint {
+    fn name(self) -> &'static str {
+        match self {
+            EntryPoint::Deploy => "deploy",
+            EntryPoint::Call => "call",
+        }
+    }
+}
+
+/// What the host functions of a running contract can access.
+pub struct Ext<'a, T: Config> {
+    pallet: &'a mut Pallet<T>,
+    contract: T::AccountId,
+    caller: T::AccountId,
+    value: BalanceOf<T>,
+    input: Vec<u8>,
+    limits: StoreLimits,
+}
+
+impl<'a, T: Config> Ext<'a, T> {
+    pub fn new(
+        pallet: &'a mut Pallet<T>,
+        contract: T::AccountId,
+        caller: T::AccountId,
+        value: BalanceOf<T>,
+        input: Vec<u8>,
+    ) -> Self {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY)
+            .instances(1)
+            .memories(1)
+            .tables(1)
+            .build();
+        Self {
+            pallet,
+            contract,
+            caller,
+            value,
+            input,
+            limits,
+        }
+    }
+}
+
+/// An error of a host function, which traps the contract.
+#[derive(Debug)]
+struct Trap(&'static str);
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl HostError for Trap {}
+
+fn trap(message: &'static str) -> wasmi::Error {
+    wasmi::Error::host(Trap(message))
+}
+
+/// The message of the error which stopped a contract.
+fn error_message(error: wasmi::Error) -> &'static str {
+    if let Some(Trap(message)) = error.downcast_ref::<Trap>() {
+        return message;
+    }
+    match error.as_trap_code() {
+        Some(TrapCode::OutOfFuel) => OUT_OF_GAS,
+        _ => TRAPPED,
+    }
+}
+
+fn engine() -> Engine {
+    let mut config = wasmi::Config::default();
+    config
+        .consume_fuel(true)
+        .floats(false);
+    Engine::new(&config)
+}
+
+/// Check that `code` is a contract: that it only imports host functions, with their signature,
+/// and exports its entry points and its memory, within `MAX_MEMORY`.
+pub fn validate<T: Config>(ext: Ext<'_, T>, code: &[u8]) -> DispatchResult {
+    let engine = engine();
+    let module = Module::new(&engine, code).map_err(|_| INVALID_CODE)?;
+    let imports_host_functions = module
+        .imports()
+        .all(|import| import.module() == "env" && HOST_FUNCTIONS.contains(&import.name()));
+    if !imports_host_functions {
+        return Err(UNKNOWN_IMPORTS);
+    }
+    for entry_point in [EntryPoint::Deploy, EntryPoint::Call] {
+        match module.get_export(entry_point.name()) {
+            Some(ExternType::Func(ty)) if ty.params().is_empty() && ty.results().is_empty() => {}
+            _ => return Err(NO_ENTRY_POINTS),
+        }
+    }
+    if !matches!(module.get_export("memory"), Some(ExternType::Memory(_))) {
+        return Err(NO_MEMORY);
+    }
+    let mut store = Store::new(&engine, ext);
+    store.limiter(|ext| &mut ext.limits);
+    linker(&engine)
+        .instantiate(&mut store, &module)
+        .map_err(|_| NOT_INSTANTIABLE)?;
+    Ok(())
+}
+
+/// Run the `entry_point` of `code`, with `gas` to use.
+pub fn run<T: Config>(
+    ext: Ext<'_, T>,
+    code: &[u8],
+    entry_point: EntryPoint,
+    gas: Weight,
+) -> DispatchResult {
+    let engine = engine();
+    let module = Module::new(&engine, code).map_err(|_| INVALID_CODE)?;
+    let mut store = Store::new(&engine, ext);
+    store.limiter(|ext| &mut ext.limits);
+    store
+        .set_fuel(gas)
+        .map_err(|_| OUT_OF_GAS)?;
+    let instance = linker(&engine)
+        .instantiate(&mut store, &module)
+        .and_then(|instance| instance.start(&mut store))
+        .map_err(error_message)?;
+    instance
+        .get_typed_func::<(), ()>(&store, entry_point.name())
+        .map_err(|_| NO_ENTRY_POINTS)?
+        .call(&mut store, ())
+        .map_err(error_message)
+}
+
+fn linker<'a, T: Config>(engine: &Engine) -> Linker<Ext<'a, T>> {
+    let mut linker = Linker::new(engine);
+    linker
+        .func_wrap("env", "input", input::<T>)
+        .and_then(|linker| linker.func_wrap("env", "caller", caller::<T>))
+        .and_then(|linker| linker.func_wrap("env", "value_transferred", value_transferred::<T>))
+        .and_then(|linker| linker.func_wrap("env", "balance", balance::<T>))
+        .and_then(|linker| linker.func_wrap("env", "storage_get", storage_get::<T>))
+        .and_then(|linker| linker.func_wrap("env", "storage_set", storage_set::<T>))
+        .and_then(|linker| linker.func_wrap("env", "storage_clear", storage_clear::<T>))
+        .and_then(|linker| linker.func_wrap("env", "transfer", transfer::<T>))
+        .and_then(|linker| linker.func_wrap("env", "deposit_event", deposit_event::<T>))
+        .expect("the host functions have different names");
+    linker
+}
+
+/// Use `gas` from what is left to the contract.
+fn charge<T: Config>(caller: &mut Caller<'_, Ext<'_, T>>, gas: Weight) -> Result<(), wasmi::Error> {
+    let fuel = caller.get_fuel()?;
+    match fuel.checked_sub(gas) {
+        Some(fuel) => Ok(caller.set_fuel(fuel)?),
+        None => {
+            caller.set_fuel(0)?;
+            Err(trap(OUT_OF_GAS))
+        }
+    }
+}
+
+/// Read `len` bytes at `ptr` in the memory of the contract. The bytes must be in the memory, so
+/// nothing more than the memory is ever allocated, whatever `len` the contract asks for.
+fn read<T: Config>(
+    caller: &mut Caller<'_, Ext<'_, T>>,
+    ptr: u32,
+    len: u32,
+) -> Result<Vec<u8>, wasmi::Error> {
+    let memory = memory(caller)?;
+    let start = ptr as usize;
+    let end = start
+        .checked_add(len as usize)
+        .filter(|end| *end <= memory.data(&*caller).len())
+        .ok_or_else(|| trap(OUT_OF_BOUNDS))?;
+    charge(caller, GAS_PER_BYTE.saturating_mul(len.into()))?;
+    Ok(memory.data(&*caller)[start..end].to_vec())
+}
+
+/// Copy as much of `output` as fits in the `out_len` bytes at `out_ptr` in the memory of the
+/// contract, and return its full length.
+fn write<T: Config>(
+    caller: &mut Caller<'_, Ext<'_, T>>,
+    out_ptr: u32,
+    out_len: u32,
+    output: &[u8],
+) -> Result<u32, wasmi::Error> {
+    let len = output
+        .len()
+        .min(out_len as usize);
+    charge(caller, GAS_PER_BYTE.saturating_mul(len as Weight))?;
+    memory(caller)?
+        .write(&mut *caller, out_ptr as usize, &output[..len])
+        .map_err(|_| trap(OUT_OF_BOUNDS))?;
+    Ok(output.len() as u32)
+}
+
+fn memory<T: Config>(caller: &Caller<'_, Ext<'_, T>>) -> Result<wasmi::Memory, wasmi::Error> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| trap(NO_MEMORY))
+}
+
+/// Read a key of at most `MAX_KEY_LEN` bytes.
+fn read_key<T: Config>(
+    caller: &mut Caller<'_, Ext<'_, T>>,
+    key_ptr: u32,
+    key_len: u32,
+) -> Result<Vec<u8>, wasmi::Error> {
+    if key_len > MAX_KEY_LEN {
+        return Err(trap(KEY_TOO_LONG));
+    }
+    read(caller, key_ptr, key_len)
+}
+
+fn input<T: Config>(
+    mut caller: Caller<'_, Ext<'_, T>>,
+    out_ptr: u32,
+    out_len: u32,
+) -> Result<u32, wasmi::Error> {
+    charge(&mut caller, HOST_FUNCTION_GAS)?;
+    let input = std::mem::take(&mut caller.data_mut().input);
+    let len = write(&mut caller, out_ptr, out_len, &input);
+    caller.data_mut().input = input;
+    len
+}
+
+fn caller<T: Config>(
+    mut caller: Caller<'_, Ext<'_, T>>,
+    out_ptr: u32,
+    out_len: u32,
+) -> Result<u32, wasmi::Error> {
+    charge(&mut caller, HOST_FUNCTION_GAS)?;
+    let account = caller.data().caller.encode();
+    write(&mut caller, out_ptr, out_len, &account)
+}
+
+fn value_transferred<T: Config>(
+    mut caller: Caller<'_, Ext<'_, T>>,
+    out_ptr: u32,
+    out_len: u32,
+) -> Result<u32, wasmi::Error> {
+    charge(&mut caller, HOST_FUNCTION_GAS)?;
+    let value = caller.data().value.encode();
+    write(&mut caller, out_ptr, out_len, &value)
+}
+
+fn balance<T: Config>(
+    mut caller: Caller<'_, Ext<'_, T>>,
+    out_ptr: u32,
+    out_len: u32,
+) -> Result<u32, wasmi::Error> {
+    charge(&mut caller, HOST_FUNCTION_GAS)?;
+    let ext = caller.data_mut();
+    let contract = ext.contract.clone();
+    let balance = ext
+        .pallet
+        .currency()
+        .map_err(trap)?
+        .free_balance(contract)
+        .encode();
+    write(&mut caller, out_ptr, out_len, &balance)
+}
+
+fn storage_get<T: Config>(
+    mut caller: Caller<'_, Ext<'_, T>>,
+    key_ptr: u32,
+    key_len: u32,
+    out_ptr: u32,
+    out_len: u32,
+) -> Result<u32, wasmi::Error> {
+    charge(&mut caller, HOST_FUNCTION_GAS)?;
+    let key = read_key(&mut caller, key_ptr, key_len)?;
+    let ext = caller.data();
+    match ext
+        .pallet
+        .contract_storage
+        .try_get(&ext.contract, &key)
+    {
+        Some(value) => write(&mut caller, out_ptr, out_len, &value),
+        None => Ok(ABSENT),
+    }
+}
+
+fn storage_set<T: Config>(
+    mut caller: Caller<'_, Ext<'_, T>>,
+    key_ptr: u32,
+    key_len: u32,
+    value_ptr: u32,
+    value_len: u32,
+) -> Result<(), wasmi::Error> {
+    charge(&mut caller, HOST_FUNCTION_GAS)?;
+    if value_len > MAX_VALUE_LEN {
+        return Err(trap(VALUE_TOO_LONG));
+    }
+    let key = read_key(&mut caller, key_ptr, key_len)?;
+    let value = read(&mut caller, value_ptr, value_len)?;
+    let ext = caller.data_mut();
+    ext.pallet
+        .contract_storage
+        .insert(&ext.contract, &key, value);
+    Ok(())
+}
+
+fn storage_clear<T: Config>(
+    mut caller: Caller<'_, Ext<'_, T>>,
+    key_ptr: u32,
+    key_len: u32,
+) -> Result<(), wasmi::Error> {
+    charge(&mut caller, HOST_FUNCTION_GAS)?;
+    let key = read_key(&mut caller, key_ptr, key_len)?;
+    let ext = caller.data_mut();
+    ext.pallet
+        .contract_storage
+        .remove(&ext.contract, &key);
+    Ok(())
+}
+
+fn transfer<T: Config>(
+    mut caller: Caller<'_, Ext<'_, T>>,
+    to_ptr: u32,
+    to_len: u32,
+    amount_ptr: u32,
+    amount_len: u32,
+) -> Result<(), wasmi::Error> {
+    charge(&mut caller, HOST_FUNCTION_GAS)?;
+    let to = read(&mut caller, to_ptr, to_len)?;
+    let to = T::AccountId::decode(&mut &to[..]).map_err(|_| trap(INVALID_ACCOUNT))?;
+    let amount = read(&mut caller, amount_ptr, amount_len)?;
+    let amount = <BalanceOf<T>>::decode(&mut &amount[..]).map_err(|_| trap(INVALID_BALANCE))?;
+    let ext = caller.data_mut();
+    let contract = ext.contract.clone();
+    ext.pallet
+        .currency()
+        .and_then(|currency| currency.transfer(contract, to, amount))
+        .map_err(trap)
+}
+
+fn deposit_event<T: Config>(
+    mut caller: Caller<'_, Ext<'_, T>>,
+    data_ptr: u32,
+    data_len: u32,
+) -> Result<(), wasmi::Error> {
+    charge(&mut caller, HOST_FUNCTION_GAS)?;
+    let data = read(&mut caller, data_ptr, data_len)?;
+    let ext = caller.data_mut();
+    let contract = ext.contract.clone();
+    ext.pallet
+        .deposit_event(Event::ContractEmitted { contract, data });
+    Ok(())
+}
+
+/// A contract of about `len` bytes, whose `deploy` function pushes and drops constants, for the
+/// benchmarks.
+pub fn benchmark_code(len: u32) -> Vec<u8> {
+    fn leb128(mut n: usize, out: &mut Vec<u8>) {
+        loop {
+            let byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    fn section(id: u8, content: &[u8], out: &mut Vec<u8>) {
+        out.push(id);
+        leb128(content.len(), out);
+        out.extend_from_slice(content);
+    }
+
+    // The body of `deploy`, without locals, and then the one of `call`.
+    let mut deploy = vec![0];
+    for _ in 0..len / 3 {
+        // i32.const 0, drop
+        deploy.extend([0x41, 0x00, 0x1a]);
+    }
+    deploy.push(0x0b);
+    let mut bodies = vec![2];
+    leb128(deploy.len(), &mut bodies);
+    bodies.extend(deploy);
+    bodies.extend([2, 0, 0x0b]);
+
+    let mut exports = vec![3];
+    for (name, kind, index) in [("memory", 2, 0), ("deploy", 0, 0), ("call", 0, 1)] {
+        leb128(name.len(), &mut exports);
+        exports.extend(name.as_bytes());
+        exports.extend([kind, index]);
+    }
+
+    let mut code = b"\0asm\x01\0\0\0".to_vec();
+    // The type `() -> ()`, of the two functions, and a memory of one page.
+    section(1, &[1, 0x60, 0, 0], &mut code);
+    section(3, &[2, 0, 0], &mut code);
+    section(5, &[1, 0, 1], &mut code);
+    section(7, &exports, &mut code);
+    section(10, &bodies, &mut code);
+    code
+}
@@ -0,0 +1,45 @@
+//! The weights of the `contracts` pallet, measured by its benchmarks.
+//!
+//! Generated by `cargo run --release --bin benchmark`, do not edit by hand. The unit of weight is
+//! one nanosecond of execution on the machine which ran the benchmarks.
+
+use crate::support::Weight;
+
+/// The weights of the callable functions of the pallet.
+pub trait WeightInfo {
+    fn upload_code(c: u32) -> Weight;
+    fn instantiate() -> Weight;
+    fn call() -> Weight;
+}
+
+/// The weights measured by the benchmarks.
+pub struct BenchmarkedWeights;
+
+impl WeightInfo for BenchmarkedWeights {
+    fn upload_code(c: u32) -> Weight {
+        10053 + 9 * Weight::from(c)
+    }
+
+    fn instantiate() -> Weight {
+        13636
+    }
+
+    fn call() -> Weight {
+        11537
+    }
+}
+
+/// No weights, for tests.
+impl WeightInfo for () {
+    fn upload_code(_c: u32) -> Weight {
+        0
+    }
+
+    fn instantiate() -> Weight {
+        0
+    }
+
+    fn call() -> Weight {
+        0
+    }
+}
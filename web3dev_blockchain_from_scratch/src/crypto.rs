@@ -0,0 +1,258 @@
+//! The keys and signatures of the accounts.
+//!
+//! An account backed by a key is identified by its public key, as a `0x` prefixed hex string. Keys
+//! are either sr25519 (schnorrkel) or ed25519 keys, derived from a BIP-39 mnemonic phrase: the
+//! first 32 bytes of the seed of the phrase are the secret seed of the key.
+//!
+//! Extrinsics of such accounts carry a `MultiSignature` of their `signing_payload`, which is
+//! checked by the runtime before they are dispatched.
+
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+use codec::{
+    Decode,
+    Encode,
+};
+use ed25519_dalek::{
+    Signer,
+    Verifier,
+};
+use rand::RngCore;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    contracts::ContractAddress,
+    evm::EvmAddress,
+    support::PalletId,
+};
+
+/// The context of the sr25519 signatures, so they can't be used by another protocol.
+const SIGNING_CONTEXT: &[u8] = b"web3dev";
+
+/// The length of the public key of an account.
+pub const PUBLIC_KEY_LENGTH: usize = 32;
+
+/// A signature of an extrinsic, by one of the supported schemes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode, macros::TypeInfo)]
+pub enum MultiSignature {
+    Sr25519(#[serde(with = "hex::serde")] [u8; 64]),
+    Ed25519(#[serde(with = "hex::serde")] [u8; 64]),
+}
+
+impl MultiSignature {
+    /// Check that this is a signature of `message` by the given public key.
+    pub fn verify(&self, message: &[u8], public: &[u8; PUBLIC_KEY_LENGTH]) -> bool {
+        match self {
+            Self::Sr25519(signature) => {
+                let Ok(public) = schnorrkel::PublicKey::from_bytes(public) else {
+                    return false;
+                };
+                let Ok(signature) = schnorrkel::Signature::from_bytes(signature) else {
+                    return false;
+                };
+                public
+                    .verify_simple(SIGNING_CONTEXT, message, &signature)
+                    .is_ok()
+            }
+            Self::Ed25519(signature) => {
+                let Ok(public) = ed25519_dalek::VerifyingKey::from_bytes(public) else {
+                    return false;
+                };
+                public
+                    .verify(message, &ed25519_dalek::Signature::from_bytes(signature))
+                    .is_ok()
+            }
+        }
+    }
+}
+
+/// The public key of an account, if it is backed by a key.
+pub fn account_public_key(account: &str) -> Option<[u8; PUBLIC_KEY_LENGTH]> {
+    let mut public = [0; PUBLIC_KEY_LENGTH];
+    hex::decode_to_slice(account.strip_prefix("0x")?, &mut public).ok()?;
+    Some(public)
+}
+
+/// The account of a public key.
+pub fn account_id(public: &[u8; PUBLIC_KEY_LENGTH]) -> String {
+    format!("0x{}", hex::encode(public))
+}
+
+/// The account of a pallet, like the treasury, whose public key has no known secret key.
+impl From<PalletId> for String {
+    fn from(id: PalletId) -> Self {
+        account_id(&id.public_key())
+    }
+}
+
+/// The account of a contract, whose address has no known secret key either.
+impl From<ContractAddress> for String {
+    fn from(address: ContractAddress) -> Self {
+        account_id(&address.0)
+    }
+}
+
+impl From<EvmAddress> for String {
+    fn from(address: EvmAddress) -> Self {
+        account_id(&address.public_key())
+    }
+}
+
+/// A signature scheme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    Sr25519,
+    Ed25519,
+}
+
+impl FromStr for Scheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sr25519" => Ok(Self::Sr25519),
+            "ed25519" => Ok(Self::Ed25519),
+            _ => Err(format!(
+                "unknown signature scheme {s}, expected sr25519 or ed25519"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Sr25519 => write!(f, "sr25519"),
+            Self::Ed25519 => write!(f, "ed25519"),
+        }
+    }
+}
+
+/// A secret key, able to sign for its account.
+pub enum Pair {
+    Sr25519(schnorrkel::Keypair),
+    Ed25519(ed25519_dalek::SigningKey),
+}
+
+impl Pair {
+    /// The key of the given scheme derived from a secret seed.
+    pub fn from_seed(scheme: Scheme, seed: &[u8; 32]) -> Self {
+        match scheme {
+            Scheme::Sr25519 => {
+                let secret = schnorrkel::MiniSecretKey::from_bytes(seed)
+                    .expect("any 32 bytes are a valid mini secret key");
+                Self::Sr25519(secret.expand_to_keypair(schnorrkel::ExpansionMode::Ed25519))
+            }
+            Scheme::Ed25519 => Self::Ed25519(ed25519_dalek::SigningKey::from_bytes(seed)),
+        }
+    }
+
+    /// The key of the given scheme derived from a mnemonic phrase, protected by an optional
+    /// `password` (which is empty if not given).
+    pub fn from_phrase(scheme: Scheme, phrase: &str, password: &str) -> Result<Self, String> {
+        let mnemonic =
+            bip39::Mnemonic::parse(phrase).map_err(|e| format!("invalid mnemonic phrase: {e}"))?;
+        let seed = mnemonic.to_seed(password);
+        let mut secret = [0; 32];
+        secret.copy_from_slice(&seed[..32]);
+        Ok(Self::from_seed(scheme, &secret))
+    }
+
+    pub fn scheme(&self) -> Scheme {
+        match self {
+            Self::Sr25519(_) => Scheme::Sr25519,
+            Self::Ed25519(_) => Scheme::Ed25519,
+        }
+    }
+
+    pub fn public(&self) -> [u8; PUBLIC_KEY_LENGTH] {
+        match self {
+            Self::Sr25519(pair) => pair.public.to_bytes(),
+            Self::Ed25519(pair) => pair
+                .verifying_key()
+                .to_bytes(),
+        }
+    }
+
+    /// The account of this key.
+    pub fn account_id(&self) -> String {
+        account_id(&self.public())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> MultiSignature {
+        match self {
+            Self::Sr25519(pair) => MultiSignature::Sr25519(
+                pair.sign_simple(SIGNING_CONTEXT, message)
+                    .to_bytes(),
+            ),
+            Self::Ed25519(pair) => MultiSignature::Ed25519(pair.sign(message).to_bytes()),
+        }
+    }
+}
+
+/// Generate a new random mnemonic phrase of 12 words.
+pub fn generate_phrase() -> String {
+    let mut entropy = [0; 16];
+    rand::rngs::OsRng.fill_bytes(&mut entropy);
+    bip39::Mnemonic::from_entropy(&entropy)
+        .expect("16 bytes are a valid entropy length")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str =
+        "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+    #[test]
+    fn sign_and_verify() {
+        for scheme in [Scheme::Sr25519, Scheme::Ed25519] {
+            let pair = Pair::from_phrase(scheme, PHRASE, "").unwrap();
+            // The same phrase always derives the same key, unless protected by a password.
+            assert_eq!(
+                pair.public(),
+                Pair::from_phrase(scheme, PHRASE, "")
+                    .unwrap()
+                    .public()
+            );
+            assert_ne!(
+                pair.public(),
+                Pair::from_phrase(scheme, PHRASE, "password")
+                    .unwrap()
+                    .public()
+            );
+
+            let account = pair.account_id();
+            assert_eq!(account_public_key(&account), Some(pair.public()));
+            let signature = pair.sign(b"message");
+            assert!(signature.verify(b"message", &pair.public()));
+            assert!(!signature.verify(b"other message", &pair.public()));
+        }
+
+        // A signature is only valid with the scheme it was made with.
+        let sr25519 = Pair::from_phrase(Scheme::Sr25519, PHRASE, "").unwrap();
+        let MultiSignature::Sr25519(bytes) = sr25519.sign(b"message") else {
+            unreachable!()
+        };
+        assert!(!MultiSignature::Ed25519(bytes).verify(b"message", &sr25519.public()));
+
+        assert_eq!(account_public_key("alice"), None);
+        assert_eq!(account_public_key("0x1234"), None);
+        assert!(Pair::from_phrase(Scheme::Sr25519, "not a phrase", "").is_err());
+        assert_eq!(
+            generate_phrase()
+                .split(' ')
+                .count(),
+            12
+        );
+    }
+}
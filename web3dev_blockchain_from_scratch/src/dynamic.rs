@@ -0,0 +1,924 @@
+//! Encode and decode calls and extrinsics using only the runtime metadata.
+//!
+//! Clients built on this module do not depend on the Rust types of the runtime, so they keep
+//! working across runtime versions as long as they fetch the current metadata, for example with the
+//! `state_getMetadata` RPC method.
+//!
+//! Calls are described the way they would be written in code:
+//!
+//! ```text
+//! balances.transfer(to="bob", amount=30)
+//! ```
+//!
+//! Values are integers, `"strings"`, `true` and `false`, `[sequences]`, `(tuples)`, `None` and
+//! `Some(value)`, `{ name=value }` structs, `Variant(name=value)` enum variants, and nested calls
+//! like `pallet.call(name=value)`.
+//!
+//! The encoding is SCALE, the same as `codec::Encode` for the runtime types. A call is encoded as
+//! the index of its pallet, the index of the call in the pallet and its arguments. An extrinsic is
+//! encoded as its envelope (the caller, its nonce, its tip and the other data checked by the
+//! runtime before dispatching it), followed by its call.
+
+use std::fmt;
+
+use codec::{
+    Compact,
+    Decode,
+    Encode,
+};
+
+use crate::{
+    metadata::{
+        Field,
+        RuntimeMetadata,
+        Type,
+        Variant,
+    },
+    support,
+};
+
+/// A value of any `Type` described by the metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    /// Any unsigned integer.
+    Int(u128),
+    String(String),
+    Sequence(Vec<Value>),
+    /// A tuple. The unit type is the empty tuple.
+    Tuple(Vec<Value>),
+    Option(Option<Box<Value>>),
+    /// A struct, with either all or none of its fields named.
+    Composite(Vec<(Option<String>, Value)>),
+    /// An enum variant, with either all or none of its fields named.
+    Variant {
+        name: String,
+        fields: Vec<(Option<String>, Value)>,
+    },
+    /// A call of the runtime, like the call made by a proxy.
+    Call(Box<Call>),
+}
+
+impl From<u128> for Value {
+    fn from(value: u128) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+/// A call of a pallet, with its named arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Call {
+    pub pallet: String,
+    pub name: String,
+    pub args: Vec<(String, Value)>,
+}
+
+impl Call {
+    /// Parse a call described like `balances.transfer(to="bob", amount=30)`.
+    pub fn parse(description: &str) -> Result<Self, String> {
+        let mut parser = Parser::new(description);
+        let call = parser.call()?;
+        parser.end()?;
+        Ok(call)
+    }
+}
+
+/// An extrinsic, with the fields of its envelope by name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Extrinsic {
+    pub envelope: Vec<(String, Value)>,
+    pub call: Call,
+}
+
+/// Encode a call, checking it against the metadata.
+pub fn encode_call(metadata: &RuntimeMetadata, call: &Call) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    encode_call_to(metadata, call, &mut out)?;
+    Ok(out)
+}
+
+/// Decode an encoded call. All the bytes must be used.
+pub fn decode_call(metadata: &RuntimeMetadata, mut bytes: &[u8]) -> Result<Call, String> {
+    let call = decode_call_from(metadata, &mut bytes, 0)?;
+    end_of_input(bytes)?;
+    Ok(call)
+}
+
+/// Encode an extrinsic, checking its envelope and its call against the metadata.
+pub fn encode_extrinsic(
+    metadata: &RuntimeMetadata,
+    extrinsic: &Extrinsic,
+) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    encode_named_fields(
+        metadata,
+        &metadata.extrinsic_envelope,
+        &extrinsic.envelope,
+        &mut out,
+    )?;
+    encode_call_to(metadata, &extrinsic.call, &mut out)?;
+    Ok(out)
+}
+
+/// Decode an encoded extrinsic. All the bytes must be used.
+pub fn decode_extrinsic(metadata: &RuntimeMetadata, mut bytes: &[u8]) -> Result<Extrinsic, String> {
+    let envelope = decode_named_fields(metadata, &metadata.extrinsic_envelope, &mut bytes, 0)?;
+    let call = decode_call_from(metadata, &mut bytes, 0)?;
+    end_of_input(bytes)?;
+    Ok(Extrinsic { envelope, call })
+}
+
+/// Encode a value of the given type.
+pub fn encode_value(
+    metadata: &RuntimeMetadata,
+    ty: &Type,
+    value: &Value,
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    match (ty, value) {
+        (Type::Unit, Value::Tuple(values)) if values.is_empty() => {}
+        (Type::Bool, Value::Bool(b)) => b.encode_to(out),
+        (Type::U8, Value::Int(n)) => int::<u8>(*n)?.encode_to(out),
+        (Type::U16, Value::Int(n)) => int::<u16>(*n)?.encode_to(out),
+        (Type::U32, Value::Int(n)) => int::<u32>(*n)?.encode_to(out),
+        (Type::U64, Value::Int(n)) => int::<u64>(*n)?.encode_to(out),
+        (Type::U128, Value::Int(n)) => n.encode_to(out),
+        (Type::String, Value::String(s)) => s.encode_to(out),
+        (Type::Sequence(ty), Value::Sequence(values)) => {
+            Compact(values.len() as u32).encode_to(out);
+            for value in values {
+                encode_value(metadata, ty, value, out)?;
+            }
+        }
+        (Type::Array { len, ty }, Value::Sequence(values)) if values.len() == *len as usize => {
+            for value in values {
+                encode_value(metadata, ty, value, out)?;
+            }
+        }
+        (Type::Option(_), Value::Option(None)) => out.push(0),
+        (Type::Option(ty), Value::Option(Some(value))) => {
+            out.push(1);
+            encode_value(metadata, ty, value, out)?;
+        }
+        (Type::Tuple(types), Value::Tuple(values)) if types.len() == values.len() => {
+            for (ty, value) in types.iter().zip(values) {
+                encode_value(metadata, ty, value, out)?;
+            }
+        }
+        (Type::Composite(fields), Value::Composite(values)) => {
+            encode_fields(metadata, fields, values, out)?
+        }
+        (Type::Variant(variants), Value::Variant { name, fields }) => {
+            let variant = variants
+                .iter()
+                .find(|variant| &variant.name == name)
+                .ok_or_else(|| format!("unknown variant {name}"))?;
+            out.push(variant.index);
+            encode_fields(metadata, &variant.fields, fields, out)?;
+        }
+        (Type::RuntimeCall, Value::Call(call)) => encode_call_to(metadata, call, out)?,
+        _ => return Err(format!("expected a value of type {ty:?}, found {value}")),
+    }
+    Ok(())
+}
+
+/// Decode a value of the given type.
+pub fn decode_value(
+    metadata: &RuntimeMetadata,
+    ty: &Type,
+    input: &mut &[u8],
+) -> Result<Value, String> {
+    decode_value_in(metadata, ty, input, 0)
+}
+
+/// Decode a value of the given type, nested in `depth` calls.
+fn decode_value_in(
+    metadata: &RuntimeMetadata,
+    ty: &Type,
+    input: &mut &[u8],
+    depth: u32,
+) -> Result<Value, String> {
+    Ok(match ty {
+        Type::Unit => Value::Tuple(vec![]),
+        Type::Bool => Value::Bool(decode(input)?),
+        Type::U8 => Value::Int(decode::<u8>(input)?.into()),
+        Type::U16 => Value::Int(decode::<u16>(input)?.into()),
+        Type::U32 => Value::Int(decode::<u32>(input)?.into()),
+        Type::U64 => Value::Int(decode::<u64>(input)?.into()),
+        Type::U128 => Value::Int(decode(input)?),
+        Type::String => Value::String(decode(input)?),
+        Type::Sequence(ty) => {
+            let len = decode::<Compact<u32>>(input)?.0;
+            let values = (0..len)
+                .map(|_| decode_value_in(metadata, ty, input, depth))
+                .collect::<Result<_, _>>()?;
+            Value::Sequence(values)
+        }
+        Type::Array { len, ty } => Value::Sequence(
+            (0..*len)
+                .map(|_| decode_value_in(metadata, ty, input, depth))
+                .collect::<Result<_, _>>()?,
+        ),
+        Type::Option(ty) => match decode::<u8>(input)? {
+            0 => Value::Option(None),
+            1 => Value::Option(Some(Box::new(decode_value_in(metadata, ty, input, depth)?))),
+            _ => return Err("invalid option".to_string()),
+        },
+        Type::Tuple(types) => Value::Tuple(
+            types
+                .iter()
+                .map(|ty| decode_value_in(metadata, ty, input, depth))
+                .collect::<Result<_, _>>()?,
+        ),
+        Type::Composite(fields) => Value::Composite(decode_fields(metadata, fields, input, depth)?),
+        Type::RuntimeCall => Value::Call(Box::new(decode_call_from(metadata, input, depth + 1)?)),
+        Type::Variant(variants) => {
+            let variant = decode_variant(variants, input)?;
+            Value::Variant {
+                name: variant.name.clone(),
+                fields: decode_fields(metadata, &variant.fields, input, depth)?,
+            }
+        }
+    })
+}
+
+fn encode_call_to(
+    metadata: &RuntimeMetadata,
+    call: &Call,
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    let pallet = metadata
+        .pallet(&call.pallet)
+        .ok_or_else(|| format!("unknown pallet {}", call.pallet))?;
+    let variant = pallet
+        .calls
+        .iter()
+        .find(|variant| variant.name == call.name)
+        .ok_or_else(|| format!("unknown call {}.{}", call.pallet, call.name))?;
+    out.push(pallet.index);
+    out.push(variant.index);
+    encode_named_fields(metadata, &variant.fields, &call.args, out)
+}
+
+/// Decode a call nested in `depth` calls, like the call of a proxy. Like `codec::DecodeLimit`, it
+/// stops at `support::MAX_DECODE_DEPTH` rather than overflowing the stack.
+fn decode_call_from(
+    metadata: &RuntimeMetadata,
+    input: &mut &[u8],
+    depth: u32,
+) -> Result<Call, String> {
+    if depth > support::MAX_DECODE_DEPTH {
+        return Err("the call is nested too deeply".to_string());
+    }
+    let index = decode::<u8>(input)?;
+    let pallet = metadata
+        .pallets
+        .iter()
+        .find(|pallet| pallet.index == index)
+        .ok_or_else(|| format!("unknown pallet index {index}"))?;
+    let variant = decode_variant(&pallet.calls, input)?;
+    Ok(Call {
+        pallet: pallet.name.clone(),
+        name: variant.name.clone(),
+        args: decode_named_fields(metadata, &variant.fields, input, depth)?,
+    })
+}
+
+/// Encode fields given by name, in the order of the metadata.
+fn encode_named_fields(
+    metadata: &RuntimeMetadata,
+    fields: &[Field],
+    values: &[(String, Value)],
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    let values = values
+        .iter()
+        .map(|(name, value)| (Some(name.clone()), value.clone()))
+        .collect::<Vec<_>>();
+    encode_fields(metadata, fields, &values, out)
+}
+
+/// Encode fields given either all by name, or all in order.
+fn encode_fields(
+    metadata: &RuntimeMetadata,
+    fields: &[Field],
+    values: &[(Option<String>, Value)],
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    if values.len() != fields.len() {
+        let msg = format!("expected {} fields, found {}", fields.len(), values.len());
+        return Err(msg);
+    }
+    if values
+        .iter()
+        .all(|(name, _)| name.is_none())
+    {
+        for (field, (_, value)) in fields.iter().zip(values) {
+            encode_value(metadata, &field.ty, value, out)?;
+        }
+        return Ok(());
+    }
+    for field in fields {
+        let name = field
+            .name
+            .as_ref()
+            .ok_or("fields without a name must be given in order")?;
+        let (_, value) = values
+            .iter()
+            .find(|(value_name, _)| value_name.as_ref() == Some(name))
+            .ok_or_else(|| format!("missing field {name}"))?;
+        encode_value(metadata, &field.ty, value, out)?;
+    }
+    Ok(())
+}
+
+fn decode_named_fields(
+    metadata: &RuntimeMetadata,
+    fields: &[Field],
+    input: &mut &[u8],
+    depth: u32,
+) -> Result<Vec<(String, Value)>, String> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let name = field
+                .name
+                .clone()
+                .unwrap_or_else(|| i.to_string());
+            Ok((name, decode_value_in(metadata, &field.ty, input, depth)?))
+        })
+        .collect()
+}
+
+fn decode_fields(
+    metadata: &RuntimeMetadata,
+    fields: &[Field],
+    input: &mut &[u8],
+    depth: u32,
+) -> Result<Vec<(Option<String>, Value)>, String> {
+    fields
+        .iter()
+        .map(|field| {
+            Ok((
+                field.name.clone(),
+                decode_value_in(metadata, &field.ty, input, depth)?,
+            ))
+        })
+        .collect()
+}
+
+fn decode_variant<'a>(variants: &'a [Variant], input: &mut &[u8]) -> Result<&'a Variant, String> {
+    let index = decode::<u8>(input)?;
+    variants
+        .iter()
+        .find(|variant| variant.index == index)
+        .ok_or_else(|| format!("unknown variant index {index}"))
+}
+
+fn decode<T: Decode>(input: &mut &[u8]) -> Result<T, String> {
+    T::decode(input).map_err(|e| e.to_string())
+}
+
+fn end_of_input(input: &[u8]) -> Result<(), String> {
+    if input.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} bytes left after decoding", input.len()))
+    }
+}
+
+/// Convert an integer to a smaller type, checking it fits.
+fn int<T: TryFrom<u128>>(n: u128) -> Result<T, String> {
+    T::try_from(n).map_err(|_| format!("{n} does not fit in a {}", std::any::type_name::<T>()))
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Int(n) => write!(f, "{n}"),
+            Value::String(s) => write!(f, "{s:?}"),
+            Value::Sequence(values) => {
+                write!(f, "[")?;
+                write_list(
+                    f,
+                    values
+                        .iter()
+                        .map(|value| (None, value)),
+                )?;
+                write!(f, "]")
+            }
+            Value::Tuple(values) => {
+                write!(f, "(")?;
+                write_list(
+                    f,
+                    values
+                        .iter()
+                        .map(|value| (None, value)),
+                )?;
+                write!(f, ")")
+            }
+            Value::Option(None) => write!(f, "None"),
+            Value::Option(Some(value)) => write!(f, "Some({value})"),
+            Value::Composite(fields) => {
+                write!(f, "{{")?;
+                write_fields(f, fields)?;
+                write!(f, "}}")
+            }
+            Value::Variant { name, fields } if fields.is_empty() => write!(f, "{name}"),
+            Value::Variant { name, fields } => {
+                write!(f, "{name}(")?;
+                write_fields(f, fields)?;
+                write!(f, ")")
+            }
+            Value::Call(call) => write!(f, "{call}"),
+        }
+    }
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}(", self.pallet, self.name)?;
+        write_list(
+            f,
+            self.args
+                .iter()
+                .map(|(name, value)| (Some(name.as_str()), value)),
+        )?;
+        write!(f, ")")
+    }
+}
+
+fn write_fields(f: &mut fmt::Formatter, fields: &[(Option<String>, Value)]) -> fmt::Result {
+    write_list(
+        f,
+        fields
+            .iter()
+            .map(|(name, value)| (name.as_deref(), value)),
+    )
+}
+
+fn write_list<'a>(
+    f: &mut fmt::Formatter,
+    items: impl Iterator<Item = (Option<&'a str>, &'a Value)>,
+) -> fmt::Result {
+    for (i, (name, value)) in items.enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        if let Some(name) = name {
+            write!(f, "{name}=")?;
+        }
+        write!(f, "{value}")?;
+    }
+    Ok(())
+}
+
+/// A parser for the descriptions of calls and values.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn call(&mut self) -> Result<Call, String> {
+        let pallet = self.ident()?;
+        self.expect('.')?;
+        let name = self.ident()?;
+        self.expect('(')?;
+        let args = self
+            .fields(')')?
+            .into_iter()
+            .map(|(name, value)| {
+                let name = name.ok_or("call arguments must be named, like `amount=30`")?;
+                Ok((name, value))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Call { pallet, name, args })
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self
+                .string()
+                .map(Value::String),
+            Some(c) if c.is_ascii_digit() => self.int().map(Value::Int),
+            Some('[') => {
+                self.expect('[')?;
+                self.values(']')
+                    .map(Value::Sequence)
+            }
+            Some('(') => {
+                self.expect('(')?;
+                self.values(')')
+                    .map(Value::Tuple)
+            }
+            Some('{') => {
+                self.expect('{')?;
+                self.fields('}')
+                    .map(Value::Composite)
+            }
+            Some(c) if is_ident_start(c) => {
+                // An identifier followed by `.` is the pallet of a call.
+                let start = self.pos;
+                let name = self.ident()?;
+                if self.peek() == Some('.') {
+                    self.pos = start;
+                    return self
+                        .call()
+                        .map(|call| Value::Call(Box::new(call)));
+                }
+                let fields = if self.peek() == Some('(') {
+                    self.expect('(')?;
+                    self.fields(')')?
+                } else {
+                    vec![]
+                };
+                Ok(match (name.as_str(), fields.as_slice()) {
+                    ("true", []) => Value::Bool(true),
+                    ("false", []) => Value::Bool(false),
+                    ("None", []) => Value::Option(None),
+                    ("Some", [(None, value)]) => Value::Option(Some(Box::new(value.clone()))),
+                    _ => Value::Variant { name, fields },
+                })
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    /// Parse values separated by commas, until the `close` delimiter.
+    fn values(&mut self, close: char) -> Result<Vec<Value>, String> {
+        let mut values = Vec::new();
+        while !self.eat(close) {
+            values.push(self.value()?);
+            if !self.eat(',') {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(values)
+    }
+
+    /// Parse values optionally prefixed by `name=`, separated by commas, until the `close`
+    /// delimiter.
+    fn fields(&mut self, close: char) -> Result<Vec<(Option<String>, Value)>, String> {
+        let mut fields = Vec::new();
+        while !self.eat(close) {
+            // A name is an identifier followed by `=`, otherwise we backtrack to parse a value.
+            let start = self.pos;
+            let name = match self.ident() {
+                Ok(name) if self.eat('=') => Some(name),
+                _ => {
+                    self.pos = start;
+                    None
+                }
+            };
+            fields.push((name, self.value()?));
+            if !self.eat(',') {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(fields)
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .find(|c: char| !is_ident_start(c) && !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if !rest.starts_with(is_ident_start) {
+            return Err(self.error("expected an identifier"));
+        }
+        self.pos += len;
+        Ok(rest[..len].to_string())
+    }
+
+    fn int(&mut self) -> Result<u128, String> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '_')
+            .unwrap_or(rest.len());
+        let n = rest[..len]
+            .replace('_', "")
+            .parse()
+            .map_err(|_| self.error("invalid integer"))?;
+        self.pos += len;
+        Ok(n)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let start = self.pos - 1;
+        let rest = &self.input[self.pos..];
+        let mut chars = rest.chars();
+        let mut s = String::new();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('0') => s.push('\0'),
+                    Some(c @ ('"' | '\'' | '\\')) => s.push(c),
+                    // Unicode escapes look like `\u{1f600}`.
+                    Some('u') => {
+                        let tail = chars.as_str();
+                        let (code, tail) = tail
+                            .strip_prefix('{')
+                            .and_then(|tail| tail.split_once('}'))
+                            .ok_or_else(|| self.error("invalid unicode escape"))?;
+                        let c = u32::from_str_radix(code, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| self.error("invalid unicode escape"))?;
+                        s.push(c);
+                        chars = tail.chars();
+                    }
+                    _ => return Err(self.error("invalid escape")),
+                },
+                Some(c) => s.push(c),
+                None => return Err(format!("unterminated string at position {start}")),
+            }
+        }
+        self.pos += rest.len() - chars.as_str().len();
+        Ok(s)
+    }
+
+    /// Check that the whole input was parsed.
+    fn end(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.pos == self.input.len() {
+            Ok(())
+        } else {
+            Err(self.error("unexpected input"))
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{c}`")))
+        }
+    }
+
+    /// Consume `c` if it is the next character.
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The next character which is not whitespace.
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.input[self.pos..]
+            .chars()
+            .next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn error(&self, msg: &str) -> String {
+        format!("{msg} at position {}", self.pos)
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use codec::DecodeLimit;
+
+    use super::*;
+    use crate::{
+        balances,
+        crypto::MultiSignature,
+        proof_of_existence,
+        proxy,
+        runtime::{
+            types,
+            Runtime,
+            RuntimeCall,
+        },
+        support,
+    };
+
+    /// The metadata as a client would get it, without the Rust types.
+    fn metadata() -> RuntimeMetadata {
+        let json = serde_json::to_string(&Runtime::metadata()).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn encode_calls_like_the_runtime() {
+        let metadata = metadata();
+
+        let call = Call::parse(r#"balances.transfer(to="bob", amount=30)"#).unwrap();
+        let expected = RuntimeCall::balances(balances::Call::transfer {
+            to: "bob".to_string(),
+            amount: 30,
+        });
+        let encoded = encode_call(&metadata, &call).unwrap();
+        assert_eq!(encoded, expected.encode());
+        assert_eq!(RuntimeCall::decode(&mut &encoded[..]), Ok(expected));
+
+        // Arguments can be given in any order, and are decoded in the order of the metadata.
+        let call = Call::parse(r#" balances . transfer ( amount = 1_000 , to = "bob\u{e9}" , ) "#)
+            .unwrap();
+        let encoded = encode_call(&metadata, &call).unwrap();
+        let decoded = decode_call(&metadata, &encoded).unwrap();
+        assert_eq!(
+            decoded.to_string(),
+            r#"balances.transfer(to="bobé", amount=1000)"#
+        );
+        assert_eq!(Call::parse(&decoded.to_string()), Ok(decoded));
+
+        let call = Call::parse(r#"proof_of_existence.revoke_claim(claim="doc")"#).unwrap();
+        let expected = RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
+            claim: "doc".to_string(),
+        });
+        assert_eq!(encode_call(&metadata, &call), Ok(expected.encode()));
+    }
+
+    #[test]
+    fn encode_nested_calls() {
+        let metadata = metadata();
+        let call = Call::parse(
+            r#"proxy.proxy(real="alice", force_proxy_type=None, call=balances.transfer(to="bob", amount=30))"#,
+        )
+        .unwrap();
+        let expected = RuntimeCall::proxy(proxy::Call::proxy {
+            real: "alice".to_string(),
+            force_proxy_type: None,
+            call: Box::new(RuntimeCall::balances(balances::Call::transfer {
+                to: "bob".to_string(),
+                amount: 30,
+            })),
+        });
+        let encoded = encode_call(&metadata, &call).unwrap();
+        assert_eq!(encoded, expected.encode());
+        let decoded = decode_call(&metadata, &encoded).unwrap();
+        assert_eq!(decoded, call);
+        assert_eq!(Call::parse(&decoded.to_string()), Ok(decoded));
+
+        // A call nested in too many calls is rejected, rather than overflowing the stack.
+        let RuntimeCall::proxy(proxy::Call::proxy { call: inner, .. }) = &expected else {
+            unreachable!()
+        };
+        let prefix = &encoded[..encoded.len() - inner.encode().len()];
+        let mut nested = prefix.repeat(100_000);
+        nested.extend(inner.encode());
+        assert_eq!(
+            decode_call(&metadata, &nested),
+            Err("the call is nested too deeply".to_string())
+        );
+        assert!(
+            RuntimeCall::decode_with_depth_limit(support::MAX_DECODE_DEPTH, &mut &nested[..])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn encode_extrinsics_like_the_runtime() {
+        let metadata = metadata();
+        let extrinsic = Extrinsic {
+            envelope: vec![
+                ("caller".to_string(), "alice".into()),
+                (
+                    "signature".to_string(),
+                    Value::Option(Some(Box::new(Value::Variant {
+                        name: "Ed25519".to_string(),
+                        fields: vec![(None, Value::Sequence(vec![1.into(); 64]))],
+                    }))),
+                ),
+                ("nonce".to_string(), 3.into()),
+                ("tip".to_string(), 5.into()),
+                (
+                    "era".to_string(),
+                    Value::Variant {
+                        name: "Mortal".to_string(),
+                        fields: vec![
+                            (Some("birth".to_string()), 8.into()),
+                            (Some("period".to_string()), 64.into()),
+                        ],
+                    },
+                ),
+                ("spec_version".to_string(), 1.into()),
+                (
+                    "genesis_hash".to_string(),
+                    Value::Sequence(vec![7.into(); 32]),
+                ),
+            ],
+            call: Call::parse(r#"proof_of_existence.create_claim(claim="doc")"#).unwrap(),
+        };
+        let expected: types::Extrinsic = support::Extrinsic {
+            caller: "alice".to_string(),
+            signature: Some(MultiSignature::Ed25519([1; 64])),
+            nonce: 3,
+            tip: 5,
+            era: support::Era::Mortal {
+                birth: 8,
+                period: 64,
+            },
+            spec_version: 1,
+            genesis_hash: [7; 32],
+            call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                claim: "doc".to_string(),
+            }),
+        };
+
+        let encoded = encode_extrinsic(&metadata, &extrinsic).unwrap();
+        assert_eq!(encoded, expected.encode());
+        assert_eq!(decode_extrinsic(&metadata, &encoded), Ok(extrinsic));
+        assert_eq!(
+            decode_extrinsic(&metadata, &[encoded.as_slice(), &[0]].concat()),
+            Err("1 bytes left after decoding".to_string())
+        );
+    }
+
+    #[test]
+    fn encode_values() {
+        let ty = Type::Sequence(Box::new(Type::Tuple(vec![
+            Type::Option(Box::new(Type::U8)),
+            Type::Bool,
+        ])));
+        let mut parser = Parser::new("[(Some(7), true), (None, false)]");
+        let value = parser.value().unwrap();
+
+        let metadata = metadata();
+        let mut encoded = Vec::new();
+        encode_value(&metadata, &ty, &value, &mut encoded).unwrap();
+        let expected: Vec<(Option<u8>, bool)> = vec![(Some(7), true), (None, false)];
+        assert_eq!(encoded, expected.encode());
+        assert_eq!(decode_value(&metadata, &ty, &mut &encoded[..]), Ok(value));
+    }
+
+    #[test]
+    fn errors() {
+        let metadata = metadata();
+        let encode = |description: &str| {
+            Call::parse(description).and_then(|call| encode_call(&metadata, &call))
+        };
+
+        assert_eq!(
+            encode(r#"assets.transfer(to="bob", amount=30)"#),
+            Err("unknown pallet assets".to_string())
+        );
+        assert_eq!(
+            encode(r#"balances.burn(amount=30)"#),
+            Err("unknown call balances.burn".to_string())
+        );
+        assert_eq!(
+            encode(r#"balances.transfer(to="bob", value=30)"#),
+            Err("missing field amount".to_string())
+        );
+        assert_eq!(
+            encode(r#"balances.transfer(to="bob")"#),
+            Err("expected 2 fields, found 1".to_string())
+        );
+        assert_eq!(
+            encode(r#"balances.transfer(to=30, amount="bob")"#),
+            Err(r#"expected a value of type String, found 30"#.to_string())
+        );
+        assert_eq!(
+            encode(r#"balances.transfer("bob", 30)"#),
+            Err("call arguments must be named, like `amount=30`".to_string())
+        );
+        assert_eq!(
+            encode(r#"balances.transfer(to="bob, amount=30)"#),
+            Err("unterminated string at position 21".to_string())
+        );
+        assert_eq!(
+            encode(r#"balances.transfer(to="bob", amount=30) extra"#),
+            Err("unexpected input at position 39".to_string())
+        );
+        assert_eq!(
+            decode_call(&metadata, &[10, 0]),
+            Err("unknown pallet index 10".to_string())
+        );
+    }
+}
@@ -0,0 +1,820 @@
+//! The escrow pallet: conditional payments, held until a condition is met.
+//!
+//! A payer locks an amount of its balance for a payee, which is reserved with the currency of the
+//! config.
+//! The escrow is released to the payee once its condition is met, and before its expiry:
+//! - a time lock is met from a given block,
+//! - a hash lock is met by revealing a preimage of its hash, which makes HTLC-style atomic swaps
+//!   possible: revealing the preimage to claim one payment lets the counterparty claim the other,
+//! - an arbiter condition is met when the arbiter approves the payment.
+//!
+//! Once expired, the escrow can only be refunded to the payer. Before that, the payer can release
+//! the payment and the payee can decline it at any time, while the arbiter of an escrow can settle
+//! it either way.
+//!
+//! The runtime lends the system pallet, for the current block, and the currency, like the balances
+//! pallet with `#[lend(system, balances)]`.
+
+use codec::{
+    Decode,
+    Encode,
+};
+use num::Zero;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    support::{
+        benchmarking::{
+            ensure,
+            Benchmark,
+            Benchmarks,
+            Component,
+        },
+        storage::{
+            blake2_256,
+            StorageMap,
+            StorageValue,
+        },
+        AsCurrency,
+        Borrower,
+        Currency,
+        DispatchResult,
+        Hooks,
+        Lent,
+        OnRuntimeUpgrade,
+        ReservableCurrency,
+    },
+    system,
+};
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+pub type EscrowId = u32;
+
+/// The longest preimage of a hash lock, in bytes.
+pub const MAX_PREIMAGE_LENGTH: u32 = 1024;
+
+pub trait Config: system::Config {
+    /// The currency holding the escrowed amounts.
+    type Currency: ReservableCurrency<Self::AccountId>;
+    /// The weights of the calls, see the `weights` module.
+    type WeightInfo: WeightInfo;
+}
+
+/// The amounts held in escrow, in the currency of the runtime `T`.
+pub type BalanceOf<T> =
+    <<T as Config>::Currency as Currency<<T as system::Config>::AccountId>>::Balance;
+
+/// The condition releasing an escrow to its payee.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode, macros::TypeInfo)]
+pub enum Condition<AccountId, BlockNumber> {
+    /// Anyone can release the escrow from this block.
+    After(BlockNumber),
+    /// Anyone can release the escrow by revealing a preimage of this BLAKE2b-256 hash.
+    HashLock(#[serde(with = "hex::serde")] [u8; 32]),
+    /// The arbiter releases or refunds the escrow.
+    Arbiter(AccountId),
+}
+
+/// An escrow, holding `amount` reserved from the payer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Encode, Decode, macros::TypeInfo)]
+pub struct EscrowDetails<AccountId, Balance, BlockNumber> {
+    pub payer: AccountId,
+    pub payee: AccountId,
+    pub amount: Balance,
+    pub condition: Condition<AccountId, BlockNumber>,
+    /// The first block in which the escrow can't be released anymore, but can be refunded by
+    /// anyone.
+    pub expiry: BlockNumber,
+}
+
+/// The escrows of the runtime `T`.
+pub type EscrowOf<T> = EscrowDetails<
+    <T as system::Config>::AccountId,
+    BalanceOf<T>,
+    <T as system::Config>::BlockNumber,
+>;
+
+/// The events emitted by the escrow pallet.
+#[derive(
+    macros::CloneNoBound,
+    macros::DebugNoBound,
+    macros::PartialEqNoBound,
+    macros::EqNoBound,
+    macros::TypeInfo,
+    Serialize,
+)]
+// Serde does not infer the bound of `BalanceOf<T>`, an alias.
+#[serde(bound(
+    serialize = "T::AccountId: Serialize, BalanceOf<T>: Serialize, T::BlockNumber: Serialize"
+))]
+pub enum Event<T: Config> {
+    /// `payer` locked `amount` for `payee` in the escrow `id`.
+    Created {
+        id: EscrowId,
+        payer: T::AccountId,
+        payee: T::AccountId,
+        amount: BalanceOf<T>,
+        expiry: T::BlockNumber,
+    },
+    /// The hash lock of the escrow `id` was opened by `preimage`.
+    PreimageRevealed { id: EscrowId, preimage: Vec<u8> },
+    /// The escrow `id` paid `amount` to `payee`.
+    Released {
+        id: EscrowId,
+        payee: T::AccountId,
+        amount: BalanceOf<T>,
+    },
+    /// The escrow `id` returned `amount` to `payer`.
+    Refunded {
+        id: EscrowId,
+        payer: T::AccountId,
+        amount: BalanceOf<T>,
+    },
+}
+
+#[derive(Debug)]
+#[macros::storage]
+pub struct Pallet<T: Config> {
+    /// The id of the next escrow.
+    #[default(0)]
+    next_escrow_id: StorageValue<EscrowId>,
+    /// The escrows which were neither released nor refunded.
+    escrows: StorageMap<EscrowId, EscrowOf<T>>,
+    /// The system pallet, giving the current block.
+    system: Lent<system::Pallet<T>>,
+    /// The currency, holding the escrowed amounts.
+    currency: Lent<T::Currency>,
+    /// The events emitted since the runtime last collected them.
+    events: Vec<Event<T>>,
+}
+
+#[macros::call]
+#[errors(
+    "the escrow is empty",
+    "the escrow is already expired",
+    "the escrow expires before it can be released",
+    "no more escrow ids",
+    "unknown escrow",
+    "the escrow is expired",
+    "the time lock of the escrow is not over",
+    "the escrow is locked by a hash",
+    "not the arbiter of the escrow",
+    "the escrow is not locked by a hash",
+    "invalid preimage",
+    "the escrow is not expired",
+    crate::support::NOT_LENT,
+    crate::support::INSUFFICIENT_BALANCE,
+    crate::support::INSUFFICIENT_RESERVED_BALANCE,
+    crate::support::OVERFLOW
+)]
+impl<T: Config> Pallet<T> {
+    /// Lock `amount` of the balance of the `caller` for `payee`, until the `condition` is met or
+    /// the escrow expires at the block `expiry`.
+    #[call_index(0)]
+    #[weight(<T as Config>::WeightInfo::create())]
+    pub fn create(
+        &mut self,
+        caller: T::AccountId,
+        payee: T::AccountId,
+        amount: BalanceOf<T>,
+        condition: Condition<T::AccountId, T::BlockNumber>,
+        expiry: T::BlockNumber,
+    ) -> DispatchResult {
+        if amount.is_zero() {
+            return Err("the escrow is empty");
+        }
+        if expiry <= self.now()? {
+            return Err("the escrow is already expired");
+        }
+        if matches!(condition, Condition::After(block) if block >= expiry) {
+            return Err("the escrow expires before it can be released");
+        }
+        let id = self.next_escrow_id.get();
+        let next = id
+            .checked_add(1)
+            .ok_or("no more escrow ids")?;
+        self.currency()?
+            .reserve(caller.clone(), amount)?;
+        self.next_escrow_id.set(next);
+        self.escrows.insert(
+            &id,
+            EscrowDetails {
+                payer: caller.clone(),
+                payee: payee.clone(),
+                amount,
+                condition,
+                expiry,
+            },
+        );
+        self.deposit_event(Event::Created {
+            id,
+            payer: caller,
+            payee,
+            amount,
+            expiry,
+        });
+        Ok(())
+    }
+
+    /// Pay an escrow to its payee. Its payer can release it at any time, and anyone else once its
+    /// time lock is met, or its arbiter, before it expires. A hash lock is released by
+    /// `release_with_preimage` instead.
+    #[call_index(1)]
+    #[weight(<T as Config>::WeightInfo::release())]
+    pub fn release(&mut self, caller: T::AccountId, id: EscrowId) -> DispatchResult {
+        let escrow = self
+            .escrow(id)
+            .ok_or("unknown escrow")?;
+        if caller != escrow.payer {
+            let now = self.now()?;
+            if now >= escrow.expiry {
+                return Err("the escrow is expired");
+            }
+            match &escrow.condition {
+                Condition::After(block) if now < *block => {
+                    return Err("the time lock of the escrow is not over");
+                }
+                Condition::After(_) => {}
+                Condition::HashLock(_) => return Err("the escrow is locked by a hash"),
+                Condition::Arbiter(arbiter) if &caller != arbiter => {
+                    return Err("not the arbiter of the escrow");
+                }
+                Condition::Arbiter(_) => {}
+            }
+        }
+        self.pay(id, escrow)
+    }
+
+    /// Pay an escrow locked by a hash to its payee, by revealing a preimage of the hash before the
+    /// escrow expires.
+    #[call_index(2)]
+    #[weight(<T as Config>::WeightInfo::release_with_preimage(preimage.len() as u32))]
+    pub fn release_with_preimage(
+        &mut self,
+        _caller: T::AccountId,
+        id: EscrowId,
+        preimage: Vec<u8>,
+    ) -> DispatchResult {
+        let escrow = self
+            .escrow(id)
+            .ok_or("unknown escrow")?;
+        let Condition::HashLock(hash) = escrow.condition else {
+            return Err("the escrow is not locked by a hash");
+        };
+        if preimage.len() > MAX_PREIMAGE_LENGTH as usize || blake2_256(&preimage) != hash {
+            return Err("invalid preimage");
+        }
+        if self.now()? >= escrow.expiry {
+            return Err("the escrow is expired");
+        }
+        self.deposit_event(Event::PreimageRevealed { id, preimage });
+        self.pay(id, escrow)
+    }
+
+    /// Return an escrow to its payer. Anyone can refund it once expired. Before that, only its
+    /// payee, declining the payment, or its arbiter can refund it.
+    #[call_index(3)]
+    #[weight(<T as Config>::WeightInfo::refund())]
+    pub fn refund(&mut self, caller: T::AccountId, id: EscrowId) -> DispatchResult {
+        let escrow = self
+            .escrow(id)
+            .ok_or("unknown escrow")?;
+        let settles = caller == escrow.payee
+            || matches!(&escrow.condition, Condition::Arbiter(arbiter) if &caller == arbiter);
+        if !settles && self.now()? < escrow.expiry {
+            return Err("the escrow is not expired");
+        }
+        self.currency()?
+            .unreserve(escrow.payer.clone(), escrow.amount)?;
+        self.escrows.remove(&id);
+        self.deposit_event(Event::Refunded {
+            id,
+            payer: escrow.payer,
+            amount: escrow.amount,
+        });
+        Ok(())
+    }
+}
+
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    pub fn escrow(&self, id: EscrowId) -> Option<EscrowOf<T>> {
+        self.escrows.try_get(&id)
+    }
+
+    /// Pay the escrow `id` to its payee, and remove it.
+    fn pay(&mut self, id: EscrowId, escrow: EscrowOf<T>) -> DispatchResult {
+        self.currency()?
+            .repatriate_reserved(escrow.payer, escrow.payee.clone(), escrow.amount)?;
+        self.escrows.remove(&id);
+        self.deposit_event(Event::Released {
+            id,
+            payee: escrow.payee,
+            amount: escrow.amount,
+        });
+        Ok(())
+    }
+
+    /// The current block, from the system pallet lent by the runtime.
+    fn now(&mut self) -> Result<T::BlockNumber, &'static str> {
+        Ok(self
+            .system
+            .get_mut()?
+            .block_number())
+    }
+
+    /// The currency lent by the runtime.
+    fn currency(&mut self) -> Result<&mut T::Currency, &'static str> {
+        self.currency.get_mut()
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    /// Take the events emitted since the last call, so the runtime can record them.
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<T: Config> Borrower<system::Pallet<T>> for Pallet<T> {
+    fn lent(&mut self) -> &mut Lent<system::Pallet<T>> {
+        &mut self.system
+    }
+}
+
+impl<T: Config> Borrower<T::Currency, AsCurrency> for Pallet<T> {
+    fn lent(&mut self) -> &mut Lent<T::Currency> {
+        &mut self.currency
+    }
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+/// The escrows are released by a caller other than their payer, which checks their condition. The
+/// hash of a preimage depends on its length `p`.
+impl<T: Config> Benchmarks for Pallet<T>
+where
+    T::AccountId: From<&'static str>,
+    T::Currency: Default,
+    BalanceOf<T>: From<u32>,
+    T::BlockNumber: From<u32>,
+{
+    /// The condition of the escrow `0`, if any.
+    type Setup = Option<Condition<T::AccountId, T::BlockNumber>>;
+
+    /// A pallet in the block `1`, with the escrow `0` from the payer to the payee if given a
+    /// condition.
+    fn setup(condition: Option<Condition<T::AccountId, T::BlockNumber>>) -> Self {
+        let mut system = system::Pallet::new();
+        system.inc_block_number();
+        let mut currency = T::Currency::default();
+        currency
+            .deposit_creating("payer".into(), u32::MAX.into())
+            .expect("the account is empty");
+        let mut pallet = Pallet::new();
+        Borrower::<system::Pallet<T>>::lent(&mut pallet).lend(system);
+        pallet.currency.lend(currency);
+        if let Some(condition) = condition {
+            pallet
+                .create(
+                    "payer".into(),
+                    "payee".into(),
+                    1000.into(),
+                    condition,
+                    10.into(),
+                )
+                .expect("the payer can pay the amount");
+        }
+        pallet
+    }
+
+    fn benchmarks() -> Vec<Benchmark<Self>> {
+        let preimage = Component {
+            name: "p",
+            min: 0,
+            max: MAX_PREIMAGE_LENGTH,
+        };
+        vec![
+            Benchmark {
+                name: "create",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::create {
+                        payee: "payee".into(),
+                        amount: 1000.into(),
+                        condition: Condition::After(1.into()),
+                        expiry: 10.into(),
+                    };
+                    (Self::setup(None), "payer".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.escrow(0).is_some()),
+            },
+            Benchmark {
+                name: "release",
+                components: vec![],
+                setup: |_| {
+                    let pallet = Self::setup(Some(Condition::Arbiter("arbiter".into())));
+                    (pallet, "arbiter".into(), Call::release { id: 0 })
+                },
+                verify: |pallet, _| ensure(pallet.escrow(0).is_none()),
+            },
+            Benchmark {
+                name: "release_with_preimage",
+                components: vec![preimage],
+                setup: |p| {
+                    let preimage = vec![0; p[0] as usize];
+                    let pallet = Self::setup(Some(Condition::HashLock(blake2_256(&preimage))));
+                    let call = Call::release_with_preimage { id: 0, preimage };
+                    (pallet, "payee".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.escrow(0).is_none()),
+            },
+            Benchmark {
+                name: "refund",
+                components: vec![],
+                setup: |_| {
+                    let pallet = Self::setup(Some(Condition::Arbiter("arbiter".into())));
+                    (pallet, "arbiter".into(), Call::refund { id: 0 })
+                },
+                verify: |pallet, _| ensure(pallet.escrow(0).is_none()),
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances,
+        support::benchmarking::{
+            self,
+            BenchmarkConfig,
+        },
+    };
+
+    struct TestConfig;
+
+    impl system::Config for TestConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type SystemWeightInfo = ();
+    }
+
+    impl balances::Config for TestConfig {
+        type Balance = u128;
+        type WeightInfo = ();
+    }
+
+    impl Config for TestConfig {
+        type Currency = balances::Pallet<TestConfig>;
+        type WeightInfo = ();
+    }
+
+    fn alice() -> String {
+        "alice".to_string()
+    }
+
+    fn bob() -> String {
+        "bob".to_string()
+    }
+
+    fn charlie() -> String {
+        "charlie".to_string()
+    }
+
+    /// The pallet in the block `1`, lent a balances pallet in which alice has 1000.
+    fn new_pallet() -> Pallet<TestConfig> {
+        let mut system = system::Pallet::new();
+        system.inc_block_number();
+        let mut currency = balances::Pallet::new();
+        currency.set_balance(alice(), 1000);
+        let mut escrow = Pallet::new();
+        escrow.system.lend(system);
+        escrow.currency.lend(currency);
+        escrow
+    }
+
+    fn run_to_block(escrow: &mut Pallet<TestConfig>, block: u32) {
+        let system = escrow
+            .system
+            .get_mut()
+            .unwrap();
+        while system.block_number() < block {
+            system.inc_block_number();
+        }
+    }
+
+    /// The balance and reserved balance of `who`.
+    fn balances(escrow: &mut Pallet<TestConfig>, who: String) -> (u128, u128) {
+        let currency = escrow.currency().unwrap();
+        (
+            currency.balance(who.clone()),
+            currency.reserved_balance(who),
+        )
+    }
+
+    #[test]
+    fn create_escrows() {
+        let mut escrow = new_pallet();
+        let after = |block| Condition::After(block);
+        assert_eq!(
+            escrow.create(alice(), bob(), 0, after(2), 5),
+            Err("the escrow is empty")
+        );
+        assert_eq!(
+            escrow.create(alice(), bob(), 100, after(0), 1),
+            Err("the escrow is already expired")
+        );
+        assert_eq!(
+            escrow.create(alice(), bob(), 100, after(5), 5),
+            Err("the escrow expires before it can be released")
+        );
+        assert_eq!(
+            escrow.create(alice(), bob(), 1001, after(2), 5),
+            Err("Insufficient balance")
+        );
+
+        assert_eq!(escrow.create(alice(), bob(), 100, after(2), 5), Ok(()));
+        assert_eq!(balances(&mut escrow, alice()), (900, 100));
+        assert_eq!(
+            escrow.escrow(0),
+            Some(EscrowDetails {
+                payer: alice(),
+                payee: bob(),
+                amount: 100,
+                condition: after(2),
+                expiry: 5,
+            })
+        );
+        assert_eq!(
+            escrow.take_events(),
+            vec![Event::Created {
+                id: 0,
+                payer: alice(),
+                payee: bob(),
+                amount: 100,
+                expiry: 5,
+            }]
+        );
+    }
+
+    #[test]
+    fn time_locks() {
+        let mut escrow = new_pallet();
+        escrow
+            .create(alice(), bob(), 100, Condition::After(3), 5)
+            .unwrap();
+        assert_eq!(
+            escrow.release(charlie(), 0),
+            Err("the time lock of the escrow is not over")
+        );
+        assert_eq!(
+            escrow.refund(charlie(), 0),
+            Err("the escrow is not expired")
+        );
+
+        run_to_block(&mut escrow, 3);
+        assert_eq!(escrow.release(charlie(), 0), Ok(()));
+        assert_eq!(escrow.escrow(0), None);
+        assert_eq!(balances(&mut escrow, alice()), (900, 0));
+        assert_eq!(balances(&mut escrow, bob()), (100, 0));
+        assert_eq!(escrow.release(charlie(), 0), Err("unknown escrow"));
+
+        // Once expired, the escrow can only be refunded.
+        escrow
+            .create(alice(), bob(), 100, Condition::After(4), 5)
+            .unwrap();
+        run_to_block(&mut escrow, 5);
+        assert_eq!(escrow.release(bob(), 1), Err("the escrow is expired"));
+        assert_eq!(escrow.refund(charlie(), 1), Ok(()));
+        assert_eq!(balances(&mut escrow, alice()), (900, 0));
+        assert_eq!(
+            escrow.take_events().last(),
+            Some(&Event::Refunded {
+                id: 1,
+                payer: alice(),
+                amount: 100,
+            })
+        );
+    }
+
+    #[test]
+    fn hash_locks() {
+        let mut escrow = new_pallet();
+        let secret = b"secret".to_vec();
+        escrow
+            .create(
+                alice(),
+                bob(),
+                100,
+                Condition::HashLock(blake2_256(&secret)),
+                5,
+            )
+            .unwrap();
+        assert_eq!(
+            escrow.release(bob(), 0),
+            Err("the escrow is locked by a hash")
+        );
+        assert_eq!(
+            escrow.release_with_preimage(bob(), 0, b"guess".to_vec()),
+            Err("invalid preimage")
+        );
+        escrow.take_events();
+
+        // Anyone knowing the preimage can release the escrow, which reveals it.
+        assert_eq!(
+            escrow.release_with_preimage(charlie(), 0, secret.clone()),
+            Ok(())
+        );
+        assert_eq!(balances(&mut escrow, bob()), (100, 0));
+        assert_eq!(
+            escrow.take_events(),
+            vec![
+                Event::PreimageRevealed {
+                    id: 0,
+                    preimage: secret.clone(),
+                },
+                Event::Released {
+                    id: 0,
+                    payee: bob(),
+                    amount: 100,
+                },
+            ]
+        );
+
+        escrow
+            .create(alice(), bob(), 100, Condition::After(2), 5)
+            .unwrap();
+        assert_eq!(
+            escrow.release_with_preimage(bob(), 1, secret),
+            Err("the escrow is not locked by a hash")
+        );
+    }
+
+    #[test]
+    fn arbiters_settle_escrows() {
+        let mut escrow = new_pallet();
+        let arbiter = || Condition::Arbiter(charlie());
+        escrow
+            .create(alice(), bob(), 100, arbiter(), 5)
+            .unwrap();
+        escrow
+            .create(alice(), bob(), 200, arbiter(), 5)
+            .unwrap();
+        assert_eq!(
+            escrow.release(bob(), 0),
+            Err("not the arbiter of the escrow")
+        );
+        assert_eq!(escrow.refund(alice(), 0), Err("the escrow is not expired"));
+
+        assert_eq!(escrow.release(charlie(), 0), Ok(()));
+        assert_eq!(escrow.refund(charlie(), 1), Ok(()));
+        assert_eq!(balances(&mut escrow, alice()), (900, 0));
+        assert_eq!(balances(&mut escrow, bob()), (100, 0));
+    }
+
+    #[test]
+    fn payers_release_and_payees_decline() {
+        let mut escrow = new_pallet();
+        let secret = blake2_256(b"secret");
+        escrow
+            .create(alice(), bob(), 100, Condition::HashLock(secret), 5)
+            .unwrap();
+        escrow
+            .create(alice(), bob(), 200, Condition::HashLock(secret), 5)
+            .unwrap();
+
+        assert_eq!(escrow.release(alice(), 0), Ok(()));
+        assert_eq!(escrow.refund(bob(), 1), Ok(()));
+        assert_eq!(balances(&mut escrow, alice()), (900, 0));
+        assert_eq!(balances(&mut escrow, bob()), (100, 0));
+    }
+
+    #[test]
+    fn calls_fail_without_the_lent_pallets() {
+        let mut escrow = Pallet::<TestConfig>::new();
+        assert_eq!(
+            escrow.create(alice(), bob(), 100, Condition::After(2), 5),
+            Err("the pallet was not lent by the runtime")
+        );
+    }
+
+    /// A currency whose funds are all frozen: nothing can be moved out of the balance of 1000 of
+    /// every account.
+    #[derive(Debug, Default)]
+    struct FrozenCurrency;
+
+    impl Currency<String> for FrozenCurrency {
+        type Balance = u128;
+
+        fn free_balance(&self, _who: String) -> u128 {
+            1000
+        }
+
+        fn total_issuance(&self) -> Result<u128, &'static str> {
+            Err("unknown total issuance")
+        }
+
+        fn transfer(&mut self, _from: String, _to: String, _amount: u128) -> DispatchResult {
+            Err("frozen")
+        }
+
+        fn deposit_creating(&mut self, _who: String, _amount: u128) -> DispatchResult {
+            Err("frozen")
+        }
+
+        fn withdraw(&mut self, _who: String, _amount: u128) -> DispatchResult {
+            Err("frozen")
+        }
+
+        fn slash(&mut self, _who: String, amount: u128) -> u128 {
+            amount
+        }
+    }
+
+    impl ReservableCurrency<String> for FrozenCurrency {
+        fn reserved_balance(&self, _who: String) -> u128 {
+            0
+        }
+
+        fn reserve(&mut self, _who: String, _amount: u128) -> DispatchResult {
+            Err("frozen")
+        }
+
+        fn unreserve(&mut self, _who: String, _amount: u128) -> DispatchResult {
+            Err("frozen")
+        }
+
+        fn repatriate_reserved(
+            &mut self,
+            _from: String,
+            _to: String,
+            _amount: u128,
+        ) -> DispatchResult {
+            Err("frozen")
+        }
+
+        fn slash_reserved(&mut self, _who: String, amount: u128) -> u128 {
+            amount
+        }
+    }
+
+    struct FrozenConfig;
+
+    impl system::Config for FrozenConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type SystemWeightInfo = ();
+    }
+
+    impl Config for FrozenConfig {
+        type Currency = FrozenCurrency;
+        type WeightInfo = ();
+    }
+
+    #[test]
+    fn escrows_are_reserved_with_the_currency_of_the_config() {
+        let mut escrow = Pallet::<FrozenConfig>::new();
+        escrow
+            .system
+            .lend(system::Pallet::new());
+        escrow
+            .currency
+            .lend(FrozenCurrency);
+        assert_eq!(
+            escrow.create(alice(), bob(), 100, Condition::After(2), 5),
+            Err("frozen")
+        );
+        assert_eq!(escrow.escrow(0), None);
+        assert_eq!(escrow.take_events(), vec![]);
+    }
+
+    #[test]
+    fn benchmarks_succeed() {
+        let config = BenchmarkConfig {
+            steps: 2,
+            repeat: 1,
+        };
+        let results = benchmarking::run::<Pallet<TestConfig>>(&config).unwrap();
+        assert_eq!(results.len(), 4);
+    }
+}
@@ -0,0 +1,54 @@
+//! The weights of the `escrow` pallet, measured by its benchmarks.
+//!
+//! Generated by `cargo run --release --bin benchmark`, do not edit by hand. The unit of weight is
+//! one nanosecond of execution on the machine which ran the benchmarks.
+
+use crate::support::Weight;
+
+/// The weights of the callable functions of the pallet.
+pub trait WeightInfo {
+    fn create() -> Weight;
+    fn release() -> Weight;
+    fn release_with_preimage(p: u32) -> Weight;
+    fn refund() -> Weight;
+}
+
+/// The weights measured by the benchmarks.
+pub struct BenchmarkedWeights;
+
+impl WeightInfo for BenchmarkedWeights {
+    fn create() -> Weight {
+        1985
+    }
+
+    fn release() -> Weight {
+        3906
+    }
+
+    fn release_with_preimage(p: u32) -> Weight {
+        2616 + Weight::from(p)
+    }
+
+    fn refund() -> Weight {
+        2503
+    }
+}
+
+/// No weights, for tests.
+impl WeightInfo for () {
+    fn create() -> Weight {
+        0
+    }
+
+    fn release() -> Weight {
+        0
+    }
+
+    fn release_with_preimage(_p: u32) -> Weight {
+        0
+    }
+
+    fn refund() -> Weight {
+        0
+    }
+}
@@ -0,0 +1,1093 @@
+//! The evm pallet: Ethereum contracts, run by the `revm` interpreter.
+//!
+//! Every account has an EVM address of 20 bytes, derived from its id by `address_of`. An address
+//! is mapped back to its account once the account makes an EVM call, and the other addresses, like
+//! the ones of the EVM contracts, are mapped to an account derived from them, so the balances of
+//! all the addresses are held in the currency of the config. The funds moved by an execution are
+//! withdrawn from the accounts it debited and deposited to the ones it credited. Funds sent to the
+//! address of an account before it made its first EVM call are moved to it by that call.
+//!
+//! The pallet stores the code, the nonce and the storage of every address. The gas used by a call
+//! is charged at `GAS_PRICE` in the native token and burned, even if the execution fails, and the
+//! logs of a successful execution are emitted as `Log` events.
+//!
+//! The weight of a call includes its whole gas limit at `WEIGHT_PER_GAS`, so the executions in a
+//! block are bounded by the maximum block weight, like its other calls.
+//!
+//! The runtime lends the system pallet, for the current block, and the currency, like the balances
+//! pallet with `#[lend(system, balances)]`.
+
+use codec::Encode;
+use num::{
+    CheckedSub,
+    Zero,
+};
+use revm::{
+    primitives::{
+        keccak256,
+        AccountInfo,
+        Address,
+        Bytecode,
+        EVMError,
+        ExecutionResult,
+        Output,
+        TxKind,
+        B256,
+        KECCAK_EMPTY,
+        U256,
+    },
+    Database,
+    Evm,
+};
+use serde::Serialize;
+
+use crate::{
+    support::{
+        benchmarking::{
+            ensure,
+            Benchmark,
+            Benchmarks,
+            Component,
+        },
+        storage::{
+            blake2_256,
+            StorageDoubleMap,
+            StorageMap,
+        },
+        AsCurrency,
+        Borrower,
+        Currency,
+        DispatchResult,
+        Hooks,
+        Lent,
+        OnRuntimeUpgrade,
+        Weight,
+    },
+    system,
+};
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+/// An EVM address.
+pub type H160 = [u8; 20];
+
+/// The longest init code and input measured by the benchmarks, in bytes.
+pub const MAX_BENCHMARKED_INPUT: u32 = 32 * 1024;
+
+/// An address which is not the one of an account, like the one of an EVM contract. Its account is
+/// derived from it, so it has no secret key and only the EVM can move its funds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvmAddress(pub H160);
+
+impl EvmAddress {
+    /// The public key of the account of the address, which is the hash of the address.
+    pub fn public_key(&self) -> [u8; 32] {
+        blake2_256(&[b"evm:".as_slice(), &self.0].concat())
+    }
+}
+
+pub trait Config: system::Config<AccountId: From<EvmAddress>, BlockNumber: Into<u64>> {
+    /// The currency holding the balances of the addresses.
+    type Currency: Currency<Self::AccountId, Balance: Into<u128> + TryFrom<u128>>;
+    /// The id of the chain, which the transactions signed for Ethereum are bound to.
+    const CHAIN_ID: u64;
+    /// The price of a unit of gas, in the native token.
+    const GAS_PRICE: BalanceOf<Self>;
+    /// The most gas a call can be given.
+    const MAX_GAS_LIMIT: u64;
+    /// The weight of a unit of gas, the most time the EVM can take to use it. A call given
+    /// `MAX_GAS_LIMIT` must fit in a block.
+    const WEIGHT_PER_GAS: Weight;
+    /// The weights of the calls, see the `weights` module.
+    type WeightInfo: WeightInfo;
+}
+
+/// The balances of the EVM addresses, in the currency of the runtime `T`.
+pub type BalanceOf<T> =
+    <<T as Config>::Currency as Currency<<T as system::Config>::AccountId>>::Balance;
+
+/// The events emitted by the evm pallet.
+#[derive(
+    macros::CloneNoBound,
+    macros::DebugNoBound,
+    macros::PartialEqNoBound,
+    macros::EqNoBound,
+    macros::TypeInfo,
+    Serialize,
+)]
+pub enum Event<T: Config> {
+    /// `creator` deployed a contract at `address`.
+    Created {
+        creator: T::AccountId,
+        #[serde(with = "hex::serde")]
+        address: H160,
+    },
+    /// The contract of `creator` failed to deploy at `address`.
+    CreatedFailed {
+        creator: T::AccountId,
+        #[serde(with = "hex::serde")]
+        address: H160,
+    },
+    /// `caller` called `address`.
+    Executed {
+        caller: T::AccountId,
+        #[serde(with = "hex::serde")]
+        address: H160,
+    },
+    /// The call of `caller` to `address` failed.
+    ExecutedFailed {
+        caller: T::AccountId,
+        #[serde(with = "hex::serde")]
+        address: H160,
+    },
+    /// The contract at `address` emitted a log.
+    Log {
+        #[serde(with = "hex::serde")]
+        address: H160,
+        topics: Vec<[u8; 32]>,
+        data: Vec<u8>,
+    },
+}
+
+#[derive(Debug)]
+#[macros::storage]
+pub struct Pallet<T: Config> {
+    /// The accounts which made an EVM call, by their address.
+    accounts: StorageMap<H160, T::AccountId>,
+    /// The code of the contracts.
+    code: StorageMap<H160, Vec<u8>>,
+    /// The nonces of the addresses, counting their transactions and the contracts they created.
+    #[default(0)]
+    nonces: StorageMap<H160, u64>,
+    /// The storage of the contracts, by slot.
+    #[default([0; 32])]
+    contract_storage: StorageDoubleMap<H160, [u8; 32], [u8; 32]>,
+    /// The system pallet, giving the current block.
+    system: Lent<system::Pallet<T>>,
+    /// The currency, holding the balances of the addresses.
+    currency: Lent<T::Currency>,
+    /// The events emitted since the runtime last collected them.
+    events: Vec<Event<T>>,
+}
+
+#[macros::call]
+#[errors(
+    "the gas limit is too high",
+    "insufficient balance to pay the gas",
+    "the cost of the gas overflows",
+    "the balance overflows",
+    "unknown code hash",
+    "invalid EVM transaction",
+    "the EVM failed",
+    crate::support::NOT_LENT,
+    crate::support::INSUFFICIENT_BALANCE,
+    crate::support::INSUFFICIENT_RESERVED_BALANCE,
+    crate::support::OVERFLOW
+)]
+impl<T: Config> Pallet<T> {
+    /// Deploy a contract by running `init_code`, giving it `value` from the balance of the
+    /// `caller`.
+    #[call_index(0)]
+    #[weight(<T as Config>::WeightInfo::create(init_code.len() as u32)
+        .saturating_add(Pallet::<T>::gas_to_weight(*gas_limit)))]
+    pub fn create(
+        &mut self,
+        caller: T::AccountId,
+        init_code: Vec<u8>,
+        value: BalanceOf<T>,
+        gas_limit: u64,
+    ) -> DispatchResult {
+        let creator = Self::address_of(&caller);
+        let address = Address::from(creator).create(self.nonces.get(&creator));
+        let result = self.execute(&caller, TxKind::Create, init_code, value, gas_limit)?;
+        let address = match result {
+            ExecutionResult::Success {
+                output: Output::Create(_, Some(address)),
+                ..
+            } => {
+                self.deposit_event(Event::Created {
+                    creator: caller,
+                    address: address.into(),
+                });
+                return Ok(());
+            }
+            _ => address.into(),
+        };
+        self.deposit_event(Event::CreatedFailed {
+            creator: caller,
+            address,
+        });
+        Ok(())
+    }
+
+    /// Call `target` with `input`, giving it `value` from the balance of the `caller`.
+    #[call_index(1)]
+    #[weight(<T as Config>::WeightInfo::call(input.len() as u32)
+        .saturating_add(Pallet::<T>::gas_to_weight(*gas_limit)))]
+    pub fn call(
+        &mut self,
+        caller: T::AccountId,
+        target: H160,
+        input: Vec<u8>,
+        value: BalanceOf<T>,
+        gas_limit: u64,
+    ) -> DispatchResult {
+        let to = TxKind::Call(target.into());
+        let result = self.execute(&caller, to, input, value, gas_limit)?;
+        let event = match result.is_success() {
+            true => Event::Executed {
+                caller,
+                address: target,
+            },
+            false => Event::ExecutedFailed {
+                caller,
+                address: target,
+            },
+        };
+        self.deposit_event(event);
+        Ok(())
+    }
+}
+
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// The EVM address of `who`: the last 20 bytes of the hash of its encoding.
+    pub fn address_of(who: &T::AccountId) -> H160 {
+        let hash = blake2_256(&who.encode());
+        let mut address = [0; 20];
+        address.copy_from_slice(&hash[12..]);
+        address
+    }
+
+    /// The account of `address`: the account it is the address of, if that account made an EVM
+    /// call, or else the account derived from the address.
+    pub fn account_of(&self, address: &H160) -> T::AccountId {
+        self.accounts
+            .try_get(address)
+            .unwrap_or_else(|| EvmAddress(*address).into())
+    }
+
+    /// The weight of `gas`, charged for the gas limit of a call on top of the weight of the call.
+    pub fn gas_to_weight(gas: u64) -> Weight {
+        T::WEIGHT_PER_GAS.saturating_mul(gas)
+    }
+
+    pub fn code(&self, address: &H160) -> Option<Vec<u8>> {
+        self.code.try_get(address)
+    }
+
+    /// The value of the storage slot `index` of the contract at `address`.
+    pub fn storage(&self, address: &H160, index: &[u8; 32]) -> [u8; 32] {
+        self.contract_storage
+            .get(address, index)
+    }
+
+    /// Run a transaction of `caller`, apply its changes and charge its gas.
+    fn execute(
+        &mut self,
+        caller: &T::AccountId,
+        to: TxKind,
+        data: Vec<u8>,
+        value: BalanceOf<T>,
+        gas_limit: u64,
+    ) -> Result<ExecutionResult, &'static str> {
+        if gas_limit > T::MAX_GAS_LIMIT {
+            return Err("the gas limit is too high");
+        }
+        let address = self.map_account(caller)?;
+        let gas_price: u128 = T::GAS_PRICE.into();
+        let max_cost = u128::from(gas_limit)
+            .saturating_mul(gas_price)
+            .saturating_add(value.into());
+        if self
+            .currency()?
+            .free_balance(caller.clone())
+            .into()
+            < max_cost
+        {
+            return Err("insufficient balance to pay the gas");
+        }
+        let number = self
+            .system
+            .get_mut()?
+            .block_number()
+            .into();
+
+        let mut evm = Evm::builder()
+            .with_db(Backend { pallet: self })
+            .modify_cfg_env(|cfg| cfg.chain_id = T::CHAIN_ID)
+            .modify_block_env(|block| block.number = U256::from(number))
+            .modify_tx_env(|tx| {
+                tx.caller = address.into();
+                tx.transact_to = to;
+                tx.data = data.into();
+                tx.value = U256::from(value.into());
+                tx.gas_limit = gas_limit;
+                tx.gas_price = U256::ZERO;
+                tx.nonce = None;
+            })
+            .build();
+        let outcome = evm.transact();
+        drop(evm);
+        let outcome = outcome.map_err(|e| match e {
+            EVMError::Database(e) => e,
+            EVMError::Transaction(_) => "invalid EVM transaction",
+            _ => "the EVM failed",
+        })?;
+
+        for (address, account) in outcome.state {
+            if !account.is_touched() {
+                continue;
+            }
+            let address: H160 = address.into();
+            let balance = match account.is_selfdestructed() {
+                true => {
+                    self.code.remove(&address);
+                    self.nonces.remove(&address);
+                    self.contract_storage
+                        .remove_prefix(&address);
+                    Zero::zero()
+                }
+                false => self.apply_changes(address, account)?,
+            };
+            let who = self.account_of(&address);
+            let currency = self.currency()?;
+            let current = currency.free_balance(who.clone());
+            match balance.checked_sub(&current) {
+                Some(increase) => currency.deposit_creating(who, increase)?,
+                None => currency.withdraw(who, current - balance)?,
+            }
+        }
+
+        let gas_used = outcome.result.gas_used();
+        let cost = <BalanceOf<T>>::try_from(u128::from(gas_used).saturating_mul(gas_price))
+            .map_err(|_| "the cost of the gas overflows")?;
+        self.currency()?
+            .withdraw(caller.clone(), cost)?;
+        if let ExecutionResult::Success { logs, .. } = &outcome.result {
+            for log in logs {
+                self.deposit_event(Event::Log {
+                    address: log.address.into(),
+                    topics: log
+                        .topics()
+                        .iter()
+                        .map(|topic| topic.0)
+                        .collect(),
+                    data: log.data.data.to_vec(),
+                });
+            }
+        }
+        Ok(outcome.result)
+    }
+
+    /// Store the code, the nonce and the storage changed by a transaction for `address`, and
+    /// return its new balance.
+    fn apply_changes(
+        &mut self,
+        address: H160,
+        account: revm::primitives::Account,
+    ) -> Result<BalanceOf<T>, &'static str> {
+        if account.is_created() {
+            if let Some(code) = account
+                .info
+                .code
+                .filter(|code| !code.is_empty())
+            {
+                self.code
+                    .insert(&address, code.original_bytes().to_vec());
+            }
+        }
+        match account.info.nonce {
+            0 => self.nonces.remove(&address),
+            nonce => self
+                .nonces
+                .insert(&address, nonce),
+        }
+        for (index, slot) in account.storage {
+            if !slot.is_changed() {
+                continue;
+            }
+            let index = index.to_be_bytes::<32>();
+            match slot.present_value {
+                U256::ZERO => self
+                    .contract_storage
+                    .remove(&address, &index),
+                value => self
+                    .contract_storage
+                    .insert(&address, &index, value.to_be_bytes::<32>()),
+            }
+        }
+        u128::try_from(account.info.balance)
+            .ok()
+            .and_then(|balance| <BalanceOf<T>>::try_from(balance).ok())
+            .ok_or("the balance overflows")
+    }
+
+    /// Map the address of `who` to it, and move to it the funds sent to its address before.
+    fn map_account(&mut self, who: &T::AccountId) -> Result<H160, &'static str> {
+        let address = Self::address_of(who);
+        if self
+            .accounts
+            .contains_key(&address)
+        {
+            return Ok(address);
+        }
+        self.accounts
+            .insert(&address, who.clone());
+        let derived: T::AccountId = EvmAddress(address).into();
+        let currency = self.currency()?;
+        let balance = currency.free_balance(derived.clone());
+        if !balance.is_zero() {
+            currency.transfer(derived, who.clone(), balance)?;
+        }
+        Ok(address)
+    }
+
+    /// The currency lent by the runtime.
+    fn currency(&mut self) -> Result<&mut T::Currency, &'static str> {
+        self.currency.get_mut()
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    /// Take the events emitted since the last call, so the runtime can record them.
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+/// The state of the pallet, as seen by the EVM.
+struct Backend<'a, T: Config> {
+    pallet: &'a mut Pallet<T>,
+}
+
+impl<T: Config> Database for Backend<'_, T> {
+    type Error = &'static str;
+
+    /// The addresses without balance, nonce nor code don't exist.
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let address: H160 = address.into();
+        let who = self
+            .pallet
+            .account_of(&address);
+        let balance: u128 = self
+            .pallet
+            .currency()?
+            .free_balance(who)
+            .into();
+        let nonce = self
+            .pallet
+            .nonces
+            .get(&address);
+        let code = self.pallet.code(&address);
+        if balance == 0 && nonce == 0 && code.is_none() {
+            return Ok(None);
+        }
+        let info = match code {
+            Some(code) => AccountInfo::new(
+                U256::from(balance),
+                nonce,
+                keccak256(&code),
+                Bytecode::new_raw(code.into()),
+            ),
+            None => AccountInfo::new(U256::from(balance), nonce, KECCAK_EMPTY, Bytecode::new()),
+        };
+        Ok(Some(info))
+    }
+
+    /// The code is always given with the account, by `basic`.
+    fn code_by_hash(&mut self, _code_hash: B256) -> Result<Bytecode, Self::Error> {
+        Err("unknown code hash")
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        let value = self
+            .pallet
+            .storage(&address.into(), &index.to_be_bytes());
+        Ok(U256::from_be_bytes(value))
+    }
+
+    /// The hashes of the blocks are not known by the runtime, so they are zero.
+    fn block_hash(&mut self, _number: U256) -> Result<B256, Self::Error> {
+        Ok(B256::ZERO)
+    }
+}
+
+impl<T: Config> Borrower<system::Pallet<T>> for Pallet<T> {
+    fn lent(&mut self) -> &mut Lent<system::Pallet<T>> {
+        &mut self.system
+    }
+}
+
+impl<T: Config> Borrower<T::Currency, AsCurrency> for Pallet<T> {
+    fn lent(&mut self) -> &mut Lent<T::Currency> {
+        &mut self.currency
+    }
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+/// The init code of the benchmarks, which returns no code: `PUSH1 0, PUSH1 0, RETURN`.
+const INIT_CODE: [u8; 5] = [0x60, 0x00, 0x60, 0x00, 0xf3];
+
+/// A contract is deployed with an init code returning no code, followed by `c` bytes which are
+/// never run, and called with an input of `i` bytes and a value, which is what is left of a call
+/// once its gas is paid for. The gas limit is left out, it is charged by `Pallet::gas_to_weight`.
+impl<T: Config> Benchmarks for Pallet<T>
+where
+    T::AccountId: From<&'static str>,
+    T::Currency: Default,
+    BalanceOf<T>: From<u32>,
+{
+    /// Whether a contract is deployed.
+    type Setup = bool;
+
+    /// A pallet in which the caller can pay, with a contract deployed if `deploy`.
+    fn setup(deploy: bool) -> Self {
+        let mut currency = T::Currency::default();
+        currency
+            .deposit_creating("caller".into(), u32::MAX.into())
+            .expect("the account is empty");
+        let mut pallet = Pallet::new();
+        Borrower::<system::Pallet<T>>::lent(&mut pallet).lend(system::Pallet::new());
+        pallet.currency.lend(currency);
+        if deploy {
+            pallet
+                .create("caller".into(), INIT_CODE.to_vec(), Zero::zero(), 100_000)
+                .expect("the caller can pay the gas");
+        }
+        pallet
+    }
+
+    fn benchmarks() -> Vec<Benchmark<Self>> {
+        let input = Component {
+            name: "i",
+            min: 0,
+            max: MAX_BENCHMARKED_INPUT,
+        };
+        vec![
+            Benchmark {
+                name: "create",
+                components: vec![Component { name: "c", ..input }],
+                setup: |c| {
+                    let mut init_code = INIT_CODE.to_vec();
+                    init_code.resize(INIT_CODE.len() + c[0] as usize, 0);
+                    let call = Call::create {
+                        init_code,
+                        value: 1000u32.into(),
+                        gas_limit: 1_000_000,
+                    };
+                    (Self::setup(false), "caller".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(matches!(pallet.events.last(), Some(Event::Created { .. })))
+                },
+            },
+            Benchmark {
+                name: "call",
+                components: vec![input],
+                setup: |i| {
+                    let creator = Pallet::<T>::address_of(&"caller".into());
+                    let call = Call::call {
+                        target: Address::from(creator)
+                            .create(0)
+                            .into(),
+                        input: vec![0; i[0] as usize],
+                        value: 1000u32.into(),
+                        gas_limit: 1_000_000,
+                    };
+                    (Self::setup(true), "caller".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(matches!(pallet.events.last(), Some(Event::Executed { .. })))
+                },
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances,
+        support::benchmarking::{
+            self,
+            BenchmarkConfig,
+        },
+    };
+
+    struct TestConfig;
+
+    impl system::Config for TestConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type SystemWeightInfo = ();
+    }
+
+    impl balances::Config for TestConfig {
+        type Balance = u128;
+        type WeightInfo = ();
+    }
+
+    impl Config for TestConfig {
+        type Currency = balances::Pallet<TestConfig>;
+        const CHAIN_ID: u64 = 42;
+        const GAS_PRICE: u128 = 2;
+        const MAX_GAS_LIMIT: u64 = 10_000_000;
+        const WEIGHT_PER_GAS: Weight = 20;
+        type WeightInfo = ();
+    }
+
+    /// The instructions of the assembler of the fixtures.
+    enum Asm {
+        Op(u8),
+        Push(Vec<u8>),
+        /// The position of the next instruction, which `Target` pushes.
+        Label(&'static str),
+        Target(&'static str),
+    }
+
+    use Asm::*;
+
+    const ADD: Asm = Op(0x01);
+    const SUB: Asm = Op(0x03);
+    const LT: Asm = Op(0x10);
+    const EQ: Asm = Op(0x14);
+    const SHR: Asm = Op(0x1c);
+    const SHA3: Asm = Op(0x20);
+    const CALLER: Asm = Op(0x33);
+    const CALLDATALOAD: Asm = Op(0x35);
+    const CODECOPY: Asm = Op(0x39);
+    const SLOAD: Asm = Op(0x54);
+    const SSTORE: Asm = Op(0x55);
+    const MSTORE: Asm = Op(0x52);
+    const JUMP: Asm = Op(0x56);
+    const JUMPI: Asm = Op(0x57);
+    const JUMPDEST: Asm = Op(0x5b);
+    const DUP1: Asm = Op(0x80);
+    const DUP3: Asm = Op(0x82);
+    const SWAP1: Asm = Op(0x90);
+    const LOG3: Asm = Op(0xa3);
+    const RETURN: Asm = Op(0xf3);
+    const REVERT: Asm = Op(0xfd);
+
+    fn push(value: u128) -> Asm {
+        let bytes = value.to_be_bytes();
+        let start = bytes
+            .iter()
+            .position(|byte| *byte != 0)
+            .unwrap_or(15);
+        Push(bytes[start..].to_vec())
+    }
+
+    /// Assemble `code`, where the targets are pushed with `PUSH2`.
+    fn assemble(code: &[Asm]) -> Vec<u8> {
+        let mut labels = std::collections::HashMap::new();
+        let mut position = 0;
+        for asm in code {
+            match asm {
+                Op(_) => position += 1,
+                Push(bytes) => position += 1 + bytes.len(),
+                Label(label) => {
+                    labels.insert(*label, position as u16);
+                }
+                Target(_) => position += 3,
+            }
+        }
+        let mut bytes = vec![];
+        for asm in code {
+            match asm {
+                Op(op) => bytes.push(*op),
+                Push(value) => {
+                    bytes.push(0x5f + value.len() as u8);
+                    bytes.extend(value);
+                }
+                Label(_) => {}
+                Target(label) => {
+                    bytes.push(0x61);
+                    bytes.extend(labels[label].to_be_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    const SUPPLY: u128 = 1_000_000;
+
+    fn selector(signature: &str) -> Vec<u8> {
+        keccak256(signature)[..4].to_vec()
+    }
+
+    fn transfer_topic() -> [u8; 32] {
+        keccak256("Transfer(address,address,uint256)").0
+    }
+
+    /// An ERC-20 token with `totalSupply`, `balanceOf` and `transfer`, which mints `SUPPLY` to its
+    /// deployer. The balance of an address is in the slot `keccak256(address . 0)`, like in a
+    /// Solidity mapping in the slot 0, and the total supply in the slot 1.
+    fn erc20() -> Vec<u8> {
+        let runtime = assemble(&[
+            push(0),
+            CALLDATALOAD,
+            push(0xe0),
+            SHR,
+            DUP1,
+            Push(selector("totalSupply()")),
+            EQ,
+            Target("total_supply"),
+            JUMPI,
+            DUP1,
+            Push(selector("balanceOf(address)")),
+            EQ,
+            Target("balance_of"),
+            JUMPI,
+            DUP1,
+            Push(selector("transfer(address,uint256)")),
+            EQ,
+            Target("transfer"),
+            JUMPI,
+            Target("fail"),
+            JUMP,
+            Label("total_supply"),
+            JUMPDEST,
+            push(1),
+            SLOAD,
+            Target("return_word"),
+            JUMP,
+            Label("balance_of"),
+            JUMPDEST,
+            push(4),
+            CALLDATALOAD,
+            push(0),
+            MSTORE,
+            push(64),
+            push(0),
+            SHA3,
+            SLOAD,
+            Target("return_word"),
+            JUMP,
+            Label("return_word"),
+            JUMPDEST,
+            push(0),
+            MSTORE,
+            push(32),
+            push(0),
+            RETURN,
+            // Debit the caller, reverting if its balance is too low.
+            Label("transfer"),
+            JUMPDEST,
+            CALLER,
+            push(0),
+            MSTORE,
+            push(64),
+            push(0),
+            SHA3,
+            DUP1,
+            SLOAD,
+            push(36),
+            CALLDATALOAD,
+            DUP1,
+            DUP3,
+            LT,
+            Target("fail"),
+            JUMPI,
+            SWAP1,
+            SUB,
+            SWAP1,
+            SSTORE,
+            // Credit the recipient.
+            push(4),
+            CALLDATALOAD,
+            push(0),
+            MSTORE,
+            push(64),
+            push(0),
+            SHA3,
+            DUP1,
+            SLOAD,
+            push(36),
+            CALLDATALOAD,
+            ADD,
+            SWAP1,
+            SSTORE,
+            // Log `Transfer(caller, recipient, amount)` and return true.
+            push(36),
+            CALLDATALOAD,
+            push(0),
+            MSTORE,
+            push(4),
+            CALLDATALOAD,
+            CALLER,
+            Push(transfer_topic().to_vec()),
+            push(32),
+            push(0),
+            LOG3,
+            push(1),
+            Target("return_word"),
+            JUMP,
+            Label("fail"),
+            JUMPDEST,
+            push(0),
+            DUP1,
+            REVERT,
+        ]);
+        let constructor = |offset: u16| {
+            assemble(&[
+                // Mint the supply to the deployer.
+                push(SUPPLY),
+                CALLER,
+                push(0),
+                MSTORE,
+                push(64),
+                push(0),
+                SHA3,
+                SSTORE,
+                push(SUPPLY),
+                push(1),
+                SSTORE,
+                // Return the runtime code, which follows the constructor.
+                Push(
+                    (runtime.len() as u16)
+                        .to_be_bytes()
+                        .to_vec(),
+                ),
+                DUP1,
+                Push(offset.to_be_bytes().to_vec()),
+                push(0),
+                CODECOPY,
+                push(0),
+                RETURN,
+            ])
+        };
+        let offset = constructor(0).len() as u16;
+        [constructor(offset), runtime].concat()
+    }
+
+    fn word(address: H160) -> [u8; 32] {
+        let mut word = [0; 32];
+        word[12..].copy_from_slice(&address);
+        word
+    }
+
+    fn transfer(to: H160, amount: u128) -> Vec<u8> {
+        let amount = U256::from(amount).to_be_bytes::<32>();
+        [
+            selector("transfer(address,uint256)"),
+            word(to).to_vec(),
+            amount.to_vec(),
+        ]
+        .concat()
+    }
+
+    /// The slot of the balance of `who` in the token.
+    fn balance_slot(who: H160) -> [u8; 32] {
+        keccak256([word(who), [0; 32]].concat()).0
+    }
+
+    fn alice() -> String {
+        "alice".to_string()
+    }
+
+    fn bob() -> String {
+        "bob".to_string()
+    }
+
+    fn address(who: &String) -> H160 {
+        Pallet::<TestConfig>::address_of(who)
+    }
+
+    /// The pallet lent a system pallet and a balances pallet in which alice has 10^8.
+    fn new_pallet() -> Pallet<TestConfig> {
+        let mut currency = balances::Pallet::new();
+        currency.set_balance(alice(), 100_000_000);
+        let mut pallet = Pallet::new();
+        pallet
+            .system
+            .lend(system::Pallet::new());
+        pallet.currency.lend(currency);
+        pallet
+    }
+
+    fn balance(pallet: &mut Pallet<TestConfig>, who: String) -> u128 {
+        pallet
+            .currency()
+            .unwrap()
+            .balance(who)
+    }
+
+    /// Deploy the token of alice, returning its address.
+    fn deploy(pallet: &mut Pallet<TestConfig>) -> H160 {
+        pallet
+            .create(alice(), erc20(), 0, 1_000_000)
+            .unwrap();
+        let token = Address::from(address(&alice()))
+            .create(0)
+            .into();
+        assert_eq!(
+            pallet.take_events(),
+            vec![Event::Created {
+                creator: alice(),
+                address: token,
+            }]
+        );
+        token
+    }
+
+    #[test]
+    fn erc20_tokens_can_be_deployed_and_called() {
+        let mut pallet = new_pallet();
+        let token = deploy(&mut pallet);
+        assert!(pallet.code(&token).is_some());
+        let slot =
+            |who: &String| U256::from_be_bytes(pallet.storage(&token, &balance_slot(address(who))));
+        assert_eq!(slot(&alice()), U256::from(SUPPLY));
+        assert_eq!(slot(&bob()), U256::ZERO);
+
+        pallet
+            .call(alice(), token, transfer(address(&bob()), 100), 0, 100_000)
+            .unwrap();
+        assert_eq!(
+            pallet.take_events(),
+            vec![
+                Event::Log {
+                    address: token,
+                    topics: vec![
+                        transfer_topic(),
+                        word(address(&alice())),
+                        word(address(&bob()))
+                    ],
+                    data: U256::from(100)
+                        .to_be_bytes::<32>()
+                        .to_vec(),
+                },
+                Event::Executed {
+                    caller: alice(),
+                    address: token,
+                },
+            ]
+        );
+        let slot =
+            |who: &String| U256::from_be_bytes(pallet.storage(&token, &balance_slot(address(who))));
+        assert_eq!(slot(&alice()), U256::from(SUPPLY - 100));
+        assert_eq!(slot(&bob()), U256::from(100));
+    }
+
+    #[test]
+    fn failed_executions_are_charged_their_gas() {
+        let mut pallet = new_pallet();
+        let token = deploy(&mut pallet);
+        let before = balance(&mut pallet, alice());
+        pallet
+            .call(
+                alice(),
+                token,
+                transfer(address(&bob()), SUPPLY + 1),
+                0,
+                100_000,
+            )
+            .unwrap();
+        assert_eq!(
+            pallet.take_events(),
+            vec![Event::ExecutedFailed {
+                caller: alice(),
+                address: token,
+            }]
+        );
+        let charged = before - balance(&mut pallet, alice());
+        assert!(charged > 2 * 21_000 && charged < 2 * 100_000);
+        let alice_balance = pallet.storage(&token, &balance_slot(address(&alice())));
+        assert_eq!(U256::from_be_bytes(alice_balance), U256::from(SUPPLY));
+
+        // All of the gas is used when the execution halts.
+        let before = balance(&mut pallet, alice());
+        pallet
+            .create(alice(), vec![0xfe], 0, 100_000)
+            .unwrap();
+        assert!(matches!(
+            pallet.take_events()[..],
+            [Event::CreatedFailed { .. }]
+        ));
+        assert_eq!(balance(&mut pallet, alice()), before - 2 * 100_000);
+    }
+
+    #[test]
+    fn the_gas_limit_must_be_paid_for() {
+        let mut pallet = new_pallet();
+        assert_eq!(
+            pallet.call(alice(), [0; 20], vec![], 0, 20_000_000),
+            Err("the gas limit is too high")
+        );
+        assert_eq!(
+            pallet.call(alice(), [0; 20], vec![], 99_990_000, 10_000),
+            Err("insufficient balance to pay the gas")
+        );
+        assert_eq!(
+            pallet.call(alice(), [0; 20], vec![], 0, 1000),
+            Err("invalid EVM transaction")
+        );
+        assert_eq!(
+            pallet.call(bob(), [0; 20], vec![], 0, 21_000),
+            Err("insufficient balance to pay the gas")
+        );
+    }
+
+    #[test]
+    fn funds_sent_to_an_address_reach_its_account() {
+        let mut pallet = new_pallet();
+        let bob_address = address(&bob());
+        pallet
+            .call(alice(), bob_address, vec![], 50_000, 21_000)
+            .unwrap();
+        assert_eq!(
+            balance(&mut pallet, alice()),
+            100_000_000 - 50_000 - 2 * 21_000
+        );
+        let derived: String = EvmAddress(bob_address).into();
+        assert_eq!(pallet.account_of(&bob_address), derived);
+        assert_eq!(balance(&mut pallet, derived.clone()), 50_000);
+
+        // Bob gets the funds with his first call, and his later funds directly.
+        pallet
+            .call(bob(), address(&alice()), vec![], 0, 21_000)
+            .unwrap();
+        assert_eq!(pallet.account_of(&bob_address), bob());
+        assert_eq!(balance(&mut pallet, derived), 0);
+        assert_eq!(balance(&mut pallet, bob()), 50_000 - 2 * 21_000);
+        pallet
+            .call(alice(), bob_address, vec![], 1000, 21_000)
+            .unwrap();
+        assert_eq!(balance(&mut pallet, bob()), 50_000 - 2 * 21_000 + 1000);
+    }
+
+    #[test]
+    fn calls_fail_without_the_lent_pallets() {
+        let mut pallet = Pallet::<TestConfig>::new();
+        assert_eq!(
+            pallet.create(alice(), erc20(), 0, 1_000_000),
+            Err("the pallet was not lent by the runtime")
+        );
+    }
+
+    #[test]
+    fn benchmarks_succeed() {
+        let config = BenchmarkConfig {
+            steps: 2,
+            repeat: 1,
+        };
+        let results = benchmarking::run::<Pallet<TestConfig>>(&config).unwrap();
+        assert_eq!(results.len(), 2);
+    }
+}
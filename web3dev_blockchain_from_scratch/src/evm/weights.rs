@@ -0,0 +1,36 @@
+//! The weights of the `evm` pallet, measured by its benchmarks.
+//!
+//! Generated by `cargo run --release --bin benchmark`, do not edit by hand. The unit of weight is
+//! one nanosecond of execution on the machine which ran the benchmarks.
+
+use crate::support::Weight;
+
+/// The weights of the callable functions of the pallet.
+pub trait WeightInfo {
+    fn create(c: u32) -> Weight;
+    fn call(i: u32) -> Weight;
+}
+
+/// The weights measured by the benchmarks.
+pub struct BenchmarkedWeights;
+
+impl WeightInfo for BenchmarkedWeights {
+    fn create(c: u32) -> Weight {
+        31242 + 3 * Weight::from(c)
+    }
+
+    fn call(i: u32) -> Weight {
+        30639 + Weight::from(i)
+    }
+}
+
+/// No weights, for tests.
+impl WeightInfo for () {
+    fn create(_c: u32) -> Weight {
+        0
+    }
+
+    fn call(_i: u32) -> Weight {
+        0
+    }
+}
@@ -1,6 +1,27 @@
 pub mod balances;
 pub mod system;
 
+pub mod crypto;
+pub mod metadata;
 pub mod support;
 
+pub mod contracts;
+pub mod escrow;
+pub mod evm;
+pub mod nfts;
 pub mod proof_of_existence;
+pub mod proxy;
+pub mod treasury;
+
+pub mod runtime;
+
+pub mod dynamic;
+
+pub mod light_client;
+pub mod node;
+pub mod rpc;
+pub mod transaction_pool;
+pub mod wallet;
+
+#[cfg(test)]
+pub mod test_utils;
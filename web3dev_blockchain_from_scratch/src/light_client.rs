@@ -0,0 +1,242 @@
+//! A light client of the chain, for devices which can't execute the runtime.
+//!
+//! It follows the chain from a trusted header, like the genesis header, only keeping the headers of
+//! the blocks: every new header must be the child of the best one. It doesn't execute the blocks,
+//! so it reads the state from a full node, with a `StorageProof` checked against the state root
+//! of one of its headers, see `Node::read_proof`.
+//!
+//! The chain has no finality gadget: its headers carry no justification, and the light client
+//! trusts the full nodes it follows to only give it the headers of the canonical chain.
+
+use codec::Decode;
+
+use crate::{
+    runtime::types,
+    support::storage::{
+        self,
+        StorageProof,
+    },
+};
+
+/// The key of the free balance of `who` in the balances pallet of the runtime.
+pub fn balance_key(who: &types::AccountId) -> Vec<u8> {
+    storage::map_key("balances", "balances", who)
+}
+
+/// The key of the owner of `claim` in the proof of existence pallet of the runtime.
+pub fn claim_key(claim: &types::Content) -> Vec<u8> {
+    storage::map_key("proof_of_existence", "claims", claim)
+}
+
+#[derive(Clone, Debug)]
+pub struct LightClient {
+    /// The headers followed so far, starting with the trusted one.
+    headers: Vec<types::Header>,
+}
+
+impl LightClient {
+    /// Follow the chain from the `trusted` header.
+    pub fn new(trusted: types::Header) -> Self {
+        Self {
+            headers: vec![trusted],
+        }
+    }
+
+    /// The header of the latest block.
+    pub fn best_header(&self) -> &types::Header {
+        self.headers
+            .last()
+            .expect("the light client starts with a trusted header")
+    }
+
+    /// Get a header by number, if it was followed.
+    pub fn header(&self, number: types::BlockNumber) -> Option<&types::Header> {
+        let index = number.checked_sub(self.headers[0].block_number)?;
+        self.headers
+            .get(usize::try_from(index).ok()?)
+    }
+
+    /// Follow the header of the next block, which must be a child of the best header.
+    pub fn import_header(&mut self, header: types::Header) -> Result<(), &'static str> {
+        let best = self.best_header();
+        if header.block_number != best.block_number + 1 {
+            return Err("header does not follow the best header");
+        }
+        if header.parent_hash != best.hash() {
+            return Err("parent hash does not match the best header");
+        }
+        self.headers.push(header);
+        Ok(())
+    }
+
+    /// The raw value of `key` after the block `number`, or `None` if it has no value, as shown by
+    /// the `proof`.
+    pub fn read(
+        &self,
+        number: types::BlockNumber,
+        key: &[u8],
+        proof: &StorageProof,
+    ) -> Result<Option<Vec<u8>>, &'static str> {
+        let header = self
+            .header(number)
+            .ok_or("unknown header")?;
+        proof.verify(header.state_root, key)
+    }
+
+    /// The free balance of `who` after the block `number`, as shown by the `proof`.
+    pub fn balance(
+        &self,
+        number: types::BlockNumber,
+        who: &types::AccountId,
+        proof: &StorageProof,
+    ) -> Result<types::Balance, &'static str> {
+        Ok(self
+            .read_value(number, &balance_key(who), proof)?
+            .unwrap_or_default())
+    }
+
+    /// The owner of `claim` after the block `number`, if any, as shown by the `proof`.
+    pub fn claim_owner(
+        &self,
+        number: types::BlockNumber,
+        claim: &types::Content,
+        proof: &StorageProof,
+    ) -> Result<Option<types::AccountId>, &'static str> {
+        self.read_value(number, &claim_key(claim), proof)
+    }
+
+    fn read_value<V: Decode>(
+        &self,
+        number: types::BlockNumber,
+        key: &[u8],
+        proof: &StorageProof,
+    ) -> Result<Option<V>, &'static str> {
+        self.read(number, key, proof)?
+            .map(|value| {
+                V::decode(&mut &value[..]).map_err(|_| "invalid value in the storage proof")
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances,
+        node::Node,
+        proof_of_existence,
+        runtime::{
+            Runtime,
+            RuntimeCall,
+        },
+        support,
+    };
+
+    fn extrinsic(node: &Node, nonce: types::Nonce, call: RuntimeCall) -> types::Extrinsic {
+        support::Extrinsic {
+            caller: "alice".to_string(),
+            signature: None,
+            nonce,
+            tip: 0,
+            era: support::Era::Immortal,
+            spec_version: crate::runtime::VERSION.spec_version,
+            genesis_hash: node
+                .runtime()
+                .system
+                .genesis_hash(),
+            call,
+        }
+    }
+
+    fn transfer(node: &Node, nonce: types::Nonce, amount: types::Balance) -> types::Extrinsic {
+        let call = RuntimeCall::balances(balances::Call::transfer {
+            to: "bob".to_string(),
+            amount,
+        });
+        extrinsic(node, nonce, call)
+    }
+
+    #[test]
+    fn follows_headers_and_reads_proven_state() {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 1000);
+        let mut node = Node::new(runtime).unwrap();
+        let mut client = LightClient::new(node.best_header().clone());
+        let (alice, bob, doc) = ("alice".to_string(), "bob".to_string(), "doc".to_string());
+
+        let claim = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+            claim: doc.clone(),
+        });
+        node.submit_extrinsic(transfer(&node, 0, 100))
+            .unwrap();
+        node.submit_extrinsic(extrinsic(&node, 1, claim))
+            .unwrap();
+        node.produce_block().unwrap();
+        client
+            .import_header(node.best_header().clone())
+            .unwrap();
+        let stale = node.read_proof(&balance_key(&bob));
+
+        node.submit_extrinsic(transfer(&node, 2, 50))
+            .unwrap();
+        node.produce_block().unwrap();
+        let header = node.best_header().clone();
+        assert_eq!(
+            client.import_header(
+                node.header(1)
+                    .unwrap()
+                    .clone()
+            ),
+            Err("header does not follow the best header")
+        );
+        let mut forked = header.clone();
+        forked.parent_hash = [1; 32];
+        assert_eq!(
+            client.import_header(forked),
+            Err("parent hash does not match the best header")
+        );
+        client
+            .import_header(header)
+            .unwrap();
+        assert_eq!(client.best_header(), node.best_header());
+
+        let balance =
+            |who: &types::AccountId| client.balance(2, who, &node.read_proof(&balance_key(who)));
+        assert_eq!(
+            balance(&alice),
+            Ok(node
+                .runtime()
+                .balances
+                .balance(alice.clone()))
+        );
+        assert_eq!(balance(&bob), Ok(150));
+        assert_eq!(balance(&"charlie".to_string()), Ok(0));
+        assert_eq!(
+            client.claim_owner(2, &doc, &node.read_proof(&claim_key(&doc))),
+            Ok(Some(alice.clone()))
+        );
+        let other = "other".to_string();
+        assert_eq!(
+            client.claim_owner(2, &other, &node.read_proof(&claim_key(&other))),
+            Ok(None)
+        );
+
+        // A proof only holds for the state it was made in, and for the key it was made for.
+        assert_eq!(client.balance(1, &bob, &stale), Ok(100));
+        assert_eq!(
+            client.balance(2, &bob, &stale),
+            Err("storage proof does not match the state root")
+        );
+        assert_eq!(
+            client.balance(2, &alice, &node.read_proof(&balance_key(&bob))),
+            Err("storage proof is not about this key")
+        );
+        assert_eq!(client.balance(3, &bob, &stale), Err("unknown header"));
+    }
+}
@@ -1,53 +1,15 @@
-use std::fmt::Debug;
-
 use web3dev_blockchain_from_scratch::{
     balances,
     proof_of_existence,
+    runtime::{
+        types,
+        Runtime,
+        RuntimeCall,
+        VERSION,
+    },
     support,
-    support::Dispatch,
-    system,
 };
 
-mod types {
-    use web3dev_blockchain_from_scratch::support;
-
-    pub type AccountId = String;
-    pub type Balance = u128;
-
-    pub type BlockNumber = u64;
-
-    pub type Nonce = u64;
-
-    pub type Extrinsic = support::Extrinsic<AccountId, crate::RuntimeCall>;
-
-    pub type Header = support::Header<BlockNumber>;
-
-    pub type Block = support::Block<Header, Extrinsic>;
-
-    pub type Content = &'static str;
-}
-impl system::Config for Runtime {
-    type AccountId = types::AccountId;
-    type BlockNumber = types::BlockNumber;
-    type Nonce = types::Nonce;
-}
-
-impl balances::Config for Runtime {
-    type Balance = types::Balance;
-}
-
-impl proof_of_existence::Config for Runtime {
-    type Content = types::Content;
-}
-
-#[derive(Debug)]
-#[macros::runtime]
-pub struct Runtime {
-    system: system::Pallet<Runtime>,
-    balances: balances::Pallet<Runtime>,
-    proof_of_existence: proof_of_existence::Pallet<Runtime>,
-}
-
 fn bob() -> String {
     "bob".to_string()
 }
@@ -59,8 +21,21 @@ fn alice() -> String {
 fn charlie() -> String {
     "charlie".to_string()
 }
+/// Print the extrinsics of a block which failed.
+fn print_failures(report: &types::BlockExecutionReport) {
+    for (extrinsic, error) in report.failed() {
+        eprintln!(
+            "Extrinsic Error\n\tExtrinsic Number: {}\n\tCaller: {}\n\tError: {:?}",
+            extrinsic.index, extrinsic.caller, error
+        );
+    }
+}
+
 fn main() {
     let mut run_time = Runtime::new();
+    run_time
+        .system
+        .allow_keyless_accounts();
     let alice = alice();
     let bob = bob();
     let charlie = charlie();
@@ -70,10 +45,19 @@ fn main() {
         .set_balance(alice.clone(), 100);
 
     let block_1 = types::Block {
-        header: support::Header { block_number: 1 },
+        header: support::Header {
+            block_number: 1,
+            ..Default::default()
+        },
         extrinsics: vec![
             support::Extrinsic {
                 caller: alice.clone(),
+                signature: None,
+                nonce: 0,
+                tip: 0,
+                era: support::Era::Immortal,
+                spec_version: VERSION.spec_version,
+                genesis_hash: run_time.system.genesis_hash(),
                 call: RuntimeCall::balances(balances::Call::transfer {
                     to: bob.clone(),
                     amount: 30,
@@ -81,6 +65,12 @@ fn main() {
             },
             support::Extrinsic {
                 caller: alice.clone(),
+                signature: None,
+                nonce: 1,
+                tip: 0,
+                era: support::Era::Immortal,
+                spec_version: VERSION.spec_version,
+                genesis_hash: run_time.system.genesis_hash(),
                 call: RuntimeCall::balances(balances::Call::transfer {
                     to: charlie,
                     amount: 20,
@@ -89,31 +79,48 @@ fn main() {
         ],
     };
 
-    run_time
+    let report = run_time
         .execute_block(block_1)
         .expect("wrong block execution!");
+    print_failures(&report);
 
     let block_2 = types::Block {
-        header: support::Header { block_number: 2 },
+        header: support::Header {
+            block_number: 2,
+            ..Default::default()
+        },
         extrinsics: vec![
             support::Extrinsic {
                 caller: alice.clone(),
+                signature: None,
+                nonce: 2,
+                tip: 0,
+                era: support::Era::Immortal,
+                spec_version: VERSION.spec_version,
+                genesis_hash: run_time.system.genesis_hash(),
                 call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "my_document",
+                    claim: "my_document".to_string(),
                 }),
             },
             support::Extrinsic {
                 caller: bob.clone(),
+                signature: None,
+                nonce: 0,
+                tip: 0,
+                era: support::Era::Immortal,
+                spec_version: VERSION.spec_version,
+                genesis_hash: run_time.system.genesis_hash(),
                 call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
-                    claim: "bob's doc",
+                    claim: "bob's doc".to_string(),
                 }),
             },
         ],
     };
 
-    run_time
+    let report = run_time
         .execute_block(block_2)
         .expect("wrong block execution!");
+    print_failures(&report);
     println!("{:?}", run_time);
 }
 
@@ -0,0 +1,238 @@
+//! A description of the runtime, generated by our macros.
+//!
+//! The metadata lists the pallets of the runtime along with their calls, events, storage items and
+//! errors. It can be serialized to JSON, so client tooling can build extrinsics and decode events
+//! without depending on the Rust types of the runtime.
+//!
+//! The types of call arguments, event fields and storage items are described with `Type`, which
+//! every such type provides through the `TypeInfo` trait. It can be derived with
+//! `macros::TypeInfo`.
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The description of the whole runtime, as returned by the generated `Runtime::metadata()`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeMetadata {
+    pub spec_name: String,
+    pub spec_version: u32,
+    /// The fields of an extrinsic which are encoded before its call, like the caller and its
+    /// nonce.
+    pub extrinsic_envelope: Vec<Field>,
+    pub pallets: Vec<PalletMetadata>,
+}
+
+impl RuntimeMetadata {
+    /// Find a pallet by name.
+    pub fn pallet(&self, name: &str) -> Option<&PalletMetadata> {
+        self.pallets
+            .iter()
+            .find(|pallet| pallet.name == name)
+    }
+}
+
+/// The description of a pallet included in the runtime.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PalletMetadata {
+    /// The name of the pallet, which is the name of its field in the `Runtime` struct.
+    pub name: String,
+    /// The position of the pallet in the `Runtime` struct. The system pallet is always `0`.
+    pub index: u8,
+    /// The callable functions of the pallet, which are the variants of its `Call` enum.
+    pub calls: Vec<Variant>,
+    /// The events of the pallet, which are the variants of its `Event` enum.
+    pub events: Vec<Variant>,
+    pub storage: Vec<StorageEntryMetadata>,
+    /// The error messages which the calls of the pallet can return.
+    pub errors: Vec<String>,
+}
+
+/// A storage item of a pallet.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageEntryMetadata {
+    pub name: String,
+    pub ty: StorageEntryType,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageEntryType {
+    /// A single value.
+    Value(Type),
+    /// A map from keys to values.
+    Map { key: Type, value: Type },
+    /// A map from pairs of keys to values.
+    DoubleMap { key1: Type, key2: Type, value: Type },
+}
+
+/// The description of a type, in terms of the few kinds of types used by our runtime.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Type {
+    Unit,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    String,
+    Sequence(Box<Type>),
+    /// A sequence of a fixed length, which is not encoded.
+    Array {
+        len: u32,
+        ty: Box<Type>,
+    },
+    Option(Box<Type>),
+    Tuple(Vec<Type>),
+    /// A struct.
+    Composite(Vec<Field>),
+    /// An enum.
+    Variant(Vec<Variant>),
+    /// A call of the runtime, like the call made by a proxy, described by the calls of the
+    /// pallets.
+    RuntimeCall,
+}
+
+/// A field of a struct or enum variant. Tuple-like fields have no name.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    pub name: Option<String>,
+    pub ty: Type,
+}
+
+/// A variant of an enum. The index is the position of the variant in the enum.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    pub index: u8,
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+/// A type which can describe itself in the metadata.
+pub trait TypeInfo {
+    fn type_info() -> Type;
+}
+
+/// A pallet which can describe its storage items in the metadata. Implemented by
+/// `#[macros::storage]`.
+pub trait StorageInfo {
+    fn storage_info() -> Vec<StorageEntryMetadata>;
+}
+
+/// The variants of the enum `E`, or nothing if `E` is not an enum.
+pub fn variants<E: TypeInfo>() -> Vec<Variant> {
+    match E::type_info() {
+        Type::Variant(variants) => variants,
+        _ => Vec::new(),
+    }
+}
+
+macro_rules! impl_type_info {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl TypeInfo for $ty {
+                fn type_info() -> Type {
+                    Type::$variant
+                }
+            }
+        )*
+    };
+}
+
+impl_type_info!(
+    () => Unit,
+    bool => Bool,
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+    u128 => U128,
+    String => String,
+    &'static str => String,
+);
+
+impl<T: TypeInfo> TypeInfo for Vec<T> {
+    fn type_info() -> Type {
+        Type::Sequence(Box::new(T::type_info()))
+    }
+}
+
+impl<T: TypeInfo, const N: usize> TypeInfo for [T; N] {
+    fn type_info() -> Type {
+        Type::Array {
+            len: N as u32,
+            ty: Box::new(T::type_info()),
+        }
+    }
+}
+
+impl<T: TypeInfo> TypeInfo for Box<T> {
+    fn type_info() -> Type {
+        T::type_info()
+    }
+}
+
+impl<T: TypeInfo> TypeInfo for Option<T> {
+    fn type_info() -> Type {
+        Type::Option(Box::new(T::type_info()))
+    }
+}
+
+impl<A: TypeInfo, B: TypeInfo> TypeInfo for (A, B) {
+    fn type_info() -> Type {
+        Type::Tuple(vec![A::type_info(), B::type_info()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(macros::TypeInfo)]
+    enum Example {
+        Unit,
+        Tuple(u32, Option<String>),
+        Named { values: Vec<(u8, bool)> },
+    }
+
+    #[test]
+    fn derive_type_info() {
+        let field = |name: Option<&str>, ty| Field {
+            name: name.map(str::to_string),
+            ty,
+        };
+        let variant = |index, name: &str, fields| Variant {
+            index,
+            name: name.to_string(),
+            fields,
+        };
+
+        assert_eq!(
+            Example::type_info(),
+            Type::Variant(vec![
+                variant(0, "Unit", vec![]),
+                variant(
+                    1,
+                    "Tuple",
+                    vec![
+                        field(None, Type::U32),
+                        field(None, Type::Option(Box::new(Type::String))),
+                    ]
+                ),
+                variant(
+                    2,
+                    "Named",
+                    vec![field(
+                        Some("values"),
+                        Type::Sequence(Box::new(Type::Tuple(vec![Type::U8, Type::Bool])))
+                    )]
+                ),
+            ])
+        );
+        assert_eq!(variants::<u32>(), vec![]);
+    }
+}
@@ -0,0 +1,1208 @@
+//! The NFTs pallet: collections of non-fungible items, like certificates, which can be transferred
+//! between accounts.
+//!
+//! Anyone can create a collection, and becomes its owner. The owner of a collection mints its
+//! items to any account, up to the optional maximum supply of the collection, and sets the
+//! metadata of the collection and the attributes of its items. The owner of an item can transfer
+//! it, or approve another account to transfer it once.
+//!
+//! The owner of a collection can freeze any of its items, or the whole collection, so the items
+//! can't be transferred until they are thawed.
+//!
+//! Storing a collection, an item, metadata or an attribute holds a deposit from the owner of the
+//! collection, reserved with the currency of the config and returned when it is removed. The
+//! runtime lends the currency to this pallet, like the balances pallet with `#[lend(balances)]`.
+
+use codec::{
+    Decode,
+    Encode,
+};
+use num::CheckedSub;
+use serde::Serialize;
+
+use crate::{
+    support::{
+        benchmarking::{
+            ensure,
+            Benchmark,
+            Benchmarks,
+        },
+        storage::{
+            StorageDoubleMap,
+            StorageMap,
+            StorageValue,
+        },
+        AsCurrency,
+        Borrower,
+        Currency,
+        DispatchResult,
+        Hooks,
+        Lent,
+        OnRuntimeUpgrade,
+        ReservableCurrency,
+    },
+    system,
+};
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+pub type CollectionId = u32;
+pub type ItemId = u32;
+
+/// The largest metadata of a collection, and the largest key or value of an attribute, in bytes.
+pub const MAX_DATA_LENGTH: usize = 256;
+
+/// The largest number of attributes of an item.
+pub const MAX_ATTRIBUTES: u32 = 64;
+
+pub trait Config: system::Config {
+    /// The currency holding the deposits.
+    type Currency: ReservableCurrency<Self::AccountId>;
+    /// The deposit held for a collection.
+    const COLLECTION_DEPOSIT: BalanceOf<Self>;
+    /// The deposit held for every item of a collection.
+    const ITEM_DEPOSIT: BalanceOf<Self>;
+    /// The deposit held for the metadata of a collection, and for every attribute.
+    const DATA_DEPOSIT: BalanceOf<Self>;
+    /// The weights of the calls, see the `weights` module.
+    type WeightInfo: WeightInfo;
+}
+
+/// The deposits held for the collections and items, in the currency of the runtime `T`.
+pub type BalanceOf<T> =
+    <<T as Config>::Currency as Currency<<T as system::Config>::AccountId>>::Balance;
+
+/// A collection of items.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Encode, Decode, macros::TypeInfo)]
+pub struct CollectionDetails<AccountId, Balance> {
+    pub owner: AccountId,
+    /// The number of items which can be minted, if limited.
+    pub max_supply: Option<u32>,
+    /// The number of items currently in the collection.
+    pub items: u32,
+    /// The items of a frozen collection can't be transferred.
+    pub frozen: bool,
+    /// The deposit held for the collection and its metadata.
+    pub deposit: Balance,
+}
+
+/// An item of a collection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Encode, Decode, macros::TypeInfo)]
+pub struct ItemDetails<AccountId, Balance> {
+    pub owner: AccountId,
+    /// The account allowed to transfer the item on behalf of its owner.
+    pub approved: Option<AccountId>,
+    /// A frozen item can't be transferred.
+    pub frozen: bool,
+    /// The number of attributes of the item.
+    pub attributes: u32,
+    /// The deposit held for the item and its attributes.
+    pub deposit: Balance,
+}
+
+/// The events emitted by the NFTs pallet.
+#[derive(
+    macros::CloneNoBound,
+    macros::DebugNoBound,
+    macros::PartialEqNoBound,
+    macros::EqNoBound,
+    macros::TypeInfo,
+    Serialize,
+)]
+pub enum Event<T: Config> {
+    /// `owner` created the `collection`.
+    Created {
+        collection: CollectionId,
+        owner: T::AccountId,
+    },
+    /// The empty `collection` was destroyed.
+    Destroyed { collection: CollectionId },
+    /// The metadata of the `collection` was set.
+    CollectionMetadataSet {
+        collection: CollectionId,
+        data: Vec<u8>,
+    },
+    /// The `item` of the `collection` was minted to `owner`.
+    Minted {
+        collection: CollectionId,
+        item: ItemId,
+        owner: T::AccountId,
+    },
+    /// The `item` of the `collection` was burned.
+    Burned {
+        collection: CollectionId,
+        item: ItemId,
+        owner: T::AccountId,
+    },
+    /// The `item` of the `collection` was transferred from `from` to `to`.
+    Transferred {
+        collection: CollectionId,
+        item: ItemId,
+        from: T::AccountId,
+        to: T::AccountId,
+    },
+    /// The attribute `key` of the `item` was set to `value`.
+    AttributeSet {
+        collection: CollectionId,
+        item: ItemId,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    /// The attribute `key` of the `item` was removed.
+    AttributeCleared {
+        collection: CollectionId,
+        item: ItemId,
+        key: Vec<u8>,
+    },
+    /// `delegate` is allowed to transfer the `item` on behalf of its owner.
+    TransferApproved {
+        collection: CollectionId,
+        item: ItemId,
+        delegate: T::AccountId,
+    },
+    /// The approval to transfer the `item` was cancelled.
+    ApprovalCancelled {
+        collection: CollectionId,
+        item: ItemId,
+    },
+    /// The `item` of the `collection` was frozen.
+    ItemFrozen {
+        collection: CollectionId,
+        item: ItemId,
+    },
+    /// The `item` of the `collection` was thawed.
+    ItemThawed {
+        collection: CollectionId,
+        item: ItemId,
+    },
+    /// The `collection` was frozen.
+    CollectionFrozen { collection: CollectionId },
+    /// The `collection` was thawed.
+    CollectionThawed { collection: CollectionId },
+}
+
+#[derive(Debug)]
+#[macros::storage]
+pub struct Pallet<T: Config> {
+    /// The id of the next collection.
+    #[default(0)]
+    next_collection_id: StorageValue<CollectionId>,
+    collections: StorageMap<CollectionId, CollectionDetails<T::AccountId, BalanceOf<T>>>,
+    collection_metadata: StorageMap<CollectionId, Vec<u8>>,
+    items: StorageDoubleMap<CollectionId, ItemId, ItemDetails<T::AccountId, BalanceOf<T>>>,
+    /// The attributes of every item, by key.
+    attributes: StorageDoubleMap<(CollectionId, ItemId), Vec<u8>, Vec<u8>>,
+    /// The currency, holding the deposits.
+    currency: Lent<T::Currency>,
+    /// The events emitted since the runtime last collected them.
+    events: Vec<Event<T>>,
+}
+
+#[macros::call]
+#[errors(
+    "no more collection ids",
+    "the collection still has items",
+    "the data is too long",
+    "the item already exists",
+    "the maximum supply of the collection is reached",
+    "unknown collection",
+    "unknown item",
+    "not the owner of the collection",
+    "not the owner of the item",
+    "not allowed to transfer the item",
+    "the item is frozen",
+    "too many attributes",
+    "unknown attribute",
+    "Underflow when removing the deposit",
+    "the item is not approved",
+    crate::support::NOT_LENT,
+    crate::support::INSUFFICIENT_BALANCE,
+    crate::support::INSUFFICIENT_RESERVED_BALANCE,
+    crate::support::OVERFLOW
+)]
+impl<T: Config> Pallet<T> {
+    /// Create a new collection owned by the `caller`, with an optional maximum supply.
+    #[call_index(0)]
+    #[weight(<T as Config>::WeightInfo::create_collection())]
+    pub fn create_collection(
+        &mut self,
+        caller: T::AccountId,
+        max_supply: Option<u32>,
+    ) -> DispatchResult {
+        let collection = self.next_collection_id.get();
+        let next = collection
+            .checked_add(1)
+            .ok_or("no more collection ids")?;
+        self.currency()?
+            .reserve(caller.clone(), T::COLLECTION_DEPOSIT)?;
+        self.next_collection_id
+            .set(next);
+        self.collections.insert(
+            &collection,
+            CollectionDetails {
+                owner: caller.clone(),
+                max_supply,
+                items: 0,
+                frozen: false,
+                deposit: T::COLLECTION_DEPOSIT,
+            },
+        );
+        self.deposit_event(Event::Created {
+            collection,
+            owner: caller,
+        });
+        Ok(())
+    }
+
+    /// Destroy a collection without items, returning its deposit. Only its owner can destroy it.
+    #[call_index(1)]
+    #[weight(<T as Config>::WeightInfo::destroy_collection())]
+    pub fn destroy_collection(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+    ) -> DispatchResult {
+        let details = self.owned_collection(&caller, collection)?;
+        if details.items > 0 {
+            return Err("the collection still has items");
+        }
+        self.currency()?
+            .unreserve(details.owner, details.deposit)?;
+        self.collections
+            .remove(&collection);
+        self.collection_metadata
+            .remove(&collection);
+        self.deposit_event(Event::Destroyed { collection });
+        Ok(())
+    }
+
+    /// Set the metadata of a collection. Only its owner can set it.
+    #[call_index(2)]
+    #[weight(<T as Config>::WeightInfo::set_collection_metadata())]
+    pub fn set_collection_metadata(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+        data: Vec<u8>,
+    ) -> DispatchResult {
+        let mut details = self.owned_collection(&caller, collection)?;
+        if data.len() > MAX_DATA_LENGTH {
+            return Err("the data is too long");
+        }
+        if !self
+            .collection_metadata
+            .contains_key(&collection)
+        {
+            self.currency()?
+                .reserve(caller, T::DATA_DEPOSIT)?;
+            details.deposit += T::DATA_DEPOSIT;
+            self.collections
+                .insert(&collection, details);
+        }
+        self.collection_metadata
+            .insert(&collection, data.clone());
+        self.deposit_event(Event::CollectionMetadataSet { collection, data });
+        Ok(())
+    }
+
+    /// Mint a new item of a collection to `owner`. Only the owner of the collection can mint its
+    /// items, and pays their deposit.
+    #[call_index(3)]
+    #[weight(<T as Config>::WeightInfo::mint())]
+    pub fn mint(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+        item: ItemId,
+        owner: T::AccountId,
+    ) -> DispatchResult {
+        let mut details = self.owned_collection(&caller, collection)?;
+        if self
+            .items
+            .contains_key(&collection, &item)
+        {
+            return Err("the item already exists");
+        }
+        if details
+            .max_supply
+            .is_some_and(|max_supply| details.items >= max_supply)
+        {
+            return Err("the maximum supply of the collection is reached");
+        }
+        self.currency()?
+            .reserve(caller, T::ITEM_DEPOSIT)?;
+        details.items += 1;
+        self.collections
+            .insert(&collection, details);
+        self.items.insert(
+            &collection,
+            &item,
+            ItemDetails {
+                owner: owner.clone(),
+                approved: None,
+                frozen: false,
+                attributes: 0,
+                deposit: T::ITEM_DEPOSIT,
+            },
+        );
+        self.deposit_event(Event::Minted {
+            collection,
+            item,
+            owner,
+        });
+        Ok(())
+    }
+
+    /// Burn an item, along with its attributes, returning their deposit to the owner of the
+    /// collection. Both the owner of the item and the owner of the collection can burn it.
+    #[call_index(4)]
+    #[weight(<T as Config>::WeightInfo::burn())]
+    pub fn burn(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+        item: ItemId,
+    ) -> DispatchResult {
+        let mut details = self
+            .collection(collection)
+            .ok_or("unknown collection")?;
+        let item_details = self
+            .item(collection, item)
+            .ok_or("unknown item")?;
+        if caller != item_details.owner && caller != details.owner {
+            return Err("not the owner of the item");
+        }
+        self.currency()?
+            .unreserve(details.owner.clone(), item_details.deposit)?;
+        details.items -= 1;
+        self.collections
+            .insert(&collection, details);
+        self.items
+            .remove(&collection, &item);
+        self.attributes
+            .remove_prefix(&(collection, item));
+        self.deposit_event(Event::Burned {
+            collection,
+            item,
+            owner: item_details.owner,
+        });
+        Ok(())
+    }
+
+    /// Transfer an item to `to`. The owner of the item, or the account it approved, can transfer
+    /// it unless the item or its collection is frozen. The approval is cleared.
+    #[call_index(5)]
+    #[weight(<T as Config>::WeightInfo::transfer())]
+    pub fn transfer(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+        item: ItemId,
+        to: T::AccountId,
+    ) -> DispatchResult {
+        let details = self
+            .collection(collection)
+            .ok_or("unknown collection")?;
+        let mut item_details = self
+            .item(collection, item)
+            .ok_or("unknown item")?;
+        if caller != item_details.owner && item_details.approved.as_ref() != Some(&caller) {
+            return Err("not allowed to transfer the item");
+        }
+        if details.frozen || item_details.frozen {
+            return Err("the item is frozen");
+        }
+        let from = std::mem::replace(&mut item_details.owner, to.clone());
+        item_details.approved = None;
+        self.items
+            .insert(&collection, &item, item_details);
+        self.deposit_event(Event::Transferred {
+            collection,
+            item,
+            from,
+            to,
+        });
+        Ok(())
+    }
+
+    /// Set the attribute `key` of an item to `value`. Only the owner of the collection can set the
+    /// attributes of its items.
+    #[call_index(6)]
+    #[weight(<T as Config>::WeightInfo::set_attribute())]
+    pub fn set_attribute(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+        item: ItemId,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    ) -> DispatchResult {
+        self.owned_collection(&caller, collection)?;
+        let mut item_details = self
+            .item(collection, item)
+            .ok_or("unknown item")?;
+        if key.len() > MAX_DATA_LENGTH || value.len() > MAX_DATA_LENGTH {
+            return Err("the data is too long");
+        }
+        if !self
+            .attributes
+            .contains_key(&(collection, item), &key)
+        {
+            if item_details.attributes >= MAX_ATTRIBUTES {
+                return Err("too many attributes");
+            }
+            self.currency()?
+                .reserve(caller, T::DATA_DEPOSIT)?;
+            item_details.attributes += 1;
+            item_details.deposit += T::DATA_DEPOSIT;
+            self.items
+                .insert(&collection, &item, item_details);
+        }
+        self.attributes
+            .insert(&(collection, item), &key, value.clone());
+        self.deposit_event(Event::AttributeSet {
+            collection,
+            item,
+            key,
+            value,
+        });
+        Ok(())
+    }
+
+    /// Remove the attribute `key` of an item, returning its deposit. Only the owner of the
+    /// collection can remove it.
+    #[call_index(7)]
+    #[weight(<T as Config>::WeightInfo::clear_attribute())]
+    pub fn clear_attribute(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+        item: ItemId,
+        key: Vec<u8>,
+    ) -> DispatchResult {
+        self.owned_collection(&caller, collection)?;
+        let mut item_details = self
+            .item(collection, item)
+            .ok_or("unknown item")?;
+        if !self
+            .attributes
+            .contains_key(&(collection, item), &key)
+        {
+            return Err("unknown attribute");
+        }
+        self.currency()?
+            .unreserve(caller, T::DATA_DEPOSIT)?;
+        item_details.attributes -= 1;
+        item_details.deposit = item_details
+            .deposit
+            .checked_sub(&T::DATA_DEPOSIT)
+            .ok_or("Underflow when removing the deposit")?;
+        self.items
+            .insert(&collection, &item, item_details);
+        self.attributes
+            .remove(&(collection, item), &key);
+        self.deposit_event(Event::AttributeCleared {
+            collection,
+            item,
+            key,
+        });
+        Ok(())
+    }
+
+    /// Allow `delegate` to transfer an item of the `caller`, replacing any previous approval.
+    #[call_index(8)]
+    #[weight(<T as Config>::WeightInfo::approve_transfer())]
+    pub fn approve_transfer(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+        item: ItemId,
+        delegate: T::AccountId,
+    ) -> DispatchResult {
+        let mut item_details = self.owned_item(&caller, collection, item)?;
+        item_details.approved = Some(delegate.clone());
+        self.items
+            .insert(&collection, &item, item_details);
+        self.deposit_event(Event::TransferApproved {
+            collection,
+            item,
+            delegate,
+        });
+        Ok(())
+    }
+
+    /// Cancel the approval to transfer an item of the `caller`.
+    #[call_index(9)]
+    #[weight(<T as Config>::WeightInfo::cancel_approval())]
+    pub fn cancel_approval(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+        item: ItemId,
+    ) -> DispatchResult {
+        let mut item_details = self.owned_item(&caller, collection, item)?;
+        if item_details
+            .approved
+            .take()
+            .is_none()
+        {
+            return Err("the item is not approved");
+        }
+        self.items
+            .insert(&collection, &item, item_details);
+        self.deposit_event(Event::ApprovalCancelled { collection, item });
+        Ok(())
+    }
+
+    /// Freeze or thaw an item, so it can't or can be transferred again. Only the owner of the
+    /// collection can freeze its items.
+    #[call_index(10)]
+    #[weight(<T as Config>::WeightInfo::set_item_frozen())]
+    pub fn set_item_frozen(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+        item: ItemId,
+        frozen: bool,
+    ) -> DispatchResult {
+        self.owned_collection(&caller, collection)?;
+        let mut item_details = self
+            .item(collection, item)
+            .ok_or("unknown item")?;
+        item_details.frozen = frozen;
+        self.items
+            .insert(&collection, &item, item_details);
+        self.deposit_event(match frozen {
+            true => Event::ItemFrozen { collection, item },
+            false => Event::ItemThawed { collection, item },
+        });
+        Ok(())
+    }
+
+    /// Freeze or thaw all the items of a collection. Only the owner of the collection can freeze
+    /// it.
+    #[call_index(11)]
+    #[weight(<T as Config>::WeightInfo::set_collection_frozen())]
+    pub fn set_collection_frozen(
+        &mut self,
+        caller: T::AccountId,
+        collection: CollectionId,
+        frozen: bool,
+    ) -> DispatchResult {
+        let mut details = self.owned_collection(&caller, collection)?;
+        details.frozen = frozen;
+        self.collections
+            .insert(&collection, details);
+        self.deposit_event(match frozen {
+            true => Event::CollectionFrozen { collection },
+            false => Event::CollectionThawed { collection },
+        });
+        Ok(())
+    }
+}
+
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    pub fn collection(
+        &self,
+        collection: CollectionId,
+    ) -> Option<CollectionDetails<T::AccountId, BalanceOf<T>>> {
+        self.collections
+            .try_get(&collection)
+    }
+
+    pub fn collection_metadata(&self, collection: CollectionId) -> Option<Vec<u8>> {
+        self.collection_metadata
+            .try_get(&collection)
+    }
+
+    pub fn item(
+        &self,
+        collection: CollectionId,
+        item: ItemId,
+    ) -> Option<ItemDetails<T::AccountId, BalanceOf<T>>> {
+        self.items
+            .try_get(&collection, &item)
+    }
+
+    /// The owner of an item.
+    pub fn owner(&self, collection: CollectionId, item: ItemId) -> Option<T::AccountId> {
+        self.item(collection, item)
+            .map(|details| details.owner)
+    }
+
+    pub fn attribute(&self, collection: CollectionId, item: ItemId, key: &[u8]) -> Option<Vec<u8>> {
+        self.attributes
+            .try_get(&(collection, item), &key.to_vec())
+    }
+
+    /// The collection, if owned by `who`.
+    fn owned_collection(
+        &self,
+        who: &T::AccountId,
+        collection: CollectionId,
+    ) -> Result<CollectionDetails<T::AccountId, BalanceOf<T>>, &'static str> {
+        let details = self
+            .collection(collection)
+            .ok_or("unknown collection")?;
+        if &details.owner != who {
+            return Err("not the owner of the collection");
+        }
+        Ok(details)
+    }
+
+    /// The item, if owned by `who`.
+    fn owned_item(
+        &self,
+        who: &T::AccountId,
+        collection: CollectionId,
+        item: ItemId,
+    ) -> Result<ItemDetails<T::AccountId, BalanceOf<T>>, &'static str> {
+        let details = self
+            .item(collection, item)
+            .ok_or("unknown item")?;
+        if &details.owner != who {
+            return Err("not the owner of the item");
+        }
+        Ok(details)
+    }
+
+    /// The currency lent by the runtime.
+    fn currency(&mut self) -> Result<&mut T::Currency, &'static str> {
+        self.currency.get_mut()
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    /// Take the events emitted since the last call, so the runtime can record them.
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<T: Config> Borrower<T::Currency, AsCurrency> for Pallet<T> {
+    fn lent(&mut self) -> &mut Lent<T::Currency> {
+        &mut self.currency
+    }
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+/// Every benchmark runs in the worst case of its call, with the longest data and the most
+/// attributes, on an item owned by the owner of its collection.
+impl<T: Config> Benchmarks for Pallet<T>
+where
+    T::AccountId: From<&'static str>,
+    T::Currency: Default,
+    BalanceOf<T>: From<u32>,
+{
+    /// Whether the item `0` is minted.
+    type Setup = bool;
+
+    /// A pallet with the collection `0` owned by the caller, and its item `0` if `item`.
+    fn setup(item: bool) -> Self {
+        let mut currency = T::Currency::default();
+        currency
+            .deposit_creating("caller".into(), u32::MAX.into())
+            .expect("the account is empty");
+        let mut pallet = Pallet::new();
+        pallet.currency.lend(currency);
+        pallet
+            .create_collection("caller".into(), Some(u32::MAX))
+            .expect("the caller can pay the deposit");
+        if item {
+            pallet
+                .mint("caller".into(), 0, 0, "caller".into())
+                .expect("the caller owns the collection");
+        }
+        pallet
+    }
+
+    fn benchmarks() -> Vec<Benchmark<Self>> {
+        fn data() -> Vec<u8> {
+            vec![0; MAX_DATA_LENGTH]
+        }
+
+        /// Set the most attributes to the item `0`, the last one with the key `data()`.
+        fn set_attributes<T: Config>(pallet: &mut Pallet<T>, owner: T::AccountId) {
+            for i in 1..MAX_ATTRIBUTES {
+                pallet
+                    .set_attribute(owner.clone(), 0, 0, i.encode(), data())
+                    .expect("the owner owns the collection");
+            }
+            pallet
+                .set_attribute(owner, 0, 0, data(), data())
+                .expect("the owner owns the collection");
+        }
+
+        vec![
+            Benchmark {
+                name: "create_collection",
+                components: vec![],
+                setup: |_| {
+                    let pallet = Self::setup(false);
+                    let call = Call::create_collection { max_supply: None };
+                    (pallet, "caller".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.collection(1).is_some()),
+            },
+            Benchmark {
+                name: "destroy_collection",
+                components: vec![],
+                setup: |_| {
+                    let mut pallet = Self::setup(false);
+                    pallet
+                        .set_collection_metadata("caller".into(), 0, data())
+                        .expect("the caller owns the collection");
+                    (
+                        pallet,
+                        "caller".into(),
+                        Call::destroy_collection { collection: 0 },
+                    )
+                },
+                verify: |pallet, _| ensure(pallet.collection(0).is_none()),
+            },
+            Benchmark {
+                name: "set_collection_metadata",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::set_collection_metadata {
+                        collection: 0,
+                        data: data(),
+                    };
+                    (Self::setup(false), "caller".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.collection_metadata(0) == Some(data())),
+            },
+            Benchmark {
+                name: "mint",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::mint {
+                        collection: 0,
+                        item: 0,
+                        owner: "owner".into(),
+                    };
+                    (Self::setup(false), "caller".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.owner(0, 0) == Some("owner".into())),
+            },
+            Benchmark {
+                name: "burn",
+                components: vec![],
+                setup: |_| {
+                    let mut pallet = Self::setup(true);
+                    set_attributes(&mut pallet, "caller".into());
+                    let call = Call::burn {
+                        collection: 0,
+                        item: 0,
+                    };
+                    (pallet, "caller".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.item(0, 0).is_none()),
+            },
+            Benchmark {
+                name: "transfer",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::transfer {
+                        collection: 0,
+                        item: 0,
+                        to: "recipient".into(),
+                    };
+                    (Self::setup(true), "caller".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.owner(0, 0) == Some("recipient".into())),
+            },
+            Benchmark {
+                name: "set_attribute",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::set_attribute {
+                        collection: 0,
+                        item: 0,
+                        key: data(),
+                        value: data(),
+                    };
+                    (Self::setup(true), "caller".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.attribute(0, 0, &data()) == Some(data())),
+            },
+            Benchmark {
+                name: "clear_attribute",
+                components: vec![],
+                setup: |_| {
+                    let mut pallet = Self::setup(true);
+                    set_attributes(&mut pallet, "caller".into());
+                    let call = Call::clear_attribute {
+                        collection: 0,
+                        item: 0,
+                        key: data(),
+                    };
+                    (pallet, "caller".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(
+                        pallet
+                            .attribute(0, 0, &data())
+                            .is_none(),
+                    )
+                },
+            },
+            Benchmark {
+                name: "approve_transfer",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::approve_transfer {
+                        collection: 0,
+                        item: 0,
+                        delegate: "delegate".into(),
+                    };
+                    (Self::setup(true), "caller".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(
+                        pallet
+                            .item(0, 0)
+                            .and_then(|item| item.approved)
+                            == Some("delegate".into()),
+                    )
+                },
+            },
+            Benchmark {
+                name: "cancel_approval",
+                components: vec![],
+                setup: |_| {
+                    let mut pallet = Self::setup(true);
+                    pallet
+                        .approve_transfer("caller".into(), 0, 0, "delegate".into())
+                        .expect("the caller owns the item");
+                    let call = Call::cancel_approval {
+                        collection: 0,
+                        item: 0,
+                    };
+                    (pallet, "caller".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(
+                        pallet
+                            .item(0, 0)
+                            .is_some_and(|item| item.approved.is_none()),
+                    )
+                },
+            },
+            Benchmark {
+                name: "set_item_frozen",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::set_item_frozen {
+                        collection: 0,
+                        item: 0,
+                        frozen: true,
+                    };
+                    (Self::setup(true), "caller".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(
+                        pallet
+                            .item(0, 0)
+                            .is_some_and(|item| item.frozen),
+                    )
+                },
+            },
+            Benchmark {
+                name: "set_collection_frozen",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::set_collection_frozen {
+                        collection: 0,
+                        frozen: true,
+                    };
+                    (Self::setup(false), "caller".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(
+                        pallet
+                            .collection(0)
+                            .is_some_and(|details| details.frozen),
+                    )
+                },
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances,
+        support::benchmarking::{
+            self,
+            BenchmarkConfig,
+        },
+    };
+
+    struct TestConfig;
+
+    impl crate::system::Config for TestConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type SystemWeightInfo = ();
+    }
+
+    impl balances::Config for TestConfig {
+        type Balance = u128;
+        type WeightInfo = ();
+    }
+
+    impl Config for TestConfig {
+        type Currency = balances::Pallet<TestConfig>;
+        const COLLECTION_DEPOSIT: u128 = 100;
+        const ITEM_DEPOSIT: u128 = 10;
+        const DATA_DEPOSIT: u128 = 1;
+        type WeightInfo = ();
+    }
+
+    fn alice() -> String {
+        "alice".to_string()
+    }
+
+    fn bob() -> String {
+        "bob".to_string()
+    }
+
+    fn charlie() -> String {
+        "charlie".to_string()
+    }
+
+    /// The pallet, lent a balances pallet in which alice has 1000.
+    fn new_pallet() -> Pallet<TestConfig> {
+        let mut currency = balances::Pallet::new();
+        currency.set_balance(alice(), 1000);
+        let mut nfts = Pallet::new();
+        nfts.lent().lend(currency);
+        nfts
+    }
+
+    fn reserved(nfts: &mut Pallet<TestConfig>, who: String) -> u128 {
+        nfts.currency()
+            .unwrap()
+            .reserved_balance(who)
+    }
+
+    #[test]
+    fn collections_hold_deposits() {
+        let mut nfts = new_pallet();
+        assert_eq!(
+            nfts.create_collection(bob(), None),
+            Err("Insufficient balance")
+        );
+
+        assert_eq!(nfts.create_collection(alice(), Some(1)), Ok(()));
+        assert_eq!(
+            nfts.set_collection_metadata(bob(), 0, b"certificates".to_vec()),
+            Err("not the owner of the collection")
+        );
+        assert_eq!(
+            nfts.set_collection_metadata(alice(), 0, vec![0; MAX_DATA_LENGTH + 1]),
+            Err("the data is too long")
+        );
+        assert_eq!(
+            nfts.set_collection_metadata(alice(), 0, b"certificates".to_vec()),
+            Ok(())
+        );
+        // Replacing the metadata does not hold another deposit.
+        assert_eq!(
+            nfts.set_collection_metadata(alice(), 0, b"diplomas".to_vec()),
+            Ok(())
+        );
+        assert_eq!(reserved(&mut nfts, alice()), 101);
+        assert_eq!(nfts.collection_metadata(0), Some(b"diplomas".to_vec()));
+
+        assert_eq!(nfts.mint(alice(), 0, 7, bob()), Ok(()));
+        assert_eq!(
+            nfts.mint(alice(), 0, 8, bob()),
+            Err("the maximum supply of the collection is reached")
+        );
+        assert_eq!(
+            nfts.destroy_collection(alice(), 0),
+            Err("the collection still has items")
+        );
+        assert_eq!(reserved(&mut nfts, alice()), 111);
+
+        // The owner of the item can burn it, and the deposit goes back to the owner of the
+        // collection.
+        assert_eq!(nfts.burn(bob(), 0, 7), Ok(()));
+        assert_eq!(nfts.destroy_collection(alice(), 0), Ok(()));
+        assert_eq!(reserved(&mut nfts, alice()), 0);
+        assert_eq!(
+            nfts.currency()
+                .unwrap()
+                .balance(alice()),
+            1000
+        );
+        assert_eq!(nfts.collection(0), None);
+        assert_eq!(nfts.collection_metadata(0), None);
+
+        // Collection ids are never reused.
+        assert_eq!(nfts.create_collection(alice(), None), Ok(()));
+        assert!(nfts.collection(1).is_some());
+    }
+
+    #[test]
+    fn transfer_items() {
+        let mut nfts = new_pallet();
+        nfts.create_collection(alice(), None)
+            .unwrap();
+        nfts.mint(alice(), 0, 0, bob())
+            .unwrap();
+        assert_eq!(
+            nfts.mint(alice(), 0, 0, bob()),
+            Err("the item already exists")
+        );
+        assert_eq!(
+            nfts.mint(bob(), 0, 1, bob()),
+            Err("not the owner of the collection")
+        );
+
+        // Only the owner, or the account it approved once, can transfer the item.
+        assert_eq!(
+            nfts.transfer(charlie(), 0, 0, charlie()),
+            Err("not allowed to transfer the item")
+        );
+        assert_eq!(nfts.approve_transfer(bob(), 0, 0, charlie()), Ok(()));
+        assert_eq!(nfts.transfer(charlie(), 0, 0, alice()), Ok(()));
+        assert_eq!(nfts.owner(0, 0), Some(alice()));
+        assert_eq!(
+            nfts.transfer(charlie(), 0, 0, charlie()),
+            Err("not allowed to transfer the item")
+        );
+
+        assert_eq!(nfts.approve_transfer(alice(), 0, 0, charlie()), Ok(()));
+        assert_eq!(nfts.cancel_approval(alice(), 0, 0), Ok(()));
+        assert_eq!(
+            nfts.cancel_approval(alice(), 0, 0),
+            Err("the item is not approved")
+        );
+        assert_eq!(
+            nfts.transfer(charlie(), 0, 0, charlie()),
+            Err("not allowed to transfer the item")
+        );
+
+        // Frozen items can't be transferred, even by their owner.
+        assert_eq!(
+            nfts.set_item_frozen(bob(), 0, 0, true),
+            Err("not the owner of the collection")
+        );
+        assert_eq!(nfts.set_item_frozen(alice(), 0, 0, true), Ok(()));
+        assert_eq!(
+            nfts.transfer(alice(), 0, 0, bob()),
+            Err("the item is frozen")
+        );
+        assert_eq!(nfts.set_item_frozen(alice(), 0, 0, false), Ok(()));
+        assert_eq!(nfts.set_collection_frozen(alice(), 0, true), Ok(()));
+        assert_eq!(
+            nfts.transfer(alice(), 0, 0, bob()),
+            Err("the item is frozen")
+        );
+        assert_eq!(nfts.set_collection_frozen(alice(), 0, false), Ok(()));
+        assert_eq!(nfts.transfer(alice(), 0, 0, bob()), Ok(()));
+
+        let events = nfts.take_events();
+        assert_eq!(
+            events.last(),
+            Some(&Event::Transferred {
+                collection: 0,
+                item: 0,
+                from: alice(),
+                to: bob(),
+            })
+        );
+        assert_eq!(events.len(), 11);
+    }
+
+    #[test]
+    fn attributes_hold_deposits() {
+        let mut nfts = new_pallet();
+        nfts.create_collection(alice(), None)
+            .unwrap();
+        nfts.mint(alice(), 0, 0, bob())
+            .unwrap();
+
+        assert_eq!(
+            nfts.set_attribute(bob(), 0, 0, b"grade".to_vec(), b"A".to_vec()),
+            Err("not the owner of the collection")
+        );
+        assert_eq!(
+            nfts.set_attribute(alice(), 0, 1, b"grade".to_vec(), b"A".to_vec()),
+            Err("unknown item")
+        );
+        assert_eq!(
+            nfts.set_attribute(alice(), 0, 0, b"grade".to_vec(), b"A".to_vec()),
+            Ok(())
+        );
+        assert_eq!(
+            nfts.set_attribute(alice(), 0, 0, b"grade".to_vec(), b"B".to_vec()),
+            Ok(())
+        );
+        assert_eq!(nfts.attribute(0, 0, b"grade"), Some(b"B".to_vec()));
+        assert_eq!(reserved(&mut nfts, alice()), 111);
+
+        assert_eq!(
+            nfts.clear_attribute(alice(), 0, 0, b"year".to_vec()),
+            Err("unknown attribute")
+        );
+        assert_eq!(
+            nfts.clear_attribute(alice(), 0, 0, b"grade".to_vec()),
+            Ok(())
+        );
+        assert_eq!(nfts.attribute(0, 0, b"grade"), None);
+        assert_eq!(reserved(&mut nfts, alice()), 110);
+
+        // Burning an item removes its attributes, and returns their deposit.
+        for i in 0..MAX_ATTRIBUTES {
+            nfts.set_attribute(alice(), 0, 0, i.encode(), vec![])
+                .unwrap();
+        }
+        assert_eq!(
+            nfts.set_attribute(alice(), 0, 0, b"grade".to_vec(), vec![]),
+            Err("too many attributes")
+        );
+        assert_eq!(nfts.burn(charlie(), 0, 0), Err("not the owner of the item"));
+        assert_eq!(nfts.burn(alice(), 0, 0), Ok(()));
+        assert_eq!(nfts.attribute(0, 0, &0u32.encode()), None);
+        assert_eq!(reserved(&mut nfts, alice()), 100);
+    }
+
+    #[test]
+    fn calls_fail_without_the_balances_pallet() {
+        let mut nfts = Pallet::<TestConfig>::new();
+        assert_eq!(
+            nfts.create_collection(alice(), None),
+            Err("the pallet was not lent by the runtime")
+        );
+    }
+
+    #[test]
+    fn benchmarks_succeed() {
+        let config = BenchmarkConfig {
+            steps: 2,
+            repeat: 1,
+        };
+        let results = benchmarking::run::<Pallet<TestConfig>>(&config).unwrap();
+        assert_eq!(results.len(), 12);
+    }
+}
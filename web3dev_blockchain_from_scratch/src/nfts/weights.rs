@@ -0,0 +1,126 @@
+//! The weights of the `nfts` pallet, measured by its benchmarks.
+//!
+//! Generated by `cargo run --release --bin benchmark`, do not edit by hand. The unit of weight is
+//! one nanosecond of execution on the machine which ran the benchmarks.
+
+use crate::support::Weight;
+
+/// The weights of the callable functions of the pallet.
+pub trait WeightInfo {
+    fn create_collection() -> Weight;
+    fn destroy_collection() -> Weight;
+    fn set_collection_metadata() -> Weight;
+    fn mint() -> Weight;
+    fn burn() -> Weight;
+    fn transfer() -> Weight;
+    fn set_attribute() -> Weight;
+    fn clear_attribute() -> Weight;
+    fn approve_transfer() -> Weight;
+    fn cancel_approval() -> Weight;
+    fn set_item_frozen() -> Weight;
+    fn set_collection_frozen() -> Weight;
+}
+
+/// The weights measured by the benchmarks.
+pub struct BenchmarkedWeights;
+
+impl WeightInfo for BenchmarkedWeights {
+    fn create_collection() -> Weight {
+        2024
+    }
+
+    fn destroy_collection() -> Weight {
+        2692
+    }
+
+    fn set_collection_metadata() -> Weight {
+        2959
+    }
+
+    fn mint() -> Weight {
+        3472
+    }
+
+    fn burn() -> Weight {
+        26531
+    }
+
+    fn transfer() -> Weight {
+        1843
+    }
+
+    fn set_attribute() -> Weight {
+        5181
+    }
+
+    fn clear_attribute() -> Weight {
+        6460
+    }
+
+    fn approve_transfer() -> Weight {
+        1415
+    }
+
+    fn cancel_approval() -> Weight {
+        1443
+    }
+
+    fn set_item_frozen() -> Weight {
+        1793
+    }
+
+    fn set_collection_frozen() -> Weight {
+        795
+    }
+}
+
+/// No weights, for tests.
+impl WeightInfo for () {
+    fn create_collection() -> Weight {
+        0
+    }
+
+    fn destroy_collection() -> Weight {
+        0
+    }
+
+    fn set_collection_metadata() -> Weight {
+        0
+    }
+
+    fn mint() -> Weight {
+        0
+    }
+
+    fn burn() -> Weight {
+        0
+    }
+
+    fn transfer() -> Weight {
+        0
+    }
+
+    fn set_attribute() -> Weight {
+        0
+    }
+
+    fn clear_attribute() -> Weight {
+        0
+    }
+
+    fn approve_transfer() -> Weight {
+        0
+    }
+
+    fn cancel_approval() -> Weight {
+        0
+    }
+
+    fn set_item_frozen() -> Weight {
+        0
+    }
+
+    fn set_collection_frozen() -> Weight {
+        0
+    }
+}
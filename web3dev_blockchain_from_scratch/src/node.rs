@@ -0,0 +1,574 @@
+//! A minimal local node around our `Runtime`.
+//!
+//! The node owns the runtime state and the chain of blocks it has executed so far. Submitted
+//! extrinsics wait in the transaction queue until the block builder drains them into a new block,
+//! which is executed on top of the chain.
+//!
+//! Blocks produced by another node, like the blocks of a chain exported to a file, are imported
+//! with `import_block`, which verifies them against their header. Replaying a chain on top of the
+//! same genesis state reproduces its state, or reports the first block which diverges from it.
+
+use std::{
+    fmt,
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use codec::{
+    DecodeLimit,
+    Encode,
+};
+
+use crate::{
+    runtime::{
+        types,
+        Runtime,
+    },
+    support::{
+        self,
+        storage::{
+            Storage,
+            StorageProof,
+        },
+    },
+    transaction_pool::{
+        TransactionPool,
+        TransactionStatus,
+    },
+};
+
+/// The maximum number of extrinsics included in a block.
+pub const MAX_BLOCK_EXTRINSICS: usize = 1024;
+
+/// A block which has been executed by the node, along with the events it emitted and the outcome of
+/// its extrinsics.
+#[derive(Clone, Debug)]
+pub struct ImportedBlock {
+    pub block: types::Block,
+    pub events: Vec<types::EventRecord>,
+    pub report: types::BlockExecutionReport,
+}
+
+/// Why a block could not be imported: the first difference between the block and the chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportError {
+    /// The block does not follow the best block of the chain.
+    BlockNumber {
+        expected: types::BlockNumber,
+        found: types::BlockNumber,
+    },
+    /// The block is not a child of the best block of the chain.
+    ParentHash { expected: [u8; 32], found: [u8; 32] },
+    /// The extrinsics of the block are not the ones committed to by its header.
+    ExtrinsicsRoot { expected: [u8; 32], found: [u8; 32] },
+    /// The block is invalid, see `Runtime::execute_block`.
+    Execution(&'static str),
+    /// Executing the block did not lead to the state root of its header. The report of the
+    /// execution tells the outcome of every extrinsic, to find the one which diverged.
+    StateRoot {
+        expected: [u8; 32],
+        found: [u8; 32],
+        report: Box<types::BlockExecutionReport>,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BlockNumber { expected, found } => {
+                write!(f, "expected block #{expected}, found block #{found}")
+            }
+            Self::ParentHash { expected, found } => write!(
+                f,
+                "expected parent hash {}, found {}",
+                hex::encode(expected),
+                hex::encode(found)
+            ),
+            Self::ExtrinsicsRoot { expected, found } => write!(
+                f,
+                "expected extrinsics root {}, found {}",
+                hex::encode(expected),
+                hex::encode(found)
+            ),
+            Self::Execution(e) => write!(f, "execution failed: {e}"),
+            Self::StateRoot {
+                expected, found, ..
+            } => write!(
+                f,
+                "expected state root {}, found {}",
+                hex::encode(expected),
+                hex::encode(found)
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Node {
+    /// The current state of the chain.
+    runtime: Runtime,
+    /// All the blocks of the chain, indexed by block number. The first one is the genesis block.
+    blocks: Vec<types::Block>,
+    /// The extrinsics waiting to be included in a block.
+    pool: TransactionPool,
+}
+
+impl Node {
+    /// Create a new node on top of the given state.
+    ///
+    /// If the state was last upgraded by another version of the runtime, its storage is migrated
+    /// first. The node does not start if a migration fails.
+    ///
+    /// A new chain, at block `0`, is identified by the state root of its genesis state, which
+    /// becomes the genesis hash extrinsics are built for.
+    pub fn new(mut runtime: Runtime) -> Result<Self, &'static str> {
+        if runtime.runtime_upgrade_pending() {
+            runtime.apply_runtime_upgrade()?;
+        }
+        if runtime.system.block_number() == 0 {
+            let genesis_hash = runtime.state_root();
+            runtime
+                .system
+                .set_genesis_hash(genesis_hash);
+        }
+        let genesis = types::Block {
+            header: support::Header {
+                block_number: runtime.system.block_number(),
+                parent_hash: [0; 32],
+                state_root: runtime.state_root(),
+                extrinsics_root: support::extrinsics_root::<types::Extrinsic>(&[]),
+            },
+            extrinsics: vec![],
+        };
+        Ok(Self {
+            runtime,
+            blocks: vec![genesis],
+            pool: TransactionPool::new(),
+        })
+    }
+
+    /// Read access to the current state of the chain.
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    /// The number of the latest block.
+    pub fn best_number(&self) -> types::BlockNumber {
+        self.runtime
+            .system
+            .block_number()
+    }
+
+    /// Get a block by number.
+    pub fn block(&self, number: types::BlockNumber) -> Option<&types::Block> {
+        let genesis = self.blocks[0]
+            .header
+            .block_number;
+        let index = number.checked_sub(genesis)?;
+        self.blocks
+            .get(usize::try_from(index).ok()?)
+    }
+
+    /// Get a block header by number.
+    pub fn header(&self, number: types::BlockNumber) -> Option<&types::Header> {
+        self.block(number)
+            .map(|block| &block.header)
+    }
+
+    /// The header of the latest block.
+    pub fn best_header(&self) -> &types::Header {
+        &self
+            .blocks
+            .last()
+            .expect("the chain starts with the genesis block")
+            .header
+    }
+
+    /// A proof of the value of `key` in the current state, against the state root of the best
+    /// header. This is how light clients read the state of the chain.
+    pub fn read_proof(&self, key: &[u8]) -> StorageProof {
+        self.runtime.read_proof(key)
+    }
+
+    /// The blocks executed by the node, after its genesis block, encoded to be imported by another
+    /// node with `decode_chain`.
+    pub fn export_chain(&self) -> Vec<u8> {
+        self.blocks[1..]
+            .iter()
+            .flat_map(Encode::encode)
+            .collect()
+    }
+
+    /// The extrinsics waiting in the transaction queue.
+    pub fn pending_extrinsics(&self) -> Vec<types::Extrinsic> {
+        self.pool.pending()
+    }
+
+    /// Validate the extrinsic and add it to the transaction queue.
+    pub fn submit_extrinsic(
+        &mut self,
+        extrinsic: types::Extrinsic,
+    ) -> Result<TransactionStatus, &'static str> {
+        self.pool
+            .submit(&self.runtime, extrinsic)
+    }
+
+    /// Build a new block on top of the chain from the ready extrinsics of the transaction queue,
+    /// up to `MAX_BLOCK_EXTRINSICS` of them weighing at most `support::MAX_BLOCK_WEIGHT`, and
+    /// execute it.
+    ///
+    /// The extrinsics themselves are allowed to fail: their errors are recorded in the block
+    /// events and in the report of the block.
+    ///
+    /// The state root of the header is the one reached by executing the block.
+    pub fn produce_block(&mut self) -> Result<ImportedBlock, &'static str> {
+        let extrinsics = self
+            .pool
+            .ready(&self.runtime, MAX_BLOCK_EXTRINSICS);
+        let mut block = types::Block {
+            header: support::Header {
+                block_number: self.best_number() + 1,
+                parent_hash: self.best_header().hash(),
+                state_root: [0; 32],
+                extrinsics_root: support::extrinsics_root(&extrinsics),
+            },
+            extrinsics,
+        };
+        let report = self
+            .runtime
+            .execute_block(block.clone())?;
+        block.header.state_root = report.state_root;
+        Ok(self.finalize_block(block, report))
+    }
+
+    /// Verify a block produced by another node, and execute it on top of the chain.
+    ///
+    /// The block must be a child of the best block, and the roots of its header must match its
+    /// extrinsics and the state reached by executing them. The state is left unchanged if the block
+    /// is not imported.
+    pub fn import_block(&mut self, block: types::Block) -> Result<ImportedBlock, ImportError> {
+        let best = self.best_header();
+        let header = &block.header;
+        if header.block_number != best.block_number + 1 {
+            return Err(ImportError::BlockNumber {
+                expected: best.block_number + 1,
+                found: header.block_number,
+            });
+        }
+        if header.parent_hash != best.hash() {
+            return Err(ImportError::ParentHash {
+                expected: best.hash(),
+                found: header.parent_hash,
+            });
+        }
+        let extrinsics_root = support::extrinsics_root(&block.extrinsics);
+        if header.extrinsics_root != extrinsics_root {
+            return Err(ImportError::ExtrinsicsRoot {
+                expected: header.extrinsics_root,
+                found: extrinsics_root,
+            });
+        }
+
+        let expected = header.state_root;
+        let report = support::storage::with_transaction(&mut self.runtime, |runtime| {
+            let report = runtime
+                .execute_block(block.clone())
+                .map_err(ImportError::Execution)?;
+            if report.state_root != expected {
+                return Err(ImportError::StateRoot {
+                    expected,
+                    found: report.state_root,
+                    report: Box::new(report),
+                });
+            }
+            Ok(report)
+        })?;
+        Ok(self.finalize_block(block, report))
+    }
+
+    /// Import the blocks of a chain one after the other, stopping at the first one which can't be
+    /// imported. Returns the number of blocks imported.
+    pub fn import_chain(
+        &mut self,
+        blocks: impl IntoIterator<Item = types::Block>,
+    ) -> Result<usize, (types::BlockNumber, ImportError)> {
+        let mut imported = 0;
+        for block in blocks {
+            let number = block.header.block_number;
+            self.import_block(block)
+                .map_err(|e| (number, e))?;
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
+    /// Add an executed block to the chain.
+    fn finalize_block(
+        &mut self,
+        block: types::Block,
+        report: types::BlockExecutionReport,
+    ) -> ImportedBlock {
+        self.blocks
+            .push(block.clone());
+        self.pool.prune(&self.runtime);
+
+        ImportedBlock {
+            block,
+            events: self
+                .runtime
+                .system
+                .events()
+                .to_vec(),
+            report,
+        }
+    }
+}
+
+/// Decode a chain exported by `Node::export_chain`: the SCALE encodings of its blocks, one after
+/// the other.
+pub fn decode_chain(mut bytes: &[u8]) -> Result<Vec<types::Block>, String> {
+    let mut blocks = Vec::new();
+    while !bytes.is_empty() {
+        let block = types::Block::decode_with_depth_limit(support::MAX_DECODE_DEPTH, &mut bytes)
+            .map_err(|e| {
+                format!(
+                    "invalid block at position {} of the chain: {e}",
+                    blocks.len()
+                )
+            })?;
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
+
+/// Produce a new block every `block_time`, forever. `on_block` is called with every new block.
+pub fn run_block_producer(
+    node: &Mutex<Node>,
+    block_time: Duration,
+    on_block: impl Fn(&ImportedBlock),
+) -> ! {
+    loop {
+        thread::sleep(block_time);
+        let imported = node
+            .lock()
+            .unwrap()
+            .produce_block();
+        match imported {
+            Ok(imported) => on_block(&imported),
+            Err(e) => eprintln!("Block production failed: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances,
+        runtime::{
+            extensions::ChargeTransactionPayment,
+            RuntimeCall,
+        },
+    };
+
+    fn transfer(node: &Node, nonce: types::Nonce, amount: types::Balance) -> types::Extrinsic {
+        support::Extrinsic {
+            caller: "alice".to_string(),
+            signature: None,
+            nonce,
+            tip: 0,
+            era: support::Era::Immortal,
+            spec_version: crate::runtime::VERSION.spec_version,
+            genesis_hash: node
+                .runtime()
+                .system
+                .genesis_hash(),
+            call: RuntimeCall::balances(balances::Call::transfer {
+                to: "bob".to_string(),
+                amount,
+            }),
+        }
+    }
+
+    #[test]
+    fn produce_block_from_the_transaction_queue() {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+        let mut node = Node::new(runtime).unwrap();
+        assert_eq!(node.best_number(), 0);
+        assert!(node.block(0).is_some());
+
+        assert_eq!(
+            node.submit_extrinsic(transfer(&node, 0, 40)),
+            Ok(TransactionStatus::Ready)
+        );
+        assert_eq!(
+            node.submit_extrinsic(transfer(&node, 2, 10)),
+            Ok(TransactionStatus::Future)
+        );
+        assert_eq!(
+            node.pending_extrinsics()
+                .len(),
+            2
+        );
+
+        let imported = node.produce_block().unwrap();
+        assert_eq!(
+            imported
+                .block
+                .header
+                .block_number,
+            1
+        );
+        assert_eq!(
+            imported
+                .block
+                .extrinsics
+                .len(),
+            1
+        );
+        // The fee, its share deposited to the treasury, the transfer and the success of the
+        // extrinsic.
+        assert_eq!(imported.events.len(), 4);
+        assert_eq!(node.best_number(), 1);
+        assert_eq!(
+            node.header(1)
+                .map(|header| header.block_number),
+            Some(1)
+        );
+        assert!(node.block(2).is_none());
+        assert_eq!(
+            node.runtime()
+                .balances
+                .balance("bob".to_string()),
+            40
+        );
+
+        // The included extrinsic left the queue, the future one is still waiting.
+        assert_eq!(
+            node.pending_extrinsics()
+                .len(),
+            1
+        );
+        assert_eq!(
+            node.submit_extrinsic(transfer(&node, 1, 10)),
+            Ok(TransactionStatus::Ready)
+        );
+        let imported = node.produce_block().unwrap();
+        assert_eq!(
+            imported
+                .block
+                .extrinsics
+                .len(),
+            2
+        );
+        assert!(node
+            .pending_extrinsics()
+            .is_empty());
+
+        // Blocks are produced even without extrinsics.
+        let imported = node.produce_block().unwrap();
+        assert!(imported
+            .block
+            .extrinsics
+            .is_empty());
+        assert_eq!(node.best_number(), 3);
+    }
+
+    #[test]
+    fn import_a_chain_and_report_the_first_divergence() {
+        let genesis = || {
+            let mut runtime = Runtime::new();
+            runtime
+                .system
+                .allow_keyless_accounts();
+            runtime
+                .balances
+                .set_balance("alice".to_string(), 100);
+            Node::new(runtime).unwrap()
+        };
+        let mut producer = genesis();
+        for nonce in 0..3 {
+            producer
+                .submit_extrinsic(transfer(&producer, nonce, 10))
+                .unwrap();
+            producer
+                .produce_block()
+                .unwrap();
+        }
+        let chain = decode_chain(&producer.export_chain()).unwrap();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain[1].header.parent_hash, chain[0].header.hash());
+
+        // Replaying the chain on the same genesis state reproduces it.
+        let mut node = genesis();
+        assert_eq!(node.import_chain(chain.clone()), Ok(3));
+        assert_eq!(node.best_header(), producer.best_header());
+        assert_eq!(
+            node.runtime().state_root(),
+            producer
+                .runtime()
+                .state_root()
+        );
+
+        // Blocks must follow the best block.
+        let mut node = genesis();
+        let root = node.runtime().state_root();
+        assert_eq!(
+            node.import_block(chain[1].clone())
+                .err(),
+            Some(ImportError::BlockNumber {
+                expected: 1,
+                found: 2
+            })
+        );
+        let mut orphan = chain[0].clone();
+        orphan.header.parent_hash = [1; 32];
+        assert!(matches!(
+            node.import_block(orphan),
+            Err(ImportError::ParentHash { .. })
+        ));
+
+        // The extrinsics are committed to by the header.
+        let mut tampered = chain[0].clone();
+        tampered.extrinsics[0].tip = 1;
+        assert!(matches!(
+            node.import_block(tampered.clone()),
+            Err(ImportError::ExtrinsicsRoot { .. })
+        ));
+
+        // A block executing differently is reported along with its execution, and is not imported.
+        tampered
+            .header
+            .extrinsics_root = support::extrinsics_root(&tampered.extrinsics);
+        let Err(ImportError::StateRoot {
+            expected, report, ..
+        }) = node.import_block(tampered.clone())
+        else {
+            panic!("the state root should diverge");
+        };
+        assert_eq!(expected, chain[0].header.state_root);
+        assert_eq!(
+            report.extrinsics[0].fee,
+            ChargeTransactionPayment::fee(&tampered.extrinsics[0])
+        );
+        assert_eq!(node.best_number(), 0);
+        assert_eq!(node.runtime().state_root(), root);
+
+        // The replay stops at the first divergence.
+        let mut diverging = chain;
+        diverging[2].header.state_root = [0; 32];
+        let mut node = genesis();
+        assert!(matches!(
+            node.import_chain(diverging),
+            Err((3, ImportError::StateRoot { .. }))
+        ));
+        assert_eq!(node.best_number(), 2);
+    }
+}
@@ -1,97 +1,284 @@
 use core::fmt::Debug;
-use std::collections::BTreeMap;
 
-use crate::support::DispatchResult;
+use codec::{
+    Decode,
+    Encode,
+};
+use serde::Serialize;
+
+use crate::support::{
+    benchmarking::{
+        Benchmark,
+        Benchmarks,
+        Component,
+    },
+    storage::StorageMap,
+    DispatchResult,
+    Hooks,
+    OnRuntimeUpgrade,
+};
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+/// The largest claim measured by the benchmarks, in bytes.
+pub const MAX_BENCHMARKED_CLAIM: u32 = 16 * 1024;
 
 pub trait Config: crate::system::Config {
-    type Content: Debug + Ord;
+    type Content: Debug + Ord + Clone + Encode + Decode;
+    /// The weights of the calls, see the `weights` module.
+    type WeightInfo: WeightInfo;
+}
+
+/// The events emitted by the Proof of Existence Module.
+#[derive(
+    macros::CloneNoBound,
+    macros::DebugNoBound,
+    macros::PartialEqNoBound,
+    macros::EqNoBound,
+    macros::TypeInfo,
+    Serialize,
+)]
+pub enum Event<T: Config> {
+    /// `who` claimed the `claim` content.
+    ClaimCreated {
+        who: T::AccountId,
+        claim: T::Content,
+    },
+    /// `who` revoked their claim on the `claim` content.
+    ClaimRevoked {
+        who: T::AccountId,
+        claim: T::Content,
+    },
 }
 
 /// This is the Proof of Existence Module.
 /// It is a simple module that allows accounts to claim existence of some data.
 #[derive(Debug)]
+#[macros::storage]
 pub struct Pallet<T: Config> {
     /// A simple storage map from content to the owner of that content.
     /// Accounts can make multiple different claims, but each claim can only have one owner.
-    claims: BTreeMap<T::Content, T::AccountId>,
+    claims: StorageMap<T::Content, T::AccountId>,
+    /// The events emitted since the runtime last collected them.
+    events: Vec<Event<T>>,
 }
 
 #[macros::call]
+#[errors(
+    "this content is already claimed",
+    "claim does not exist",
+    "this content is owned by someone else"
+)]
 impl<T: Config> Pallet<T> {
     /// Create a new claim on behalf of the `caller`.
     /// This function will return an error if someone already has claimed that content.
+    #[call_index(0)]
+    #[weight(T::WeightInfo::create_claim(claim.encoded_size() as u32))]
     pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
         if self
             .claims
             .contains_key(&claim)
         {
-            return Err(&"this content is already claimed");
+            return Err("this content is already claimed");
         }
         self.claims
-            .insert(claim, caller);
+            .insert(&claim, caller.clone());
+        self.deposit_event(Event::ClaimCreated { who: caller, claim });
         Ok(())
     }
 
     /// Revoke an existing claim on some content.
     /// This function should only succeed if the caller is the owner of an existing claim.
     /// It will return an error if the claim does not exist, or if the caller is not the owner.
+    #[call_index(1)]
+    #[weight(T::WeightInfo::revoke_claim(claim.encoded_size() as u32))]
     pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
         let owner = self
             .get_claim(&claim)
             .ok_or("claim does not exist")?;
-        if caller != *owner {
-            return Err(&"this content is owned by someone else");
+        if caller != owner {
+            return Err("this content is owned by someone else");
         }
         self.claims.remove(&claim);
+        self.deposit_event(Event::ClaimRevoked { who: caller, claim });
         Ok(())
     }
 }
 
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: Config> Pallet<T> {
-    /// Create a new instance of the Proof of Existence Module.
-    pub fn new() -> Self {
-        Pallet {
-            claims: BTreeMap::new(),
+    pub fn get_claim(&self, claim: &T::Content) -> Option<T::AccountId> {
+        self.claims.try_get(claim)
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    /// Take the events emitted since the last call, so the runtime can record them.
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+/// The claims are hashed into their storage keys, so their weight depends on their size `c`.
+impl<T: Config> Benchmarks for Pallet<T>
+where
+    T::AccountId: From<&'static str>,
+    T::Content: From<String>,
+{
+    /// The claim of the caller, if any.
+    type Setup = Option<T::Content>;
+
+    fn setup(claim: Option<T::Content>) -> Self {
+        let mut pallet = Pallet::new();
+        if let Some(claim) = claim {
+            pallet
+                .claims
+                .insert(&claim, "caller".into());
         }
+        pallet
     }
 
-    pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
-        self.claims.get(claim)
+    fn benchmarks() -> Vec<Benchmark<Self>> {
+        fn claim<T: Config>(size: u32) -> T::Content
+        where
+            T::Content: From<String>,
+        {
+            "x".repeat(size as usize)
+                .into()
+        }
+
+        let size = Component {
+            name: "c",
+            min: 0,
+            max: MAX_BENCHMARKED_CLAIM,
+        };
+        vec![
+            Benchmark {
+                name: "create_claim",
+                components: vec![size],
+                setup: |c| {
+                    let call = Call::create_claim {
+                        claim: claim::<T>(c[0]),
+                    };
+                    (Self::setup(None), "caller".into(), call)
+                },
+                verify: |pallet, c| {
+                    if pallet.get_claim(&claim::<T>(c[0])) != Some("caller".into()) {
+                        return Err("the claim was not created");
+                    }
+                    Ok(())
+                },
+            },
+            Benchmark {
+                name: "revoke_claim",
+                components: vec![size],
+                setup: |c| {
+                    let call = Call::revoke_claim {
+                        claim: claim::<T>(c[0]),
+                    };
+                    (Self::setup(Some(claim::<T>(c[0]))), "caller".into(), call)
+                },
+                verify: |pallet, c| {
+                    if pallet
+                        .get_claim(&claim::<T>(c[0]))
+                        .is_some()
+                    {
+                        return Err("the claim was not revoked");
+                    }
+                    Ok(())
+                },
+            },
+        ]
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::system::Config;
+    use crate::{
+        support::benchmarking::{
+            self,
+            BenchmarkConfig,
+        },
+        system::Config,
+    };
 
     struct TestConfig;
 
     impl Config for TestConfig {
-        type AccountId = &'static str;
+        type AccountId = String;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = ();
+        type SystemWeightInfo = ();
     }
 
     impl super::Config for TestConfig {
-        type Content = &'static str;
+        type Content = String;
+        type WeightInfo = ();
     }
 
     #[test]
     fn basic_proof_of_existence() {
+        let alice = || "alice".to_string();
+        let bob = || "bob".to_string();
+        let document = || "my_document".to_string();
+
         let mut poe = super::Pallet::<TestConfig>::new();
-        let _ = poe.create_claim("alice", "my_document");
-        assert_eq!(poe.get_claim(&"my_document"), Some(&"alice"));
+        let _ = poe.create_claim(alice(), document());
+        assert_eq!(poe.get_claim(&document()), Some(alice()));
 
-        let res = poe.revoke_claim(&"bob", "my_document");
+        let res = poe.revoke_claim(bob(), document());
         assert_eq!(res, Err("this content is owned by someone else"));
 
-        let res = poe.create_claim("bob", "my_document");
+        let res = poe.create_claim(bob(), document());
         assert_eq!(res, Err("this content is already claimed"));
 
-        let res = poe.revoke_claim("alice", "non existent");
+        let res = poe.revoke_claim(alice(), "non existent".to_string());
         assert_eq!(res, Err("claim does not exist"));
 
-        let _ = poe.revoke_claim("alice", "my_document");
-        assert_eq!(poe.get_claim(&"my_document"), None);
+        let _ = poe.revoke_claim(alice(), document());
+        assert_eq!(poe.get_claim(&document()), None);
+
+        assert_eq!(
+            poe.take_events(),
+            vec![
+                super::Event::ClaimCreated {
+                    who: alice(),
+                    claim: document(),
+                },
+                super::Event::ClaimRevoked {
+                    who: alice(),
+                    claim: document(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn benchmarks_succeed() {
+        let config = BenchmarkConfig {
+            steps: 2,
+            repeat: 1,
+        };
+        let results = benchmarking::run::<super::Pallet<TestConfig>>(&config).unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| (result.name, result.slopes.len()))
+                .collect::<Vec<_>>(),
+            vec![("create_claim", 1), ("revoke_claim", 1)]
+        );
     }
 }
@@ -0,0 +1,36 @@
+//! The weights of the `proof_of_existence` pallet, measured by its benchmarks.
+//!
+//! Generated by `cargo run --release --bin benchmark`, do not edit by hand. The unit of weight is
+//! one nanosecond of execution on the machine which ran the benchmarks.
+
+use crate::support::Weight;
+
+/// The weights of the callable functions of the pallet.
+pub trait WeightInfo {
+    fn create_claim(c: u32) -> Weight;
+    fn revoke_claim(c: u32) -> Weight;
+}
+
+/// The weights measured by the benchmarks.
+pub struct BenchmarkedWeights;
+
+impl WeightInfo for BenchmarkedWeights {
+    fn create_claim(c: u32) -> Weight {
+        1549 + 6 * Weight::from(c)
+    }
+
+    fn revoke_claim(c: u32) -> Weight {
+        1669 + 7 * Weight::from(c)
+    }
+}
+
+/// No weights, for tests.
+impl WeightInfo for () {
+    fn create_claim(_c: u32) -> Weight {
+        0
+    }
+
+    fn revoke_claim(_c: u32) -> Weight {
+        0
+    }
+}
@@ -0,0 +1,1007 @@
+//! The proxy pallet: accounts making calls on behalf of other accounts.
+//!
+//! An account, the real account, adds proxies: delegate accounts allowed to make calls for it,
+//! restricted by a `ProxyType`. This lets hot keys do the day-to-day operations of an account
+//! whose keys are kept offline, without being able to move its funds with a `NonTransfer` proxy.
+//! The runtime decides which calls every proxy type allows, by implementing `support::CallFilter`
+//! for `ProxyType`.
+//!
+//! A proxy with a delay must announce the hash of a call before making it, and can only make it
+//! once the delay is over, which leaves time to the real account to reject it, or to remove the
+//! proxy if its keys were compromised.
+//!
+//! The pallet can't dispatch the calls of other pallets, so it forwards them to the runtime, which
+//! dispatches them right after the call of the proxy and fails both if the call isn't allowed (see
+//! `support::ForwardCalls`). The runtime lends it the system pallet, for the current block, with
+//! `#[lend(system)]`.
+
+use std::fmt::Debug;
+
+use codec::{
+    Decode,
+    Encode,
+};
+use num::{
+    CheckedAdd,
+    Zero,
+};
+use serde::{
+    de::DeserializeOwned,
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    metadata::TypeInfo,
+    support::{
+        benchmarking::{
+            ensure,
+            Benchmark,
+            Benchmarks,
+        },
+        storage::{
+            blake2_256,
+            StorageMap,
+        },
+        Borrower,
+        CallFilter,
+        DispatchResult,
+        ForwardCalls,
+        ForwardedCall,
+        GetWeight,
+        Hooks,
+        Lent,
+        OnRuntimeUpgrade,
+    },
+    system,
+};
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+/// The most proxies an account can have.
+pub const MAX_PROXIES: u32 = 32;
+
+/// The most announcements a proxy can have pending.
+pub const MAX_ANNOUNCEMENTS: u32 = 32;
+
+pub trait Config: system::Config {
+    /// The calls of the runtime, which proxies make on behalf of their real accounts.
+    type RuntimeCall: Clone
+        + Debug
+        + Eq
+        + Encode
+        + Decode
+        + Serialize
+        + DeserializeOwned
+        + TypeInfo
+        + GetWeight;
+    /// The weights of the calls, see the `weights` module.
+    type WeightInfo: WeightInfo;
+}
+
+/// The calls a proxy is allowed to make, as decided by the runtime.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode, macros::TypeInfo,
+)]
+pub enum ProxyType {
+    /// Any call.
+    Any,
+    /// The calls of the balances pallets.
+    BalancesOnly,
+    /// The calls of the proof of existence pallet.
+    ClaimsOnly,
+    /// The calls which can't move funds or items, and aren't root calls.
+    NonTransfer,
+}
+
+/// A proxy of an account.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Encode, Decode, macros::TypeInfo)]
+pub struct ProxyDefinition<AccountId, BlockNumber> {
+    pub delegate: AccountId,
+    pub proxy_type: ProxyType,
+    /// The number of blocks between the announcement of a call and the call. A proxy without delay
+    /// doesn't announce its calls.
+    pub delay: BlockNumber,
+}
+
+/// A call announced by a proxy, by its BLAKE2b-256 hash.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Encode, Decode, macros::TypeInfo)]
+pub struct Announcement<AccountId, BlockNumber> {
+    pub real: AccountId,
+    #[serde(with = "hex::serde")]
+    pub call_hash: [u8; 32],
+    /// The block of the announcement.
+    pub height: BlockNumber,
+}
+
+/// The proxies of the runtime `T`.
+pub type ProxyDefinitionOf<T> =
+    ProxyDefinition<<T as system::Config>::AccountId, <T as system::Config>::BlockNumber>;
+
+/// The announcements of the runtime `T`.
+pub type AnnouncementOf<T> =
+    Announcement<<T as system::Config>::AccountId, <T as system::Config>::BlockNumber>;
+
+/// The events emitted by the proxy pallet.
+#[derive(
+    macros::CloneNoBound,
+    macros::DebugNoBound,
+    macros::PartialEqNoBound,
+    macros::EqNoBound,
+    macros::TypeInfo,
+    Serialize,
+)]
+pub enum Event<T: Config> {
+    /// `delegate` became a proxy of `real`.
+    ProxyAdded {
+        real: T::AccountId,
+        delegate: T::AccountId,
+        proxy_type: ProxyType,
+        delay: T::BlockNumber,
+    },
+    /// `delegate` is no longer a proxy of `real`.
+    ProxyRemoved {
+        real: T::AccountId,
+        delegate: T::AccountId,
+        proxy_type: ProxyType,
+        delay: T::BlockNumber,
+    },
+    /// `delegate` announced a call for `real`.
+    Announced {
+        real: T::AccountId,
+        delegate: T::AccountId,
+        #[serde(with = "hex::serde")]
+        call_hash: [u8; 32],
+    },
+    /// An announcement was removed by its proxy, or rejected by its real account.
+    AnnouncementRemoved {
+        real: T::AccountId,
+        delegate: T::AccountId,
+        #[serde(with = "hex::serde")]
+        call_hash: [u8; 32],
+    },
+    /// `delegate` made a call for `real`, which the runtime dispatches next.
+    ProxyExecuted {
+        real: T::AccountId,
+        delegate: T::AccountId,
+        proxy_type: ProxyType,
+    },
+}
+
+#[derive(Debug)]
+#[macros::storage]
+pub struct Pallet<T: Config> {
+    /// The proxies of every real account.
+    #[default(Vec::new())]
+    proxies: StorageMap<T::AccountId, Vec<ProxyDefinitionOf<T>>>,
+    /// The pending announcements of every proxy.
+    #[default(Vec::new())]
+    announcements: StorageMap<T::AccountId, Vec<AnnouncementOf<T>>>,
+    /// The call made by the last call of the pallet, until the runtime takes it.
+    forwarded: Option<ForwardedCall<T::AccountId, T::RuntimeCall, ProxyType>>,
+    /// The system pallet, giving the current block.
+    system: Lent<system::Pallet<T>>,
+    /// The events emitted since the runtime last collected them.
+    events: Vec<Event<T>>,
+}
+
+#[macros::call]
+#[errors(
+    "an account can't be its own proxy",
+    "the proxy already exists",
+    "too many proxies",
+    "unknown proxy",
+    "not a proxy of the account",
+    "the proxy must announce its calls",
+    "too many announcements",
+    "unknown announcement",
+    "the delay of the announcement is not over",
+    crate::support::NOT_LENT
+)]
+impl<T: Config> Pallet<T> {
+    /// Make `delegate` a proxy of the `caller`, making the calls allowed by `proxy_type`, and
+    /// announcing them `delay` blocks in advance if not zero.
+    #[call_index(0)]
+    #[weight(T::WeightInfo::add_proxy())]
+    pub fn add_proxy(
+        &mut self,
+        caller: T::AccountId,
+        delegate: T::AccountId,
+        proxy_type: ProxyType,
+        delay: T::BlockNumber,
+    ) -> DispatchResult {
+        if delegate == caller {
+            return Err("an account can't be its own proxy");
+        }
+        let definition = ProxyDefinition {
+            delegate: delegate.clone(),
+            proxy_type,
+            delay,
+        };
+        let mut proxies = self.proxies(&caller);
+        if proxies.contains(&definition) {
+            return Err("the proxy already exists");
+        }
+        if proxies.len() >= MAX_PROXIES as usize {
+            return Err("too many proxies");
+        }
+        proxies.push(definition);
+        self.proxies
+            .insert(&caller, proxies);
+        self.deposit_event(Event::ProxyAdded {
+            real: caller,
+            delegate,
+            proxy_type,
+            delay,
+        });
+        Ok(())
+    }
+
+    /// Remove a proxy of the `caller`.
+    #[call_index(1)]
+    #[weight(T::WeightInfo::remove_proxy())]
+    pub fn remove_proxy(
+        &mut self,
+        caller: T::AccountId,
+        delegate: T::AccountId,
+        proxy_type: ProxyType,
+        delay: T::BlockNumber,
+    ) -> DispatchResult {
+        let definition = ProxyDefinition {
+            delegate: delegate.clone(),
+            proxy_type,
+            delay,
+        };
+        let mut proxies = self.proxies(&caller);
+        let index = proxies
+            .iter()
+            .position(|proxy| proxy == &definition)
+            .ok_or("unknown proxy")?;
+        proxies.remove(index);
+        self.set_proxies(&caller, proxies);
+        self.deposit_event(Event::ProxyRemoved {
+            real: caller,
+            delegate,
+            proxy_type,
+            delay,
+        });
+        Ok(())
+    }
+
+    /// Remove all the proxies of the `caller`.
+    #[call_index(2)]
+    #[weight(T::WeightInfo::remove_proxies())]
+    pub fn remove_proxies(&mut self, caller: T::AccountId) -> DispatchResult {
+        for proxy in self.proxies(&caller) {
+            self.deposit_event(Event::ProxyRemoved {
+                real: caller.clone(),
+                delegate: proxy.delegate,
+                proxy_type: proxy.proxy_type,
+                delay: proxy.delay,
+            });
+        }
+        self.proxies.remove(&caller);
+        Ok(())
+    }
+
+    /// Make `call` on behalf of `real`, as one of its proxies without delay. The proxy can be
+    /// chosen with `force_proxy_type`, otherwise the first one is used.
+    #[call_index(3)]
+    #[weight(T::WeightInfo::proxy().saturating_add(call.weight()))]
+    // The call is boxed in `Call`, whose variants can't hold a `T::RuntimeCall` holding them.
+    #[allow(clippy::boxed_local)]
+    pub fn proxy(
+        &mut self,
+        caller: T::AccountId,
+        real: T::AccountId,
+        force_proxy_type: Option<ProxyType>,
+        call: Box<T::RuntimeCall>,
+    ) -> DispatchResult {
+        let proxies = self.find_proxies(&real, &caller, force_proxy_type)?;
+        let proxy = proxies
+            .into_iter()
+            .find(|proxy| proxy.delay.is_zero())
+            .ok_or("the proxy must announce its calls")?;
+        self.forward(real, caller, proxy.proxy_type, *call);
+        Ok(())
+    }
+
+    /// Announce the call with the hash `call_hash`, which the `caller` will make as a proxy of
+    /// `real` once the delay of the proxy is over.
+    #[call_index(4)]
+    #[weight(T::WeightInfo::announce())]
+    pub fn announce(
+        &mut self,
+        caller: T::AccountId,
+        real: T::AccountId,
+        call_hash: [u8; 32],
+    ) -> DispatchResult {
+        self.find_proxies(&real, &caller, None)?;
+        let mut announcements = self.announcements(&caller);
+        if announcements.len() >= MAX_ANNOUNCEMENTS as usize {
+            return Err("too many announcements");
+        }
+        announcements.push(Announcement {
+            real: real.clone(),
+            call_hash,
+            height: self.now()?,
+        });
+        self.announcements
+            .insert(&caller, announcements);
+        self.deposit_event(Event::Announced {
+            real,
+            delegate: caller,
+            call_hash,
+        });
+        Ok(())
+    }
+
+    /// Remove an announcement of the `caller`.
+    #[call_index(5)]
+    #[weight(T::WeightInfo::remove_announcement())]
+    pub fn remove_announcement(
+        &mut self,
+        caller: T::AccountId,
+        real: T::AccountId,
+        call_hash: [u8; 32],
+    ) -> DispatchResult {
+        self.remove_announced(real, caller, call_hash)
+    }
+
+    /// Reject an announcement made for the `caller` by its proxy `delegate`.
+    #[call_index(6)]
+    #[weight(T::WeightInfo::reject_announcement())]
+    pub fn reject_announcement(
+        &mut self,
+        caller: T::AccountId,
+        delegate: T::AccountId,
+        call_hash: [u8; 32],
+    ) -> DispatchResult {
+        self.remove_announced(caller, delegate, call_hash)
+    }
+
+    /// Make the `call` announced by `delegate` on behalf of `real`, once the delay of the proxy is
+    /// over. Anyone can make it, since it was announced.
+    #[call_index(7)]
+    #[weight(T::WeightInfo::proxy_announced().saturating_add(call.weight()))]
+    pub fn proxy_announced(
+        &mut self,
+        _caller: T::AccountId,
+        delegate: T::AccountId,
+        real: T::AccountId,
+        force_proxy_type: Option<ProxyType>,
+        call: Box<T::RuntimeCall>,
+    ) -> DispatchResult {
+        let proxies = self.find_proxies(&real, &delegate, force_proxy_type)?;
+        let call_hash = blake2_256(&call.encode());
+        let mut announcements = self.announcements(&delegate);
+        let announced = |announcement: &AnnouncementOf<T>| {
+            announcement.real == real && announcement.call_hash == call_hash
+        };
+        if !announcements
+            .iter()
+            .any(announced)
+        {
+            return Err("unknown announcement");
+        }
+        let now = self.now()?;
+        let due = |proxy: &ProxyDefinitionOf<T>, announcement: &AnnouncementOf<T>| {
+            announcement
+                .height
+                .checked_add(&proxy.delay)
+                .is_some_and(|due| due <= now)
+        };
+        let (proxy, index) = proxies
+            .iter()
+            .find_map(|proxy| {
+                let index = announcements
+                    .iter()
+                    .position(|announcement| announced(announcement) && due(proxy, announcement))?;
+                Some((proxy, index))
+            })
+            .ok_or("the delay of the announcement is not over")?;
+        let proxy_type = proxy.proxy_type;
+        announcements.remove(index);
+        self.set_announcements(&delegate, announcements);
+        self.forward(real, delegate, proxy_type, *call);
+        Ok(())
+    }
+}
+
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// The proxies of `real`.
+    pub fn proxies(&self, real: &T::AccountId) -> Vec<ProxyDefinitionOf<T>> {
+        self.proxies.get(real)
+    }
+
+    /// The pending announcements of the proxy `delegate`.
+    pub fn announcements(&self, delegate: &T::AccountId) -> Vec<AnnouncementOf<T>> {
+        self.announcements
+            .get(delegate)
+    }
+
+    /// The proxies of `real` which `delegate` is, of the type `proxy_type` if given.
+    fn find_proxies(
+        &self,
+        real: &T::AccountId,
+        delegate: &T::AccountId,
+        proxy_type: Option<ProxyType>,
+    ) -> Result<Vec<ProxyDefinitionOf<T>>, &'static str> {
+        let proxies: Vec<_> = self
+            .proxies(real)
+            .into_iter()
+            .filter(|proxy| &proxy.delegate == delegate)
+            .filter(|proxy| proxy_type.is_none_or(|proxy_type| proxy.proxy_type == proxy_type))
+            .collect();
+        match proxies.is_empty() {
+            true => Err("not a proxy of the account"),
+            false => Ok(proxies),
+        }
+    }
+
+    fn set_proxies(&mut self, real: &T::AccountId, proxies: Vec<ProxyDefinitionOf<T>>) {
+        match proxies.is_empty() {
+            true => self.proxies.remove(real),
+            false => self
+                .proxies
+                .insert(real, proxies),
+        }
+    }
+
+    fn set_announcements(
+        &mut self,
+        delegate: &T::AccountId,
+        announcements: Vec<AnnouncementOf<T>>,
+    ) {
+        match announcements.is_empty() {
+            true => self
+                .announcements
+                .remove(delegate),
+            false => self
+                .announcements
+                .insert(delegate, announcements),
+        }
+    }
+
+    /// Remove the announcement of `call_hash` made by `delegate` for `real`.
+    fn remove_announced(
+        &mut self,
+        real: T::AccountId,
+        delegate: T::AccountId,
+        call_hash: [u8; 32],
+    ) -> DispatchResult {
+        let mut announcements = self.announcements(&delegate);
+        let index = announcements
+            .iter()
+            .position(|announcement| {
+                announcement.real == real && announcement.call_hash == call_hash
+            })
+            .ok_or("unknown announcement")?;
+        announcements.remove(index);
+        self.set_announcements(&delegate, announcements);
+        self.deposit_event(Event::AnnouncementRemoved {
+            real,
+            delegate,
+            call_hash,
+        });
+        Ok(())
+    }
+
+    /// Forward `call` to the runtime, to be dispatched on behalf of `real` if `proxy_type` allows
+    /// it.
+    fn forward(
+        &mut self,
+        real: T::AccountId,
+        delegate: T::AccountId,
+        proxy_type: ProxyType,
+        call: T::RuntimeCall,
+    ) {
+        self.forwarded = Some(ForwardedCall {
+            caller: real.clone(),
+            call,
+            filter: proxy_type,
+        });
+        self.deposit_event(Event::ProxyExecuted {
+            real,
+            delegate,
+            proxy_type,
+        });
+    }
+
+    /// The current block, from the system pallet lent by the runtime.
+    fn now(&mut self) -> Result<T::BlockNumber, &'static str> {
+        Ok(self
+            .system
+            .get_mut()?
+            .block_number())
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    /// Take the events emitted since the last call, so the runtime can record them.
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<T: Config> ForwardCalls<T::AccountId, T::RuntimeCall> for Pallet<T>
+where
+    ProxyType: CallFilter<T::RuntimeCall>,
+{
+    type Filter = ProxyType;
+
+    fn take_forwarded(&mut self) -> Option<ForwardedCall<T::AccountId, T::RuntimeCall, ProxyType>> {
+        self.forwarded.take()
+    }
+}
+
+impl<T: Config> Borrower<system::Pallet<T>> for Pallet<T> {
+    fn lent(&mut self) -> &mut Lent<system::Pallet<T>> {
+        &mut self.system
+    }
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+/// The real account has as many proxies as possible, and its proxy as many announcements, with the
+/// ones used last. The weight of the calls made by proxies is added to their own.
+impl<T: Config> Benchmarks for Pallet<T>
+where
+    T::AccountId: From<&'static str>,
+    T::BlockNumber: From<u32>,
+    T::RuntimeCall: From<system::Call<T>>,
+{
+    /// The numbers of proxies and announcements, and the last proxy, if any.
+    type Setup = (u32, u8, Option<(ProxyType, u32)>);
+
+    /// A pallet in the block `1`, in which the delegate has `proxies` proxies of the real
+    /// account, with increasing delays from `1`, and announced `announcements` calls, with the
+    /// hashes `[1; 32]`, `[2; 32]`... The last proxy is the proxy `last`, if given.
+    fn setup((proxies, announcements, last): (u32, u8, Option<(ProxyType, u32)>)) -> Self {
+        let mut system = system::Pallet::new();
+        system.inc_block_number();
+        let mut pallet = Pallet::new();
+        Borrower::<system::Pallet<T>>::lent(&mut pallet).lend(system);
+        let mut definitions: Vec<_> = (1..=proxies)
+            .map(|delay| ProxyDefinition {
+                delegate: "delegate".into(),
+                proxy_type: ProxyType::NonTransfer,
+                delay: delay.into(),
+            })
+            .collect();
+        if let Some((proxy_type, delay)) = last {
+            definitions.pop();
+            definitions.push(ProxyDefinition {
+                delegate: "delegate".into(),
+                proxy_type,
+                delay: delay.into(),
+            });
+        }
+        pallet
+            .proxies
+            .insert(&"real".into(), definitions);
+        let announcements = (1..=announcements)
+            .map(|hash| Announcement {
+                real: "real".into(),
+                call_hash: [hash; 32],
+                height: 0.into(),
+            })
+            .collect();
+        pallet
+            .announcements
+            .insert(&"delegate".into(), announcements);
+        pallet
+    }
+
+    fn benchmarks() -> Vec<Benchmark<Self>> {
+        fn call<T: Config>() -> Box<T::RuntimeCall>
+        where
+            T::RuntimeCall: From<system::Call<T>>,
+        {
+            Box::new(system::Call::remark { remark: vec![] }.into())
+        }
+
+        vec![
+            Benchmark {
+                name: "add_proxy",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::add_proxy {
+                        delegate: "delegate".into(),
+                        proxy_type: ProxyType::Any,
+                        delay: 0.into(),
+                    };
+                    (Self::setup((MAX_PROXIES - 1, 0, None)), "real".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(
+                        pallet
+                            .proxies(&"real".into())
+                            .len()
+                            == MAX_PROXIES as usize,
+                    )
+                },
+            },
+            Benchmark {
+                name: "remove_proxy",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::remove_proxy {
+                        delegate: "delegate".into(),
+                        proxy_type: ProxyType::Any,
+                        delay: 0.into(),
+                    };
+                    let pallet = Self::setup((MAX_PROXIES, 0, Some((ProxyType::Any, 0))));
+                    (pallet, "real".into(), call)
+                },
+                verify: |pallet, _| {
+                    ensure(
+                        pallet
+                            .proxies(&"real".into())
+                            .len()
+                            == MAX_PROXIES as usize - 1,
+                    )
+                },
+            },
+            Benchmark {
+                name: "remove_proxies",
+                components: vec![],
+                setup: |_| {
+                    (
+                        Self::setup((MAX_PROXIES, 0, None)),
+                        "real".into(),
+                        Call::remove_proxies {},
+                    )
+                },
+                verify: |pallet, _| {
+                    ensure(
+                        pallet
+                            .proxies(&"real".into())
+                            .is_empty(),
+                    )
+                },
+            },
+            Benchmark {
+                name: "proxy",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::proxy {
+                        real: "real".into(),
+                        force_proxy_type: None,
+                        call: call::<T>(),
+                    };
+                    let pallet = Self::setup((MAX_PROXIES, 0, Some((ProxyType::Any, 0))));
+                    (pallet, "delegate".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.forwarded.is_some()),
+            },
+            Benchmark {
+                name: "announce",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::announce {
+                        real: "real".into(),
+                        call_hash: [0; 32],
+                    };
+                    let pallet = Self::setup((MAX_PROXIES, MAX_ANNOUNCEMENTS as u8 - 1, None));
+                    (pallet, "delegate".into(), call)
+                },
+                verify: |pallet, _| {
+                    let announcements = pallet.announcements(&"delegate".into());
+                    ensure(announcements.len() == MAX_ANNOUNCEMENTS as usize)
+                },
+            },
+            Benchmark {
+                name: "remove_announcement",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::remove_announcement {
+                        real: "real".into(),
+                        call_hash: [MAX_ANNOUNCEMENTS as u8; 32],
+                    };
+                    let pallet = Self::setup((0, MAX_ANNOUNCEMENTS as u8, None));
+                    (pallet, "delegate".into(), call)
+                },
+                verify: |pallet, _| {
+                    let announcements = pallet.announcements(&"delegate".into());
+                    ensure(announcements.len() == MAX_ANNOUNCEMENTS as usize - 1)
+                },
+            },
+            Benchmark {
+                name: "reject_announcement",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::reject_announcement {
+                        delegate: "delegate".into(),
+                        call_hash: [MAX_ANNOUNCEMENTS as u8; 32],
+                    };
+                    let pallet = Self::setup((0, MAX_ANNOUNCEMENTS as u8, None));
+                    (pallet, "real".into(), call)
+                },
+                verify: |pallet, _| {
+                    let announcements = pallet.announcements(&"delegate".into());
+                    ensure(announcements.len() == MAX_ANNOUNCEMENTS as usize - 1)
+                },
+            },
+            Benchmark {
+                name: "proxy_announced",
+                components: vec![],
+                setup: |_| {
+                    let inner = call::<T>();
+                    let mut pallet = Self::setup((MAX_PROXIES, MAX_ANNOUNCEMENTS as u8 - 1, None));
+                    let mut announcements = pallet.announcements(&"delegate".into());
+                    announcements.push(Announcement {
+                        real: "real".into(),
+                        call_hash: blake2_256(&inner.encode()),
+                        height: 0.into(),
+                    });
+                    pallet
+                        .announcements
+                        .insert(&"delegate".into(), announcements);
+                    let call = Call::proxy_announced {
+                        delegate: "delegate".into(),
+                        real: "real".into(),
+                        force_proxy_type: None,
+                        call: inner,
+                    };
+                    (pallet, "anyone".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.forwarded.is_some()),
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::benchmarking::{
+        self,
+        BenchmarkConfig,
+    };
+
+    struct TestConfig;
+
+    impl system::Config for TestConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type SystemWeightInfo = ();
+    }
+
+    /// The proxies only make the calls of the system pallet.
+    impl Config for TestConfig {
+        type RuntimeCall = system::Call<TestConfig>;
+        type WeightInfo = ();
+    }
+
+    fn alice() -> String {
+        "alice".to_string()
+    }
+
+    fn bob() -> String {
+        "bob".to_string()
+    }
+
+    fn charlie() -> String {
+        "charlie".to_string()
+    }
+
+    fn remark() -> system::Call<TestConfig> {
+        system::Call::remark {
+            remark: b"hello".to_vec(),
+        }
+    }
+
+    /// The pallet in the block `1`.
+    fn new_pallet() -> Pallet<TestConfig> {
+        let mut system = system::Pallet::new();
+        system.inc_block_number();
+        let mut proxy = Pallet::new();
+        proxy.system.lend(system);
+        proxy
+    }
+
+    fn run_to_block(proxy: &mut Pallet<TestConfig>, block: u32) {
+        let system = proxy
+            .system
+            .get_mut()
+            .unwrap();
+        while system.block_number() < block {
+            system.inc_block_number();
+        }
+    }
+
+    #[test]
+    fn add_and_remove_proxies() {
+        let mut proxy = new_pallet();
+        assert_eq!(
+            proxy.add_proxy(alice(), alice(), ProxyType::Any, 0),
+            Err("an account can't be its own proxy")
+        );
+        assert_eq!(proxy.add_proxy(alice(), bob(), ProxyType::Any, 0), Ok(()));
+        assert_eq!(
+            proxy.add_proxy(alice(), bob(), ProxyType::Any, 0),
+            Err("the proxy already exists")
+        );
+        assert_eq!(
+            proxy.add_proxy(alice(), bob(), ProxyType::NonTransfer, 2),
+            Ok(())
+        );
+        assert_eq!(proxy.proxies(&alice()).len(), 2);
+
+        assert_eq!(
+            proxy.remove_proxy(alice(), bob(), ProxyType::NonTransfer, 0),
+            Err("unknown proxy")
+        );
+        assert_eq!(
+            proxy.remove_proxy(alice(), bob(), ProxyType::Any, 0),
+            Ok(())
+        );
+        assert_eq!(
+            proxy.proxies(&alice()),
+            vec![ProxyDefinition {
+                delegate: bob(),
+                proxy_type: ProxyType::NonTransfer,
+                delay: 2,
+            }]
+        );
+        assert_eq!(proxy.remove_proxies(alice()), Ok(()));
+        assert_eq!(proxy.proxies(&alice()), vec![]);
+        assert_eq!(
+            proxy.take_events().last(),
+            Some(&Event::ProxyRemoved {
+                real: alice(),
+                delegate: bob(),
+                proxy_type: ProxyType::NonTransfer,
+                delay: 2,
+            })
+        );
+
+        for delay in 0..MAX_PROXIES {
+            proxy
+                .add_proxy(alice(), bob(), ProxyType::Any, delay)
+                .unwrap();
+        }
+        assert_eq!(
+            proxy.add_proxy(alice(), charlie(), ProxyType::Any, 0),
+            Err("too many proxies")
+        );
+    }
+
+    #[test]
+    fn proxies_forward_calls() {
+        let mut proxy = new_pallet();
+        assert_eq!(
+            proxy.proxy(bob(), alice(), None, Box::new(remark())),
+            Err("not a proxy of the account")
+        );
+        assert_eq!(proxy.forwarded.take(), None);
+
+        proxy
+            .add_proxy(alice(), bob(), ProxyType::NonTransfer, 0)
+            .unwrap();
+        assert_eq!(
+            proxy.proxy(bob(), alice(), Some(ProxyType::Any), Box::new(remark())),
+            Err("not a proxy of the account")
+        );
+        proxy.take_events();
+        assert_eq!(
+            proxy.proxy(bob(), alice(), None, Box::new(remark())),
+            Ok(())
+        );
+        assert_eq!(
+            proxy.forwarded.take(),
+            Some(ForwardedCall {
+                caller: alice(),
+                call: remark(),
+                filter: ProxyType::NonTransfer,
+            })
+        );
+        assert_eq!(
+            proxy.take_events(),
+            vec![Event::ProxyExecuted {
+                real: alice(),
+                delegate: bob(),
+                proxy_type: ProxyType::NonTransfer,
+            }]
+        );
+    }
+
+    #[test]
+    fn delayed_proxies_announce_their_calls() {
+        let mut proxy = new_pallet();
+        proxy
+            .add_proxy(alice(), bob(), ProxyType::Any, 2)
+            .unwrap();
+        let call_hash = blake2_256(&remark().encode());
+        assert_eq!(
+            proxy.proxy(bob(), alice(), None, Box::new(remark())),
+            Err("the proxy must announce its calls")
+        );
+        assert_eq!(
+            proxy.announce(charlie(), alice(), call_hash),
+            Err("not a proxy of the account")
+        );
+        assert_eq!(
+            proxy.proxy_announced(charlie(), bob(), alice(), None, Box::new(remark())),
+            Err("unknown announcement")
+        );
+
+        assert_eq!(proxy.announce(bob(), alice(), call_hash), Ok(()));
+        assert_eq!(
+            proxy.proxy_announced(charlie(), bob(), alice(), None, Box::new(remark())),
+            Err("the delay of the announcement is not over")
+        );
+        run_to_block(&mut proxy, 3);
+        assert_eq!(
+            proxy.proxy_announced(charlie(), bob(), alice(), None, Box::new(remark())),
+            Ok(())
+        );
+        assert_eq!(proxy.announcements(&bob()), vec![]);
+        assert_eq!(
+            proxy
+                .forwarded
+                .take()
+                .map(|forwarded| forwarded.caller),
+            Some(alice())
+        );
+
+        // The real account rejects the calls it doesn't want.
+        proxy
+            .announce(bob(), alice(), call_hash)
+            .unwrap();
+        assert_eq!(
+            proxy.reject_announcement(charlie(), bob(), call_hash),
+            Err("unknown announcement")
+        );
+        assert_eq!(proxy.reject_announcement(alice(), bob(), call_hash), Ok(()));
+        run_to_block(&mut proxy, 6);
+        assert_eq!(
+            proxy.proxy_announced(charlie(), bob(), alice(), None, Box::new(remark())),
+            Err("unknown announcement")
+        );
+        assert_eq!(
+            proxy.take_events().last(),
+            Some(&Event::AnnouncementRemoved {
+                real: alice(),
+                delegate: bob(),
+                call_hash,
+            })
+        );
+    }
+
+    #[test]
+    fn calls_fail_without_the_lent_pallets() {
+        let mut proxy = Pallet::<TestConfig>::new();
+        proxy
+            .add_proxy(alice(), bob(), ProxyType::Any, 2)
+            .unwrap();
+        assert_eq!(
+            proxy.announce(bob(), alice(), [0; 32]),
+            Err("the pallet was not lent by the runtime")
+        );
+    }
+
+    #[test]
+    fn benchmarks_succeed() {
+        let config = BenchmarkConfig {
+            steps: 2,
+            repeat: 1,
+        };
+        let results = benchmarking::run::<Pallet<TestConfig>>(&config).unwrap();
+        assert_eq!(results.len(), 8);
+    }
+}
@@ -0,0 +1,90 @@
+//! The weights of the `proxy` pallet, measured by its benchmarks.
+//!
+//! Generated by `cargo run --release --bin benchmark`, do not edit by hand. The unit of weight is
+//! one nanosecond of execution on the machine which ran the benchmarks.
+
+use crate::support::Weight;
+
+/// The weights of the callable functions of the pallet.
+pub trait WeightInfo {
+    fn add_proxy() -> Weight;
+    fn remove_proxy() -> Weight;
+    fn remove_proxies() -> Weight;
+    fn proxy() -> Weight;
+    fn announce() -> Weight;
+    fn remove_announcement() -> Weight;
+    fn reject_announcement() -> Weight;
+    fn proxy_announced() -> Weight;
+}
+
+/// The weights measured by the benchmarks.
+pub struct BenchmarkedWeights;
+
+impl WeightInfo for BenchmarkedWeights {
+    fn add_proxy() -> Weight {
+        7636
+    }
+
+    fn remove_proxy() -> Weight {
+        6420
+    }
+
+    fn remove_proxies() -> Weight {
+        7923
+    }
+
+    fn proxy() -> Weight {
+        5583
+    }
+
+    fn announce() -> Weight {
+        11693
+    }
+
+    fn remove_announcement() -> Weight {
+        7097
+    }
+
+    fn reject_announcement() -> Weight {
+        6975
+    }
+
+    fn proxy_announced() -> Weight {
+        14281
+    }
+}
+
+/// No weights, for tests.
+impl WeightInfo for () {
+    fn add_proxy() -> Weight {
+        0
+    }
+
+    fn remove_proxy() -> Weight {
+        0
+    }
+
+    fn remove_proxies() -> Weight {
+        0
+    }
+
+    fn proxy() -> Weight {
+        0
+    }
+
+    fn announce() -> Weight {
+        0
+    }
+
+    fn remove_announcement() -> Weight {
+        0
+    }
+
+    fn reject_announcement() -> Weight {
+        0
+    }
+
+    fn proxy_announced() -> Weight {
+        0
+    }
+}
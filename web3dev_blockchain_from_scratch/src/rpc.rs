@@ -0,0 +1,997 @@
+//! A JSON-RPC 2.0 server exposing a `Node` on localhost, over both HTTP and WebSocket.
+//!
+//! HTTP clients send one request per `POST`. WebSocket clients can send any number of requests on
+//! the same connection, and are the only ones able to use subscriptions.
+//!
+//! Every connection is served on its own thread, up to `MAX_CONNECTIONS`. A client sending its
+//! request too slowly, or with too large headers, is disconnected.
+//!
+//! The supported methods are listed in `METHODS`. Extrinsics, blocks and events use the JSON
+//! representation of our runtime types, for example a transfer extrinsic looks like:
+//!
+//! ```json
+//! {
+//!   "caller": "alice",
+//!   "nonce": 0,
+//!   "tip": 0,
+//!   "era": { "Mortal": { "birth": 1, "period": 64 } },
+//!   "spec_version": 1,
+//!   "genesis_hash": "a1b2...",
+//!   "call": { "balances": { "transfer": { "to": "bob", "amount": 30 } } }
+//! }
+//! ```
+//!
+//! The `tip` and the `era` are optional, and default to no tip and an extrinsic valid forever. The
+//! `spec_version` and the `genesis_hash` must be the ones returned by `state_getRuntimeVersion` and
+//! `chain_getGenesisHash`.
+//!
+//! Extrinsics of accounts backed by a key, like the ones of the `wallet` binary, also carry the
+//! `signature` of their caller. The development accounts, like `alice`, are not signed.
+//!
+//! `author_submitExtrinsic` also accepts the SCALE encoding of an extrinsic as a hex string, as
+//! produced by the `dynamic` clients from the metadata returned by `state_getMetadata`.
+//!
+//! Submitted extrinsics go to the transaction queue of the node, and subscribers are notified when
+//! the block producer includes them in a block.
+
+pub mod client;
+
+use std::{
+    collections::BTreeMap,
+    io::{
+        self,
+        BufRead,
+        BufReader,
+        Read,
+        Write,
+    },
+    net::{
+        TcpListener,
+        TcpStream,
+    },
+    sync::{
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+        mpsc,
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use codec::DecodeLimit;
+use serde::de::DeserializeOwned;
+use serde_json::{
+    json,
+    Value,
+};
+use tungstenite::Message;
+
+use crate::{
+    node::{
+        ImportedBlock,
+        Node,
+    },
+    runtime::{
+        self,
+        types,
+        Runtime,
+    },
+    support,
+};
+
+/// All the methods supported by the server.
+pub const METHODS: &[&str] = &[
+    "rpc_methods",
+    "author_submitExtrinsic",
+    "author_pendingExtrinsics",
+    "chain_getBlock",
+    "chain_getHeader",
+    "chain_getGenesisHash",
+    "state_getBalance",
+    "state_getClaim",
+    "system_accountNextIndex",
+    "system_account",
+    "state_getRuntimeVersion",
+    "state_getMetadata",
+    "chain_subscribeNewBlocks",
+    "chain_unsubscribeNewBlocks",
+    "state_subscribeEvents",
+    "state_unsubscribeEvents",
+];
+
+/// The largest HTTP request body we accept.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// The largest request line and headers we accept, for HTTP requests and WebSocket handshakes.
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// The most connections served at the same time. Further clients are disconnected right away.
+pub const MAX_CONNECTIONS: usize = 256;
+
+/// How long a client can stay silent while sending the head or the body of a request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait before accepting connections again after failing to, like when the process
+/// runs out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// How long a WebSocket connection waits for a message before checking for notifications.
+const WS_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A JSON-RPC error, as returned to the client.
+#[derive(Debug, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    const PARSE_ERROR: i64 = -32700;
+    const INVALID_REQUEST: i64 = -32600;
+    const METHOD_NOT_FOUND: i64 = -32601;
+    const INVALID_PARAMS: i64 = -32602;
+    /// Errors returned by the node or the runtime.
+    const NODE_ERROR: i64 = -32000;
+
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// The sending half of a WebSocket connection, used to push subscription notifications.
+pub type Subscriber = mpsc::Sender<String>;
+
+/// The active subscriptions, by kind and subscription id.
+#[derive(Default)]
+struct Subscriptions {
+    next_id: u64,
+    new_blocks: BTreeMap<u64, Subscriber>,
+    events: BTreeMap<u64, Subscriber>,
+}
+
+impl Subscriptions {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+pub struct RpcServer {
+    node: Mutex<Node>,
+    subscriptions: Mutex<Subscriptions>,
+    /// The number of connections being served.
+    connections: AtomicUsize,
+}
+
+impl RpcServer {
+    pub fn new(node: Node) -> Self {
+        Self {
+            node: Mutex::new(node),
+            subscriptions: Mutex::new(Subscriptions::default()),
+            connections: AtomicUsize::new(0),
+        }
+    }
+
+    /// Handle a raw JSON-RPC request, and return the raw response.
+    ///
+    /// Subscriptions are only possible when a `subscriber` is given, which is where the
+    /// notifications will be sent.
+    pub fn handle(&self, request: &str, subscriber: Option<&Subscriber>) -> String {
+        let (id, result) = match serde_json::from_str::<Value>(request) {
+            Ok(request) => {
+                let id = request
+                    .get("id")
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                (id, self.handle_request(&request, subscriber))
+            }
+            Err(e) => (
+                Value::Null,
+                Err(RpcError::new(RpcError::PARSE_ERROR, e.to_string())),
+            ),
+        };
+
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(RpcError { code, message }) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        response.to_string()
+    }
+
+    fn handle_request(
+        &self,
+        request: &Value,
+        subscriber: Option<&Subscriber>,
+    ) -> Result<Value, RpcError> {
+        let method = request
+            .get("method")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(RpcError::INVALID_REQUEST, "missing method"))?;
+        let params = match request.get("params") {
+            None | Some(Value::Null) => vec![],
+            Some(Value::Array(params)) => params.clone(),
+            Some(_) => {
+                let msg = "params must be an array";
+                return Err(RpcError::new(RpcError::INVALID_PARAMS, msg));
+            }
+        };
+
+        match method {
+            "rpc_methods" => Ok(json!(METHODS)),
+            "author_submitExtrinsic" => {
+                let extrinsic = extrinsic_param(&params, 0)?;
+                let status = self
+                    .node
+                    .lock()
+                    .unwrap()
+                    .submit_extrinsic(extrinsic)
+                    .map_err(|e| RpcError::new(RpcError::NODE_ERROR, e))?;
+                Ok(json!(status))
+            }
+            "author_pendingExtrinsics" => {
+                let node = self.node.lock().unwrap();
+                Ok(json!(node.pending_extrinsics()))
+            }
+            "chain_getBlock" => {
+                let node = self.node.lock().unwrap();
+                let number = optional_param(&params, 0)?.unwrap_or(node.best_number());
+                Ok(json!(node.block(number)))
+            }
+            "chain_getHeader" => {
+                let node = self.node.lock().unwrap();
+                let number = optional_param(&params, 0)?.unwrap_or(node.best_number());
+                Ok(json!(node.header(number)))
+            }
+            "chain_getGenesisHash" => {
+                let node = self.node.lock().unwrap();
+                let genesis_hash = node
+                    .runtime()
+                    .system
+                    .genesis_hash();
+                Ok(json!(hex::encode(genesis_hash)))
+            }
+            "state_getBalance" => {
+                let who: types::AccountId = param(&params, 0)?;
+                let node = self.node.lock().unwrap();
+                Ok(json!(node
+                    .runtime()
+                    .balances
+                    .balance(who)))
+            }
+            "state_getClaim" => {
+                let claim: types::Content = param(&params, 0)?;
+                let node = self.node.lock().unwrap();
+                Ok(json!(node
+                    .runtime()
+                    .proof_of_existence
+                    .get_claim(&claim)))
+            }
+            "system_accountNextIndex" => {
+                let who: types::AccountId = param(&params, 0)?;
+                let node = self.node.lock().unwrap();
+                Ok(json!(node
+                    .runtime()
+                    .system
+                    .nonce(&who)))
+            }
+            "system_account" => {
+                let who: types::AccountId = param(&params, 0)?;
+                let node = self.node.lock().unwrap();
+                Ok(json!(node
+                    .runtime()
+                    .system
+                    .account(&who)))
+            }
+            "state_getRuntimeVersion" => Ok(json!(runtime::VERSION)),
+            "state_getMetadata" => Ok(json!(Runtime::metadata())),
+            "chain_subscribeNewBlocks" | "state_subscribeEvents" => {
+                let subscriber = subscriber.ok_or_else(|| {
+                    let msg = "subscriptions are only supported over WebSocket";
+                    RpcError::new(RpcError::METHOD_NOT_FOUND, msg)
+                })?;
+                let mut subscriptions = self
+                    .subscriptions
+                    .lock()
+                    .unwrap();
+                let id = subscriptions.next_id();
+                let kind = if method == "chain_subscribeNewBlocks" {
+                    &mut subscriptions.new_blocks
+                } else {
+                    &mut subscriptions.events
+                };
+                kind.insert(id, subscriber.clone());
+                Ok(json!(id))
+            }
+            "chain_unsubscribeNewBlocks" | "state_unsubscribeEvents" => {
+                let id: u64 = param(&params, 0)?;
+                let mut subscriptions = self
+                    .subscriptions
+                    .lock()
+                    .unwrap();
+                let kind = if method == "chain_unsubscribeNewBlocks" {
+                    &mut subscriptions.new_blocks
+                } else {
+                    &mut subscriptions.events
+                };
+                Ok(json!(kind.remove(&id).is_some()))
+            }
+            _ => Err(RpcError::new(
+                RpcError::METHOD_NOT_FOUND,
+                format!("unknown method {method}"),
+            )),
+        }
+    }
+
+    /// The node served by this server.
+    pub fn node(&self) -> &Mutex<Node> {
+        &self.node
+    }
+
+    /// Send the notifications for a newly imported block to all the subscribers. Subscribers whose
+    /// connection was closed are removed.
+    pub fn notify(&self, imported: &ImportedBlock) {
+        let mut subscriptions = self
+            .subscriptions
+            .lock()
+            .unwrap();
+        let events = json!({
+            "block_number": imported.block.header.block_number,
+            "events": imported.events,
+        });
+        let block = json!(imported.block);
+
+        subscriptions
+            .new_blocks
+            .retain(|id, subscriber| {
+                subscriber
+                    .send(notification("chain_newBlock", *id, &block))
+                    .is_ok()
+            });
+        subscriptions
+            .events
+            .retain(|id, subscriber| {
+                subscriber
+                    .send(notification("state_events", *id, &events))
+                    .is_ok()
+            });
+    }
+
+    /// Accept connections on `listener` forever, serving each of them on its own thread, up to
+    /// `MAX_CONNECTIONS` at a time.
+    ///
+    /// Failing to accept a connection is logged, and the server keeps listening.
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("RPC accept error: {e}");
+                    thread::sleep(ACCEPT_BACKOFF);
+                    continue;
+                }
+            };
+            if self
+                .connections
+                .fetch_add(1, Ordering::SeqCst)
+                >= MAX_CONNECTIONS
+            {
+                self.connections
+                    .fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            let server = self.clone();
+            thread::spawn(move || {
+                if let Err(e) = server.serve_connection(stream) {
+                    eprintln!("RPC connection error: {e}");
+                }
+                server
+                    .connections
+                    .fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        Ok(())
+    }
+
+    fn serve_connection(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let head = read_head(&mut reader)?;
+        if String::from_utf8_lossy(&head)
+            .to_ascii_lowercase()
+            .contains("upgrade: websocket")
+        {
+            self.serve_websocket(Replayed {
+                head: io::Cursor::new(head),
+                reader,
+                stream,
+            })
+        } else {
+            self.serve_http(&String::from_utf8_lossy(&head), reader, stream)
+        }
+    }
+
+    /// Serve a single HTTP request, whose `head` was already read, and close the connection.
+    fn serve_http(
+        &self,
+        head: &str,
+        mut reader: BufReader<TcpStream>,
+        mut stream: TcpStream,
+    ) -> io::Result<()> {
+        let mut lines = head.lines();
+        let request_line = lines
+            .next()
+            .unwrap_or_default();
+        let mut content_length = 0;
+        for line in lines {
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value
+                        .trim()
+                        .parse()
+                        .unwrap_or(0);
+                }
+            }
+        }
+
+        let (status, body) = if !request_line.starts_with("POST ") {
+            ("405 Method Not Allowed", String::new())
+        } else if content_length > MAX_BODY_SIZE {
+            ("413 Payload Too Large", String::new())
+        } else {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            ("200 OK", self.handle(&String::from_utf8_lossy(&body), None))
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        stream.flush()
+    }
+
+    /// Serve JSON-RPC requests and push subscription notifications until the client disconnects.
+    fn serve_websocket(&self, stream: Replayed) -> io::Result<()> {
+        stream
+            .stream
+            .set_read_timeout(Some(WS_POLL_INTERVAL))?;
+        let mut socket = tungstenite::accept(stream).map_err(io::Error::other)?;
+        let (subscriber, notifications) = mpsc::channel();
+
+        loop {
+            while let Ok(notification) = notifications.try_recv() {
+                socket
+                    .send(Message::text(notification))
+                    .map_err(io::Error::other)?;
+            }
+
+            match socket.read() {
+                Ok(Message::Text(request)) => {
+                    let response = self.handle(request.as_str(), Some(&subscriber));
+                    socket
+                        .send(Message::text(response))
+                        .map_err(io::Error::other)?;
+                }
+                Ok(Message::Close(_))
+                | Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    return Ok(())
+                }
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(e) => return Err(io::Error::other(e)),
+            }
+        }
+    }
+}
+
+/// Build a subscription notification message.
+fn notification(method: &str, subscription: u64, result: &Value) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": { "subscription": subscription, "result": result },
+    })
+    .to_string()
+}
+
+/// Decode the parameter at `index`.
+fn param<T: DeserializeOwned>(params: &[Value], index: usize) -> Result<T, RpcError> {
+    optional_param(params, index)?.ok_or_else(|| {
+        RpcError::new(
+            RpcError::INVALID_PARAMS,
+            format!("missing parameter {index}"),
+        )
+    })
+}
+
+/// Decode the extrinsic at `index`, given either as JSON or as its SCALE encoding in hex.
+fn extrinsic_param(params: &[Value], index: usize) -> Result<types::Extrinsic, RpcError> {
+    let Some(Value::String(encoded)) = params.get(index) else {
+        return param(params, index);
+    };
+    let bytes = hex::decode(
+        encoded
+            .strip_prefix("0x")
+            .unwrap_or(encoded),
+    )
+    .map_err(|e| {
+        let msg = format!("invalid parameter {index}: {e}");
+        RpcError::new(RpcError::INVALID_PARAMS, msg)
+    })?;
+    types::Extrinsic::decode_with_depth_limit(support::MAX_DECODE_DEPTH, &mut &bytes[..]).map_err(
+        |e| {
+            let msg = format!("invalid parameter {index}: {e}");
+            RpcError::new(RpcError::INVALID_PARAMS, msg)
+        },
+    )
+}
+
+/// Decode the parameter at `index`, if it was given.
+fn optional_param<T: DeserializeOwned>(
+    params: &[Value],
+    index: usize,
+) -> Result<Option<T>, RpcError> {
+    match params.get(index) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(|e| {
+                let msg = format!("invalid parameter {index}: {e}");
+                RpcError::new(RpcError::INVALID_PARAMS, msg)
+            }),
+    }
+}
+
+/// Read the request line and the headers of a request, up to the empty line ending them. Fails if
+/// they are longer than `MAX_HEAD_SIZE`, or if the client stops sending them.
+fn read_head(reader: &mut BufReader<TcpStream>) -> io::Result<Vec<u8>> {
+    let mut head = Vec::new();
+    loop {
+        let start = head.len();
+        let limit = MAX_HEAD_SIZE.saturating_sub(start) as u64;
+        let read = reader
+            .by_ref()
+            .take(limit)
+            .read_until(b'\n', &mut head)?;
+        // Nothing is read once the head is full, or once the client closed the connection.
+        if read == 0 || !head.ends_with(b"\n") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request head too large or incomplete",
+            ));
+        }
+        if matches!(&head[start..], b"\r\n" | b"\n") {
+            return Ok(head);
+        }
+    }
+}
+
+/// A connection whose request head was already read, replayed before the rest of the stream so the
+/// WebSocket handshake can read it again.
+struct Replayed {
+    head: io::Cursor<Vec<u8>>,
+    reader: BufReader<TcpStream>,
+    stream: TcpStream,
+}
+
+impl Read for Replayed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.head.read(buf)? {
+            0 => self.reader.read(buf),
+            len => Ok(len),
+        }
+    }
+}
+
+impl Write for Replayed {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dynamic,
+        runtime::extensions::ChargeTransactionPayment,
+    };
+
+    fn server() -> RpcServer {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+        runtime
+            .balances
+            .set_balance("bob".to_string(), 100);
+        RpcServer::new(Node::new(runtime).unwrap())
+    }
+
+    fn call(server: &RpcServer, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        serde_json::from_str(&server.handle(&request.to_string(), None)).unwrap()
+    }
+
+    fn genesis_hash(server: &RpcServer) -> Value {
+        call(server, "chain_getGenesisHash", json!([]))["result"].clone()
+    }
+
+    fn transfer(server: &RpcServer, nonce: u64, to: &str, amount: u128) -> Value {
+        json!({
+            "caller": "alice",
+            "nonce": nonce,
+            "tip": 0,
+            "era": "Immortal",
+            "spec_version": runtime::VERSION.spec_version,
+            "genesis_hash": genesis_hash(server),
+            "call": { "balances": { "transfer": { "to": to, "amount": amount } } },
+        })
+    }
+
+    fn produce_block(server: &RpcServer) {
+        let imported = server
+            .node()
+            .lock()
+            .unwrap()
+            .produce_block()
+            .unwrap();
+        server.notify(&imported);
+    }
+
+    #[test]
+    fn submit_and_query() {
+        let server = server();
+
+        let response = call(
+            &server,
+            "author_submitExtrinsic",
+            json!([transfer(&server, 0, "bob", 30)]),
+        );
+        assert_eq!(response["result"], json!("ready"));
+
+        // The tip and the era are optional.
+        let claim = json!({
+            "caller": "bob",
+            "nonce": 0,
+            "spec_version": 1,
+            "genesis_hash": genesis_hash(&server),
+            "call": { "proof_of_existence": { "create_claim": { "claim": "my_document" } } },
+        });
+        let response = call(&server, "author_submitExtrinsic", json!([claim]));
+        assert_eq!(response["result"], json!("ready"));
+        // An extrinsic encoded from the metadata.
+        let metadata =
+            serde_json::from_value(call(&server, "state_getMetadata", json!([]))["result"].clone())
+                .unwrap();
+        let extrinsic = dynamic::Extrinsic {
+            envelope: vec![
+                ("caller".to_string(), "alice".into()),
+                ("signature".to_string(), dynamic::Value::Option(None)),
+                ("nonce".to_string(), 1.into()),
+                ("tip".to_string(), 0.into()),
+                (
+                    "era".to_string(),
+                    dynamic::Value::Variant {
+                        name: "Immortal".to_string(),
+                        fields: vec![],
+                    },
+                ),
+                ("spec_version".to_string(), 1.into()),
+                (
+                    "genesis_hash".to_string(),
+                    dynamic::Value::Sequence(
+                        server
+                            .node()
+                            .lock()
+                            .unwrap()
+                            .runtime()
+                            .system
+                            .genesis_hash()
+                            .map(|byte| u128::from(byte).into())
+                            .to_vec(),
+                    ),
+                ),
+            ],
+            call: dynamic::Call::parse(r#"balances.transfer(to="charlie", amount=5)"#).unwrap(),
+        };
+        let encoded = dynamic::encode_extrinsic(&metadata, &extrinsic).unwrap();
+        let response = call(
+            &server,
+            "author_submitExtrinsic",
+            json!([format!("0x{}", hex::encode(encoded))]),
+        );
+        assert_eq!(response["result"], json!("ready"));
+        let response = call(&server, "author_submitExtrinsic", json!(["0x0102"]));
+        assert_eq!(response["error"]["code"], json!(RpcError::INVALID_PARAMS));
+
+        let response = call(&server, "author_pendingExtrinsics", json!([]));
+        assert_eq!(
+            response["result"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // The fees paid by an account depend on the benchmarked weights of its calls.
+        let pending = server
+            .node()
+            .lock()
+            .unwrap()
+            .pending_extrinsics();
+        let fees = |caller: &str| -> types::Balance {
+            pending
+                .iter()
+                .filter(|extrinsic| extrinsic.caller == caller)
+                .map(ChargeTransactionPayment::fee)
+                .sum()
+        };
+        produce_block(&server);
+        let response = call(&server, "author_pendingExtrinsics", json!([]));
+        assert_eq!(response["result"], json!([]));
+
+        assert_eq!(
+            call(&server, "state_getBalance", json!(["alice"]))["result"],
+            json!(65 - fees("alice"))
+        );
+        assert_eq!(
+            call(&server, "state_getBalance", json!(["bob"]))["result"],
+            json!(130 - fees("bob"))
+        );
+        assert_eq!(
+            call(&server, "state_getClaim", json!(["my_document"]))["result"],
+            json!("bob")
+        );
+        assert_eq!(
+            call(&server, "state_getClaim", json!(["other"]))["result"],
+            Value::Null
+        );
+        assert_eq!(
+            call(&server, "system_accountNextIndex", json!(["alice"]))["result"],
+            json!(2)
+        );
+        assert_eq!(
+            call(&server, "system_account", json!(["alice"]))["result"],
+            json!({ "nonce": 2 })
+        );
+
+        assert_eq!(
+            call(&server, "state_getRuntimeVersion", json!([]))["result"],
+            json!({ "spec_name": "web3dev", "spec_version": 1 })
+        );
+
+        let metadata = call(&server, "state_getMetadata", json!([]))["result"].clone();
+        assert_eq!(metadata["pallets"][1]["name"], json!("balances"));
+        assert_eq!(
+            metadata["pallets"][1]["calls"][0]["fields"][1],
+            json!({ "name": "amount", "ty": "u128" })
+        );
+
+        let block = call(&server, "chain_getBlock", json!([1]))["result"].clone();
+        assert_eq!(block["header"]["block_number"], json!(1));
+        assert_eq!(block["extrinsics"][0], transfer(&server, 0, "bob", 30));
+
+        // The genesis hash is the state root of the genesis state.
+        assert_eq!(
+            genesis_hash(&server)
+                .as_str()
+                .map(str::len),
+            Some(64)
+        );
+
+        let header = call(&server, "chain_getHeader", json!([]))["result"].clone();
+        assert_eq!(header, block["header"]);
+        let genesis = call(&server, "chain_getHeader", json!([0]))["result"].clone();
+        let genesis: types::Header = serde_json::from_value(genesis).unwrap();
+        assert_eq!(genesis.parent_hash, [0; 32]);
+        assert_eq!(header["parent_hash"], json!(hex::encode(genesis.hash())));
+        assert_eq!(
+            call(&server, "chain_getHeader", json!([2]))["result"],
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let server = server();
+
+        let response: Value = serde_json::from_str(&server.handle("{", None)).unwrap();
+        assert_eq!(response["error"]["code"], json!(RpcError::PARSE_ERROR));
+
+        let response = call(&server, "unknown", json!([]));
+        assert_eq!(response["error"]["code"], json!(RpcError::METHOD_NOT_FOUND));
+
+        let response = call(
+            &server,
+            "author_submitExtrinsic",
+            json!([{ "caller": "alice" }]),
+        );
+        assert_eq!(response["error"]["code"], json!(RpcError::INVALID_PARAMS));
+
+        let mut tipped = transfer(&server, 0, "bob", 30);
+        tipped["tip"] = json!(1000);
+        let response = call(&server, "author_submitExtrinsic", json!([tipped]));
+        assert_eq!(response["error"]["code"], json!(RpcError::NODE_ERROR));
+        assert_eq!(
+            response["error"]["message"],
+            json!("insufficient balance to pay the fee")
+        );
+
+        // Subscriptions need a WebSocket connection.
+        let response = call(&server, "chain_subscribeNewBlocks", json!([]));
+        assert_eq!(response["error"]["code"], json!(RpcError::METHOD_NOT_FOUND));
+    }
+
+    #[test]
+    fn subscriptions() {
+        let server = server();
+        let (subscriber, notifications) = mpsc::channel();
+
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "state_subscribeEvents" });
+        let response: Value =
+            serde_json::from_str(&server.handle(&request.to_string(), Some(&subscriber))).unwrap();
+        let id = response["result"].clone();
+
+        call(
+            &server,
+            "author_submitExtrinsic",
+            json!([transfer(&server, 0, "bob", 30)]),
+        );
+        produce_block(&server);
+        let notification: Value = serde_json::from_str(&notifications.recv().unwrap()).unwrap();
+        assert_eq!(notification["method"], json!("state_events"));
+        assert_eq!(notification["params"]["subscription"], id);
+        assert_eq!(notification["params"]["result"]["block_number"], json!(1));
+        assert_eq!(
+            notification["params"]["result"]["events"][3]["event"],
+            json!({ "system": "ExtrinsicSuccess" })
+        );
+
+        let response = call(&server, "state_unsubscribeEvents", json!([id]));
+        assert_eq!(response["result"], json!(true));
+        produce_block(&server);
+        assert!(notifications
+            .try_recv()
+            .is_err());
+    }
+
+    #[test]
+    fn http_and_websocket_transports() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Arc::new(server());
+        let serving = server.clone();
+        thread::spawn(move || serving.serve(listener));
+
+        // A subscription over WebSocket.
+        let stream = TcpStream::connect(addr).unwrap();
+        let (mut socket, _) = tungstenite::client(format!("ws://{addr}"), stream).unwrap();
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "chain_subscribeNewBlocks" });
+        socket
+            .send(Message::text(request.to_string()))
+            .unwrap();
+        let response: Value = serde_json::from_str(
+            socket
+                .read()
+                .unwrap()
+                .to_text()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(response["id"], json!(1));
+
+        // An extrinsic submitted over HTTP.
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "author_submitExtrinsic",
+            "params": [transfer(&server, 0, "charlie", 10)],
+        })
+        .to_string();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{request}",
+            request.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        let (_, body) = response
+            .split_once("\r\n\r\n")
+            .unwrap();
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["result"], json!("ready"));
+
+        // The WebSocket subscriber is notified of the new block.
+        produce_block(&server);
+        let notification: Value = serde_json::from_str(
+            socket
+                .read()
+                .unwrap()
+                .to_text()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(notification["method"], json!("chain_newBlock"));
+        assert_eq!(
+            notification["params"]["result"]["header"]["block_number"],
+            json!(1)
+        );
+        assert_eq!(
+            notification["params"]["result"]["extrinsics"][0],
+            transfer(&server, 0, "charlie", 10)
+        );
+    }
+
+    #[test]
+    fn request_heads_are_bounded() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || Arc::new(server()).serve(listener));
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "rpc_methods" }).to_string();
+        let post = |headers: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "POST / HTTP/1.1\r\n{headers}").unwrap();
+            // The rest of the head is sent later, which the server waits for.
+            thread::sleep(Duration::from_millis(50));
+            let _ = write!(stream, "Content-Length: {}\r\n\r\n{request}", request.len());
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response);
+            response
+        };
+
+        assert!(post("Host: localhost\r\n").starts_with("HTTP/1.1 200 OK"));
+        let too_large = format!("X-Padding: {}\r\n", "a".repeat(MAX_HEAD_SIZE));
+        assert_eq!(post(&too_large), "");
+        assert!(post("").starts_with("HTTP/1.1 200 OK"));
+
+        // A head filling `MAX_HEAD_SIZE` exactly, without the empty line ending it, is rejected
+        // right away.
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let request_line = "POST / HTTP/1.1\r\n";
+        let padding = MAX_HEAD_SIZE - request_line.len() - "X-Padding: \r\n".len();
+        let full = format!("{request_line}X-Padding: {}\r\n", "a".repeat(padding));
+        assert_eq!(full.len(), MAX_HEAD_SIZE);
+        stream
+            .write_all(full.as_bytes())
+            .unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(
+            stream
+                .read(&mut [0; 16])
+                .unwrap(),
+            0
+        );
+        assert!(post("Host: localhost\r\n").starts_with("HTTP/1.1 200 OK"));
+    }
+}
@@ -0,0 +1,112 @@
+//! A minimal client for the HTTP transport of the `RpcServer`, used by the command line tools.
+
+use std::{
+    io::{
+        Read,
+        Write,
+    },
+    net::TcpStream,
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::{
+    json,
+    Value,
+};
+
+pub struct RpcClient {
+    /// The `host:port` of the server.
+    addr: String,
+}
+
+impl RpcClient {
+    /// A client of the server at `url`, like `http://127.0.0.1:9944`.
+    pub fn new(url: &str) -> Self {
+        let addr = url
+            .strip_prefix("http://")
+            .unwrap_or(url)
+            .trim_end_matches('/');
+        Self {
+            addr: addr.to_string(),
+        }
+    }
+
+    /// Call `method` with the given parameters, and deserialize its result.
+    pub fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, String> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = self
+            .post(&request.to_string())
+            .map_err(|e| format!("request to {} failed: {e}", self.addr))?;
+        let mut response: Value = serde_json::from_str(&response)
+            .map_err(|e| format!("invalid response from {}: {e}", self.addr))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(format!(
+                "{method} failed: {} ({})",
+                error["message"]
+                    .as_str()
+                    .unwrap_or_default(),
+                error["code"]
+            ));
+        }
+        serde_json::from_value(response["result"].take())
+            .map_err(|e| format!("invalid result of {method}: {e}"))
+    }
+
+    /// Send a `POST` request, and return the body of the response.
+    fn post(&self, body: &str) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(&self.addr)?;
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.addr,
+            body.len()
+        )?;
+        stream.flush()?;
+
+        // The server closes the connection after the response.
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (_, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| std::io::Error::other("malformed HTTP response"))?;
+        Ok(body.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::TcpListener,
+        sync::Arc,
+        thread,
+    };
+
+    use super::*;
+    use crate::{
+        node::Node,
+        rpc::RpcServer,
+        runtime::Runtime,
+    };
+
+    #[test]
+    fn call_a_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = Arc::new(RpcServer::new(Node::new(Runtime::new()).unwrap()));
+        thread::spawn(move || server.serve(listener));
+
+        let client = RpcClient::new(&url);
+        let nonce: u64 = client
+            .call("system_accountNextIndex", json!(["alice"]))
+            .unwrap();
+        assert_eq!(nonce, 0);
+        assert_eq!(
+            client.call::<Value>("unknown", json!([])),
+            Err("unknown failed: unknown method unknown (-32601)".to_string())
+        );
+        assert!(RpcClient::new("127.0.0.1:1")
+            .call::<Value>("rpc_methods", json!([]))
+            .is_err());
+    }
+}
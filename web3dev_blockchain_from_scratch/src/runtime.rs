@@ -0,0 +1,1993 @@
+//! The runtime of our blockchain, combining all of our pallets.
+//!
+//! It lives in the library so that it can be shared by the binaries, like the `node` which exposes
+//! it over JSON-RPC.
+
+pub mod extensions;
+
+use crate::{
+    balances,
+    contracts,
+    escrow,
+    evm,
+    nfts,
+    proof_of_existence,
+    proxy,
+    support,
+    support::{
+        CallFilter,
+        Dispatch,
+        DispatchResult,
+        GetCallName,
+        SignedExtension,
+        TransactionValidity,
+    },
+    system,
+    treasury,
+};
+
+pub mod types {
+    use crate::support;
+
+    pub type AccountId = String;
+    pub type Balance = u128;
+
+    pub type BlockNumber = u64;
+
+    pub type Nonce = u64;
+
+    pub type Extrinsic =
+        support::Extrinsic<AccountId, super::RuntimeCall, Nonce, Balance, BlockNumber>;
+
+    pub type Era = support::Era<BlockNumber>;
+
+    /// The checks run on every extrinsic before it is dispatched, in order.
+    pub type SignedExtra = (
+        super::extensions::CheckSignature,
+        super::extensions::CheckSpecVersion,
+        super::extensions::CheckGenesis,
+        super::extensions::CheckMortality,
+        super::extensions::CheckNonce,
+        super::extensions::ChargeTransactionPayment,
+    );
+
+    pub type Header = support::Header<BlockNumber>;
+
+    pub type Block = support::Block<Header, Extrinsic>;
+
+    pub type Content = String;
+
+    pub type EventRecord = crate::system::EventRecord<super::RuntimeEvent>;
+
+    pub type BlockExecutionReport =
+        support::BlockExecutionReport<AccountId, Balance, super::RuntimeEvent>;
+}
+
+/// The version of this runtime.
+///
+/// `spec_version` must be increased whenever the logic or the storage layout of a pallet changes.
+/// The first block executed by a new version runs the `support::OnRuntimeUpgrade` hooks of all the
+/// pallets, to migrate their storage.
+pub const VERSION: support::RuntimeVersion = support::RuntimeVersion {
+    spec_name: "web3dev",
+    spec_version: 1,
+};
+
+impl system::Config for Runtime {
+    type AccountId = types::AccountId;
+    type BlockNumber = types::BlockNumber;
+    type Nonce = types::Nonce;
+    type RuntimeEvent = RuntimeEvent;
+    type SystemWeightInfo = system::weights::BenchmarkedWeights;
+}
+
+impl balances::Config for Runtime {
+    type Balance = types::Balance;
+    type WeightInfo = balances::weights::BenchmarkedWeights;
+}
+
+/// The stablecoin is a second instance of the balances pallet.
+impl balances::Config<support::Instance1> for Runtime {
+    type Balance = types::Balance;
+    type WeightInfo = balances::weights::BenchmarkedWeights;
+}
+
+impl nfts::Config for Runtime {
+    type Currency = balances::Pallet<Runtime>;
+    const COLLECTION_DEPOSIT: types::Balance = 100;
+    const ITEM_DEPOSIT: types::Balance = 10;
+    const DATA_DEPOSIT: types::Balance = 1;
+    type WeightInfo = nfts::weights::BenchmarkedWeights;
+}
+
+impl escrow::Config for Runtime {
+    type Currency = balances::Pallet<Runtime>;
+    type WeightInfo = escrow::weights::BenchmarkedWeights;
+}
+
+impl proxy::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type WeightInfo = proxy::weights::BenchmarkedWeights;
+}
+
+impl treasury::Config for Runtime {
+    type Currency = balances::Pallet<Runtime>;
+    const PALLET_ID: support::PalletId = support::PalletId(*b"py/trsry");
+    const PROPOSAL_BOND: types::Balance = 100;
+    const SPEND_PERIOD: types::BlockNumber = 10;
+    const BURN: support::Percent = support::Percent::from_percent(10);
+    type WeightInfo = treasury::weights::BenchmarkedWeights;
+}
+
+impl contracts::Config for Runtime {
+    type Currency = balances::Pallet<Runtime>;
+    const MAX_CODE_LEN: u32 = 64 * 1024;
+    const MAX_GAS_LIMIT: support::Weight = 1_000_000_000;
+    type WeightInfo = contracts::weights::BenchmarkedWeights;
+}
+
+impl evm::Config for Runtime {
+    type Currency = balances::Pallet<Runtime>;
+    const CHAIN_ID: u64 = 0x7733;
+    const GAS_PRICE: types::Balance = 1;
+    const MAX_GAS_LIMIT: u64 = 30_000_000;
+    /// Well above the time `revm` takes for a unit of gas, so a call given the most gas takes at
+    /// most a third of a block.
+    const WEIGHT_PER_GAS: support::Weight = 20;
+    type WeightInfo = evm::weights::BenchmarkedWeights;
+}
+
+impl proof_of_existence::Config for Runtime {
+    type Content = types::Content;
+    type WeightInfo = proof_of_existence::weights::BenchmarkedWeights;
+}
+
+#[derive(Debug)]
+#[macros::runtime]
+pub struct Runtime {
+    pub system: system::Pallet<Runtime>,
+    #[pallet_index = 1]
+    pub balances: balances::Pallet<Runtime>,
+    #[pallet_index = 3]
+    pub stablecoin: balances::Pallet<Runtime, support::Instance1>,
+    #[pallet_index = 2]
+    pub proof_of_existence: proof_of_existence::Pallet<Runtime>,
+    #[pallet_index = 4]
+    #[lend(balances)]
+    pub nfts: nfts::Pallet<Runtime>,
+    #[pallet_index = 5]
+    #[lend(system, balances)]
+    pub escrow: escrow::Pallet<Runtime>,
+    #[pallet_index = 6]
+    #[lend(system)]
+    #[forward_calls]
+    pub proxy: proxy::Pallet<Runtime>,
+    #[pallet_index = 7]
+    #[lend(system, balances)]
+    pub treasury: treasury::Pallet<Runtime>,
+    #[pallet_index = 8]
+    #[lend(balances)]
+    pub contracts: contracts::Pallet<Runtime>,
+    #[pallet_index = 9]
+    #[lend(system, balances)]
+    pub evm: evm::Pallet<Runtime>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The base call filter, which every call dispatched by the runtime must pass, before the filters
+/// of its origin. It rejects the calls blocked by root with `system::Call::block_calls`.
+impl CallFilter<RuntimeCall> for Runtime {
+    fn allows(&self, call: &RuntimeCall) -> bool {
+        !self
+            .system
+            .is_call_blocked(call.pallet_name(), call.call_name())
+    }
+}
+
+/// The calls the proxies of every type can make. Only the `Any` proxies can manage proxies, so the
+/// other ones can't give themselves more rights.
+impl CallFilter<RuntimeCall> for proxy::ProxyType {
+    fn allows(&self, call: &RuntimeCall) -> bool {
+        match self {
+            proxy::ProxyType::Any => true,
+            proxy::ProxyType::BalancesOnly => {
+                matches!(call, RuntimeCall::balances(_) | RuntimeCall::stablecoin(_))
+            }
+            proxy::ProxyType::ClaimsOnly => matches!(call, RuntimeCall::proof_of_existence(_)),
+            // An allow-list, so the calls added later are denied until they are known not to move
+            // funds or items. The root calls are denied too, in case the account is root.
+            proxy::ProxyType::NonTransfer => matches!(
+                call,
+                RuntimeCall::system(
+                    system::Call::remark { .. } | system::Call::remark_with_event { .. }
+                ) | RuntimeCall::proof_of_existence(_)
+                    | RuntimeCall::nfts(
+                        nfts::Call::create_collection { .. }
+                            | nfts::Call::destroy_collection { .. }
+                            | nfts::Call::set_collection_metadata { .. }
+                            | nfts::Call::set_attribute { .. }
+                            | nfts::Call::clear_attribute { .. }
+                            | nfts::Call::cancel_approval { .. }
+                            | nfts::Call::set_item_frozen { .. }
+                            | nfts::Call::set_collection_frozen { .. }
+                    )
+                    | RuntimeCall::contracts(contracts::Call::upload_code { .. })
+            ),
+        }
+    }
+}
+
+impl support::ValidateTransaction for Runtime {
+    type Extrinsic = types::Extrinsic;
+    type Pre = <types::SignedExtra as SignedExtension<Self, types::Extrinsic>>::Pre;
+    type Fee = types::Balance;
+
+    fn validate_transaction(&self, extrinsic: &Self::Extrinsic) -> TransactionValidity {
+        // An extrinsic heavier than a block could never be included.
+        if Self::weight(extrinsic) > support::MAX_BLOCK_WEIGHT {
+            return Err("the extrinsic is heavier than a block");
+        }
+        types::SignedExtra::validate(self, extrinsic)
+    }
+
+    fn pre_dispatch(&mut self, extrinsic: &Self::Extrinsic) -> Result<Self::Pre, &'static str> {
+        types::SignedExtra::pre_dispatch(self, extrinsic)
+    }
+
+    fn post_dispatch(&mut self, pre: Self::Pre, result: &DispatchResult) -> DispatchResult {
+        types::SignedExtra::post_dispatch(self, pre, result)
+    }
+
+    fn fee_paid(pre: &Self::Pre) -> types::Balance {
+        // The last check of `types::SignedExtra` is `ChargeTransactionPayment`.
+        pre.5
+    }
+
+    fn weight(extrinsic: &Self::Extrinsic) -> support::Weight {
+        extensions::ChargeTransactionPayment::weight(extrinsic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::{
+        GetStorageVersion,
+        GetWeight,
+        StorageVersion,
+        ValidTransaction,
+        ValidateTransaction,
+    };
+
+    /// An extrinsic for a runtime created with `Runtime::new`, whose genesis hash is not set.
+    fn extrinsic(caller: &str, nonce: types::Nonce, call: RuntimeCall) -> types::Extrinsic {
+        support::Extrinsic {
+            caller: caller.to_string(),
+            signature: None,
+            nonce,
+            tip: 0,
+            era: support::Era::Immortal,
+            spec_version: VERSION.spec_version,
+            genesis_hash: [0; 32],
+            call,
+        }
+    }
+
+    fn transfer(caller: &str, nonce: types::Nonce, to: &str, amount: u128) -> types::Extrinsic {
+        let call = RuntimeCall::balances(balances::Call::transfer {
+            to: to.to_string(),
+            amount,
+        });
+        extrinsic(caller, nonce, call)
+    }
+
+    /// The fee of a transfer without a tip, which depends on the benchmarked weight of the call.
+    fn transfer_fee() -> types::Balance {
+        extensions::ChargeTransactionPayment::fee(&transfer("alice", 0, "bob", 0))
+    }
+
+    /// The deposit of the share of a fee to the treasury.
+    fn treasury_share(fee: types::Balance) -> RuntimeEvent {
+        RuntimeEvent::balances(balances::Event::Deposit {
+            who: treasury::Pallet::<Runtime>::account_id(),
+            amount: extensions::TREASURY_FEE_SHARE.mul_floor(fee),
+        })
+    }
+
+    #[test]
+    fn execute_block_records_events() {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![
+                transfer("alice", 0, "bob", 30),
+                transfer("bob", 0, "alice", 31),
+            ],
+        };
+        runtime
+            .execute_block(block)
+            .unwrap();
+
+        let phase = |i| system::Phase::ApplyExtrinsic(i);
+        let withdraw = |who: &str| {
+            RuntimeEvent::balances(balances::Event::Withdraw {
+                who: who.to_string(),
+                amount: transfer_fee(),
+            })
+        };
+        let events = runtime
+            .system
+            .events()
+            .to_vec();
+        assert_eq!(
+            events,
+            vec![
+                types::EventRecord {
+                    phase: phase(0),
+                    event: withdraw("alice"),
+                },
+                types::EventRecord {
+                    phase: phase(0),
+                    event: treasury_share(transfer_fee()),
+                },
+                types::EventRecord {
+                    phase: phase(0),
+                    event: RuntimeEvent::balances(balances::Event::Transfer {
+                        from: "alice".to_string(),
+                        to: "bob".to_string(),
+                        amount: 30,
+                    }),
+                },
+                types::EventRecord {
+                    phase: phase(0),
+                    event: RuntimeEvent::system(system::Event::ExtrinsicSuccess),
+                },
+                types::EventRecord {
+                    phase: phase(1),
+                    event: withdraw("bob"),
+                },
+                types::EventRecord {
+                    phase: phase(1),
+                    event: treasury_share(transfer_fee()),
+                },
+                types::EventRecord {
+                    phase: phase(1),
+                    event: RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                        error: "Insufficient balance",
+                    }),
+                },
+            ]
+        );
+        assert_eq!(
+            runtime
+                .system
+                .nonce(&"bob".to_string()),
+            1
+        );
+    }
+
+    #[test]
+    fn validate_transaction() {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+
+        let mut tipped = transfer("alice", 0, "bob", 30);
+        tipped.tip = 10;
+        assert_eq!(
+            runtime.validate_transaction(&tipped),
+            Ok(ValidTransaction { priority: 10 })
+        );
+        tipped.tip = 101;
+        assert_eq!(
+            runtime.validate_transaction(&tipped),
+            Err("insufficient balance to pay the fee")
+        );
+
+        // Future nonces are valid, but can't be dispatched yet.
+        let future = transfer("alice", 1, "bob", 30);
+        assert!(runtime
+            .validate_transaction(&future)
+            .is_ok());
+        assert_eq!(
+            runtime
+                .pre_dispatch(&future)
+                .map(|_| ()),
+            Err("future nonce")
+        );
+
+        tipped.tip = 10;
+        let pre = runtime
+            .pre_dispatch(&tipped)
+            .unwrap();
+        // The fee paid is passed to the post-dispatch step.
+        assert_eq!(pre.5, 10 + transfer_fee());
+        assert_eq!(
+            runtime
+                .balances
+                .balance("alice".to_string()),
+            90 - transfer_fee()
+        );
+        assert_eq!(
+            runtime
+                .system
+                .nonce(&"alice".to_string()),
+            1
+        );
+        assert_eq!(runtime.validate_transaction(&tipped), Err("stale nonce"));
+    }
+
+    #[test]
+    fn signed_extensions_reject_extrinsics() {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+        runtime
+            .system
+            .set_genesis_hash([1; 32]);
+
+        let mut extrinsic = transfer("alice", 0, "bob", 30);
+        assert_eq!(
+            runtime.validate_transaction(&extrinsic),
+            Err("extrinsic built for another chain")
+        );
+        extrinsic.genesis_hash = [1; 32];
+        extrinsic.spec_version = VERSION.spec_version + 1;
+        assert_eq!(
+            runtime.validate_transaction(&extrinsic),
+            Err("extrinsic built for another runtime version")
+        );
+        extrinsic.spec_version = VERSION.spec_version;
+
+        // The extrinsic can be included in the blocks 2 and 3.
+        extrinsic.era = support::Era::Mortal {
+            birth: 2,
+            period: 2,
+        };
+        assert_eq!(
+            runtime.validate_transaction(&extrinsic),
+            Err("extrinsic era has not started")
+        );
+        runtime
+            .system
+            .inc_block_number();
+        assert!(runtime
+            .validate_transaction(&extrinsic)
+            .is_ok());
+        runtime
+            .system
+            .inc_block_number();
+        runtime
+            .system
+            .inc_block_number();
+        assert_eq!(
+            runtime.validate_transaction(&extrinsic),
+            Err("extrinsic era has ended")
+        );
+        let block = types::Block {
+            header: support::Header {
+                block_number: 4,
+                ..Default::default()
+            },
+            extrinsics: vec![extrinsic],
+        };
+        runtime
+            .execute_block(block)
+            .unwrap();
+        assert_eq!(
+            runtime.system.events()[0].event,
+            RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                error: "extrinsic era has ended"
+            })
+        );
+
+        // The checks failing after the nonce check do not increment the nonce either.
+        let mut extrinsic = transfer("alice", 0, "bob", 30);
+        extrinsic.genesis_hash = [1; 32];
+        extrinsic.tip = 1000;
+        let block = types::Block {
+            header: support::Header {
+                block_number: 5,
+                ..Default::default()
+            },
+            extrinsics: vec![extrinsic],
+        };
+        runtime
+            .execute_block(block)
+            .unwrap();
+        assert_eq!(
+            runtime.system.events()[0].event,
+            RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                error: "insufficient balance to pay the fee"
+            })
+        );
+        assert_eq!(
+            runtime
+                .system
+                .nonce(&"alice".to_string()),
+            0
+        );
+    }
+
+    #[test]
+    fn fees_are_withdrawn_even_if_the_call_fails() {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+
+        let mut extrinsic = transfer("alice", 0, "bob", 95);
+        extrinsic.tip = 10;
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![extrinsic],
+        };
+        runtime
+            .execute_block(block)
+            .unwrap();
+
+        let events = runtime
+            .system
+            .events()
+            .iter()
+            .map(|record| record.event.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                RuntimeEvent::balances(balances::Event::Withdraw {
+                    who: "alice".to_string(),
+                    amount: 10 + transfer_fee(),
+                }),
+                treasury_share(10 + transfer_fee()),
+                RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                    error: "Insufficient balance"
+                }),
+            ]
+        );
+        assert_eq!(
+            runtime
+                .balances
+                .balance("alice".to_string()),
+            90 - transfer_fee()
+        );
+    }
+
+    #[test]
+    fn invalid_extrinsics_are_not_dispatched() {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![transfer("alice", 1, "bob", 30)],
+        };
+        runtime
+            .execute_block(block)
+            .unwrap();
+
+        assert_eq!(
+            runtime
+                .balances
+                .balance("bob".to_string()),
+            0
+        );
+        assert_eq!(
+            runtime
+                .system
+                .nonce(&"alice".to_string()),
+            0
+        );
+        assert_eq!(
+            runtime.system.events()[0].event,
+            RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                error: "future nonce"
+            })
+        );
+    }
+
+    #[test]
+    fn execute_block_reports_every_extrinsic() {
+        use crate::support::{
+            storage::Storage,
+            ExtrinsicError,
+            ExtrinsicReport,
+        };
+
+        let mut runtime = Runtime::new();
+
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+
+        let mut tipped = transfer("alice", 0, "bob", 30);
+        tipped.tip = 5;
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![
+                tipped,
+                transfer("alice", 1, "bob", 1000),
+                transfer("alice", 5, "bob", 1),
+            ],
+        };
+        let report = runtime
+            .execute_block(block)
+            .unwrap();
+
+        let weight = transfer("alice", 0, "bob", 0)
+            .call
+            .weight();
+        let withdraw = |amount| {
+            RuntimeEvent::balances(balances::Event::Withdraw {
+                who: "alice".to_string(),
+                amount,
+            })
+        };
+        assert_eq!(report.state_root, runtime.state_root());
+        assert_eq!(
+            report.extrinsics,
+            vec![
+                ExtrinsicReport {
+                    index: 0,
+                    caller: "alice".to_string(),
+                    result: Ok(()),
+                    weight,
+                    fee: 5 + transfer_fee(),
+                    events: vec![
+                        withdraw(5 + transfer_fee()),
+                        treasury_share(5 + transfer_fee()),
+                        RuntimeEvent::balances(balances::Event::Transfer {
+                            from: "alice".to_string(),
+                            to: "bob".to_string(),
+                            amount: 30,
+                        }),
+                        RuntimeEvent::system(system::Event::ExtrinsicSuccess),
+                    ],
+                },
+                ExtrinsicReport {
+                    index: 1,
+                    caller: "alice".to_string(),
+                    result: Err(ExtrinsicError::Dispatch("Insufficient balance")),
+                    weight,
+                    fee: transfer_fee(),
+                    events: vec![
+                        withdraw(transfer_fee()),
+                        treasury_share(transfer_fee()),
+                        RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                            error: "Insufficient balance",
+                        }),
+                    ],
+                },
+                ExtrinsicReport {
+                    index: 2,
+                    caller: "alice".to_string(),
+                    result: Err(ExtrinsicError::Invalid("future nonce")),
+                    weight: 0,
+                    fee: 0,
+                    events: vec![RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                        error: "future nonce",
+                    })],
+                },
+            ]
+        );
+        assert_eq!(
+            report
+                .failed()
+                .map(|(extrinsic, _)| extrinsic.index)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn parallel_execution_has_the_outcome_of_sequential_execution() {
+        use crate::support::storage::Storage;
+
+        let runtime = || {
+            let mut runtime = Runtime::new();
+            runtime
+                .system
+                .allow_keyless_accounts();
+            for i in 0..100 {
+                runtime
+                    .balances
+                    .set_balance(format!("sender{i}"), 1000);
+            }
+            runtime
+                .balances
+                .set_balance("alice".to_string(), 1000);
+            runtime
+        };
+        let (mut sequential, mut parallel) = (runtime(), runtime());
+
+        let claim = |caller, nonce| {
+            let call = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                claim: "doc".to_string(),
+            });
+            extrinsic(caller, nonce, call)
+        };
+        // Independent transfers, along with ones depending on the calls before them: bob only
+        // has funds after the first transfer of alice, and only the first claim of the document
+        // succeeds.
+        let mut extrinsics = (0..100)
+            .map(|i| {
+                transfer(
+                    &format!("sender{i}"),
+                    0,
+                    &format!("receiver{i}"),
+                    10 + i as u128,
+                )
+            })
+            .collect::<Vec<_>>();
+        extrinsics.extend([
+            transfer("alice", 0, "bob", 500),
+            transfer("bob", 0, "charlie", 200),
+            transfer("alice", 1, "bob", 1000),
+            claim("charlie", 0),
+            claim("alice", 2),
+            transfer("sender0", 1, "receiver1", 5),
+            transfer("nobody", 0, "bob", 1),
+        ]);
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics,
+        };
+
+        let expected = sequential
+            .execute_block(block.clone())
+            .unwrap();
+        let report = parallel
+            .execute_block_parallel(block)
+            .unwrap();
+        assert_eq!(report, expected);
+        assert_eq!(parallel.state_root(), sequential.state_root());
+        assert_eq!(parallel.system.events(), sequential.system.events());
+        assert_eq!(
+            report
+                .failed()
+                .map(|(extrinsic, _)| extrinsic.index)
+                .collect::<Vec<_>>(),
+            vec![102, 104, 106]
+        );
+    }
+
+    #[test]
+    fn first_block_of_a_new_version_migrates_storage() {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        assert!(!runtime.runtime_upgrade_pending());
+
+        // The state of an older runtime, before balances storage version 1.
+        runtime
+            .system
+            .note_runtime_upgrade(VERSION.spec_version - 1);
+        runtime
+            .balances
+            .set_on_chain_storage_version(StorageVersion(0));
+        assert!(runtime.runtime_upgrade_pending());
+
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![],
+        };
+        runtime
+            .execute_block(block)
+            .unwrap();
+        assert!(!runtime.runtime_upgrade_pending());
+        assert_eq!(
+            runtime
+                .system
+                .last_runtime_upgrade(),
+            Some(VERSION.spec_version)
+        );
+        assert_eq!(
+            runtime
+                .balances
+                .on_chain_storage_version(),
+            balances::STORAGE_VERSION
+        );
+    }
+
+    #[test]
+    fn upgrading_a_state_without_storage_version_migrates_it() {
+        use codec::Encode;
+
+        use crate::support::storage::Storage;
+
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        assert_eq!(
+            runtime
+                .balances
+                .on_chain_storage_version(),
+            balances::STORAGE_VERSION
+        );
+
+        // The state of a chain started before the storage versions were recorded.
+        runtime
+            .system
+            .note_runtime_upgrade(VERSION.spec_version - 1);
+        let version_key = support::storage::storage_prefix("balances", "storage_version");
+        runtime
+            .set_raw(&version_key, None)
+            .unwrap();
+        // Version 0 kept the zero balances.
+        let alice_key = support::storage::map_key("balances", "balances", &"alice".to_string());
+        runtime
+            .set_raw(&alice_key, Some(0u128.encode()))
+            .unwrap();
+        assert_eq!(
+            runtime
+                .balances
+                .on_chain_storage_version(),
+            StorageVersion(0)
+        );
+
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![],
+        };
+        runtime
+            .execute_block(block)
+            .unwrap();
+        assert_eq!(
+            runtime
+                .balances
+                .on_chain_storage_version(),
+            balances::STORAGE_VERSION
+        );
+        // The zero balances were removed by the migration to version 1.
+        assert_eq!(runtime.get_raw(&alice_key), None);
+    }
+
+    #[test]
+    fn failed_runtime_upgrade_rejects_the_block() {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .system
+            .note_runtime_upgrade(VERSION.spec_version + 1);
+        runtime
+            .balances
+            .set_on_chain_storage_version(StorageVersion(u16::MAX));
+
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![],
+        };
+        assert_eq!(
+            runtime.execute_block(block),
+            Err("storage version is newer than the code")
+        );
+        assert!(runtime.runtime_upgrade_pending());
+        // The increment of the block number is rolled back with the rest of the block.
+        assert_eq!(runtime.system.block_number(), 0);
+    }
+
+    #[test]
+    fn blocks_over_the_max_block_weight_are_rejected() {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        let call = |nonce| {
+            let call = RuntimeCall::contracts(contracts::Call::call {
+                contract: "contract".to_string(),
+                value: 0,
+                gas_limit: 900_000_000,
+                data: vec![],
+            });
+            extrinsic("alice", nonce, call)
+        };
+        let weight = Runtime::weight;
+        assert!(weight(&call(0)) * 2 <= support::MAX_BLOCK_WEIGHT);
+        assert!(weight(&call(0)) * 3 > support::MAX_BLOCK_WEIGHT);
+
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![call(0), call(1), call(2)],
+        };
+        assert_eq!(
+            runtime.execute_block(block.clone()),
+            Err("the block is heavier than the maximum block weight")
+        );
+        assert_eq!(
+            runtime.execute_block_parallel(block),
+            Err("the block is heavier than the maximum block weight")
+        );
+        assert_eq!(runtime.system.block_number(), 0);
+    }
+
+    #[test]
+    fn state_root_commits_to_the_executed_blocks() {
+        use crate::support::storage::Storage;
+
+        let mut runtime = Runtime::new();
+
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+        let genesis_root = runtime.state_root();
+
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![transfer("alice", 0, "bob", 30)],
+        };
+        runtime
+            .execute_block(block)
+            .unwrap();
+        let root = runtime.state_root();
+        assert_ne!(root, genesis_root);
+
+        // A block with the wrong number is rejected, and does not change the state.
+        let block = types::Block {
+            header: support::Header {
+                block_number: 3,
+                ..Default::default()
+            },
+            extrinsics: vec![transfer("alice", 1, "bob", 30)],
+        };
+        assert!(runtime
+            .execute_block(block)
+            .is_err());
+        assert_eq!(runtime.state_root(), root);
+        assert_eq!(
+            runtime
+                .balances
+                .balance("bob".to_string()),
+            30
+        );
+    }
+
+    #[test]
+    fn pallet_instances_are_independent() {
+        use codec::Encode;
+
+        let mut runtime = Runtime::new();
+
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+        runtime
+            .stablecoin
+            .set_balance("alice".to_string(), 50);
+
+        let call = RuntimeCall::stablecoin(balances::Call::transfer {
+            to: "bob".to_string(),
+            amount: 20,
+        });
+        // The encoding uses the index given to the pallet, not its position in the runtime.
+        assert_eq!(call.encode()[..2], [3, 0]);
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![extrinsic("alice", 0, call)],
+        };
+        runtime
+            .execute_block(block)
+            .unwrap();
+
+        // The fee is paid in the native token.
+        let (alice, bob) = ("alice".to_string(), "bob".to_string());
+        assert_eq!(
+            runtime
+                .balances
+                .balance(alice.clone()),
+            100 - transfer_fee()
+        );
+        assert_eq!(
+            runtime
+                .balances
+                .balance(bob.clone()),
+            0
+        );
+        assert_eq!(
+            runtime
+                .stablecoin
+                .balance(alice),
+            30
+        );
+        assert_eq!(
+            runtime
+                .stablecoin
+                .balance(bob),
+            20
+        );
+        assert_eq!(
+            runtime.system.events()[2].event,
+            RuntimeEvent::stablecoin(balances::Event::Transfer {
+                from: "alice".to_string(),
+                to: "bob".to_string(),
+                amount: 20,
+            })
+        );
+
+        // The storage keys of the instances are prefixed by their names in the runtime.
+        use crate::support::storage::Storage;
+        let prefixes = [
+            runtime.balances.raw_storage()[0].prefix(),
+            runtime
+                .stablecoin
+                .raw_storage()[0]
+                .prefix(),
+        ];
+        assert_ne!(prefixes[0], prefixes[1]);
+    }
+
+    #[test]
+    fn borrowing_pallets_are_lent_the_balances_pallet() {
+        use crate::test_utils::ExtBuilder;
+
+        let mut mock = ExtBuilder::default()
+            .balance("alice", 1000)
+            .build();
+        let mint = |item| {
+            RuntimeCall::nfts(nfts::Call::mint {
+                collection: 0,
+                item,
+                owner: "bob".to_string(),
+            })
+        };
+        let report = mock.run_block(vec![
+            (
+                "alice",
+                RuntimeCall::nfts(nfts::Call::create_collection {
+                    max_supply: Some(1),
+                }),
+            ),
+            ("alice", mint(0)),
+            ("alice", mint(1)),
+        ]);
+        assert_eq!(
+            report
+                .failed()
+                .map(|(extrinsic, error)| (extrinsic.index, error))
+                .collect::<Vec<_>>(),
+            vec![(
+                2,
+                support::ExtrinsicError::Dispatch(
+                    "the maximum supply of the collection is reached"
+                )
+            )]
+        );
+
+        // The deposits are reserved in the balances pallet of the runtime, which is given back
+        // after every call, even a failed one.
+        let alice = "alice".to_string();
+        assert_eq!(
+            mock.balances
+                .reserved_balance(alice.clone()),
+            110
+        );
+        assert_eq!(
+            mock.balances
+                .balance(alice.clone()),
+            1000 - 110
+                - report
+                    .extrinsics
+                    .iter()
+                    .map(|extrinsic| extrinsic.fee)
+                    .sum::<types::Balance>()
+        );
+        assert_eq!(mock.nfts.owner(0, 0), Some("bob".to_string()));
+        mock.assert_has_event(RuntimeEvent::balances(balances::Event::Reserved {
+            who: alice,
+            amount: 10,
+        }));
+    }
+
+    #[test]
+    fn the_system_pallet_can_be_lent() {
+        use crate::test_utils::ExtBuilder;
+
+        let mut mock = ExtBuilder::default()
+            .balance("alice", 1000)
+            .balance("bob", 100)
+            .build();
+        let release = || RuntimeCall::escrow(escrow::Call::release { id: 0 });
+        let report = mock.run_block(vec![
+            (
+                "alice",
+                RuntimeCall::escrow(escrow::Call::create {
+                    payee: "bob".to_string(),
+                    amount: 500,
+                    condition: escrow::Condition::After(2),
+                    expiry: 5,
+                }),
+            ),
+            ("bob", release()),
+        ]);
+        assert_eq!(
+            report
+                .failed()
+                .map(|(_, error)| error.message())
+                .collect::<Vec<_>>(),
+            vec!["the time lock of the escrow is not over"]
+        );
+
+        // The escrow reads the block number of the system pallet, which is given back after the
+        // call.
+        mock.run_block(vec![("bob", release())]);
+        assert_eq!(mock.system.block_number(), 2);
+        assert_eq!(mock.escrow.escrow(0), None);
+        mock.assert_has_event(RuntimeEvent::escrow(escrow::Event::Released {
+            id: 0,
+            payee: "bob".to_string(),
+            amount: 500,
+        }));
+    }
+
+    #[test]
+    fn proxies_make_the_calls_their_type_allows() {
+        use crate::test_utils::ExtBuilder;
+
+        let mut mock = ExtBuilder::default()
+            .balance("alice", 1000)
+            .balance("bob", 100)
+            .balance("charlie", 100)
+            .build();
+        let proxy = |call| {
+            RuntimeCall::proxy(proxy::Call::proxy {
+                real: "alice".to_string(),
+                force_proxy_type: None,
+                call: Box::new(call),
+            })
+        };
+        let transfer = RuntimeCall::balances(balances::Call::transfer {
+            to: "bob".to_string(),
+            amount: 500,
+        });
+        let claim = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+            claim: "doc".to_string(),
+        });
+        let report = mock.run_block(vec![
+            (
+                "alice",
+                RuntimeCall::proxy(proxy::Call::add_proxy {
+                    delegate: "bob".to_string(),
+                    proxy_type: proxy::ProxyType::NonTransfer,
+                    delay: 0,
+                }),
+            ),
+            ("bob", proxy(transfer.clone())),
+            ("bob", proxy(claim)),
+        ]);
+        assert_eq!(
+            report
+                .failed()
+                .map(|(_, error)| error.message())
+                .collect::<Vec<_>>(),
+            vec![support::CALL_FILTERED]
+        );
+        assert_eq!(
+            mock.proof_of_existence
+                .get_claim(&"doc".to_string()),
+            Some("alice".to_string())
+        );
+        mock.assert_has_event(RuntimeEvent::proxy(proxy::Event::ProxyExecuted {
+            real: "alice".to_string(),
+            delegate: "bob".to_string(),
+            proxy_type: proxy::ProxyType::NonTransfer,
+        }));
+
+        // The filter applies to the calls forwarded by the forwarded calls too, so the proxy can't
+        // escape it through a proxy of its own.
+        let report = mock.run_block(vec![
+            (
+                "alice",
+                RuntimeCall::proxy(proxy::Call::add_proxy {
+                    delegate: "charlie".to_string(),
+                    proxy_type: proxy::ProxyType::Any,
+                    delay: 0,
+                }),
+            ),
+            (
+                "bob",
+                proxy(RuntimeCall::proxy(proxy::Call::proxy {
+                    real: "alice".to_string(),
+                    force_proxy_type: None,
+                    call: Box::new(transfer.clone()),
+                })),
+            ),
+        ]);
+        assert_eq!(report.failed().count(), 1);
+
+        let report = mock.run_block(vec![("charlie", proxy(transfer))]);
+        assert_eq!(report.failed().count(), 0);
+        mock.assert_has_event(RuntimeEvent::balances(balances::Event::Transfer {
+            from: "alice".to_string(),
+            to: "bob".to_string(),
+            amount: 500,
+        }));
+    }
+
+    #[test]
+    fn non_transfer_proxies_make_no_root_calls() {
+        use crate::test_utils::ExtBuilder;
+
+        let mut mock = ExtBuilder::default()
+            .balance("alice", 1000)
+            .balance("bob", 100)
+            .balance("root", 100)
+            .root_key("root")
+            .build();
+        let proxy = |call| {
+            RuntimeCall::proxy(proxy::Call::proxy {
+                real: "root".to_string(),
+                force_proxy_type: None,
+                call: Box::new(call),
+            })
+        };
+        let report = mock.run_block(vec![
+            (
+                "root",
+                RuntimeCall::proxy(proxy::Call::add_proxy {
+                    delegate: "bob".to_string(),
+                    proxy_type: proxy::ProxyType::NonTransfer,
+                    delay: 0,
+                }),
+            ),
+            (
+                "alice",
+                RuntimeCall::treasury(treasury::Call::propose_spend {
+                    value: 100,
+                    beneficiary: "bob".to_string(),
+                }),
+            ),
+            (
+                "bob",
+                proxy(RuntimeCall::treasury(treasury::Call::approve_proposal {
+                    index: 0,
+                })),
+            ),
+            (
+                "bob",
+                proxy(RuntimeCall::treasury(treasury::Call::reject_proposal {
+                    index: 0,
+                })),
+            ),
+            (
+                "bob",
+                proxy(RuntimeCall::system(system::Call::set_code {
+                    spec_version: VERSION.spec_version + 1,
+                })),
+            ),
+            (
+                "bob",
+                proxy(RuntimeCall::system(system::Call::block_calls {
+                    pallet: "balances".to_string(),
+                    call: None,
+                })),
+            ),
+            (
+                "bob",
+                proxy(RuntimeCall::system(system::Call::remark {
+                    remark: b"hello".to_vec(),
+                })),
+            ),
+        ]);
+        assert_eq!(
+            report
+                .failed()
+                .map(|(report, error)| (report.index, error.message()))
+                .collect::<Vec<_>>(),
+            vec![
+                (2, support::CALL_FILTERED),
+                (3, support::CALL_FILTERED),
+                (4, support::CALL_FILTERED),
+                (5, support::CALL_FILTERED),
+            ]
+        );
+        assert!(mock
+            .treasury
+            .proposal(0)
+            .is_some());
+        assert!(mock
+            .treasury
+            .approvals()
+            .is_empty());
+        assert!(!mock
+            .system
+            .is_call_blocked("balances", "transfer"));
+    }
+
+    #[test]
+    fn forwarded_calls_are_nested_at_most_max_call_depth() {
+        use crate::test_utils::ExtBuilder;
+
+        let mut mock = ExtBuilder::default()
+            .balance("alice", 1000)
+            .balance("bob", 1000)
+            .build();
+        // Alice and bob are proxies of each other, so bob can make a call as alice, making a call
+        // as bob, and so on.
+        let nested = |depth: usize| {
+            let mut call = RuntimeCall::system(system::Call::remark { remark: vec![] });
+            for level in (1..=depth).rev() {
+                let real = if level % 2 == 1 { "alice" } else { "bob" };
+                call = RuntimeCall::proxy(proxy::Call::proxy {
+                    real: real.to_string(),
+                    force_proxy_type: None,
+                    call: Box::new(call),
+                });
+            }
+            call
+        };
+        let add_proxy = |delegate: &str| {
+            RuntimeCall::proxy(proxy::Call::add_proxy {
+                delegate: delegate.to_string(),
+                proxy_type: proxy::ProxyType::Any,
+                delay: 0,
+            })
+        };
+        let report = mock.run_block(vec![
+            ("alice", add_proxy("bob")),
+            ("bob", add_proxy("alice")),
+            ("bob", nested(support::MAX_CALL_DEPTH)),
+            ("bob", nested(support::MAX_CALL_DEPTH + 1)),
+        ]);
+        assert_eq!(
+            report
+                .failed()
+                .map(|(report, error)| (report.index, error.message()))
+                .collect::<Vec<_>>(),
+            vec![(3, support::CALL_TOO_DEEP)]
+        );
+    }
+
+    #[test]
+    fn root_blocks_calls_by_name() {
+        use crate::test_utils::ExtBuilder;
+
+        let mut mock = ExtBuilder::default()
+            .balance("alice", 1000)
+            .balance("bob", 100)
+            .balance("root", 100)
+            .root_key("root")
+            .build();
+        let block = |pallet: &str, call: Option<&str>| {
+            RuntimeCall::system(system::Call::block_calls {
+                pallet: pallet.to_string(),
+                call: call.map(str::to_string),
+            })
+        };
+        let transfer = || {
+            RuntimeCall::balances(balances::Call::transfer {
+                to: "bob".to_string(),
+                amount: 10,
+            })
+        };
+        let claim = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+            claim: "doc".to_string(),
+        });
+        let report = mock.run_block(vec![
+            ("alice", block("balances", Some("transfer"))),
+            ("root", block("system", None)),
+            ("root", block("balances", Some("transfer"))),
+            ("alice", transfer()),
+            ("alice", claim),
+            (
+                "alice",
+                RuntimeCall::stablecoin(balances::Call::transfer {
+                    to: "bob".to_string(),
+                    amount: 0,
+                }),
+            ),
+        ]);
+        assert_eq!(
+            report
+                .failed()
+                .map(|(report, error)| (report.index, error))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, support::ExtrinsicError::Dispatch("caller is not root")),
+                (
+                    1,
+                    support::ExtrinsicError::Dispatch(
+                        "the calls of the system pallet can't be blocked"
+                    )
+                ),
+                (3, support::ExtrinsicError::Filtered(support::CALL_FILTERED)),
+            ]
+        );
+        assert_eq!(
+            report.extrinsics[3].events,
+            vec![
+                RuntimeEvent::balances(balances::Event::Withdraw {
+                    who: "alice".to_string(),
+                    amount: transfer_fee(),
+                }),
+                treasury_share(transfer_fee()),
+                RuntimeEvent::system(system::Event::CallFiltered {
+                    pallet: "balances",
+                    call: "transfer",
+                }),
+                RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                    error: support::CALL_FILTERED,
+                }),
+            ]
+        );
+
+        // The calls forwarded by a proxy are filtered too, and reported by their own name.
+        let report = mock.run_block(vec![
+            (
+                "alice",
+                RuntimeCall::proxy(proxy::Call::add_proxy {
+                    delegate: "bob".to_string(),
+                    proxy_type: proxy::ProxyType::Any,
+                    delay: 0,
+                }),
+            ),
+            (
+                "bob",
+                RuntimeCall::proxy(proxy::Call::proxy {
+                    real: "alice".to_string(),
+                    force_proxy_type: None,
+                    call: Box::new(transfer()),
+                }),
+            ),
+        ]);
+        assert_eq!(report.failed().count(), 1);
+        mock.assert_has_event(RuntimeEvent::system(system::Event::CallFiltered {
+            pallet: "balances",
+            call: "transfer",
+        }));
+
+        let report = mock.run_block(vec![
+            (
+                "root",
+                RuntimeCall::system(system::Call::unblock_calls {
+                    pallet: "balances".to_string(),
+                    call: Some("transfer".to_string()),
+                }),
+            ),
+            ("alice", transfer()),
+        ]);
+        assert_eq!(report.failed().count(), 0);
+        assert_eq!(mock.system.blocked_calls(), vec![]);
+    }
+
+    #[test]
+    fn the_treasury_is_funded_by_fees_and_pays_in_its_hooks() {
+        use crate::test_utils::ExtBuilder;
+
+        let account = treasury::Pallet::<Runtime>::account_id();
+        let mut mock = ExtBuilder::default()
+            .balance("alice", 1000)
+            .balance("root", 1000)
+            .balance(&account, 1000)
+            .root_key("root")
+            .build();
+
+        let report = mock.run_block(vec![(
+            "alice",
+            RuntimeCall::treasury(treasury::Call::propose_spend {
+                value: 500,
+                beneficiary: "bob".to_string(),
+            }),
+        )]);
+        let fee = report.extrinsics[0].fee;
+        assert_eq!(report.extrinsics[0].events[1], treasury_share(fee));
+        assert_eq!(
+            mock.balances
+                .balance(account.clone()),
+            1000 + extensions::TREASURY_FEE_SHARE.mul_floor(fee)
+        );
+
+        mock.run_block(vec![(
+            "root",
+            RuntimeCall::treasury(treasury::Call::approve_proposal { index: 0 }),
+        )]);
+        while mock.system.block_number() < 9 {
+            mock.run_block(vec![]);
+        }
+        let funds = mock
+            .balances
+            .balance(account.clone());
+        assert_eq!(
+            mock.balances
+                .balance("bob".to_string()),
+            0
+        );
+
+        // The payout happens when the spend period starts, before the extrinsics of the block.
+        mock.run_block(vec![]);
+        let record = |event| types::EventRecord {
+            phase: system::Phase::Initialization,
+            event: RuntimeEvent::treasury(event),
+        };
+        let burnt_funds = (funds - 500) / 10;
+        let events = mock.system.events().to_vec();
+        assert!(events.contains(&record(treasury::Event::Awarded {
+            index: 0,
+            award: 500,
+            beneficiary: "bob".to_string(),
+        })));
+        assert!(events.contains(&record(treasury::Event::Burnt { burnt_funds })));
+        assert_eq!(
+            mock.balances
+                .balance("bob".to_string()),
+            500
+        );
+        assert_eq!(mock.balances.balance(account), funds - 500 - burnt_funds);
+        assert!(mock
+            .treasury
+            .approvals()
+            .is_empty());
+    }
+
+    #[test]
+    fn contracts_pay_for_their_gas_and_roll_back_when_they_trap() {
+        use crate::test_utils::ExtBuilder;
+
+        // Stores its input, then pays 10 to bob.
+        let code = wat::parse_str(
+            r#"(module
+                (import "env" "input" (func $input (param i32 i32) (result i32)))
+                (import "env" "storage_set" (func $storage_set (param i32 i32 i32 i32)))
+                (import "env" "transfer" (func $transfer (param i32 i32 i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "last")
+                (data (i32.const 16) "\0cbob")
+                (data (i32.const 32) "\0a\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00")
+                (func (export "deploy"))
+                (func (export "call")
+                    (call $storage_set
+                        (i32.const 0) (i32.const 4)
+                        (i32.const 64) (call $input (i32.const 64) (i32.const 64)))
+                    (call $transfer (i32.const 16) (i32.const 4) (i32.const 32) (i32.const 16))))"#,
+        )
+        .unwrap();
+        let code_hash = support::storage::blake2_256(&code);
+        let contract =
+            contracts::Pallet::<Runtime>::contract_address(&"alice".to_string(), &code_hash, &[]);
+        let call = |value, data: &[u8]| {
+            RuntimeCall::contracts(contracts::Call::call {
+                contract: contract.clone(),
+                value,
+                gas_limit: 100_000,
+                data: data.to_vec(),
+            })
+        };
+
+        let mut mock = ExtBuilder::default()
+            .balance("alice", 10_000)
+            .build();
+        let report = mock.run_block(vec![
+            (
+                "alice",
+                RuntimeCall::contracts(contracts::Call::upload_code { code }),
+            ),
+            (
+                "alice",
+                RuntimeCall::contracts(contracts::Call::instantiate {
+                    code_hash,
+                    value: 0,
+                    gas_limit: 100_000,
+                    data: vec![],
+                    salt: vec![],
+                }),
+            ),
+            ("alice", call(0, b"unpaid")),
+        ]);
+        // The fee pays for the gas limit.
+        assert!(report.extrinsics[1].fee > 100_000 / extensions::WEIGHT_PER_FEE as u128);
+        assert_eq!(
+            report
+                .failed()
+                .map(|(extrinsic, error)| (extrinsic.index, error))
+                .collect::<Vec<_>>(),
+            vec![(2, support::ExtrinsicError::Dispatch("Insufficient balance"))]
+        );
+        assert_eq!(
+            mock.contracts
+                .contract_storage(&contract, b"last"),
+            None
+        );
+
+        let report = mock.run_block(vec![("alice", call(10, b"paid"))]);
+        assert_eq!(report.failed().count(), 0);
+        assert_eq!(
+            mock.contracts
+                .contract_storage(&contract, b"last"),
+            Some(b"paid".to_vec())
+        );
+        assert_eq!(
+            mock.balances
+                .balance("bob".to_string()),
+            10
+        );
+        assert_eq!(
+            mock.balances
+                .balance(contract),
+            0
+        );
+    }
+
+    #[test]
+    fn evm_contracts_pay_for_their_gas_and_emit_logs() {
+        use crate::test_utils::ExtBuilder;
+
+        // Logs the topic 7, and deploys no code: `PUSH1 7, PUSH1 0, PUSH1 0, LOG1, PUSH1 0,
+        // PUSH1 0, RETURN`.
+        let init_code = vec![
+            0x60, 0x07, 0x60, 0x00, 0x60, 0x00, 0xa1, 0x60, 0x00, 0x60, 0x00, 0xf3,
+        ];
+        let create = |gas_limit| {
+            RuntimeCall::evm(evm::Call::create {
+                init_code: init_code.clone(),
+                value: 0,
+                gas_limit,
+            })
+        };
+
+        let mut mock = ExtBuilder::default()
+            .balance("alice", 100_000)
+            .build();
+        let report = mock.run_block(vec![("alice", create(200_000)), ("alice", create(60_000))]);
+        assert_eq!(
+            report
+                .failed()
+                .map(|(extrinsic, error)| (extrinsic.index, error))
+                .collect::<Vec<_>>(),
+            vec![(
+                0,
+                support::ExtrinsicError::Dispatch("insufficient balance to pay the gas")
+            )]
+        );
+
+        let alice = evm::Pallet::<Runtime>::address_of(&"alice".to_string());
+        let contract = revm::primitives::Address::from(alice)
+            .create(0)
+            .into();
+        let events = &report.extrinsics[1].events;
+        let mut topic = [0; 32];
+        topic[31] = 7;
+        assert!(events.contains(&RuntimeEvent::evm(evm::Event::Log {
+            address: contract,
+            topics: vec![topic],
+            data: vec![],
+        })));
+        assert!(events.contains(&RuntimeEvent::evm(evm::Event::Created {
+            creator: "alice".to_string(),
+            address: contract,
+        })));
+        let gas = events
+            .iter()
+            .find_map(|event| match event {
+                RuntimeEvent::balances(balances::Event::Withdraw { amount, .. })
+                    if *amount != report.extrinsics[1].fee =>
+                {
+                    Some(*amount)
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert!(gas > 53_000 && gas < 60_000);
+        assert_eq!(
+            mock.balances
+                .balance("alice".to_string()),
+            100_000 - report.extrinsics[0].fee - report.extrinsics[1].fee - gas
+        );
+    }
+
+    #[test]
+    fn evm_gas_limits_count_against_the_block_weight() {
+        let mut runtime = Runtime::new();
+        let max_gas_limit = <Runtime as evm::Config>::MAX_GAS_LIMIT;
+        let create = |nonce| {
+            let call = RuntimeCall::evm(evm::Call::create {
+                init_code: vec![],
+                value: 0,
+                gas_limit: max_gas_limit,
+            });
+            extrinsic("alice", nonce, call)
+        };
+        let weight = Runtime::weight;
+        assert!(weight(&create(0)) > evm::Pallet::<Runtime>::gas_to_weight(max_gas_limit));
+        assert!(weight(&create(0)) * 3 <= support::MAX_BLOCK_WEIGHT);
+        assert!(weight(&create(0)) * 4 > support::MAX_BLOCK_WEIGHT);
+
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: (0..4).map(create).collect(),
+        };
+        assert_eq!(
+            runtime.execute_block(block),
+            Err("the block is heavier than the maximum block weight")
+        );
+    }
+
+    #[test]
+    fn metadata_describes_the_pallets() {
+        use crate::metadata::{
+            Field,
+            StorageEntryType,
+            Type,
+        };
+
+        let metadata = Runtime::metadata();
+        assert_eq!(metadata.spec_name, VERSION.spec_name);
+        assert_eq!(metadata.spec_version, VERSION.spec_version);
+        let pallets = metadata
+            .pallets
+            .iter()
+            .map(|pallet| (pallet.name.as_str(), pallet.index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pallets,
+            vec![
+                ("system", 0),
+                ("balances", 1),
+                ("stablecoin", 3),
+                ("proof_of_existence", 2),
+                ("nfts", 4),
+                ("escrow", 5),
+                ("proxy", 6),
+                ("treasury", 7),
+                ("contracts", 8),
+                ("evm", 9)
+            ]
+        );
+
+        let field = |name: &str, ty| Field {
+            name: Some(name.to_string()),
+            ty,
+        };
+        let balances = metadata
+            .pallet("balances")
+            .unwrap();
+        assert_eq!(balances.calls.len(), 1);
+        assert_eq!(balances.calls[0].name, "transfer");
+        assert_eq!(
+            balances.calls[0].fields,
+            vec![field("to", Type::String), field("amount", Type::U128)]
+        );
+        assert_eq!(balances.events[0].name, "Transfer");
+        assert_eq!(
+            balances.storage[0].ty,
+            StorageEntryType::Map {
+                key: Type::String,
+                value: Type::U128
+            }
+        );
+        assert_eq!(
+            balances.errors,
+            vec!["Insufficient balance", "Overflow when adding balance"]
+        );
+
+        let proof_of_existence = metadata
+            .pallet("proof_of_existence")
+            .unwrap();
+        assert_eq!(
+            proof_of_existence.errors,
+            vec![
+                "this content is already claimed",
+                "claim does not exist",
+                "this content is owned by someone else"
+            ]
+        );
+
+        let system = metadata
+            .pallet("system")
+            .unwrap();
+        let calls = system
+            .calls
+            .iter()
+            .map(|call| (call.name.as_str(), call.index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            calls,
+            vec![
+                ("remark", 0),
+                ("remark_with_event", 1),
+                ("set_storage", 2),
+                ("kill_storage", 3),
+                ("set_code", 4),
+                ("block_calls", 5),
+                ("unblock_calls", 6)
+            ]
+        );
+        assert_eq!(system.events[1].fields, vec![field("error", Type::String)]);
+        assert_eq!(
+            system.events[2].fields[1],
+            field(
+                "hash",
+                Type::Array {
+                    len: 32,
+                    ty: Box::new(Type::U8)
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn system_calls() {
+        use codec::Encode;
+
+        use crate::support::storage::Storage;
+
+        let mut runtime = Runtime::new();
+
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .system
+            .set_root_key("root".to_string());
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+        runtime
+            .balances
+            .set_balance("root".to_string(), 100);
+        let extrinsic = |caller, nonce, call| extrinsic(caller, nonce, RuntimeCall::system(call));
+
+        // Root can write the storage of any pallet, with the keys and values of its storage items.
+        let key = |pallet: &balances::Pallet<Runtime>, who: &str| {
+            let mut key = pallet.raw_storage()[0]
+                .prefix()
+                .to_vec();
+            let who = who.to_string().encode();
+            key.extend(crate::support::storage::blake2_128(&who));
+            key.extend(who);
+            key
+        };
+        let alice = key(&runtime.balances, "alice");
+        let bob = key(&runtime.balances, "bob");
+        let block = types::Block {
+            header: support::Header {
+                block_number: 1,
+                ..Default::default()
+            },
+            extrinsics: vec![
+                extrinsic(
+                    "alice",
+                    0,
+                    system::Call::remark_with_event { remark: vec![1, 2] },
+                ),
+                extrinsic(
+                    "alice",
+                    1,
+                    system::Call::set_storage {
+                        items: vec![(bob.clone(), 50u128.encode())],
+                    },
+                ),
+                extrinsic(
+                    "root",
+                    0,
+                    system::Call::set_storage {
+                        items: vec![(bob, 50u128.encode())],
+                    },
+                ),
+                extrinsic("root", 1, system::Call::kill_storage { keys: vec![alice] }),
+                extrinsic("root", 2, system::Call::set_code { spec_version: 2 }),
+            ],
+        };
+        runtime
+            .execute_block(block)
+            .unwrap();
+
+        let events = runtime
+            .system
+            .events()
+            .iter()
+            .map(|record| record.event.clone())
+            .collect::<Vec<_>>();
+        assert!(
+            events.contains(&RuntimeEvent::system(system::Event::Remarked {
+                sender: "alice".to_string(),
+                hash: crate::support::storage::blake2_256(&[1, 2]),
+            }))
+        );
+        assert!(
+            events.contains(&RuntimeEvent::system(system::Event::ExtrinsicFailed {
+                error: "caller is not root"
+            }))
+        );
+        assert_eq!(
+            runtime
+                .balances
+                .balance("alice".to_string()),
+            0
+        );
+        assert_eq!(
+            runtime
+                .balances
+                .balance("bob".to_string()),
+            50
+        );
+
+        // After `set_code`, this version of the runtime can no longer execute the chain.
+        assert_eq!(runtime.system.code_upgrade(), Some(2));
+        let block = types::Block {
+            header: support::Header {
+                block_number: 2,
+                ..Default::default()
+            },
+            extrinsics: vec![],
+        };
+        assert_eq!(
+            runtime.execute_block(block),
+            Err("the chain was upgraded to a newer version of the runtime")
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! The checks run on every extrinsic of the runtime before it is dispatched, combined in
+//! `types::SignedExtra`.
+//!
+//! They are run by the transaction queue and the block builder with `validate`, and by
+//! `execute_block` with `pre_dispatch`, which also applies their side effects.
+
+use super::{
+    types,
+    Runtime,
+    VERSION,
+};
+use crate::{
+    crypto,
+    support::{
+        Currency,
+        Era,
+        GetWeight,
+        Percent,
+        SignedExtension,
+        TransactionValidity,
+        ValidTransaction,
+        Weight,
+    },
+    treasury,
+};
+
+/// The weight paid for by every unit of fee. The weights are measured in nanoseconds by the
+/// benchmarks, so a unit of fee pays for a microsecond of execution.
+pub const WEIGHT_PER_FEE: Weight = 1_000;
+
+/// The weight of every extrinsic besides its call, for checking its signature and its nonce and
+/// charging its fee. The cheapest calls, like an empty remark, still pay for it.
+pub const EXTRINSIC_BASE_WEIGHT: Weight = 10_000;
+
+/// The share of the fees given to the treasury. The rest is burned.
+pub const TREASURY_FEE_SHARE: Percent = Percent::from_percent(50);
+
+/// Check the signature of the extrinsics of the accounts backed by a key.
+///
+/// The other accounts, like the development accounts `alice` and `bob`, have no key. Their
+/// extrinsics are not signed, so they are only accepted on the development chains allowing them,
+/// see `system::Pallet::allow_keyless_accounts`.
+pub struct CheckSignature;
+
+impl SignedExtension<Runtime, types::Extrinsic> for CheckSignature {
+    type Pre = ();
+
+    fn validate(runtime: &Runtime, extrinsic: &types::Extrinsic) -> TransactionValidity {
+        let public = crypto::account_public_key(&extrinsic.caller);
+        match (public, &extrinsic.signature) {
+            (Some(_), None) => return Err("extrinsic is not signed"),
+            (Some(public), Some(signature)) => {
+                if !signature.verify(&extrinsic.signing_payload(), &public) {
+                    return Err("invalid signature");
+                }
+            }
+            (None, Some(_)) => return Err("extrinsic signed for an account without a key"),
+            (None, None)
+                if runtime
+                    .system
+                    .keyless_accounts_allowed() => {}
+            (None, None) => return Err("account has no key"),
+        }
+        Ok(ValidTransaction::default())
+    }
+
+    fn pre_dispatch(
+        runtime: &mut Runtime,
+        extrinsic: &types::Extrinsic,
+    ) -> Result<(), &'static str> {
+        Self::validate(runtime, extrinsic).map(|_| ())
+    }
+}
+
+/// Reject extrinsics built for another version of the runtime.
+pub struct CheckSpecVersion;
+
+impl SignedExtension<Runtime, types::Extrinsic> for CheckSpecVersion {
+    type Pre = ();
+
+    fn validate(_runtime: &Runtime, extrinsic: &types::Extrinsic) -> TransactionValidity {
+        if extrinsic.spec_version != VERSION.spec_version {
+            return Err("extrinsic built for another runtime version");
+        }
+        Ok(ValidTransaction::default())
+    }
+
+    fn pre_dispatch(
+        runtime: &mut Runtime,
+        extrinsic: &types::Extrinsic,
+    ) -> Result<(), &'static str> {
+        Self::validate(runtime, extrinsic).map(|_| ())
+    }
+}
+
+/// Reject extrinsics built for another chain.
+pub struct CheckGenesis;
+
+impl SignedExtension<Runtime, types::Extrinsic> for CheckGenesis {
+    type Pre = ();
+
+    fn validate(runtime: &Runtime, extrinsic: &types::Extrinsic) -> TransactionValidity {
+        if extrinsic.genesis_hash != runtime.system.genesis_hash() {
+            return Err("extrinsic built for another chain");
+        }
+        Ok(ValidTransaction::default())
+    }
+
+    fn pre_dispatch(
+        runtime: &mut Runtime,
+        extrinsic: &types::Extrinsic,
+    ) -> Result<(), &'static str> {
+        Self::validate(runtime, extrinsic).map(|_| ())
+    }
+}
+
+/// Reject extrinsics included outside of their era.
+pub struct CheckMortality;
+
+impl CheckMortality {
+    /// Check that an extrinsic with the given era can be included in the block `number`.
+    fn check(era: &types::Era, number: types::BlockNumber) -> Result<(), &'static str> {
+        match *era {
+            Era::Immortal => Ok(()),
+            Era::Mortal { birth, .. } if number < birth => Err("extrinsic era has not started"),
+            Era::Mortal { birth, period } if number - birth >= period => {
+                Err("extrinsic era has ended")
+            }
+            Era::Mortal { .. } => Ok(()),
+        }
+    }
+}
+
+impl SignedExtension<Runtime, types::Extrinsic> for CheckMortality {
+    type Pre = ();
+
+    fn validate(runtime: &Runtime, extrinsic: &types::Extrinsic) -> TransactionValidity {
+        // The extrinsic would be included in the next block.
+        let next = runtime.system.block_number() + 1;
+        Self::check(&extrinsic.era, next)?;
+        Ok(ValidTransaction::default())
+    }
+
+    fn pre_dispatch(
+        runtime: &mut Runtime,
+        extrinsic: &types::Extrinsic,
+    ) -> Result<(), &'static str> {
+        // The block number is already incremented for the block being executed.
+        Self::check(&extrinsic.era, runtime.system.block_number())
+    }
+}
+
+/// Check the nonce of the caller, and increment it.
+pub struct CheckNonce;
+
+impl SignedExtension<Runtime, types::Extrinsic> for CheckNonce {
+    type Pre = ();
+
+    fn validate(runtime: &Runtime, extrinsic: &types::Extrinsic) -> TransactionValidity {
+        if extrinsic.nonce
+            < runtime
+                .system
+                .nonce(&extrinsic.caller)
+        {
+            return Err("stale nonce");
+        }
+        Ok(ValidTransaction::default())
+    }
+
+    fn pre_dispatch(
+        runtime: &mut Runtime,
+        extrinsic: &types::Extrinsic,
+    ) -> Result<(), &'static str> {
+        Self::validate(runtime, extrinsic)?;
+        if extrinsic.nonce
+            != runtime
+                .system
+                .nonce(&extrinsic.caller)
+        {
+            return Err("future nonce");
+        }
+        runtime
+            .system
+            .inc_nonce(&extrinsic.caller);
+        Ok(())
+    }
+}
+
+/// Charge the fee of the extrinsic to the caller. The extrinsics paying a higher tip get a higher
+/// priority.
+///
+/// The `TREASURY_FEE_SHARE` of the fee is deposited to the treasury, and the rest is burned.
+pub struct ChargeTransactionPayment;
+
+impl ChargeTransactionPayment {
+    /// The weight of an extrinsic: the `EXTRINSIC_BASE_WEIGHT` and the weight of its call.
+    pub fn weight(extrinsic: &types::Extrinsic) -> Weight {
+        EXTRINSIC_BASE_WEIGHT.saturating_add(extrinsic.call.weight())
+    }
+
+    /// The fee of an extrinsic: its weight rounded up to a whole fee, plus its tip.
+    pub fn fee(extrinsic: &types::Extrinsic) -> types::Balance {
+        types::Balance::from(Self::weight(extrinsic).div_ceil(WEIGHT_PER_FEE))
+            .saturating_add(extrinsic.tip)
+    }
+
+    /// The currency the fees are paid in, only used through its `Currency` implementation, like
+    /// the currencies of the pallets.
+    fn currency(runtime: &Runtime) -> &impl Currency<types::AccountId, Balance = types::Balance> {
+        &runtime.balances
+    }
+
+    fn currency_mut(
+        runtime: &mut Runtime,
+    ) -> &mut impl Currency<types::AccountId, Balance = types::Balance> {
+        &mut runtime.balances
+    }
+}
+
+impl SignedExtension<Runtime, types::Extrinsic> for ChargeTransactionPayment {
+    /// The fee paid.
+    type Pre = types::Balance;
+
+    fn validate(runtime: &Runtime, extrinsic: &types::Extrinsic) -> TransactionValidity {
+        if Self::currency(runtime).free_balance(extrinsic.caller.clone()) < Self::fee(extrinsic) {
+            return Err("insufficient balance to pay the fee");
+        }
+        Ok(ValidTransaction {
+            priority: u64::try_from(extrinsic.tip).unwrap_or(u64::MAX),
+        })
+    }
+
+    fn pre_dispatch(
+        runtime: &mut Runtime,
+        extrinsic: &types::Extrinsic,
+    ) -> Result<types::Balance, &'static str> {
+        Self::validate(runtime, extrinsic)?;
+        let fee = Self::fee(extrinsic);
+        let currency = Self::currency_mut(runtime);
+        currency.withdraw(extrinsic.caller.clone(), fee)?;
+        currency.deposit_creating(
+            treasury::Pallet::<Runtime>::account_id(),
+            TREASURY_FEE_SHARE.mul_floor(fee),
+        )?;
+        Ok(fee)
+    }
+}
@@ -1,4 +1,37 @@
+pub mod benchmarking;
+pub mod storage;
+
+use core::fmt::Debug;
+use std::ops::AddAssign;
+
+use codec::{
+    Decode,
+    Encode,
+};
+use num::{
+    CheckedAdd,
+    CheckedSub,
+    One,
+    Zero,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    crypto::MultiSignature,
+    metadata::{
+        Field,
+        TypeInfo,
+    },
+};
+
 /// The most primitive representation of a Blockchain block.
+///
+/// A chain of blocks can be stored as the SCALE encodings of its blocks, one after the other, and
+/// imported again by a node (see `node::decode_chain`).
+#[derive(Clone, Debug, Serialize, Deserialize, Encode, Decode)]
 pub struct Block<Header, Extrinsic> {
     /// The block header contains metadata about the block.
     pub header: Header,
@@ -6,28 +39,253 @@ pub struct Block<Header, Extrinsic> {
     pub extrinsics: Vec<Extrinsic>,
 }
 
-/// We are using an extremely simplified header which only contains the current block number.
-/// On a real blockchain, you would expect to also find:
-/// - parent block hash
-/// - state root
-/// - extrinsics root
-/// - etc...
+/// The header of a block, which commits to its content and to the chain it belongs to.
+///
+/// A block is identified by the `hash` of its header. Every header links to the header of its
+/// parent block, so the hash of the best block commits to the whole chain. The roots are hex
+/// strings in JSON.
+///
+/// On a real blockchain, you would also expect to find a digest with the data of the consensus.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
 pub struct Header<BlockNumber> {
     pub block_number: BlockNumber,
+    /// The hash of the header of the parent block, or zero for the genesis block.
+    #[serde(with = "hex::serde")]
+    pub parent_hash: [u8; 32],
+    /// The state root after the execution of the block, see `storage::state_root`.
+    #[serde(with = "hex::serde")]
+    pub state_root: [u8; 32],
+    /// The root of the extrinsics of the block, see `extrinsics_root`.
+    #[serde(with = "hex::serde")]
+    pub extrinsics_root: [u8; 32],
+}
+
+impl<BlockNumber: Encode> Header<BlockNumber> {
+    /// The hash of the block, the blake2-256 hash of the SCALE encoding of its header.
+    pub fn hash(&self) -> [u8; 32] {
+        storage::blake2_256(&self.encode())
+    }
+}
+
+/// The root of the binary merkle tree whose leaves are the encoded extrinsics, in order, see
+/// `storage::merkle_root`. The root of a block without extrinsics is all zeros.
+pub fn extrinsics_root<E: Encode>(extrinsics: &[E]) -> [u8; 32] {
+    storage::merkle_root(
+        extrinsics
+            .iter()
+            .map(|extrinsic| storage::leaf_hash(&extrinsic.encode()))
+            .collect(),
+    )
 }
 
 /// This is an "extrinsic": literally an external message from outside of the blockchain.
 /// This simplified version of an extrinsic tells us who is making the call, and which call they are
 /// making.
-pub struct Extrinsic<Caller, Call> {
+///
+/// It also carries the data checked by the `SignedExtension`s of the runtime before the call is
+/// dispatched:
+/// - the `signature` of the `signing_payload` by the caller, when the caller is an account backed
+///   by a key (see the `crypto` module).
+/// - the `nonce` of the caller, which orders the extrinsics of an account and prevents replaying
+///   them.
+/// - an optional `tip` the caller pays, on top of the fee, to get its extrinsic included sooner.
+/// - the `era` during which the extrinsic can be included, forever by default.
+/// - the `spec_version` of the runtime and the `genesis_hash` of the chain the extrinsic was built
+///   for, so it is not executed by another runtime or on another chain.
+///
+/// Its SCALE encoding is the "envelope" of the extrinsic, described by `envelope_metadata`,
+/// followed by the encoded `call`. In JSON, the genesis hash is a hex string.
+#[derive(Clone, Debug, Serialize, Deserialize, Encode, Decode)]
+pub struct Extrinsic<Caller, Call, Nonce, Balance, BlockNumber> {
     pub caller: Caller,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<MultiSignature>,
+    pub nonce: Nonce,
+    #[serde(default)]
+    pub tip: Balance,
+    #[serde(default)]
+    pub era: Era<BlockNumber>,
+    pub spec_version: u32,
+    #[serde(with = "hex::serde")]
+    pub genesis_hash: [u8; 32],
     pub call: Call,
 }
 
+impl<Caller, Call, Nonce, Balance, BlockNumber> Extrinsic<Caller, Call, Nonce, Balance, BlockNumber>
+where
+    Caller: Encode,
+    Call: Encode,
+    Nonce: Encode,
+    Balance: Encode,
+    BlockNumber: Encode,
+{
+    /// The message signed by the caller: the SCALE encoding of all the fields of the extrinsic but
+    /// the signature.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        (
+            &self.caller,
+            &self.nonce,
+            &self.tip,
+            &self.era,
+            self.spec_version,
+            self.genesis_hash,
+            &self.call,
+        )
+            .encode()
+    }
+}
+
+impl<Caller, Call, Nonce, Balance, BlockNumber> Extrinsic<Caller, Call, Nonce, Balance, BlockNumber>
+where
+    Caller: TypeInfo,
+    Nonce: TypeInfo,
+    Balance: TypeInfo,
+    BlockNumber: TypeInfo,
+{
+    /// The fields encoded before the call, for the runtime metadata.
+    pub fn envelope_metadata() -> Vec<Field> {
+        let field = |name: &str, ty| Field {
+            name: Some(name.to_string()),
+            ty,
+        };
+        vec![
+            field("caller", Caller::type_info()),
+            field("signature", Option::<MultiSignature>::type_info()),
+            field("nonce", Nonce::type_info()),
+            field("tip", Balance::type_info()),
+            field("era", Era::<BlockNumber>::type_info()),
+            field("spec_version", u32::type_info()),
+            field("genesis_hash", <[u8; 32]>::type_info()),
+        ]
+    }
+}
+
+/// The blocks in which an extrinsic can be included.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Encode,
+    Decode,
+    macros::TypeInfo,
+)]
+pub enum Era<BlockNumber> {
+    /// The extrinsic can be included in any block.
+    #[default]
+    Immortal,
+    /// The extrinsic can only be included in the `period` blocks starting at the block `birth`.
+    Mortal {
+        birth: BlockNumber,
+        period: BlockNumber,
+    },
+}
+
 /// The Result type for our runtime. When everything completes successfully, we return `Ok(())`,
 /// otherwise return a static error message.
 pub type DispatchResult = Result<(), &'static str>;
 
+/// The error of a call blocked by a `CallFilter`, either the base call filter of the runtime or a
+/// filter of its origin.
+pub const CALL_FILTERED: &str = "the call is filtered";
+
+/// The error of a pallet using a pallet it borrows while the runtime did not lend it.
+pub const NOT_LENT: &str = "the pallet was not lent by the runtime";
+
+/// The error of a call forwarded by more than `MAX_CALL_DEPTH` calls, like a proxy call of a proxy
+/// call of a proxy call.
+pub const CALL_TOO_DEEP: &str = "the call is nested too deeply";
+
+/// How many calls can forward a call, one after the other. Every forwarded call is dispatched by
+/// the call which forwarded it, so the runtime caps them to bound its stack.
+pub const MAX_CALL_DEPTH: usize = 8;
+
+/// How deeply the values decoded from untrusted bytes, like extrinsics and blocks, can be nested. A
+/// call can hold other calls, like the call of a proxy, so decoding without a limit overflows the
+/// stack on a deeply nested encoding. Decode them with `codec::DecodeLimit`.
+pub const MAX_DECODE_DEPTH: u32 = 64;
+
+/// Why an extrinsic of a block failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ExtrinsicError {
+    /// The extrinsic failed the checks of the runtime, like its nonce or its fee, and was not
+    /// dispatched.
+    Invalid(&'static str),
+    /// The call of the extrinsic failed, and its changes were rolled back.
+    Dispatch(&'static str),
+    /// The call of the extrinsic, or a call it forwarded, was blocked by a call filter, with the
+    /// error `CALL_FILTERED`. Its changes were rolled back.
+    Filtered(&'static str),
+}
+
+impl ExtrinsicError {
+    /// The error message, whatever the kind of error.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::Invalid(message) | Self::Dispatch(message) | Self::Filtered(message) => message,
+        }
+    }
+}
+
+/// The outcome of an extrinsic executed in a block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ExtrinsicReport<Caller, Balance, Event> {
+    /// The index of the extrinsic in the block.
+    pub index: u32,
+    pub caller: Caller,
+    pub result: Result<(), ExtrinsicError>,
+    /// The weight of the call, or zero if it was not dispatched.
+    pub weight: Weight,
+    /// The fee paid by the caller, including the tip.
+    pub fee: Balance,
+    /// The events recorded for the extrinsic, ending with `ExtrinsicSuccess` or `ExtrinsicFailed`.
+    pub events: Vec<Event>,
+}
+
+/// The outcome of a call dispatched ahead of its turn by `execute_block_parallel`, on a copy of
+/// the state. It stands for the real dispatch if the values it read are unchanged by then.
+#[derive(Clone, Debug)]
+pub struct Speculation<Event> {
+    /// The values read by the dispatch, for each storage item of the runtime.
+    pub reads: Vec<storage::Reads>,
+    /// The changes made by the dispatch, for each storage item of the runtime.
+    pub changes: Vec<storage::Changes>,
+    pub result: DispatchResult,
+    pub events: Vec<Event>,
+    /// The call blocked by a call filter, if any.
+    pub filtered: Option<(&'static str, &'static str)>,
+}
+
+/// The outcome of executing a block, returned by `execute_block`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BlockExecutionReport<Caller, Balance, Event> {
+    /// The outcome of every extrinsic, in the order of the block.
+    pub extrinsics: Vec<ExtrinsicReport<Caller, Balance, Event>>,
+    /// The state root after the block.
+    #[serde(with = "hex::serde")]
+    pub state_root: [u8; 32],
+}
+
+impl<Caller, Balance, Event> BlockExecutionReport<Caller, Balance, Event> {
+    /// The extrinsics which failed, along with their errors.
+    pub fn failed(
+        &self,
+    ) -> impl Iterator<Item = (&ExtrinsicReport<Caller, Balance, Event>, ExtrinsicError)> {
+        self.extrinsics
+            .iter()
+            .filter_map(|extrinsic| {
+                extrinsic
+                    .result
+                    .err()
+                    .map(|error| (extrinsic, error))
+            })
+    }
+}
+
 /// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
 /// function call.
 pub trait Dispatch {
@@ -40,3 +298,666 @@ pub trait Dispatch {
     /// based on the outcome of that function call.
     fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
 }
+
+/// The cost of executing a call, in abstract units of computation.
+pub type Weight = u64;
+
+/// The maximum total weight of the extrinsics of a block, two seconds of execution. Blocks over it
+/// are invalid, and the block builder stops including extrinsics before reaching it.
+pub const MAX_BLOCK_WEIGHT: Weight = 2_000_000_000;
+
+/// A trait for calls which know their own weight, given by the `#[weight(..)]` attribute of the
+/// callable functions.
+pub trait GetWeight {
+    fn weight(&self) -> Weight;
+}
+
+/// A type without any value. It is used by the hidden variant of the generated `Call` enums, which
+/// only holds their generic parameters and can never be built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Never {}
+
+/// A pallet of the runtime lent to another pallet while the runtime dispatches its calls, like the
+/// balances pallet holding the deposits of the calls of the nfts pallet.
+///
+/// The runtime lends the pallet given by the `#[lend(..)]` attribute of the borrowing pallet (see
+/// `macros::runtime`) right before dispatching a call, and takes it back right after.
+#[derive(Debug)]
+pub struct Lent<P>(Option<P>);
+
+impl<P> Default for Lent<P> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<P> Lent<P> {
+    /// Lend `pallet` until it is taken back.
+    pub fn lend(&mut self, pallet: P) {
+        self.0 = Some(pallet);
+    }
+
+    /// Take back the lent pallet.
+    pub fn take(&mut self) -> Option<P> {
+        self.0.take()
+    }
+
+    /// The lent pallet. It is only available while a call is dispatched by a runtime lending it.
+    pub fn get_mut(&mut self) -> Result<&mut P, &'static str> {
+        self.0
+            .as_mut()
+            .ok_or(NOT_LENT)
+    }
+}
+
+/// A pallet whose calls need the pallet `P` of the runtime, which is lent to it with `Lent`.
+///
+/// `Role` tells apart the pallets given by the config of the borrower, like the `T::Currency` lent
+/// `AsCurrency`, which the compiler can't otherwise tell apart from its other lent pallets.
+pub trait Borrower<P, Role = ()> {
+    fn lent(&mut self) -> &mut Lent<P>;
+}
+
+/// The role of a pallet lent as the `Currency` of its borrower.
+pub struct AsCurrency;
+
+/// The error of a `Currency` whose balance is too low for an operation.
+pub const INSUFFICIENT_BALANCE: &str = "Insufficient balance";
+
+/// The error of a `ReservableCurrency` whose reserved balance is too low for an operation.
+pub const INSUFFICIENT_RESERVED_BALANCE: &str = "Insufficient reserved balance";
+
+/// The error of a `Currency` whose balance would overflow.
+pub const OVERFLOW: &str = "Overflow when adding balance";
+
+/// The funds of the accounts, like the native token of the balances pallet.
+///
+/// The pallets holding or moving funds get a currency from their config, with
+/// `type Currency: Currency<Self::AccountId>`, whose `Balance` they use, instead of depending on
+/// the balances pallet, so a runtime can give them any of its currencies and their tests a mock
+/// one. The errors of its operations are the ones above, which the pallets list among theirs.
+/// Doing nothing, like withdrawing a zero amount, does not emit any event.
+pub trait Currency<AccountId> {
+    type Balance: Zero
+        + One
+        + CheckedAdd
+        + CheckedSub
+        + AddAssign
+        + Copy
+        + Eq
+        + Debug
+        + Encode
+        + Decode;
+
+    /// The balance `who` can spend.
+    fn free_balance(&self, who: AccountId) -> Self::Balance;
+
+    /// The sum of the balances of all accounts.
+    fn total_issuance(&self) -> Result<Self::Balance, &'static str>;
+
+    /// Move `amount` from the balance of `from` to the one of `to`.
+    fn transfer(&mut self, from: AccountId, to: AccountId, amount: Self::Balance)
+        -> DispatchResult;
+
+    /// Mint `amount` and add it to the balance of `who`, creating its account if needed.
+    fn deposit_creating(&mut self, who: AccountId, amount: Self::Balance) -> DispatchResult;
+
+    /// Withdraw `amount` from the balance of `who` and burn it, failing if it is too low.
+    fn withdraw(&mut self, who: AccountId, amount: Self::Balance) -> DispatchResult;
+
+    /// Burn up to `amount` from the balance of `who`, returning the amount which was not burned
+    /// because the balance was too low.
+    fn slash(&mut self, who: AccountId, amount: Self::Balance) -> Self::Balance;
+}
+
+/// A `Currency` whose funds can be reserved, like the deposits held by a pallet.
+pub trait ReservableCurrency<AccountId>: Currency<AccountId> {
+    /// The balance of `who` which can't be spent until it is unreserved.
+    fn reserved_balance(&self, who: AccountId) -> Self::Balance;
+
+    /// Move `amount` from the balance of `who` to its reserved balance.
+    fn reserve(&mut self, who: AccountId, amount: Self::Balance) -> DispatchResult;
+
+    /// Move `amount` from the reserved balance of `who` back to its balance.
+    fn unreserve(&mut self, who: AccountId, amount: Self::Balance) -> DispatchResult;
+
+    /// Move `amount` from the reserved balance of `from` to the balance of `to`.
+    fn repatriate_reserved(
+        &mut self,
+        from: AccountId,
+        to: AccountId,
+        amount: Self::Balance,
+    ) -> DispatchResult;
+
+    /// Burn up to `amount` from the reserved balance of `who`, returning the amount which was not
+    /// burned because the reserved balance was too low.
+    fn slash_reserved(&mut self, who: AccountId, amount: Self::Balance) -> Self::Balance;
+}
+
+/// A filter of the calls of the runtime, like the calls a proxy is allowed to make.
+///
+/// The runtime implements it for itself as its base call filter, which every call dispatched by the
+/// runtime must pass. The other filters come from the origin of a call, like the type of the proxy
+/// which made it, and only apply to this call and the calls it forwards.
+pub trait CallFilter<Call> {
+    fn allows(&self, call: &Call) -> bool;
+}
+
+/// The name of a call, which is the name of its function in the pallet. It is implemented by the
+/// `Call` enums generated by `macros::call`, and by `RuntimeCall`.
+pub trait GetCallName {
+    fn call_name(&self) -> &'static str;
+}
+
+/// A call made by a pallet on behalf of `caller`, which the runtime dispatches after the call of
+/// the pallet if `filter` allows it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForwardedCall<Caller, Call, Filter> {
+    pub caller: Caller,
+    pub call: Call,
+    pub filter: Filter,
+}
+
+/// A pallet whose calls make other calls of the runtime, like the proxy pallet. Only the runtime
+/// can dispatch them, so the pallet forwards them to the runtime, which takes them with
+/// `take_forwarded` after every call of the pallet (see `#[forward_calls]` in `macros::runtime`).
+///
+/// A forwarded call must be allowed by its filter, and by the filters of all the calls it was
+/// forwarded from.
+pub trait ForwardCalls<Caller, Call> {
+    type Filter: CallFilter<Call>;
+
+    fn take_forwarded(&mut self) -> Option<ForwardedCall<Caller, Call, Self::Filter>>;
+}
+
+/// The id of a pallet owning an account, like the treasury. Its account is derived from the id, so
+/// it has no secret key and only the pallet can move its funds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PalletId(pub [u8; 8]);
+
+impl PalletId {
+    /// The public key of the account of the pallet, which is the hash of its id.
+    pub fn public_key(&self) -> [u8; 32] {
+        storage::blake2_256(&[b"modl".as_slice(), &self.0].concat())
+    }
+}
+
+/// A fraction of an amount, in percent, like the share of the fees given to the treasury.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct Percent(u8);
+
+impl Percent {
+    /// The fraction `percent`%, saturating at 100%.
+    pub const fn from_percent(percent: u8) -> Self {
+        match percent {
+            0..=100 => Self(percent),
+            _ => Self(100),
+        }
+    }
+
+    /// This fraction of `amount`, rounded down. It doesn't overflow if `N` can hold 10 000.
+    pub fn mul_floor<N: num::Integer + From<u8> + Copy>(self, amount: N) -> N {
+        let (quotient, remainder) = amount.div_rem(&N::from(100));
+        let percent = N::from(self.0);
+        quotient * percent + remainder * percent / N::from(100)
+    }
+}
+
+/// Markers for the instances of a pallet which is included more than once in the runtime, next to
+/// its default instance `()`.
+#[derive(Debug)]
+pub struct Instance1;
+#[derive(Debug)]
+pub struct Instance2;
+
+/// Information about a valid transaction, used by the transaction queue to order it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ValidTransaction {
+    /// Transactions with a higher priority are included first.
+    pub priority: u64,
+}
+
+impl ValidTransaction {
+    /// Combine the results of two checks of the same transaction, adding up their priorities.
+    pub fn combine_with(self, other: Self) -> Self {
+        Self {
+            priority: self
+                .priority
+                .saturating_add(other.priority),
+        }
+    }
+}
+
+/// The Result of validating a transaction. Invalid transactions return a static error message.
+pub type TransactionValidity = Result<ValidTransaction, &'static str>;
+
+/// A trait implemented by the runtime to check extrinsics before they are dispatched.
+///
+/// The same checks are used by the transaction queue when accepting an extrinsic, by the block
+/// builder when selecting the extrinsics of a new block, and by `execute_block` right before
+/// dispatching them, so that all agree on what is valid. The runtime usually implements it with a
+/// tuple of `SignedExtension`s.
+pub trait ValidateTransaction {
+    /// The extrinsic type of the runtime.
+    type Extrinsic;
+    /// Information passed from `pre_dispatch` to `post_dispatch`.
+    type Pre;
+    /// The type of the fees paid by the extrinsics.
+    type Fee: Default;
+
+    /// Check whether the extrinsic could be included in the next block, given the current state.
+    ///
+    /// This does not modify the state. Extrinsics which are not valid yet but could become valid,
+    /// like ones using a future nonce of the caller, are accepted.
+    fn validate_transaction(&self, extrinsic: &Self::Extrinsic) -> TransactionValidity;
+
+    /// Check that the extrinsic can be dispatched right now, and apply its side effects like
+    /// incrementing the nonce of the caller and charging its fee.
+    fn pre_dispatch(&mut self, extrinsic: &Self::Extrinsic) -> Result<Self::Pre, &'static str>;
+
+    /// Called after the extrinsic was dispatched, with the result of its call. An error makes the
+    /// whole block invalid.
+    fn post_dispatch(&mut self, pre: Self::Pre, result: &DispatchResult) -> DispatchResult;
+
+    /// The fee charged by `pre_dispatch`, for the report of the block.
+    fn fee_paid(pre: &Self::Pre) -> Self::Fee;
+
+    /// The weight of the extrinsic, counted against the `MAX_BLOCK_WEIGHT` of its block.
+    fn weight(extrinsic: &Self::Extrinsic) -> Weight;
+}
+
+/// A single check of the extrinsics of the runtime `R`, like checking the nonce of the caller or
+/// charging a fee. See `ValidateTransaction` for when each step runs.
+///
+/// Checks are combined by implementing this trait for tuples of `SignedExtension`s, which run them
+/// in order. Each of them can reject the extrinsic, and the priorities they give are added up.
+pub trait SignedExtension<R, Extrinsic> {
+    /// Information passed from `pre_dispatch` to `post_dispatch`.
+    type Pre;
+
+    /// Check the extrinsic against the current state, without modifying it.
+    fn validate(_runtime: &R, _extrinsic: &Extrinsic) -> TransactionValidity {
+        Ok(ValidTransaction::default())
+    }
+
+    /// Check the extrinsic right before it is dispatched, and apply the side effects of the check.
+    fn pre_dispatch(runtime: &mut R, extrinsic: &Extrinsic) -> Result<Self::Pre, &'static str>;
+
+    /// Run after the extrinsic was dispatched, whether its call succeeded or not.
+    fn post_dispatch(
+        _runtime: &mut R,
+        _pre: Self::Pre,
+        _result: &DispatchResult,
+    ) -> DispatchResult {
+        Ok(())
+    }
+}
+
+macro_rules! impl_signed_extension_for_tuples {
+    ($($extension:ident $index:tt),+) => {
+        impl<R, X, $($extension: SignedExtension<R, X>),+> SignedExtension<R, X> for ($($extension,)+) {
+            type Pre = ($($extension::Pre,)+);
+
+            fn validate(runtime: &R, extrinsic: &X) -> TransactionValidity {
+                let valid = ValidTransaction::default();
+                $(let valid = valid.combine_with($extension::validate(runtime, extrinsic)?);)+
+                Ok(valid)
+            }
+
+            fn pre_dispatch(runtime: &mut R, extrinsic: &X) -> Result<Self::Pre, &'static str> {
+                Ok(($($extension::pre_dispatch(runtime, extrinsic)?,)+))
+            }
+
+            fn post_dispatch(runtime: &mut R, pre: Self::Pre, result: &DispatchResult) -> DispatchResult {
+                $($extension::post_dispatch(runtime, pre.$index, result)?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_signed_extension_for_tuples!(A 0);
+impl_signed_extension_for_tuples!(A 0, B 1);
+impl_signed_extension_for_tuples!(A 0, B 1, C 2);
+impl_signed_extension_for_tuples!(A 0, B 1, C 2, D 3);
+impl_signed_extension_for_tuples!(A 0, B 1, C 2, D 3, E 4);
+impl_signed_extension_for_tuples!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// The version of the runtime.
+///
+/// `spec_version` must be bumped whenever the runtime logic changes. When the runtime finds state
+/// last upgraded by another `spec_version`, it runs the `OnRuntimeUpgrade` hooks of all pallets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct RuntimeVersion {
+    /// The name of the runtime, which never changes.
+    pub spec_name: &'static str,
+    /// The version of the runtime logic.
+    pub spec_version: u32,
+}
+
+/// The version of the storage layout of a pallet.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Encode,
+    Decode,
+    macros::TypeInfo,
+)]
+pub struct StorageVersion(pub u16);
+
+/// A trait for pallets which keep track of the version of their storage layout.
+pub trait GetStorageVersion {
+    /// The storage version expected by the current code of the pallet.
+    const CURRENT_STORAGE_VERSION: StorageVersion;
+
+    /// The storage version of the data currently stored by the pallet.
+    fn on_chain_storage_version(&self) -> StorageVersion;
+
+    /// Record the storage version of the data stored by the pallet, after a migration.
+    fn set_on_chain_storage_version(&mut self, version: StorageVersion);
+}
+
+/// A hook called on every pallet when the runtime is upgraded, so it can migrate its storage.
+pub trait OnRuntimeUpgrade {
+    /// Migrate the storage of the pallet. An error aborts the upgrade.
+    fn on_runtime_upgrade(&mut self) -> DispatchResult {
+        Ok(())
+    }
+
+    /// Called instead when the runtime starts a new chain, whose storage already has the current
+    /// layout, like to record the current storage version of the pallet.
+    fn on_genesis(&mut self) {}
+}
+
+/// The hooks called on every pallet by the runtime around the extrinsics of every block, like to
+/// make the payouts of a pallet at regular intervals. An error makes the block invalid.
+pub trait Hooks<BlockNumber> {
+    /// Run at the start of the block `n`, before its extrinsics.
+    fn on_initialize(&mut self, _n: BlockNumber) -> DispatchResult {
+        Ok(())
+    }
+
+    /// Run at the end of the block `n`, after its extrinsics.
+    fn on_finalize(&mut self, _n: BlockNumber) -> DispatchResult {
+        Ok(())
+    }
+}
+
+/// A migration of the storage of the pallet `P` from one storage version to the next.
+pub trait Migration<P> {
+    /// The storage version this migration applies to.
+    const FROM: StorageVersion;
+    /// The storage version after this migration.
+    const TO: StorageVersion;
+
+    /// Information collected before the migration, to be checked after it.
+    type State;
+
+    /// Check the storage before the migration, and collect the information needed by
+    /// `post_upgrade`.
+    fn pre_upgrade(pallet: &P) -> Result<Self::State, &'static str>;
+
+    /// Migrate the storage of the pallet.
+    fn migrate(pallet: &mut P);
+
+    /// Check the storage after the migration.
+    fn post_upgrade(pallet: &P, state: Self::State) -> DispatchResult;
+}
+
+/// An ordered list of migrations, implemented for tuples of `Migration`s.
+pub trait Migrations<P> {
+    /// Run, in order, every migration which applies to the current storage version of the pallet.
+    fn run(pallet: &mut P) -> DispatchResult;
+}
+
+impl<P> Migrations<P> for () {
+    fn run(_pallet: &mut P) -> DispatchResult {
+        Ok(())
+    }
+}
+
+macro_rules! impl_migrations_for_tuples {
+    ($($migration:ident),+) => {
+        impl<P: GetStorageVersion, $($migration: Migration<P>),+> Migrations<P> for ($($migration,)+) {
+            fn run(pallet: &mut P) -> DispatchResult {
+                $(run_migration::<P, $migration>(pallet)?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_migrations_for_tuples!(A);
+impl_migrations_for_tuples!(A, B);
+impl_migrations_for_tuples!(A, B, C);
+impl_migrations_for_tuples!(A, B, C, D);
+
+/// Run a single migration, with its checks, if it applies to the current storage version.
+fn run_migration<P: GetStorageVersion, M: Migration<P>>(pallet: &mut P) -> DispatchResult {
+    if pallet.on_chain_storage_version() != M::FROM {
+        return Ok(());
+    }
+    let state = M::pre_upgrade(pallet)?;
+    M::migrate(pallet);
+    pallet.set_on_chain_storage_version(M::TO);
+    M::post_upgrade(pallet, state)
+}
+
+/// Bring the storage of a pallet to its current storage version by running the needed `Migrations`.
+///
+/// This is meant to be called from `OnRuntimeUpgrade::on_runtime_upgrade`.
+pub fn migrate<P: GetStorageVersion, M: Migrations<P>>(pallet: &mut P) -> DispatchResult {
+    let on_chain = pallet.on_chain_storage_version();
+    if on_chain == P::CURRENT_STORAGE_VERSION {
+        return Ok(());
+    }
+    if on_chain > P::CURRENT_STORAGE_VERSION {
+        return Err("storage version is newer than the code");
+    }
+
+    M::run(pallet)?;
+    if pallet.on_chain_storage_version() != P::CURRENT_STORAGE_VERSION {
+        return Err("no migration to the current storage version");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use codec::Encode;
+
+    use super::{
+        Dispatch,
+        GetWeight,
+        Percent,
+    };
+    use crate::metadata::variants;
+
+    mod counter {
+        use crate::support::DispatchResult;
+
+        pub trait Config {
+            type AccountId;
+            type Step: Clone + core::fmt::Debug + Eq;
+        }
+
+        pub struct Counter<C: Config> {
+            pub value: u64,
+            pub last_caller: Option<C::AccountId>,
+        }
+
+        // The calls are split in two blocks, with a where clause and a generic not named `T`.
+        #[macros::call]
+        mod calls {
+            use super::*;
+
+            #[call]
+            impl<C: Config> Counter<C>
+            where
+                C::Step: Into<u64>,
+            {
+                #[call_index(3)]
+                #[weight(10 * *times as u64)]
+                pub fn increment(
+                    &mut self,
+                    caller: C::AccountId,
+                    times: u8,
+                    step: C::Step,
+                ) -> DispatchResult {
+                    self.value += times as u64 * step.into();
+                    self.last_caller = Some(caller);
+                    Ok(())
+                }
+            }
+
+            #[call]
+            #[errors("nothing to reset")]
+            impl<C: Config> Counter<C>
+            where
+                C::Step: Into<u64>,
+            {
+                pub fn reset(&mut self, _caller: C::AccountId) -> DispatchResult {
+                    if self.value == 0 {
+                        return Err("nothing to reset");
+                    }
+                    self.value = 0;
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    struct TestConfig;
+    impl counter::Config for TestConfig {
+        type AccountId = String;
+        type Step = u32;
+    }
+
+    #[test]
+    fn percent_of_amounts() {
+        let half = Percent::from_percent(50);
+        assert_eq!(half.mul_floor(7u32), 3);
+        assert_eq!(half.mul_floor(u128::MAX), u128::MAX / 2);
+        assert_eq!(Percent::from_percent(33).mul_floor(1_000u64), 330);
+        assert_eq!(Percent::from_percent(150), Percent::from_percent(100));
+        assert_eq!(Percent::from_percent(100).mul_floor(u16::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn call_macro_merges_call_blocks() {
+        let mut counter = counter::Counter::<TestConfig> {
+            value: 0,
+            last_caller: None,
+        };
+        let increment = counter::Call::<TestConfig>::increment { times: 3, step: 2 };
+        assert_eq!(increment.weight(), 30);
+        assert_eq!(counter::Call::<TestConfig>::reset {}.weight(), 0);
+
+        counter
+            .dispatch("alice".to_string(), increment.clone())
+            .unwrap();
+        assert_eq!(counter.value, 6);
+        assert_eq!(counter.last_caller, Some("alice".to_string()));
+        counter
+            .dispatch("alice".to_string(), counter::Call::reset {})
+            .unwrap();
+        assert_eq!(
+            counter.dispatch("alice".to_string(), counter::Call::reset {}),
+            Err("nothing to reset")
+        );
+
+        // The call index is used in the encoding and in the metadata.
+        assert_eq!(increment.encode(), vec![3, 3, 2, 0, 0, 0]);
+        let indices = variants::<counter::Call<TestConfig>>()
+            .iter()
+            .map(|variant| (variant.name.clone(), variant.index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            indices,
+            vec![("increment".to_string(), 3), ("reset".to_string(), 1)]
+        );
+        assert_eq!(counter::Call::<TestConfig>::ERRORS, &["nothing to reset"]);
+    }
+
+    /// Checks of a runtime which only records the steps they run.
+    struct First;
+    struct Second;
+
+    impl super::SignedExtension<Vec<String>, u64> for First {
+        type Pre = &'static str;
+
+        fn validate(_log: &Vec<String>, extrinsic: &u64) -> super::TransactionValidity {
+            Ok(super::ValidTransaction {
+                priority: *extrinsic,
+            })
+        }
+
+        fn pre_dispatch(
+            log: &mut Vec<String>,
+            _extrinsic: &u64,
+        ) -> Result<Self::Pre, &'static str> {
+            log.push("first pre_dispatch".to_string());
+            Ok("first")
+        }
+
+        fn post_dispatch(
+            log: &mut Vec<String>,
+            pre: Self::Pre,
+            result: &super::DispatchResult,
+        ) -> super::DispatchResult {
+            log.push(format!("{pre} post_dispatch {result:?}"));
+            Ok(())
+        }
+    }
+
+    impl super::SignedExtension<Vec<String>, u64> for Second {
+        type Pre = ();
+
+        fn validate(_log: &Vec<String>, extrinsic: &u64) -> super::TransactionValidity {
+            if *extrinsic == 0 {
+                return Err("zero");
+            }
+            Ok(super::ValidTransaction { priority: 1 })
+        }
+
+        fn pre_dispatch(log: &mut Vec<String>, extrinsic: &u64) -> Result<(), &'static str> {
+            Self::validate(log, extrinsic)?;
+            log.push("second pre_dispatch".to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn signed_extensions_run_in_order() {
+        use super::SignedExtension;
+
+        type Extra = (First, Second);
+        let mut log = vec![];
+        assert_eq!(
+            Extra::validate(&log, &5),
+            Ok(super::ValidTransaction { priority: 6 })
+        );
+        assert_eq!(Extra::validate(&log, &0), Err("zero"));
+
+        assert_eq!(Extra::pre_dispatch(&mut log, &0), Err("zero"));
+        let pre = Extra::pre_dispatch(&mut log, &5).unwrap();
+        Extra::post_dispatch(&mut log, pre, &Err("failed")).unwrap();
+        assert_eq!(
+            log,
+            vec![
+                "first pre_dispatch",
+                "first pre_dispatch",
+                "second pre_dispatch",
+                "first post_dispatch Err(\"failed\")"
+            ]
+        );
+    }
+}
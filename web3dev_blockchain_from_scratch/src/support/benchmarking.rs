@@ -0,0 +1,392 @@
+//! Benchmarks measuring the weights of the callable functions of the pallets.
+//!
+//! A pallet declares, by implementing `Benchmarks`, how to set up the worst case of each of its
+//! calls, which can depend on some components like the length of an argument. `run` measures every
+//! call repeatedly over the range of each of its components, and fits a linear model
+//! `base + slope * component` to the measurements. `weights_file` renders the results as the
+//! `weights` module of the pallet, whose `WeightInfo` trait gives the weights used by the
+//! `#[weight(..)]` attributes of its calls.
+//!
+//! The unit of weight is one nanosecond of execution on the machine running the benchmarks. They
+//! are run, and the weights files regenerated, with `cargo run --release --bin benchmark`.
+
+use std::{
+    fmt::Write,
+    time::Instant,
+};
+
+use super::{
+    Dispatch,
+    DispatchResult,
+    Weight,
+};
+
+/// A parameter of a benchmark, like the length of an argument, which the weight of the call
+/// depends on linearly.
+#[derive(Clone, Copy, Debug)]
+pub struct Component {
+    /// The name of the parameter of the generated weight function.
+    pub name: &'static str,
+    pub min: u32,
+    pub max: u32,
+}
+
+/// Build the pallet in which a call is measured, along with its caller and the call itself, given
+/// the value of every component.
+pub type Setup<P> = fn(&[u32]) -> (P, <P as Dispatch>::Caller, <P as Dispatch>::Call);
+
+/// The benchmark of a callable function.
+pub struct Benchmark<P: Dispatch> {
+    /// The name of the generated weight function.
+    pub name: &'static str,
+    pub components: Vec<Component>,
+    pub setup: Setup<P>,
+    /// Check that the call did what was expected, given the value of every component.
+    pub verify: fn(&P, &[u32]) -> DispatchResult,
+}
+
+/// The benchmarks of the callable functions of a pallet.
+pub trait Benchmarks: Dispatch + Sized {
+    /// What the benchmarks of the pallet set it up from, like the number of items it stores.
+    type Setup;
+
+    /// The pallet set up for a benchmark, shared by the `setup` of its benchmarks.
+    fn setup(setup: Self::Setup) -> Self;
+
+    fn benchmarks() -> Vec<Benchmark<Self>>;
+}
+
+/// The error of a benchmark whose call did not do what was expected.
+pub const NO_EFFECT: &str = "the call had no effect";
+
+/// Check, in the `verify` of a benchmark, that the call did what was expected.
+pub fn ensure(condition: bool) -> DispatchResult {
+    match condition {
+        true => Ok(()),
+        false => Err(NO_EFFECT),
+    }
+}
+
+/// How thoroughly the benchmarks are run.
+#[derive(Clone, Copy, Debug)]
+pub struct BenchmarkConfig {
+    /// The number of values measured over the range of every component.
+    pub steps: u32,
+    /// The number of times every value is measured. The median measurement is kept.
+    pub repeat: u32,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            steps: 10,
+            repeat: 20,
+        }
+    }
+}
+
+/// The weight of a call, fitted from its measurements: `base` plus the slope of every component
+/// times its value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchmarkResult {
+    pub name: &'static str,
+    pub base: Weight,
+    pub slopes: Vec<(&'static str, Weight)>,
+}
+
+impl BenchmarkResult {
+    /// The weight of the call for the given value of every component.
+    pub fn weight(&self, components: &[u32]) -> Weight {
+        self.slopes
+            .iter()
+            .zip(components)
+            .fold(self.base, |weight, ((_, slope), value)| {
+                weight.saturating_add(slope.saturating_mul(Weight::from(*value)))
+            })
+    }
+}
+
+/// Run all the benchmarks of the pallet `P`.
+///
+/// Every component is measured over its range while the other components are at their minimum.
+/// The weights are rounded up, so they never underestimate the measurements.
+pub fn run<P: Benchmarks>(config: &BenchmarkConfig) -> Result<Vec<BenchmarkResult>, String> {
+    P::benchmarks()
+        .iter()
+        .map(|benchmark| run_benchmark(benchmark, config))
+        .collect()
+}
+
+fn run_benchmark<P: Dispatch>(
+    benchmark: &Benchmark<P>,
+    config: &BenchmarkConfig,
+) -> Result<BenchmarkResult, String> {
+    let minimum = benchmark
+        .components
+        .iter()
+        .map(|component| component.min)
+        .collect::<Vec<_>>();
+
+    let mut slopes = Vec::new();
+    for (i, component) in benchmark
+        .components
+        .iter()
+        .enumerate()
+    {
+        let mut points = Vec::new();
+        for value in steps(component, config.steps) {
+            let mut values = minimum.clone();
+            values[i] = value;
+            points.push((
+                f64::from(value),
+                measure(benchmark, &values, config.repeat)?,
+            ));
+        }
+        let (_, slope) = linear_fit(&points);
+        slopes.push((component.name, slope.max(0.0)));
+    }
+
+    // The base weight is what remains of the measurement at the minimum of every component.
+    let at_minimum = measure(benchmark, &minimum, config.repeat)?;
+    let base = slopes
+        .iter()
+        .zip(&minimum)
+        .fold(at_minimum, |base, ((_, slope), min)| {
+            base - slope * f64::from(*min)
+        });
+
+    Ok(BenchmarkResult {
+        name: benchmark.name,
+        base: round_up(base),
+        slopes: slopes
+            .into_iter()
+            .map(|(name, slope)| (name, round_up(slope)))
+            .collect(),
+    })
+}
+
+/// The values measured over the range of a component, evenly spaced.
+fn steps(component: &Component, steps: u32) -> Vec<u32> {
+    let steps = steps.max(2);
+    let range = u64::from(component.max - component.min);
+    let mut values = (0..steps)
+        .map(|step| {
+            let offset = range * u64::from(step) / u64::from(steps - 1);
+            component.min + offset as u32
+        })
+        .collect::<Vec<_>>();
+    values.dedup();
+    values
+}
+
+/// The median execution time of the call, in nanoseconds, over `repeat` runs.
+fn measure<P: Dispatch>(
+    benchmark: &Benchmark<P>,
+    components: &[u32],
+    repeat: u32,
+) -> Result<f64, String> {
+    let mut times = Vec::new();
+    for _ in 0..repeat.max(1) {
+        let (mut pallet, caller, call) = (benchmark.setup)(components);
+        let start = Instant::now();
+        let result = pallet.dispatch(caller, call);
+        let elapsed = start.elapsed();
+        result
+            .and_then(|()| (benchmark.verify)(&pallet, components))
+            .map_err(|e| format!("benchmark {} failed: {e}", benchmark.name))?;
+        times.push(elapsed.as_nanos() as f64);
+    }
+    times.sort_by(f64::total_cmp);
+    Ok(times[times.len() / 2])
+}
+
+/// The least squares fit of `y = intercept + slope * x` to the points, as `(intercept, slope)`.
+pub fn linear_fit(points: &[(f64, f64)]) -> (f64, f64) {
+    let n = points.len() as f64;
+    let mean_x = points
+        .iter()
+        .map(|(x, _)| x)
+        .sum::<f64>()
+        / n;
+    let mean_y = points
+        .iter()
+        .map(|(_, y)| y)
+        .sum::<f64>()
+        / n;
+    let (covariance, variance) =
+        points
+            .iter()
+            .fold((0.0, 0.0), |(covariance, variance), (x, y)| {
+                (
+                    covariance + (x - mean_x) * (y - mean_y),
+                    variance + (x - mean_x) * (x - mean_x),
+                )
+            });
+    let slope = if variance == 0.0 {
+        0.0
+    } else {
+        covariance / variance
+    };
+    (mean_y - slope * mean_x, slope)
+}
+
+fn round_up(weight: f64) -> Weight {
+    weight.max(0.0).ceil() as Weight
+}
+
+/// Render the `weights` module of a pallet from the results of its benchmarks.
+///
+/// It declares the `WeightInfo` trait with a function per benchmark, taking the value of every
+/// component, and implements it for `BenchmarkedWeights` with the results, and for `()` with zero
+/// weights, for tests.
+pub fn weights_file(pallet: &str, results: &[BenchmarkResult]) -> String {
+    let mut file = String::new();
+    let _ = writeln!(
+        file,
+        "//! The weights of the `{pallet}` pallet, measured by its benchmarks.\n\
+         //!\n\
+         //! Generated by `cargo run --release --bin benchmark`, do not edit by hand. The unit of \
+         weight is\n\
+         //! one nanosecond of execution on the machine which ran the benchmarks.\n\
+         \n\
+         use crate::support::Weight;\n\
+         \n\
+         /// The weights of the callable functions of the pallet.\n\
+         pub trait WeightInfo {{"
+    );
+    for result in results {
+        let _ = writeln!(
+            file,
+            "    fn {}({}) -> Weight;",
+            result.name,
+            parameters(result, |_| true)
+        );
+    }
+    let _ = writeln!(
+        file,
+        "}}\n\
+         \n\
+         /// The weights measured by the benchmarks.\n\
+         pub struct BenchmarkedWeights;\n\
+         \n\
+         impl WeightInfo for BenchmarkedWeights {{"
+    );
+    for (i, result) in results.iter().enumerate() {
+        let mut terms = Vec::new();
+        if result.base != 0
+            || result
+                .slopes
+                .iter()
+                .all(|(_, slope)| *slope == 0)
+        {
+            terms.push(result.base.to_string());
+        }
+        for (name, slope) in &result.slopes {
+            match slope {
+                0 => {}
+                1 => terms.push(format!("Weight::from({name})")),
+                _ => terms.push(format!("{slope} * Weight::from({name})")),
+            }
+        }
+        let _ = writeln!(
+            file,
+            "{}    fn {}({}) -> Weight {{\n        {}\n    }}",
+            if i == 0 { "" } else { "\n" },
+            result.name,
+            parameters(result, |slope| slope != 0),
+            terms.join(" + ")
+        );
+    }
+    let _ = writeln!(
+        file,
+        "}}\n\
+         \n\
+         /// No weights, for tests.\n\
+         impl WeightInfo for () {{"
+    );
+    for (i, result) in results.iter().enumerate() {
+        let _ = writeln!(
+            file,
+            "{}    fn {}({}) -> Weight {{\n        0\n    }}",
+            if i == 0 { "" } else { "\n" },
+            result.name,
+            parameters(result, |_| false)
+        );
+    }
+    file.push_str("}\n");
+    file
+}
+
+/// The parameters of the weight function of a benchmark. The ones which are not used, given the
+/// slope of their component, are prefixed with an underscore.
+fn parameters(result: &BenchmarkResult, used: impl Fn(Weight) -> bool) -> String {
+    result
+        .slopes
+        .iter()
+        .map(|(name, slope)| {
+            if used(*slope) {
+                format!("{name}: u32")
+            } else {
+                format!("_{name}: u32")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_fit_of_points() {
+        assert_eq!(
+            linear_fit(&[(0.0, 10.0), (5.0, 20.0), (10.0, 30.0)]),
+            (10.0, 2.0)
+        );
+        // A single value of the component gives a flat weight.
+        assert_eq!(linear_fit(&[(3.0, 7.0), (3.0, 9.0)]), (8.0, 0.0));
+
+        let component = Component {
+            name: "c",
+            min: 1,
+            max: 10,
+        };
+        assert_eq!(steps(&component, 4), vec![1, 4, 7, 10]);
+        assert_eq!(steps(&component, 20).len(), 10);
+    }
+
+    #[test]
+    fn render_weights_file() {
+        let results = [
+            BenchmarkResult {
+                name: "flat",
+                base: 1200,
+                slopes: vec![],
+            },
+            BenchmarkResult {
+                name: "linear",
+                base: 300,
+                slopes: vec![("c", 2), ("d", 0)],
+            },
+            BenchmarkResult {
+                name: "unit",
+                base: 10,
+                slopes: vec![("p", 1)],
+            },
+        ];
+        assert_eq!(results[1].weight(&[10, 1000]), 320);
+
+        let file = weights_file("example", &results);
+        assert!(file.starts_with("//! The weights of the `example` pallet"));
+        assert!(file.contains("    fn linear(c: u32, d: u32) -> Weight;\n"));
+        assert!(file.contains(
+            "    fn linear(c: u32, _d: u32) -> Weight {\n        300 + 2 * Weight::from(c)\n    }\n"
+        ));
+        assert!(file.contains("    fn flat() -> Weight {\n        1200\n    }\n"));
+        assert!(
+            file.contains("    fn unit(p: u32) -> Weight {\n        10 + Weight::from(p)\n    }\n")
+        );
+        assert!(file.ends_with("        0\n    }\n}\n"));
+    }
+}
@@ -0,0 +1,1248 @@
+//! Typed storage items for the pallets, declared with `#[macros::storage]`.
+//!
+//! Every storage item keeps its values SCALE encoded under keys namespaced by the pallet and the
+//! item names, so the state of all the pallets together forms a single key-value store. Its
+//! `state_root` commits to the whole state.
+//!
+//! Changes are made in the innermost open transaction, if any, and only reach the committed state
+//! when all the transactions are committed. The runtime opens a transaction around every dispatched
+//! call, so the changes of a failed call are rolled back.
+//!
+//! The values read by a call can be recorded, along with the changes it made, so it can be executed
+//! ahead of its turn and its changes reused if nothing it read was changed in the meantime.
+//!
+//! A `StorageProof` shows the value of a key, or its absence, to someone who only knows the state
+//! root, like a light client.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    marker::PhantomData,
+    sync::Arc,
+};
+
+use blake2::{
+    digest::consts::{
+        U16,
+        U32,
+    },
+    Blake2b,
+    Digest,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+
+use crate::metadata::{
+    StorageEntryType,
+    TypeInfo,
+};
+
+/// The error of a raw storage change to a key of no storage item, see `Storage::set_raw`.
+pub const UNKNOWN_KEY: &str = "key of no storage item";
+
+/// The length of the prefix of every key of a storage item.
+const PREFIX_LEN: usize = 32;
+/// The length of the hash prepended to every map key.
+const HASH_LEN: usize = 16;
+
+pub fn blake2_128(data: &[u8]) -> [u8; 16] {
+    Blake2b::<U16>::digest(data).into()
+}
+
+pub fn blake2_256(data: &[u8]) -> [u8; 32] {
+    Blake2b::<U32>::digest(data).into()
+}
+
+/// The prefix of the keys of a storage item: `blake2_128(pallet) ++ blake2_128(item)`.
+pub fn storage_prefix(pallet: &str, item: &str) -> [u8; PREFIX_LEN] {
+    let mut prefix = [0; PREFIX_LEN];
+    prefix[..16].copy_from_slice(&blake2_128(pallet.as_bytes()));
+    prefix[16..].copy_from_slice(&blake2_128(item.as_bytes()));
+    prefix
+}
+
+/// Hash a map key, keeping it in clear after the hash so it can be decoded when iterating.
+fn blake2_128_concat(key: &impl Encode) -> Vec<u8> {
+    let encoded = key.encode();
+    let mut hashed = blake2_128(&encoded).to_vec();
+    hashed.extend(encoded);
+    hashed
+}
+
+/// Decode a key hashed with `blake2_128_concat`.
+fn decode_concat<K: Decode>(input: &mut &[u8]) -> Option<K> {
+    *input = input.get(HASH_LEN..)?;
+    K::decode(input).ok()
+}
+
+/// The root of the binary merkle tree whose leaves are the `(key, value)` entries, ordered by key,
+/// see `merkle_root`.
+pub fn state_root<'a>(entries: impl IntoIterator<Item = (&'a [u8], &'a [u8])>) -> [u8; 32] {
+    let mut entries = entries
+        .into_iter()
+        .collect::<Vec<_>>();
+    entries.sort();
+    merkle_root(
+        entries
+            .into_iter()
+            .map(|(key, value)| entry_hash(key, value))
+            .collect(),
+    )
+}
+
+/// The prefix of the hashed leaves of the merkle trees, see `leaf_hash`.
+const LEAF_PREFIX: u8 = 0x00;
+/// The prefix of the hashed pairs of nodes of the merkle trees, so they can't be taken for leaves.
+const NODE_PREFIX: u8 = 0x01;
+
+/// The hash of `data` as a leaf of a merkle tree.
+pub fn leaf_hash(data: &[u8]) -> [u8; 32] {
+    blake2_256(&[&[LEAF_PREFIX], data].concat())
+}
+
+/// The root of the binary merkle tree of the given leaves, in order, hashed with `leaf_hash`.
+///
+/// A node with no sibling is moved up as is. The root commits to the number of leaves along with
+/// the top node of the tree, so the same leaves can't be proven to be part of a longer or shorter
+/// tree. The root of an empty tree is all zeros.
+pub fn merkle_root(mut layer: Vec<[u8; 32]>) -> [u8; 32] {
+    if layer.is_empty() {
+        return [0; 32];
+    }
+    let len = layer.len();
+    while layer.len() > 1 {
+        layer = parent_layer(&layer);
+    }
+    root_hash(len, &layer[0])
+}
+
+/// The root of a tree of `len` leaves whose top node is `top`.
+fn root_hash(len: usize, top: &[u8; 32]) -> [u8; 32] {
+    blake2_256(&(len as u64, top).encode())
+}
+
+/// The nodes of the merkle tree above `layer`.
+fn parent_layer(layer: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    layer
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_pair(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    blake2_256(&[[NODE_PREFIX].as_slice(), left, right].concat())
+}
+
+/// The siblings of the leaf at `index` on its way up to the `merkle_root` of the leaves, from the
+/// bottom of the tree. The nodes with no sibling have nothing in the path.
+pub fn merkle_path(mut layer: Vec<[u8; 32]>, mut index: usize) -> Vec<[u8; 32]> {
+    let mut path = Vec::new();
+    while layer.len() > 1 {
+        if let Some(sibling) = layer.get(index ^ 1) {
+            path.push(*sibling);
+        }
+        layer = parent_layer(&layer);
+        index /= 2;
+    }
+    path
+}
+
+/// Whether `leaf` is at `index` among `len` leaves whose `merkle_root` is `root`, given its
+/// `merkle_path`.
+pub fn verify_merkle_path(
+    root: [u8; 32],
+    leaf: [u8; 32],
+    mut index: usize,
+    len: usize,
+    path: &[[u8; 32]],
+) -> bool {
+    if index >= len {
+        return false;
+    }
+    let mut path = path.iter();
+    let mut node = leaf;
+    let mut layer_len = len;
+    while layer_len > 1 {
+        if index % 2 == 1 {
+            let Some(sibling) = path.next() else {
+                return false;
+            };
+            node = hash_pair(sibling, &node);
+        } else if index + 1 < layer_len {
+            let Some(sibling) = path.next() else {
+                return false;
+            };
+            node = hash_pair(&node, sibling);
+        }
+        index /= 2;
+        layer_len = layer_len.div_ceil(2);
+    }
+    path.next().is_none() && root_hash(len, &node) == root
+}
+
+/// The hash of an entry of the state, a leaf of the tree of the `state_root`.
+fn entry_hash(key: &[u8], value: &[u8]) -> [u8; 32] {
+    leaf_hash(&(key, value).encode())
+}
+
+/// The key of `key` in the map `item` of the pallet `pallet`, as stored by a `StorageMap`.
+pub fn map_key(pallet: &str, item: &str, key: &impl Encode) -> Vec<u8> {
+    [
+        storage_prefix(pallet, item).as_slice(),
+        &blake2_128_concat(key),
+    ]
+    .concat()
+}
+
+/// An entry of the state, along with its position in the tree of the `state_root`.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct ProvenEntry {
+    /// The index of the entry among the entries of the state, ordered by key.
+    pub index: u32,
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+    /// The `merkle_path` of the entry.
+    pub path: Vec<[u8; 32]>,
+}
+
+/// The proof of the value of a key in the state with a given `state_root`, made by
+/// `Storage::read_proof`.
+///
+/// It contains the entry of the key if there is one, and otherwise the entries right before and
+/// after where the key would be, which shows there is no entry in between.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct StorageProof {
+    /// The number of entries in the state.
+    pub len: u32,
+    pub entries: Vec<ProvenEntry>,
+}
+
+impl StorageProof {
+    /// The value of `key` in the state whose root is `root`, or `None` if it has no value.
+    ///
+    /// Fails if the proof is not made for this state and key.
+    pub fn verify(&self, root: [u8; 32], key: &[u8]) -> Result<Option<Vec<u8>>, &'static str> {
+        let len = self.len as usize;
+        for entry in &self.entries {
+            let leaf = entry_hash(&entry.key, &entry.value);
+            if !verify_merkle_path(root, leaf, entry.index as usize, len, &entry.path) {
+                return Err("storage proof does not match the state root");
+            }
+        }
+        let absent = match self.entries.as_slice() {
+            [] => len == 0 && root == merkle_root(Vec::new()),
+            [entry] if entry.key == key => return Ok(Some(entry.value.clone())),
+            [entry] => {
+                (entry.index == 0 && key < entry.key.as_slice())
+                    || (entry.index + 1 == self.len && entry.key.as_slice() < key)
+            }
+            [before, after] => {
+                before.index + 1 == after.index
+                    && before.key.as_slice() < key
+                    && key < after.key.as_slice()
+            }
+            _ => false,
+        };
+        match absent {
+            true => Ok(None),
+            false => Err("storage proof is not about this key"),
+        }
+    }
+}
+
+/// Changes of the raw storage, ordered by key. `None` marks a removed key.
+pub type Changes = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// Entries of the raw storage, ordered by key.
+type Entries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// The values read from a storage item since `RawStorage::track_reads`, as they were when the
+/// tracking started.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reads {
+    keys: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    prefixes: BTreeMap<Vec<u8>, Entries>,
+}
+
+/// The encoded keys and values of a storage item, along with the changes made in the open
+/// transactions.
+///
+/// The committed values are shared by the clones of the storage until one of them commits a
+/// change, so a clone only copies the changes of the open transactions.
+#[derive(Clone, Debug, Default)]
+pub struct RawStorage {
+    prefix: Vec<u8>,
+    committed: Arc<BTreeMap<Vec<u8>, Vec<u8>>>,
+    /// The changes of every open transaction, the innermost last.
+    transactions: Vec<Changes>,
+    /// The values read, if tracked, along with the number of transactions open when the tracking
+    /// started. Reads are recorded through `&self`, like the storage is read.
+    reads: Option<(usize, RefCell<Reads>)>,
+}
+
+impl RawStorage {
+    pub fn new(pallet: &str, item: &str) -> Self {
+        Self {
+            prefix: storage_prefix(pallet, item).to_vec(),
+            ..Default::default()
+        }
+    }
+
+    /// The prefix of all the keys of this storage item.
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        if let Some((depth, reads)) = &self.reads {
+            reads
+                .borrow_mut()
+                .keys
+                .entry(key.to_vec())
+                .or_insert_with(|| self.get_at(key, *depth));
+        }
+        self.get_at(key, self.transactions.len())
+    }
+
+    /// The value of `key` as seen by the first `depth` open transactions.
+    fn get_at(&self, key: &[u8], depth: usize) -> Option<Vec<u8>> {
+        for changes in self.transactions[..depth]
+            .iter()
+            .rev()
+        {
+            if let Some(value) = changes.get(key) {
+                return value.clone();
+            }
+        }
+        self.committed
+            .get(key)
+            .cloned()
+    }
+
+    pub fn set(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.change(key, Some(value));
+    }
+
+    pub fn remove(&mut self, key: Vec<u8>) {
+        self.change(key, None);
+    }
+
+    fn change(&mut self, key: Vec<u8>, value: Option<Vec<u8>>) {
+        debug_assert!(key.starts_with(&self.prefix));
+        match self.transactions.last_mut() {
+            Some(changes) => {
+                changes.insert(key, value);
+            }
+            None => match value {
+                Some(value) => {
+                    Arc::make_mut(&mut self.committed).insert(key, value);
+                }
+                None => {
+                    Arc::make_mut(&mut self.committed).remove(&key);
+                }
+            },
+        }
+    }
+
+    /// All the entries whose key starts with `prefix`, ordered by key, including the changes of the
+    /// open transactions.
+    pub fn entries(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        if let Some((depth, reads)) = &self.reads {
+            reads
+                .borrow_mut()
+                .prefixes
+                .entry(prefix.to_vec())
+                .or_insert_with(|| self.entries_at(prefix, *depth));
+        }
+        self.entries_at(prefix, self.transactions.len())
+    }
+
+    /// The entries whose key starts with `prefix` as seen by the first `depth` open transactions.
+    fn entries_at(&self, prefix: &[u8], depth: usize) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let in_prefix = |key: &&Vec<u8>| key.starts_with(prefix);
+        let mut entries = self
+            .committed
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| in_prefix(key))
+            .map(|(key, value)| (key.clone(), Some(value.clone())))
+            .collect::<BTreeMap<_, _>>();
+        for changes in &self.transactions[..depth] {
+            for (key, value) in changes.range(prefix.to_vec()..) {
+                if !in_prefix(&key) {
+                    break;
+                }
+                entries.insert(key.clone(), value.clone());
+            }
+        }
+        entries
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value?)))
+            .collect()
+    }
+
+    /// Open a new transaction. The following changes are only kept if it is committed.
+    pub fn start_transaction(&mut self) {
+        self.transactions
+            .push(BTreeMap::new());
+    }
+
+    /// Keep the changes of the innermost transaction.
+    pub fn commit_transaction(&mut self) {
+        let changes = self
+            .transactions
+            .pop()
+            .expect("no open transaction");
+        for (key, value) in changes {
+            self.change(key, value);
+        }
+    }
+
+    /// Discard the changes of the innermost transaction.
+    pub fn rollback_transaction(&mut self) {
+        self.take_transaction();
+    }
+
+    /// Close the innermost transaction without keeping its changes, returning them instead.
+    pub fn take_transaction(&mut self) -> Changes {
+        self.transactions
+            .pop()
+            .expect("no open transaction")
+    }
+
+    /// Make the `changes` in the innermost open transaction, if any.
+    pub fn apply(&mut self, changes: Changes) {
+        for (key, value) in changes {
+            self.change(key, value);
+        }
+    }
+
+    /// Record the values read from now on, as they are in the current state: the values changed
+    /// later on are recorded as they were before the change.
+    pub fn track_reads(&mut self) {
+        self.reads = Some((self.transactions.len(), RefCell::default()));
+    }
+
+    /// Stop tracking the reads, returning the values read since `track_reads`.
+    pub fn take_reads(&mut self) -> Reads {
+        self.reads
+            .take()
+            .map(|(_, reads)| reads.into_inner())
+            .unwrap_or_default()
+    }
+
+    /// Whether all the values in `reads` are still the same.
+    pub fn unchanged(&self, reads: &Reads) -> bool {
+        let depth = self.transactions.len();
+        reads
+            .keys
+            .iter()
+            .all(|(key, value)| self.get_at(key, depth) == *value)
+            && reads
+                .prefixes
+                .iter()
+                .all(|(prefix, entries)| self.entries_at(prefix, depth) == *entries)
+    }
+}
+
+/// A pallet, or a runtime, whose state is made of storage items. Implemented by
+/// `#[macros::storage]`.
+pub trait Storage {
+    /// All the storage items.
+    fn raw_storage(&self) -> Vec<&RawStorage>;
+
+    fn raw_storage_mut(&mut self) -> Vec<&mut RawStorage>;
+
+    fn start_transaction(&mut self) {
+        self.raw_storage_mut()
+            .into_iter()
+            .for_each(RawStorage::start_transaction);
+    }
+
+    fn commit_transaction(&mut self) {
+        self.raw_storage_mut()
+            .into_iter()
+            .for_each(RawStorage::commit_transaction);
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.raw_storage_mut()
+            .into_iter()
+            .for_each(RawStorage::rollback_transaction);
+    }
+
+    /// Close the innermost transaction of every storage item, returning their changes in the order
+    /// of `raw_storage`.
+    fn take_transaction(&mut self) -> Vec<Changes> {
+        self.raw_storage_mut()
+            .into_iter()
+            .map(RawStorage::take_transaction)
+            .collect()
+    }
+
+    /// Make the changes returned by `take_transaction`, in the innermost open transaction.
+    fn apply_changes(&mut self, changes: Vec<Changes>) {
+        self.raw_storage_mut()
+            .into_iter()
+            .zip(changes)
+            .for_each(|(storage, changes)| storage.apply(changes));
+    }
+
+    /// Record the values read from every storage item, see `RawStorage::track_reads`.
+    fn track_reads(&mut self) {
+        self.raw_storage_mut()
+            .into_iter()
+            .for_each(RawStorage::track_reads);
+    }
+
+    /// The values read from every storage item since `track_reads`, in the order of `raw_storage`.
+    fn take_reads(&mut self) -> Vec<Reads> {
+        self.raw_storage_mut()
+            .into_iter()
+            .map(RawStorage::take_reads)
+            .collect()
+    }
+
+    /// Whether all the values returned by `take_reads` are still the same.
+    fn reads_unchanged(&self, reads: &[Reads]) -> bool {
+        self.raw_storage()
+            .into_iter()
+            .zip(reads)
+            .all(|(storage, reads)| storage.unchanged(reads))
+    }
+
+    /// The raw value of `key`, in the storage item whose prefix `key` starts with.
+    fn get_raw(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.raw_storage()
+            .into_iter()
+            .find(|storage| key.starts_with(storage.prefix()))?
+            .get(key)
+    }
+
+    /// Set the raw value of `key`, or remove it if `value` is `None`, in the storage item whose
+    /// prefix `key` starts with. Fails if `key` belongs to no storage item.
+    fn set_raw(&mut self, key: &[u8], value: Option<Vec<u8>>) -> Result<(), &'static str> {
+        let storage = self
+            .raw_storage_mut()
+            .into_iter()
+            .find(|storage| key.starts_with(storage.prefix()))
+            .ok_or(UNKNOWN_KEY)?;
+        match value {
+            Some(value) => storage.set(key.to_vec(), value),
+            None => storage.remove(key.to_vec()),
+        }
+        Ok(())
+    }
+
+    /// A proof of the value of `key` in the current state, or of its absence, against the
+    /// `state_root`.
+    fn read_proof(&self, key: &[u8]) -> StorageProof {
+        let mut entries = self
+            .raw_storage()
+            .into_iter()
+            .flat_map(|storage| storage.entries(storage.prefix()))
+            .collect::<Vec<_>>();
+        entries.sort();
+        let leaves = entries
+            .iter()
+            .map(|(key, value)| entry_hash(key, value))
+            .collect::<Vec<_>>();
+        let indices = match entries.binary_search_by(|(entry, _)| entry.as_slice().cmp(key)) {
+            Ok(index) => index..index + 1,
+            Err(index) => index.saturating_sub(1)..(index + 1).min(entries.len()),
+        };
+        StorageProof {
+            len: entries.len() as u32,
+            entries: indices
+                .map(|index| {
+                    let (key, value) = entries[index].clone();
+                    ProvenEntry {
+                        index: index as u32,
+                        key,
+                        value,
+                        path: merkle_path(leaves.clone(), index),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// The root of all the entries of all the storage items, see `state_root`.
+    fn state_root(&self) -> [u8; 32] {
+        let entries = self
+            .raw_storage()
+            .into_iter()
+            .flat_map(|storage| storage.entries(storage.prefix()))
+            .collect::<Vec<_>>();
+        state_root(
+            entries
+                .iter()
+                .map(|(key, value)| (key.as_slice(), value.as_slice())),
+        )
+    }
+}
+
+/// Run `f` in a new transaction, which is committed if `f` succeeds and rolled back otherwise.
+pub fn with_transaction<S: Storage + ?Sized, R, E>(
+    storage: &mut S,
+    f: impl FnOnce(&mut S) -> Result<R, E>,
+) -> Result<R, E> {
+    storage.start_transaction();
+    let result = f(storage);
+    match result {
+        Ok(_) => storage.commit_transaction(),
+        Err(_) => storage.rollback_transaction(),
+    }
+    result
+}
+
+/// A storage item of a pallet, created by `#[macros::storage]`.
+pub trait StorageItem {
+    /// The type of the values, which is returned when no value is stored.
+    type Value;
+
+    /// Create the item named `item` of the pallet `pallet`, returning `default`, if any, when no
+    /// value is stored.
+    fn new(pallet: &str, item: &str, default: Option<fn() -> Self::Value>) -> Self;
+
+    fn raw(&self) -> &RawStorage;
+
+    fn raw_mut(&mut self) -> &mut RawStorage;
+}
+
+/// A storage item which can be described in the metadata.
+pub trait StorageItemInfo {
+    fn entry_type() -> StorageEntryType;
+}
+
+fn default<V>(default: Option<fn() -> V>) -> V {
+    default.expect("no value is stored, and the storage item has no default value")()
+}
+
+fn decode_value<V: Decode>(bytes: &[u8]) -> V {
+    V::decode(&mut &bytes[..]).expect("storage values are encoded by the storage items")
+}
+
+/// A single value.
+#[derive(Clone, Debug)]
+pub struct StorageValue<V> {
+    raw: RawStorage,
+    default: Option<fn() -> V>,
+}
+
+impl<V: Encode + Decode> StorageValue<V> {
+    /// The stored value, or the default value.
+    ///
+    /// Panics if no value is stored and the item has no default value.
+    pub fn get(&self) -> V {
+        self.try_get()
+            .unwrap_or_else(|| default(self.default))
+    }
+
+    pub fn try_get(&self) -> Option<V> {
+        self.raw
+            .get(self.raw.prefix())
+            .map(|bytes| decode_value(&bytes))
+    }
+
+    pub fn exists(&self) -> bool {
+        self.try_get().is_some()
+    }
+
+    pub fn set(&mut self, value: V) {
+        let key = self.raw.prefix().to_vec();
+        self.raw
+            .set(key, value.encode());
+    }
+
+    /// Remove the value, so the default value is returned from now on.
+    pub fn kill(&mut self) {
+        let key = self.raw.prefix().to_vec();
+        self.raw.remove(key);
+    }
+
+    /// Change the value in place, starting from the default value if none is stored.
+    pub fn mutate<R>(&mut self, f: impl FnOnce(&mut V) -> R) -> R {
+        let mut value = self.get();
+        let result = f(&mut value);
+        self.set(value);
+        result
+    }
+}
+
+impl<V> StorageItem for StorageValue<V> {
+    type Value = V;
+
+    fn new(pallet: &str, item: &str, default: Option<fn() -> V>) -> Self {
+        Self {
+            raw: RawStorage::new(pallet, item),
+            default,
+        }
+    }
+
+    fn raw(&self) -> &RawStorage {
+        &self.raw
+    }
+
+    fn raw_mut(&mut self) -> &mut RawStorage {
+        &mut self.raw
+    }
+}
+
+impl<V: TypeInfo> StorageItemInfo for StorageValue<V> {
+    fn entry_type() -> StorageEntryType {
+        StorageEntryType::Value(V::type_info())
+    }
+}
+
+/// A map from keys to values.
+#[derive(Clone, Debug)]
+pub struct StorageMap<K, V> {
+    raw: RawStorage,
+    default: Option<fn() -> V>,
+    _key: PhantomData<K>,
+}
+
+impl<K: Encode + Decode, V: Encode + Decode> StorageMap<K, V> {
+    fn key(&self, key: &K) -> Vec<u8> {
+        [self.raw.prefix(), &blake2_128_concat(key)].concat()
+    }
+
+    /// The value stored at `key`, or the default value.
+    ///
+    /// Panics if no value is stored and the map has no default value.
+    pub fn get(&self, key: &K) -> V {
+        self.try_get(key)
+            .unwrap_or_else(|| default(self.default))
+    }
+
+    pub fn try_get(&self, key: &K) -> Option<V> {
+        self.raw
+            .get(&self.key(key))
+            .map(|bytes| decode_value(&bytes))
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.try_get(key).is_some()
+    }
+
+    pub fn insert(&mut self, key: &K, value: V) {
+        let key = self.key(key);
+        self.raw
+            .set(key, value.encode());
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        let key = self.key(key);
+        self.raw.remove(key);
+    }
+
+    /// Change the value at `key` in place, starting from the default value if none is stored.
+    pub fn mutate<R>(&mut self, key: &K, f: impl FnOnce(&mut V) -> R) -> R {
+        let mut value = self.get(key);
+        let result = f(&mut value);
+        self.insert(key, value);
+        result
+    }
+
+    /// All the entries of the map, ordered by the hash of their key.
+    pub fn iter(&self) -> impl Iterator<Item = (K, V)> {
+        self.raw
+            .entries(self.raw.prefix())
+            .into_iter()
+            .map(|(key, value)| {
+                let key =
+                    decode_concat(&mut &key[PREFIX_LEN..]).expect("keys are encoded by the map");
+                (key, decode_value(&value))
+            })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = K> {
+        self.iter()
+            .map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = V> {
+        self.iter()
+            .map(|(_, value)| value)
+    }
+
+    /// Keep only the entries for which `f` returns `true`.
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &V) -> bool) {
+        for (key, value) in self
+            .iter()
+            .collect::<Vec<_>>()
+        {
+            if !f(&key, &value) {
+                self.remove(&key);
+            }
+        }
+    }
+}
+
+impl<K, V> StorageItem for StorageMap<K, V> {
+    type Value = V;
+
+    fn new(pallet: &str, item: &str, default: Option<fn() -> V>) -> Self {
+        Self {
+            raw: RawStorage::new(pallet, item),
+            default,
+            _key: PhantomData,
+        }
+    }
+
+    fn raw(&self) -> &RawStorage {
+        &self.raw
+    }
+
+    fn raw_mut(&mut self) -> &mut RawStorage {
+        &mut self.raw
+    }
+}
+
+impl<K: TypeInfo, V: TypeInfo> StorageItemInfo for StorageMap<K, V> {
+    fn entry_type() -> StorageEntryType {
+        StorageEntryType::Map {
+            key: K::type_info(),
+            value: V::type_info(),
+        }
+    }
+}
+
+/// A map from pairs of keys to values, where all the entries sharing the first key can be iterated
+/// or removed together.
+#[derive(Clone, Debug)]
+pub struct StorageDoubleMap<K1, K2, V> {
+    raw: RawStorage,
+    default: Option<fn() -> V>,
+    _keys: PhantomData<(K1, K2)>,
+}
+
+impl<K1: Encode + Decode, K2: Encode + Decode, V: Encode + Decode> StorageDoubleMap<K1, K2, V> {
+    fn prefix(&self, key1: &K1) -> Vec<u8> {
+        [self.raw.prefix(), &blake2_128_concat(key1)].concat()
+    }
+
+    fn key(&self, key1: &K1, key2: &K2) -> Vec<u8> {
+        [self.prefix(key1), blake2_128_concat(key2)].concat()
+    }
+
+    /// The value stored at `(key1, key2)`, or the default value.
+    ///
+    /// Panics if no value is stored and the map has no default value.
+    pub fn get(&self, key1: &K1, key2: &K2) -> V {
+        self.try_get(key1, key2)
+            .unwrap_or_else(|| default(self.default))
+    }
+
+    pub fn try_get(&self, key1: &K1, key2: &K2) -> Option<V> {
+        self.raw
+            .get(&self.key(key1, key2))
+            .map(|bytes| decode_value(&bytes))
+    }
+
+    pub fn contains_key(&self, key1: &K1, key2: &K2) -> bool {
+        self.try_get(key1, key2)
+            .is_some()
+    }
+
+    pub fn insert(&mut self, key1: &K1, key2: &K2, value: V) {
+        let key = self.key(key1, key2);
+        self.raw
+            .set(key, value.encode());
+    }
+
+    pub fn remove(&mut self, key1: &K1, key2: &K2) {
+        let key = self.key(key1, key2);
+        self.raw.remove(key);
+    }
+
+    /// Change the value at `(key1, key2)` in place, starting from the default value if none is
+    /// stored.
+    pub fn mutate<R>(&mut self, key1: &K1, key2: &K2, f: impl FnOnce(&mut V) -> R) -> R {
+        let mut value = self.get(key1, key2);
+        let result = f(&mut value);
+        self.insert(key1, key2, value);
+        result
+    }
+
+    /// All the entries of the map, ordered by the hashes of their keys.
+    pub fn iter(&self) -> impl Iterator<Item = (K1, K2, V)> {
+        self.raw
+            .entries(self.raw.prefix())
+            .into_iter()
+            .map(|(key, value)| {
+                let mut key = &key[PREFIX_LEN..];
+                let key1 = decode_concat(&mut key).expect("keys are encoded by the map");
+                let key2 = decode_concat(&mut key).expect("keys are encoded by the map");
+                (key1, key2, decode_value(&value))
+            })
+    }
+
+    /// All the entries sharing the first key `key1`.
+    pub fn iter_prefix(&self, key1: &K1) -> impl Iterator<Item = (K2, V)> {
+        let prefix = self.prefix(key1);
+        self.raw
+            .entries(&prefix)
+            .into_iter()
+            .map(move |(key, value)| {
+                let key2 =
+                    decode_concat(&mut &key[prefix.len()..]).expect("keys are encoded by the map");
+                (key2, decode_value(&value))
+            })
+    }
+
+    /// Remove all the entries sharing the first key `key1`.
+    pub fn remove_prefix(&mut self, key1: &K1) {
+        for (key, _) in self
+            .raw
+            .entries(&self.prefix(key1))
+        {
+            self.raw.remove(key);
+        }
+    }
+}
+
+impl<K1, K2, V> StorageItem for StorageDoubleMap<K1, K2, V> {
+    type Value = V;
+
+    fn new(pallet: &str, item: &str, default: Option<fn() -> V>) -> Self {
+        Self {
+            raw: RawStorage::new(pallet, item),
+            default,
+            _keys: PhantomData,
+        }
+    }
+
+    fn raw(&self) -> &RawStorage {
+        &self.raw
+    }
+
+    fn raw_mut(&mut self) -> &mut RawStorage {
+        &mut self.raw
+    }
+}
+
+impl<K1: TypeInfo, K2: TypeInfo, V: TypeInfo> StorageItemInfo for StorageDoubleMap<K1, K2, V> {
+    fn entry_type() -> StorageEntryType {
+        StorageEntryType::DoubleMap {
+            key1: K1::type_info(),
+            key2: K2::type_info(),
+            value: V::type_info(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> StorageMap<String, u32> {
+        StorageMap::new("test", "map", Some(|| 0))
+    }
+
+    #[test]
+    fn storage_value() {
+        let mut value = StorageValue::<u32>::new("test", "value", Some(|| 7));
+        assert_eq!(value.get(), 7);
+        assert_eq!(value.try_get(), None);
+
+        value.mutate(|v| *v += 1);
+        assert_eq!(value.try_get(), Some(8));
+        assert_eq!(
+            value.raw().entries(&[]),
+            vec![(storage_prefix("test", "value").to_vec(), 8u32.encode())]
+        );
+
+        value.kill();
+        assert!(!value.exists());
+    }
+
+    #[test]
+    fn storage_maps() {
+        let mut map = map();
+        map.insert(&"alice".to_string(), 1);
+        map.insert(&"bob".to_string(), 2);
+        map.mutate(&"charlie".to_string(), |v| *v += 3);
+        map.remove(&"bob".to_string());
+
+        assert_eq!(map.get(&"alice".to_string()), 1);
+        assert_eq!(map.get(&"bob".to_string()), 0);
+        let mut entries = map.iter().collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![("alice".to_string(), 1), ("charlie".to_string(), 3)]
+        );
+
+        let mut double_map = StorageDoubleMap::<u8, u8, bool>::new("test", "double_map", None);
+        double_map.insert(&1, &1, true);
+        double_map.insert(&1, &2, true);
+        double_map.insert(&2, &1, true);
+        assert!(double_map.get(&1, &2));
+        assert_eq!(double_map.try_get(&2, &2), None);
+        let mut first = double_map
+            .iter_prefix(&1)
+            .collect::<Vec<_>>();
+        first.sort();
+        assert_eq!(first, vec![(1, true), (2, true)]);
+
+        double_map.remove_prefix(&1);
+        assert_eq!(
+            double_map
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![(2, 1, true)]
+        );
+    }
+
+    #[test]
+    fn transactions() {
+        let mut map = map();
+        let alice = "alice".to_string();
+        map.insert(&alice, 1);
+        let root = map.raw().entries(&[]);
+
+        map.raw_mut()
+            .start_transaction();
+        map.insert(&alice, 2);
+        map.raw_mut()
+            .start_transaction();
+        map.remove(&alice);
+        assert_eq!(map.try_get(&alice), None);
+        map.raw_mut()
+            .rollback_transaction();
+        assert_eq!(map.get(&alice), 2);
+        map.raw_mut()
+            .commit_transaction();
+        assert_eq!(map.get(&alice), 2);
+
+        map.raw_mut()
+            .start_transaction();
+        map.insert(&alice, 1);
+        map.raw_mut()
+            .commit_transaction();
+        assert_eq!(map.raw().entries(&[]), root);
+    }
+
+    #[test]
+    fn reads_are_tracked_as_they_were() {
+        let mut map = map();
+        let (alice, bob) = ("alice".to_string(), "bob".to_string());
+        map.insert(&alice, 1);
+        let mut other = map.clone();
+
+        map.raw_mut().track_reads();
+        map.raw_mut()
+            .start_transaction();
+        map.insert(&alice, 2);
+        map.mutate(&alice, |v| *v += 1);
+        assert_eq!(map.get(&bob), 0);
+        let changes = map
+            .raw_mut()
+            .take_transaction();
+        let reads = map.raw_mut().take_reads();
+        assert_eq!(map.get(&alice), 1);
+        assert!(map.raw().unchanged(&reads));
+
+        // The changes can be made again elsewhere, as long as the values read are the same there.
+        assert!(other.raw().unchanged(&reads));
+        other.raw_mut().apply(changes);
+        assert_eq!(other.get(&alice), 3);
+        other.insert(&bob, 5);
+        assert!(!other.raw().unchanged(&reads));
+    }
+
+    #[test]
+    fn clones_share_the_committed_values() {
+        let mut map = map();
+        let (alice, bob) = ("alice".to_string(), "bob".to_string());
+        map.insert(&alice, 1);
+        map.raw_mut()
+            .start_transaction();
+        map.insert(&bob, 2);
+
+        let mut clone = map.raw().clone();
+        assert!(Arc::ptr_eq(&clone.committed, &map.raw().committed));
+        assert_eq!(clone.transactions, map.raw().transactions);
+
+        // Committing a change in the clone copies the committed values first.
+        clone.commit_transaction();
+        assert!(!Arc::ptr_eq(&clone.committed, &map.raw().committed));
+        assert_eq!(clone.committed.len(), 2);
+        assert_eq!(map.raw().committed.len(), 1);
+        assert_eq!(map.get(&bob), 2);
+    }
+
+    #[test]
+    fn state_root_commits_to_all_entries() {
+        let a: (&[u8], &[u8]) = (b"a", b"1");
+        let b: (&[u8], &[u8]) = (b"b", b"2");
+        let c: (&[u8], &[u8]) = (b"c", b"3");
+
+        assert_eq!(state_root([]), [0; 32]);
+        assert_eq!(state_root([a, b, c]), state_root([c, a, b]));
+        assert_ne!(state_root([a, b, c]), state_root([a, b]));
+        assert_ne!(state_root([a, b]), state_root([a, (b"b", b"3")]));
+        // Leaves are hashed with a `0x00` prefix and nodes with a `0x01` prefix, and the root
+        // commits to the number of leaves.
+        let leaf = |entry: (&[u8], &[u8])| blake2_256(&[[0].as_slice(), &entry.encode()].concat());
+        let node = blake2_256(&[[1].as_slice(), &leaf(a), &leaf(b)].concat());
+        assert_eq!(state_root([a, b]), blake2_256(&(2u64, node).encode()));
+    }
+
+    #[test]
+    fn merkle_paths_lead_to_the_root() {
+        for len in 0..12 {
+            let leaves = (0..len)
+                .map(|i: u8| blake2_256(&[i]))
+                .collect::<Vec<_>>();
+            let root = merkle_root(leaves.clone());
+            for (index, leaf) in leaves.iter().enumerate() {
+                let path = merkle_path(leaves.clone(), index);
+                assert!(verify_merkle_path(root, *leaf, index, leaves.len(), &path));
+                assert!(!verify_merkle_path(
+                    root,
+                    *leaf,
+                    index ^ 1,
+                    leaves.len(),
+                    &path
+                ));
+                assert!(!verify_merkle_path(
+                    root,
+                    [0; 32],
+                    index,
+                    leaves.len(),
+                    &path
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn nodes_are_not_leaves() {
+        let leaves = (0..4u8)
+            .map(|i| leaf_hash(&[i]))
+            .collect::<Vec<_>>();
+        let root = merkle_root(leaves.clone());
+        // The parents of the leaves, as the leaves of a tree of half as many leaves.
+        let parents = parent_layer(&leaves);
+        assert_ne!(merkle_root(parents.clone()), root);
+        assert!(!verify_merkle_path(root, parents[0], 0, 2, &[parents[1]]));
+        // A leaf whose data is the concatenation of two nodes is not their parent.
+        assert_ne!(leaf_hash(&[leaves[0], leaves[1]].concat()), parents[0]);
+    }
+
+    /// A state made of a few storage items.
+    struct State(Vec<RawStorage>);
+
+    impl Storage for State {
+        fn raw_storage(&self) -> Vec<&RawStorage> {
+            self.0.iter().collect()
+        }
+
+        fn raw_storage_mut(&mut self) -> Vec<&mut RawStorage> {
+            self.0.iter_mut().collect()
+        }
+    }
+
+    #[test]
+    fn storage_proofs_show_values_and_their_absence() {
+        let mut state = State(vec![map().raw().clone(), RawStorage::new("test", "value")]);
+        let empty = state.read_proof(b"key");
+        assert_eq!(empty.verify(state.state_root(), b"key"), Ok(None));
+
+        let keys = ["bob", "dave", "frank"].map(|name| map_key("test", "map", &name.to_string()));
+        for (i, key) in keys.iter().enumerate() {
+            state
+                .set_raw(key, Some(vec![i as u8]))
+                .unwrap();
+        }
+        let value_key = storage_prefix("test", "value").to_vec();
+        state
+            .set_raw(&value_key, Some(vec![7]))
+            .unwrap();
+        let root = state.state_root();
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(
+                state
+                    .read_proof(key)
+                    .verify(root, key),
+                Ok(Some(vec![i as u8]))
+            );
+        }
+        assert_eq!(
+            state
+                .read_proof(&value_key)
+                .verify(root, &value_key),
+            Ok(Some(vec![7]))
+        );
+        // Keys before, between and after all the entries.
+        let mut absent = vec![vec![0], vec![0xff; 40]];
+        absent.extend(
+            ["alice", "charlie", "eve", "grace"]
+                .map(|name| map_key("test", "map", &name.to_string())),
+        );
+        for key in &absent {
+            assert_eq!(
+                state
+                    .read_proof(key)
+                    .verify(root, key),
+                Ok(None)
+            );
+        }
+
+        // A proof is only valid for its key and its state.
+        let proof = state.read_proof(&keys[0]);
+        assert!(proof
+            .verify(root, &keys[1])
+            .is_err());
+        assert!(proof
+            .verify(merkle_root(Vec::new()), &keys[0])
+            .is_err());
+        let mut forged = proof.clone();
+        forged.entries[0].value = vec![9];
+        assert!(forged
+            .verify(root, &keys[0])
+            .is_err());
+        let mut partial = state.read_proof(&absent[2]);
+        partial.entries.truncate(1);
+        assert!(partial
+            .verify(root, &absent[2])
+            .is_err());
+    }
+
+    #[test]
+    fn storage_proofs_with_another_len_are_rejected() {
+        let mut state = State(vec![map().raw().clone()]);
+        let names = ["alice", "bob", "charlie", "dave", "eve", "frank"];
+        let keys = names.map(|name| map_key("test", "map", &name.to_string()));
+        for key in &keys[..5] {
+            state
+                .set_raw(key, Some(vec![1]))
+                .unwrap();
+        }
+        let root = state.state_root();
+
+        for key in &keys {
+            let proof = state.read_proof(key);
+            assert!(proof
+                .verify(root, key)
+                .is_ok());
+            for len in [0, proof.len - 1, proof.len + 1, proof.len * 2] {
+                let mut forged = proof.clone();
+                forged.len = len;
+                assert_eq!(
+                    forged.verify(root, key),
+                    Err("storage proof does not match the state root")
+                );
+            }
+        }
+    }
+}
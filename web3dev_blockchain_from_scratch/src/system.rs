@@ -1,67 +1,657 @@
-use std::{
-    collections::BTreeMap,
-    ops::AddAssign,
-};
+use core::fmt::Debug;
+use std::ops::AddAssign;
 
+use codec::{
+    Decode,
+    Encode,
+};
 use num::{
     CheckedAdd,
     One,
     Zero,
 };
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::support::{
+    benchmarking::{
+        ensure,
+        Benchmark,
+        Benchmarks,
+        Component,
+    },
+    storage::{
+        self,
+        StorageMap,
+        StorageValue,
+    },
+    DispatchResult,
+    Hooks,
+    OnRuntimeUpgrade,
+};
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+/// The error of the calls only root can make, when made by another account.
+pub const NOT_ROOT: &str = "caller is not root";
+
+/// The largest remark measured by the benchmarks, in bytes.
+pub const MAX_BENCHMARKED_REMARK: u32 = 64 * 1024;
+
+/// How many calls root can block at once, with `block_calls`. The calls blocking and unblocking
+/// calls are charged for this many, since their arguments don't tell how many are blocked.
+pub const MAX_BLOCKED_CALLS: u32 = 100;
+
+/// The most storage items set or killed at once measured by the benchmarks.
+pub const MAX_BENCHMARKED_STORAGE_ITEMS: u32 = 1000;
 
 pub trait Config {
-    type AccountId: Ord + Clone;
-    type BlockNumber: Zero + One + CheckedAdd + AddAssign + Copy;
-    type Nonce: Zero + One + CheckedAdd + Copy;
+    type AccountId: Ord + Clone + Debug + Encode + Decode;
+    type BlockNumber: Zero + One + CheckedAdd + AddAssign + Copy + Ord + Debug + Encode + Decode;
+    type Nonce: Zero + One + CheckedAdd + Copy + Debug + Encode + Decode;
+    /// The aggregated event type of the runtime, generated by `macros::runtime`.
+    type RuntimeEvent: Clone + Debug;
+    /// The weights of the calls, see the `weights` module. It is not named `WeightInfo` like in
+    /// the other pallets, whose configs extend this one.
+    type SystemWeightInfo: WeightInfo;
 }
 
-#[derive(Debug)]
-pub struct Pallet<T: Config> {
-    block_number: T::BlockNumber,
-    // keep track of user vs number of tx
-    nonce: BTreeMap<T::AccountId, T::Nonce>,
+/// The events emitted by the system pallet itself.
+#[derive(
+    macros::CloneNoBound,
+    macros::DebugNoBound,
+    macros::PartialEqNoBound,
+    macros::EqNoBound,
+    macros::TypeInfo,
+    Serialize,
+)]
+pub enum Event<T: Config> {
+    /// An extrinsic completed successfully.
+    ExtrinsicSuccess,
+    /// An extrinsic failed with the given error.
+    ExtrinsicFailed { error: &'static str },
+    /// `sender` made a remark, whose hash is `hash`.
+    Remarked {
+        sender: T::AccountId,
+        hash: [u8; 32],
+    },
+    /// The chain must now be executed by a runtime with at least the given `spec_version`.
+    CodeUpdated { spec_version: u32 },
+    /// Root blocked the calls matching `blocked`.
+    CallsBlocked { blocked: BlockedCalls },
+    /// Root unblocked the calls matching `blocked`.
+    CallsUnblocked { blocked: BlockedCalls },
+    /// The call `call` of the pallet `pallet` was blocked by a call filter, which failed its
+    /// extrinsic.
+    CallFiltered {
+        pallet: &'static str,
+        call: &'static str,
+    },
 }
 
-impl<T: Config> Pallet<T> {
-    /// Create a new instance of the System Pallet.
-    pub fn new() -> Self {
+/// Calls blocked by root, like the transfers during a maintenance window: the calls of a pallet of
+/// the runtime, by the name of its field, and only the call `call` if given.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Encode, Decode, macros::TypeInfo)]
+pub struct BlockedCalls {
+    pub pallet: String,
+    pub call: Option<String>,
+}
+
+impl BlockedCalls {
+    /// Whether the call `call` of the pallet `pallet` is blocked.
+    pub fn matches(&self, pallet: &str, call: &str) -> bool {
+        self.pallet == pallet
+            && self
+                .call
+                .as_ref()
+                .is_none_or(|blocked| blocked == call)
+    }
+}
+
+/// The information kept about an account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Encode, Decode, macros::TypeInfo)]
+pub struct AccountInfo<Nonce> {
+    /// The number of transactions the account has made.
+    pub nonce: Nonce,
+}
+
+impl<Nonce: Zero> Default for AccountInfo<Nonce> {
+    fn default() -> Self {
         Self {
-            block_number: T::BlockNumber::zero(),
-            nonce: BTreeMap::new(),
+            nonce: Nonce::zero(),
         }
     }
+}
+
+/// The phase of the block execution in which an event was emitted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Phase {
+    /// The event was emitted by the `on_initialize` hooks of the pallets, before the extrinsics.
+    Initialization,
+    /// The event was emitted while applying the extrinsic at this index of the block.
+    ApplyExtrinsic(u32),
+    /// The event was emitted by the `on_finalize` hooks of the pallets, after the extrinsics.
+    Finalization,
+}
 
+/// An event emitted during the current block, along with the phase in which it was emitted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EventRecord<Event> {
+    pub phase: Phase,
+    pub event: Event,
+}
+
+#[derive(Debug)]
+#[macros::storage]
+pub struct Pallet<T: Config> {
+    #[default(T::BlockNumber::zero())]
+    block_number: StorageValue<T::BlockNumber>,
+    // keep track of the nonce of every account
+    #[default(AccountInfo::default())]
+    account: StorageMap<T::AccountId, AccountInfo<T::Nonce>>,
+    // the events emitted during the current block, cleared when a new block starts
+    events: Vec<EventRecord<T::RuntimeEvent>>,
+    // the events emitted by the calls of this pallet, taken by the runtime after every dispatch
+    pallet_events: Vec<Event<T>>,
+    // the `spec_version` of the runtime which last upgraded the state
+    last_runtime_upgrade: StorageValue<u32>,
+    // the minimum `spec_version` of the runtime executing the chain, set by `set_code`
+    code_upgrade: StorageValue<u32>,
+    // the account allowed to make the root calls
+    root_key: StorageValue<T::AccountId>,
+    // whether the accounts without a key can make extrinsics, on development chains
+    #[default(false)]
+    keyless_accounts: StorageValue<bool>,
+    // the hash identifying the chain, checked by the extrinsics
+    #[default([0; 32])]
+    genesis_hash: StorageValue<[u8; 32]>,
+    // the raw storage changes made by the root calls, applied by the runtime after the dispatch
+    storage_changes: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    // the calls blocked by root, which the base call filter of the runtime rejects
+    #[default(Vec::new())]
+    blocked_calls: StorageValue<Vec<BlockedCalls>>,
+    // the last call blocked by a call filter during the current extrinsic, reported by the runtime
+    filtered_call: Option<(&'static str, &'static str)>,
+}
+
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> Pallet<T> {
     /// Get the current block number.
     pub fn block_number(&self) -> T::BlockNumber {
-        self.block_number
+        self.block_number.get()
     }
 
     // This function can be used to increment the block number.
     // Increases the block number by one.
     pub fn inc_block_number(&mut self) {
-        self.block_number += T::BlockNumber::one();
+        self.block_number
+            .mutate(|n| *n += T::BlockNumber::one());
+    }
+
+    /// Get the information kept about an account.
+    pub fn account(&self, who: &T::AccountId) -> AccountInfo<T::Nonce> {
+        self.account.get(who)
+    }
+
+    /// Get the nonce of an account, which is the number of transactions it has made.
+    pub fn nonce(&self, who: &T::AccountId) -> T::Nonce {
+        self.account(who).nonce
     }
 
     // Increment the nonce of an account. This helps us keep track of how many transactions each
     // account has made.
     pub fn inc_nonce(&mut self, who: &T::AccountId) {
-        let nonce = *self
-            .nonce
-            .get(&who)
-            .unwrap_or(&T::Nonce::zero());
-        let new_nonce = nonce + T::Nonce::one();
-        self.nonce
-            .insert(who.clone(), new_nonce);
+        self.account
+            .mutate(who, |account| {
+                account.nonce = account.nonce + T::Nonce::one()
+            });
+    }
+
+    /// Record an event emitted during the given phase of the current block.
+    pub fn deposit_event(&mut self, phase: Phase, event: T::RuntimeEvent) {
+        self.events
+            .push(EventRecord { phase, event });
+    }
+
+    /// Get all the events emitted so far in the current block.
+    pub fn events(&self) -> &[EventRecord<T::RuntimeEvent>] {
+        &self.events
+    }
+
+    /// Clear the events of the previous block. This is called when a new block starts.
+    pub fn reset_events(&mut self) {
+        self.events.clear();
+    }
+
+    /// The `spec_version` of the runtime which last upgraded the state, if any.
+    pub fn last_runtime_upgrade(&self) -> Option<u32> {
+        self.last_runtime_upgrade
+            .try_get()
+    }
+
+    /// Record that the state was upgraded to the runtime with the given `spec_version`.
+    ///
+    /// This fulfills the code upgrade set by `set_code`, if the runtime is recent enough.
+    pub fn note_runtime_upgrade(&mut self, spec_version: u32) {
+        self.last_runtime_upgrade
+            .set(spec_version);
+        if self
+            .code_upgrade()
+            .is_some_and(|required| required <= spec_version)
+        {
+            self.code_upgrade.kill();
+        }
+    }
+
+    /// The minimum `spec_version` of the runtime which can execute the next blocks, if a code
+    /// upgrade was set by `set_code` and the chain is not yet executed by such a runtime.
+    pub fn code_upgrade(&self) -> Option<u32> {
+        self.code_upgrade.try_get()
+    }
+
+    /// Set the account allowed to make the root calls, like `set_storage`.
+    pub fn set_root_key(&mut self, who: T::AccountId) {
+        self.root_key.set(who);
+    }
+
+    /// Whether the accounts without a key, like the development accounts `alice` and `bob`, can
+    /// make extrinsics without a signature.
+    pub fn keyless_accounts_allowed(&self) -> bool {
+        self.keyless_accounts.get()
+    }
+
+    /// Allow the accounts without a key to make extrinsics, at the genesis of a development chain.
+    /// Anyone can make their extrinsics, so they must never hold funds on a public chain.
+    pub fn allow_keyless_accounts(&mut self) {
+        self.keyless_accounts
+            .set(true);
+    }
+
+    /// The hash identifying the chain, which extrinsics must be built for.
+    pub fn genesis_hash(&self) -> [u8; 32] {
+        self.genesis_hash.get()
+    }
+
+    /// Set the hash identifying the chain, at genesis.
+    pub fn set_genesis_hash(&mut self, hash: [u8; 32]) {
+        self.genesis_hash.set(hash);
+    }
+
+    /// Check that `caller` is the account allowed to make the root calls.
+    pub fn ensure_root(&self, caller: &T::AccountId) -> DispatchResult {
+        match self.root_key.try_get() {
+            Some(root) if &root == caller => Ok(()),
+            _ => Err(NOT_ROOT),
+        }
+    }
+
+    fn deposit_pallet_event(&mut self, event: Event<T>) {
+        self.pallet_events.push(event);
+    }
+
+    /// Take the events emitted by the calls of this pallet since the last call, so the runtime can
+    /// record them.
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        std::mem::take(&mut self.pallet_events)
+    }
+
+    /// The calls blocked by root.
+    pub fn blocked_calls(&self) -> Vec<BlockedCalls> {
+        self.blocked_calls.get()
+    }
+
+    /// Whether root blocked the call `call` of the pallet `pallet`.
+    pub fn is_call_blocked(&self, pallet: &str, call: &str) -> bool {
+        self.blocked_calls()
+            .iter()
+            .any(|blocked| blocked.matches(pallet, call))
+    }
+
+    /// Record that the call `call` of the pallet `pallet` was blocked by a call filter.
+    pub fn note_filtered_call(&mut self, pallet: &'static str, call: &'static str) {
+        self.filtered_call = Some((pallet, call));
+    }
+
+    /// Take the call blocked by a call filter since the last call, so the runtime can report it.
+    pub fn take_filtered_call(&mut self) -> Option<(&'static str, &'static str)> {
+        self.filtered_call.take()
+    }
+
+    /// Take the raw storage changes made by `set_storage` and `kill_storage` since the last call,
+    /// so the runtime can apply them to the storage of any pallet.
+    pub fn take_storage_changes(&mut self) -> Vec<(Vec<u8>, Option<Vec<u8>>)> {
+        std::mem::take(&mut self.storage_changes)
+    }
+}
+
+#[macros::call]
+#[errors(
+    NOT_ROOT,
+    storage::UNKNOWN_KEY,
+    "spec version must increase",
+    "the calls of the system pallet can't be blocked",
+    "the calls are already blocked",
+    "too many blocked calls",
+    "the calls are not blocked"
+)]
+impl<T: Config> Pallet<T> {
+    /// Make a remark. It does nothing, but the remark is kept in the block.
+    #[call_index(0)]
+    #[weight(T::SystemWeightInfo::remark(remark.len() as u32))]
+    pub fn remark(&mut self, _caller: T::AccountId, remark: Vec<u8>) -> DispatchResult {
+        let _ = remark;
+        Ok(())
+    }
+
+    /// Make a remark, and emit an event with its hash.
+    #[call_index(1)]
+    #[weight(T::SystemWeightInfo::remark_with_event(remark.len() as u32))]
+    pub fn remark_with_event(&mut self, caller: T::AccountId, remark: Vec<u8>) -> DispatchResult {
+        let hash = storage::blake2_256(&remark);
+        self.deposit_pallet_event(Event::Remarked {
+            sender: caller,
+            hash,
+        });
+        Ok(())
+    }
+
+    /// Set raw storage values, of any pallet. Only root can call it.
+    ///
+    /// The values must be encoded like the storage item they belong to expects.
+    #[call_index(2)]
+    #[weight(T::SystemWeightInfo::set_storage(items.len() as u32))]
+    pub fn set_storage(
+        &mut self,
+        caller: T::AccountId,
+        items: Vec<(Vec<u8>, Vec<u8>)>,
+    ) -> DispatchResult {
+        self.ensure_root(&caller)?;
+        self.storage_changes.extend(
+            items
+                .into_iter()
+                .map(|(key, value)| (key, Some(value))),
+        );
+        Ok(())
+    }
+
+    /// Remove raw storage values, of any pallet. Only root can call it.
+    #[call_index(3)]
+    #[weight(T::SystemWeightInfo::kill_storage(keys.len() as u32))]
+    pub fn kill_storage(&mut self, caller: T::AccountId, keys: Vec<Vec<u8>>) -> DispatchResult {
+        self.ensure_root(&caller)?;
+        self.storage_changes.extend(
+            keys.into_iter()
+                .map(|key| (key, None)),
+        );
+        Ok(())
+    }
+
+    /// Upgrade the chain to the runtime with the given `spec_version`. Only root can call it.
+    ///
+    /// Since the runtime is part of the node, the code itself cannot be replaced by a call.
+    /// Instead, the next blocks can only be executed by a runtime with at least this
+    /// `spec_version`, which migrates the storage when executing its first block.
+    #[call_index(4)]
+    #[weight(T::SystemWeightInfo::set_code())]
+    pub fn set_code(&mut self, caller: T::AccountId, spec_version: u32) -> DispatchResult {
+        self.ensure_root(&caller)?;
+        if self
+            .last_runtime_upgrade()
+            .is_some_and(|current| spec_version <= current)
+        {
+            return Err("spec version must increase");
+        }
+        self.code_upgrade
+            .set(spec_version);
+        self.deposit_pallet_event(Event::CodeUpdated { spec_version });
+        Ok(())
+    }
+
+    /// Block the calls of the pallet `pallet`, or only its call `call` if given, until they are
+    /// unblocked. Only root can call it.
+    ///
+    /// The calls of the system pallet can't be blocked, so root can always unblock the others. At
+    /// most `MAX_BLOCKED_CALLS` calls can be blocked at once.
+    #[call_index(5)]
+    #[weight(T::SystemWeightInfo::block_calls(MAX_BLOCKED_CALLS))]
+    pub fn block_calls(
+        &mut self,
+        caller: T::AccountId,
+        pallet: String,
+        call: Option<String>,
+    ) -> DispatchResult {
+        self.ensure_root(&caller)?;
+        if pallet == "system" {
+            return Err("the calls of the system pallet can't be blocked");
+        }
+        let blocked = BlockedCalls { pallet, call };
+        let mut blocked_calls = self.blocked_calls();
+        if blocked_calls.contains(&blocked) {
+            return Err("the calls are already blocked");
+        }
+        if blocked_calls.len() >= MAX_BLOCKED_CALLS as usize {
+            return Err("too many blocked calls");
+        }
+        blocked_calls.push(blocked.clone());
+        self.blocked_calls
+            .set(blocked_calls);
+        self.deposit_pallet_event(Event::CallsBlocked { blocked });
+        Ok(())
+    }
+
+    /// Unblock calls blocked by `block_calls`, with the same arguments. Only root can call it.
+    #[call_index(6)]
+    #[weight(T::SystemWeightInfo::unblock_calls(MAX_BLOCKED_CALLS))]
+    pub fn unblock_calls(
+        &mut self,
+        caller: T::AccountId,
+        pallet: String,
+        call: Option<String>,
+    ) -> DispatchResult {
+        self.ensure_root(&caller)?;
+        let blocked = BlockedCalls { pallet, call };
+        let mut blocked_calls = self.blocked_calls();
+        let index = blocked_calls
+            .iter()
+            .position(|calls| calls == &blocked)
+            .ok_or("the calls are not blocked")?;
+        blocked_calls.remove(index);
+        match blocked_calls.is_empty() {
+            true => self.blocked_calls.kill(),
+            false => self
+                .blocked_calls
+                .set(blocked_calls),
+        }
+        self.deposit_pallet_event(Event::CallsUnblocked { blocked });
+        Ok(())
+    }
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+
+/// The remarks are hashed by `remark_with_event`, so the weight of both remarks depends on their
+/// size `r`. The root calls depend on the number `i` of storage items they change, or on the number
+/// `b` of calls already blocked.
+impl<T: Config> Benchmarks for Pallet<T>
+where
+    T::AccountId: From<&'static str>,
+{
+    /// The number of blocked calls.
+    type Setup = u32;
+
+    /// A pallet whose root is `root`, with `blocked` calls blocked.
+    fn setup(blocked: u32) -> Self {
+        let mut pallet = Pallet::new();
+        pallet.set_root_key("root".into());
+        pallet.blocked_calls.set(
+            (0..blocked)
+                .map(|i| BlockedCalls {
+                    pallet: format!("pallet_{i}"),
+                    call: None,
+                })
+                .collect(),
+        );
+        pallet
+    }
+
+    fn benchmarks() -> Vec<Benchmark<Self>> {
+        let remark = Component {
+            name: "r",
+            min: 0,
+            max: MAX_BENCHMARKED_REMARK,
+        };
+        let items = Component {
+            name: "i",
+            min: 0,
+            max: MAX_BENCHMARKED_STORAGE_ITEMS,
+        };
+        let blocked = Component {
+            name: "b",
+            min: 0,
+            max: MAX_BLOCKED_CALLS - 1,
+        };
+        vec![
+            Benchmark {
+                name: "remark",
+                components: vec![remark],
+                setup: |r| {
+                    let call = Call::remark {
+                        remark: vec![0; r[0] as usize],
+                    };
+                    (Self::setup(0), "caller".into(), call)
+                },
+                verify: |_, _| Ok(()),
+            },
+            Benchmark {
+                name: "remark_with_event",
+                components: vec![remark],
+                setup: |r| {
+                    let call = Call::remark_with_event {
+                        remark: vec![0; r[0] as usize],
+                    };
+                    (Self::setup(0), "caller".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.pallet_events.len() == 1),
+            },
+            Benchmark {
+                name: "set_storage",
+                components: vec![items],
+                setup: |i| {
+                    let call = Call::set_storage {
+                        items: (0..i[0])
+                            .map(|i| (i.to_be_bytes().to_vec(), vec![0; 32]))
+                            .collect(),
+                    };
+                    (Self::setup(0), "root".into(), call)
+                },
+                verify: |pallet, i| ensure(pallet.storage_changes.len() == i[0] as usize),
+            },
+            Benchmark {
+                name: "kill_storage",
+                components: vec![items],
+                setup: |i| {
+                    let call = Call::kill_storage {
+                        keys: (0..i[0])
+                            .map(|i| i.to_be_bytes().to_vec())
+                            .collect(),
+                    };
+                    (Self::setup(0), "root".into(), call)
+                },
+                verify: |pallet, i| ensure(pallet.storage_changes.len() == i[0] as usize),
+            },
+            Benchmark {
+                name: "set_code",
+                components: vec![],
+                setup: |_| {
+                    (
+                        Self::setup(0),
+                        "root".into(),
+                        Call::set_code { spec_version: 2 },
+                    )
+                },
+                verify: |pallet, _| ensure(pallet.code_upgrade.try_get() == Some(2)),
+            },
+            Benchmark {
+                name: "block_calls",
+                components: vec![blocked],
+                setup: |b| {
+                    let call = Call::block_calls {
+                        pallet: "balances".to_string(),
+                        call: Some("transfer".to_string()),
+                    };
+                    (Self::setup(b[0]), "root".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.is_call_blocked("balances", "transfer")),
+            },
+            Benchmark {
+                name: "unblock_calls",
+                components: vec![Component {
+                    min: 1,
+                    max: MAX_BLOCKED_CALLS,
+                    ..blocked
+                }],
+                // The last blocked calls are unblocked, so they are compared to all the others.
+                setup: |b| {
+                    let call = Call::unblock_calls {
+                        pallet: format!("pallet_{}", b[0] - 1),
+                        call: None,
+                    };
+                    (Self::setup(b[0]), "root".into(), call)
+                },
+                verify: |pallet, b| ensure(pallet.blocked_calls().len() == b[0] as usize - 1),
+            },
+        ]
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::support::benchmarking::{
+        self,
+        BenchmarkConfig,
+    };
+
     struct TestConfig;
     impl super::Config for TestConfig {
         type AccountId = String;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = super::Event<TestConfig>;
+        type SystemWeightInfo = ();
+    }
+
+    #[test]
+    fn benchmarks_succeed() {
+        let config = BenchmarkConfig {
+            steps: 2,
+            repeat: 1,
+        };
+        let results = benchmarking::run::<super::Pallet<TestConfig>>(&config).unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| (result.name, result.slopes.len()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("remark", 1),
+                ("remark_with_event", 1),
+                ("set_storage", 1),
+                ("kill_storage", 1),
+                ("set_code", 0),
+                ("block_calls", 1),
+                ("unblock_calls", 1),
+            ]
+        );
     }
 
     #[test]
@@ -73,15 +663,71 @@ mod tests {
         assert_eq!(system.block_number(), 1);
         assert_eq!(
             system
-                .nonce
-                .get(&"alice".to_string()),
-            Some(&1)
+                .account
+                .try_get(&"alice".to_string()),
+            Some(super::AccountInfo { nonce: 1 })
         );
         assert_eq!(
             system
-                .nonce
-                .get(&"bob".to_string()),
+                .account
+                .try_get(&"bob".to_string()),
             None
         );
+        assert_eq!(system.nonce(&"alice".to_string()), 1);
+        assert_eq!(system.nonce(&"bob".to_string()), 0);
+    }
+
+    #[test]
+    fn deposit_and_reset_events() {
+        let mut system = super::Pallet::<TestConfig>::new();
+        system.deposit_event(
+            super::Phase::ApplyExtrinsic(0),
+            super::Event::ExtrinsicSuccess,
+        );
+
+        assert_eq!(
+            system.events(),
+            &[super::EventRecord {
+                phase: super::Phase::ApplyExtrinsic(0),
+                event: super::Event::ExtrinsicSuccess,
+            }]
+        );
+
+        system.reset_events();
+        assert!(system.events().is_empty());
+    }
+
+    #[test]
+    fn root_calls() {
+        let mut system = super::Pallet::<TestConfig>::new();
+        let (root, alice) = ("root".to_string(), "alice".to_string());
+        system.note_runtime_upgrade(1);
+        system.set_root_key(root.clone());
+
+        assert_eq!(
+            system.kill_storage(alice.clone(), vec![vec![1]]),
+            Err("caller is not root")
+        );
+        system
+            .kill_storage(root.clone(), vec![vec![1]])
+            .unwrap();
+        assert_eq!(system.take_storage_changes(), vec![(vec![1], None)]);
+
+        assert_eq!(
+            system.set_code(root.clone(), 1),
+            Err("spec version must increase")
+        );
+        system
+            .set_code(root, 2)
+            .unwrap();
+        assert_eq!(system.code_upgrade(), Some(2));
+        assert_eq!(
+            system.take_events(),
+            vec![super::Event::CodeUpdated { spec_version: 2 }]
+        );
+
+        // The upgrade is fulfilled by a runtime with the new version.
+        system.note_runtime_upgrade(2);
+        assert_eq!(system.code_upgrade(), None);
     }
 }
@@ -0,0 +1,81 @@
+//! The weights of the `system` pallet, measured by its benchmarks.
+//!
+//! Generated by `cargo run --release --bin benchmark`, do not edit by hand. The unit of weight is
+//! one nanosecond of execution on the machine which ran the benchmarks.
+
+use crate::support::Weight;
+
+/// The weights of the callable functions of the pallet.
+pub trait WeightInfo {
+    fn remark(r: u32) -> Weight;
+    fn remark_with_event(r: u32) -> Weight;
+    fn set_storage(i: u32) -> Weight;
+    fn kill_storage(i: u32) -> Weight;
+    fn set_code() -> Weight;
+    fn block_calls(b: u32) -> Weight;
+    fn unblock_calls(b: u32) -> Weight;
+}
+
+/// The weights measured by the benchmarks.
+pub struct BenchmarkedWeights;
+
+impl WeightInfo for BenchmarkedWeights {
+    fn remark(r: u32) -> Weight {
+        56 + Weight::from(r)
+    }
+
+    fn remark_with_event(r: u32) -> Weight {
+        318 + 2 * Weight::from(r)
+    }
+
+    fn set_storage(i: u32) -> Weight {
+        127 + 10 * Weight::from(i)
+    }
+
+    fn kill_storage(i: u32) -> Weight {
+        239 + 3 * Weight::from(i)
+    }
+
+    fn set_code() -> Weight {
+        345
+    }
+
+    fn block_calls(b: u32) -> Weight {
+        333 + 154 * Weight::from(b)
+    }
+
+    fn unblock_calls(b: u32) -> Weight {
+        285 + 94 * Weight::from(b)
+    }
+}
+
+/// No weights, for tests.
+impl WeightInfo for () {
+    fn remark(_r: u32) -> Weight {
+        0
+    }
+
+    fn remark_with_event(_r: u32) -> Weight {
+        0
+    }
+
+    fn set_storage(_i: u32) -> Weight {
+        0
+    }
+
+    fn kill_storage(_i: u32) -> Weight {
+        0
+    }
+
+    fn set_code() -> Weight {
+        0
+    }
+
+    fn block_calls(_b: u32) -> Weight {
+        0
+    }
+
+    fn unblock_calls(_b: u32) -> Weight {
+        0
+    }
+}
@@ -0,0 +1,448 @@
+//! Helpers for testing the pallets inside the full runtime.
+//!
+//! `ExtBuilder` creates a runtime from a genesis state, and `MockRuntime` runs blocks of calls on
+//! it, filling in the envelope of every extrinsic. The `strategies` generate random calls for
+//! property-based tests, which check the invariants of the pallets over any sequence of blocks.
+
+use std::{
+    collections::BTreeMap,
+    ops::{
+        Deref,
+        DerefMut,
+    },
+};
+
+use crate::{
+    runtime::{
+        types,
+        Runtime,
+        RuntimeCall,
+        RuntimeEvent,
+        VERSION,
+    },
+    support::{
+        self,
+        storage::Storage,
+    },
+};
+
+/// The accounts used by the tests.
+pub const ACCOUNTS: &[&str] = &["alice", "bob", "charlie", "dave"];
+
+/// Build a `MockRuntime` with the given genesis state.
+#[derive(Debug, Default)]
+pub struct ExtBuilder {
+    balances: Vec<(types::AccountId, types::Balance)>,
+    stablecoin: Vec<(types::AccountId, types::Balance)>,
+    root_key: Option<types::AccountId>,
+}
+
+impl ExtBuilder {
+    /// Endow `who` with `amount` of the native token.
+    pub fn balance(mut self, who: &str, amount: types::Balance) -> Self {
+        self.balances
+            .push((who.to_string(), amount));
+        self
+    }
+
+    /// Endow `who` with `amount` of the stablecoin.
+    pub fn stablecoin(mut self, who: &str, amount: types::Balance) -> Self {
+        self.stablecoin
+            .push((who.to_string(), amount));
+        self
+    }
+
+    /// Make `who` the root account.
+    pub fn root_key(mut self, who: &str) -> Self {
+        self.root_key = Some(who.to_string());
+        self
+    }
+
+    pub fn build(self) -> MockRuntime {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        for (who, amount) in self.balances {
+            runtime
+                .balances
+                .set_balance(who, amount);
+        }
+        for (who, amount) in self.stablecoin {
+            runtime
+                .stablecoin
+                .set_balance(who, amount);
+        }
+        if let Some(root) = self.root_key {
+            runtime
+                .system
+                .set_root_key(root);
+        }
+        // Like on a node, the chain is identified by the state root of its genesis state.
+        let genesis_hash = runtime.state_root();
+        runtime
+            .system
+            .set_genesis_hash(genesis_hash);
+        MockRuntime { runtime }
+    }
+}
+
+/// A runtime running blocks of calls. It gives access to the runtime itself.
+#[derive(Debug)]
+pub struct MockRuntime {
+    runtime: Runtime,
+}
+
+impl Deref for MockRuntime {
+    type Target = Runtime;
+
+    fn deref(&self) -> &Runtime {
+        &self.runtime
+    }
+}
+
+impl DerefMut for MockRuntime {
+    fn deref_mut(&mut self) -> &mut Runtime {
+        &mut self.runtime
+    }
+}
+
+impl MockRuntime {
+    /// An extrinsic of `caller`, valid forever on this chain.
+    pub fn extrinsic(
+        &self,
+        caller: &str,
+        nonce: types::Nonce,
+        tip: types::Balance,
+        call: RuntimeCall,
+    ) -> types::Extrinsic {
+        support::Extrinsic {
+            caller: caller.to_string(),
+            signature: None,
+            nonce,
+            tip,
+            era: support::Era::Immortal,
+            spec_version: VERSION.spec_version,
+            genesis_hash: self.system.genesis_hash(),
+            call,
+        }
+    }
+
+    /// Execute the next block, with an extrinsic for every call.
+    pub fn run_block(&mut self, calls: Vec<(&str, RuntimeCall)>) -> types::BlockExecutionReport {
+        let calls = calls
+            .into_iter()
+            .map(|(caller, call)| (caller, 0, call))
+            .collect();
+        self.run_tipped_block(calls)
+    }
+
+    /// Execute the next block, with an extrinsic for every call, paying the given tip.
+    ///
+    /// The nonces of the extrinsics of every caller follow its current nonce.
+    pub fn run_tipped_block(
+        &mut self,
+        calls: Vec<(&str, types::Balance, RuntimeCall)>,
+    ) -> types::BlockExecutionReport {
+        let mut nonces = BTreeMap::new();
+        let extrinsics = calls
+            .into_iter()
+            .map(|(caller, tip, call)| {
+                let nonce = nonces
+                    .entry(caller)
+                    .or_insert_with(|| {
+                        self.system
+                            .nonce(&caller.to_string())
+                    });
+                let extrinsic = self.extrinsic(caller, *nonce, tip, call);
+                *nonce += 1;
+                extrinsic
+            })
+            .collect();
+        self.execute(extrinsics)
+            .expect("the block is valid")
+    }
+
+    /// Execute the next block, with the given extrinsics.
+    pub fn execute(
+        &mut self,
+        extrinsics: Vec<types::Extrinsic>,
+    ) -> Result<types::BlockExecutionReport, &'static str> {
+        let block = types::Block {
+            header: support::Header {
+                block_number: self.system.block_number() + 1,
+                ..Default::default()
+            },
+            extrinsics,
+        };
+        self.runtime
+            .execute_block(block)
+    }
+
+    /// The events emitted in the last block, in order.
+    pub fn events(&self) -> Vec<RuntimeEvent> {
+        self.system
+            .events()
+            .iter()
+            .map(|record| record.event.clone())
+            .collect()
+    }
+
+    /// Check that the last block emitted exactly the given events, in order.
+    #[track_caller]
+    pub fn assert_events(&self, expected: Vec<RuntimeEvent>) {
+        assert_eq!(self.events(), expected);
+    }
+
+    /// Check that the last block emitted the given event.
+    #[track_caller]
+    pub fn assert_has_event(&self, event: RuntimeEvent) {
+        let events = self.events();
+        assert!(events.contains(&event), "{event:?} not found in {events:?}");
+    }
+}
+
+/// Generators of random calls, for property-based tests with `proptest`.
+pub mod strategies {
+    use proptest::{
+        collection,
+        prelude::*,
+        sample,
+    };
+
+    use super::ACCOUNTS;
+    use crate::{
+        balances,
+        proof_of_existence,
+        runtime::{
+            types,
+            RuntimeCall,
+        },
+        system,
+    };
+
+    /// The contents claimed by the tests.
+    pub const CLAIMS: &[&str] = &["doc1", "doc2", "doc3"];
+
+    pub fn account() -> impl Strategy<Value = types::AccountId> {
+        sample::select(ACCOUNTS).prop_map(str::to_string)
+    }
+
+    /// Mostly small amounts, but also amounts close to the maximum, to trigger overflows.
+    pub fn amount() -> impl Strategy<Value = types::Balance> {
+        prop_oneof![
+            4 => 0..=1_000u128,
+            1 => (u128::MAX - 1_000)..=u128::MAX,
+        ]
+    }
+
+    pub fn claim() -> impl Strategy<Value = types::Content> {
+        sample::select(CLAIMS).prop_map(str::to_string)
+    }
+
+    /// Any call of the runtime which can be made by a signed account.
+    pub fn call() -> impl Strategy<Value = RuntimeCall> {
+        prop_oneof![
+            3 => (account(), amount()).prop_map(|(to, amount)| {
+                RuntimeCall::balances(balances::Call::transfer { to, amount })
+            }),
+            1 => (account(), amount()).prop_map(|(to, amount)| {
+                RuntimeCall::stablecoin(balances::Call::transfer { to, amount })
+            }),
+            2 => claim().prop_map(|claim| {
+                RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim { claim })
+            }),
+            2 => claim().prop_map(|claim| {
+                RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim { claim })
+            }),
+            1 => collection::vec(any::<u8>(), 0..8).prop_map(|remark| {
+                RuntimeCall::system(system::Call::remark_with_event { remark })
+            }),
+        ]
+    }
+
+    /// The calls of a block: their caller, tip and call.
+    pub fn block() -> impl Strategy<Value = Vec<(types::AccountId, types::Balance, RuntimeCall)>> {
+        collection::vec((account(), 0..=10u128, call()), 0..8)
+    }
+
+    /// The balances of the accounts at genesis, small enough that their total does not overflow.
+    pub fn genesis() -> impl Strategy<Value = Vec<types::Balance>> {
+        collection::vec(0..=u128::MAX / 8, ACCOUNTS.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use proptest::prelude::*;
+
+    use super::{
+        strategies,
+        ExtBuilder,
+        MockRuntime,
+        ACCOUNTS,
+    };
+    use crate::{
+        balances,
+        proof_of_existence,
+        runtime::{
+            extensions::TREASURY_FEE_SHARE,
+            types,
+            RuntimeCall,
+            RuntimeEvent,
+        },
+        support::ExtrinsicError,
+    };
+
+    fn mock(genesis: &[types::Balance]) -> MockRuntime {
+        ACCOUNTS
+            .iter()
+            .zip(genesis)
+            .fold(ExtBuilder::default(), |builder, (who, amount)| {
+                builder
+                    .balance(who, *amount)
+                    .stablecoin(who, *amount)
+            })
+            .build()
+    }
+
+    fn run_block(
+        mock: &mut MockRuntime,
+        calls: &[(types::AccountId, types::Balance, RuntimeCall)],
+    ) -> types::BlockExecutionReport {
+        let calls = calls
+            .iter()
+            .map(|(caller, tip, call)| (caller.as_str(), *tip, call.clone()))
+            .collect();
+        mock.run_tipped_block(calls)
+    }
+
+    #[test]
+    fn run_blocks_of_calls() {
+        let mut mock = ExtBuilder::default()
+            .balance("alice", 200)
+            .build();
+        let transfer = |amount| {
+            RuntimeCall::balances(balances::Call::transfer {
+                to: "bob".to_string(),
+                amount,
+            })
+        };
+
+        let report = mock.run_block(vec![("alice", transfer(30)), ("alice", transfer(180))]);
+        assert_eq!(
+            report
+                .failed()
+                .map(|(extrinsic, error)| (extrinsic.index, error))
+                .collect::<Vec<_>>(),
+            vec![(1, ExtrinsicError::Dispatch("Insufficient balance"))]
+        );
+        mock.assert_has_event(RuntimeEvent::balances(balances::Event::Transfer {
+            from: "alice".to_string(),
+            to: "bob".to_string(),
+            amount: 30,
+        }));
+
+        // The nonces follow the ones of the previous block.
+        mock.run_block(vec![("alice", transfer(50))]);
+        assert_eq!(mock.system.block_number(), 2);
+        assert_eq!(
+            mock.balances
+                .balance("bob".to_string()),
+            80
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        /// Transfers move balances between accounts, and fees burn them except for the share of
+        /// the treasury: no balance is ever created.
+        #[test]
+        fn balances_are_conserved(
+            genesis in strategies::genesis(),
+            blocks in proptest::collection::vec(strategies::block(), 1..5),
+        ) {
+            let mut mock = mock(&genesis);
+            let mut issuance = mock.balances.total_issuance().unwrap();
+            let stablecoin_issuance = mock.stablecoin.total_issuance().unwrap();
+
+            for calls in &blocks {
+                let report = run_block(&mut mock, calls);
+                let burnt = report
+                    .extrinsics
+                    .iter()
+                    .map(|extrinsic| extrinsic.fee - TREASURY_FEE_SHARE.mul_floor(extrinsic.fee))
+                    .sum::<u128>();
+                issuance -= burnt;
+                prop_assert_eq!(mock.balances.total_issuance(), Ok(issuance));
+                prop_assert_eq!(mock.stablecoin.total_issuance(), Ok(stablecoin_issuance));
+            }
+        }
+
+        /// Every content is claimed by at most one account, and only its owner can revoke it.
+        #[test]
+        fn claims_have_a_single_owner(
+            blocks in proptest::collection::vec(strategies::block(), 1..5),
+        ) {
+            let mut mock = mock(&[1_000; 4]);
+            let mut owners = BTreeMap::new();
+
+            for calls in &blocks {
+                let report = run_block(&mut mock, calls);
+                for (extrinsic, (caller, _, call)) in report.extrinsics.iter().zip(calls) {
+                    let RuntimeCall::proof_of_existence(call) = call else { continue };
+                    if extrinsic.result.is_err() {
+                        continue;
+                    }
+                    match call {
+                        proof_of_existence::Call::create_claim { claim } => {
+                            let previous = owners.insert(claim.clone(), caller.clone());
+                            prop_assert_eq!(previous, None);
+                        }
+                        proof_of_existence::Call::revoke_claim { claim } => {
+                            let owner = owners.remove(claim);
+                            prop_assert_eq!(owner.as_ref(), Some(caller));
+                        }
+                        _ => {}
+                    }
+                }
+                for claim in strategies::CLAIMS {
+                    let claim = claim.to_string();
+                    prop_assert_eq!(
+                        mock.proof_of_existence.get_claim(&claim),
+                        owners.get(&claim).cloned()
+                    );
+                }
+            }
+        }
+
+        /// The nonce of an account counts its extrinsics which passed the checks of the runtime.
+        #[test]
+        fn nonces_count_the_valid_extrinsics(
+            genesis in strategies::genesis(),
+            blocks in proptest::collection::vec(strategies::block(), 1..5),
+        ) {
+            let mut mock = mock(&genesis);
+            let mut expected = BTreeMap::<types::AccountId, types::Nonce>::new();
+
+            for calls in &blocks {
+                let report = run_block(&mut mock, calls);
+                for extrinsic in &report.extrinsics {
+                    if !matches!(extrinsic.result, Err(ExtrinsicError::Invalid(_))) {
+                        *expected.entry(extrinsic.caller.clone()).or_default() += 1;
+                    }
+                }
+            }
+            for who in ACCOUNTS {
+                let who = who.to_string();
+                prop_assert_eq!(
+                    mock.system.nonce(&who),
+                    expected.get(&who).copied().unwrap_or_default()
+                );
+            }
+        }
+    }
+}
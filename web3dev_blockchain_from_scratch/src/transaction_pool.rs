@@ -0,0 +1,398 @@
+//! The transaction queue of the node.
+//!
+//! Submitted extrinsics are validated against the current state with the runtime's
+//! `support::ValidateTransaction` checks, and wait here until the block builder includes them. The
+//! block builder checks them again, since they can become invalid while waiting, like when their
+//! era ends.
+//!
+//! The extrinsics of an account are always included in nonce order. Between accounts, the
+//! extrinsic with the highest priority (i.e. the highest tip) goes first.
+
+use std::collections::{
+    BTreeMap,
+    BinaryHeap,
+};
+
+use serde::Serialize;
+
+use crate::{
+    runtime::{
+        types,
+        Runtime,
+    },
+    support::{
+        ValidateTransaction,
+        Weight,
+        MAX_BLOCK_WEIGHT,
+    },
+};
+
+/// The maximum number of extrinsics waiting in the queue.
+pub const MAX_POOL_SIZE: usize = 8192;
+
+/// Whether a queued extrinsic can be included in the next block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionStatus {
+    /// The extrinsic uses the current nonce of its caller, or follows other queued extrinsics of
+    /// the same caller.
+    Ready,
+    /// The extrinsic waits for an extrinsic with a lower nonce of the same caller.
+    Future,
+}
+
+#[derive(Clone, Debug)]
+struct PooledTransaction {
+    extrinsic: types::Extrinsic,
+    priority: u64,
+    /// Used to order extrinsics with the same priority by arrival.
+    insertion_id: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct TransactionPool {
+    /// The queued extrinsics of each account, by nonce.
+    pending: BTreeMap<types::AccountId, BTreeMap<types::Nonce, PooledTransaction>>,
+    next_insertion_id: u64,
+}
+
+impl TransactionPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of queued extrinsics.
+    pub fn len(&self) -> usize {
+        self.pending
+            .values()
+            .map(BTreeMap::len)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// All the queued extrinsics, ordered by account and nonce.
+    pub fn pending(&self) -> Vec<types::Extrinsic> {
+        self.pending
+            .values()
+            .flat_map(BTreeMap::values)
+            .map(|pooled| pooled.extrinsic.clone())
+            .collect()
+    }
+
+    /// Validate the extrinsic against the current state and add it to the queue.
+    ///
+    /// An extrinsic using the same nonce as a queued one replaces it only if it pays a higher tip.
+    pub fn submit(
+        &mut self,
+        runtime: &Runtime,
+        extrinsic: types::Extrinsic,
+    ) -> Result<TransactionStatus, &'static str> {
+        let valid = runtime.validate_transaction(&extrinsic)?;
+        let who = extrinsic.caller.clone();
+        let nonce = extrinsic.nonce;
+
+        let queued = self
+            .pending
+            .get(&who)
+            .and_then(|account| account.get(&nonce));
+        match queued {
+            Some(queued) if queued.priority >= valid.priority => {
+                return Err("an extrinsic with the same nonce and a higher priority is queued");
+            }
+            Some(_) => {}
+            None if self.len() >= MAX_POOL_SIZE => return Err("transaction pool is full"),
+            None => {}
+        }
+
+        let account = self
+            .pending
+            .entry(who.clone())
+            .or_default();
+        account.insert(
+            nonce,
+            PooledTransaction {
+                extrinsic,
+                priority: valid.priority,
+                insertion_id: self.next_insertion_id,
+            },
+        );
+        self.next_insertion_id += 1;
+
+        // The extrinsic is ready if there is no gap between the current nonce of the caller and
+        // its nonce.
+        let mut expected = runtime.system.nonce(&who);
+        for queued in account.keys() {
+            if *queued != expected {
+                break;
+            }
+            expected += 1;
+        }
+        Ok(if nonce < expected {
+            TransactionStatus::Ready
+        } else {
+            TransactionStatus::Future
+        })
+    }
+
+    /// Select up to `limit` extrinsics which can be included, in order, in the next block, weighing
+    /// at most `support::MAX_BLOCK_WEIGHT` together.
+    ///
+    /// For every account, only the extrinsics following its current nonce without gaps are
+    /// selected. The next extrinsic of every account competes with the others on priority.
+    /// Extrinsics which are no longer valid, or which don't fit in the weight left in the block,
+    /// are skipped, along with the following ones of the same account.
+    pub fn ready(&self, runtime: &Runtime, limit: usize) -> Vec<types::Extrinsic> {
+        // The candidates, ordered by priority and then by arrival. `Reverse` makes the oldest
+        // extrinsic win on equal priorities in the max-heap.
+        let mut candidates = BinaryHeap::new();
+        let push_next =
+            |candidates: &mut BinaryHeap<_>, who: &types::AccountId, nonce: types::Nonce| {
+                if let Some(pooled) = self
+                    .pending
+                    .get(who)
+                    .and_then(|account| account.get(&nonce))
+                    .filter(|pooled| {
+                        runtime
+                            .validate_transaction(&pooled.extrinsic)
+                            .is_ok()
+                    })
+                {
+                    candidates.push((
+                        pooled.priority,
+                        std::cmp::Reverse(pooled.insertion_id),
+                        who.clone(),
+                        nonce,
+                    ));
+                }
+            };
+
+        for who in self.pending.keys() {
+            push_next(&mut candidates, who, runtime.system.nonce(who));
+        }
+
+        let mut ready = Vec::new();
+        let mut weight: Weight = 0;
+        while ready.len() < limit {
+            let Some((_, _, who, nonce)) = candidates.pop() else {
+                break;
+            };
+            let extrinsic = &self.pending[&who][&nonce].extrinsic;
+            let total = weight.saturating_add(Runtime::weight(extrinsic));
+            if total > MAX_BLOCK_WEIGHT {
+                continue;
+            }
+            weight = total;
+            ready.push(extrinsic.clone());
+            push_next(&mut candidates, &who, nonce + 1);
+        }
+        ready
+    }
+
+    /// Remove the extrinsics which are no longer valid on top of the current state, for example
+    /// because they were included in a block or their era ended.
+    pub fn prune(&mut self, runtime: &Runtime) {
+        for account in self.pending.values_mut() {
+            account.retain(|_, pooled| {
+                runtime
+                    .validate_transaction(&pooled.extrinsic)
+                    .is_ok()
+            });
+        }
+        self.pending
+            .retain(|_, account| !account.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances,
+        runtime::RuntimeCall,
+        support,
+    };
+
+    fn transfer(caller: &str, nonce: types::Nonce, tip: types::Balance) -> types::Extrinsic {
+        support::Extrinsic {
+            caller: caller.to_string(),
+            signature: None,
+            nonce,
+            tip,
+            era: support::Era::Immortal,
+            spec_version: crate::runtime::VERSION.spec_version,
+            genesis_hash: [0; 32],
+            call: RuntimeCall::balances(balances::Call::transfer {
+                to: "charlie".to_string(),
+                amount: 1,
+            }),
+        }
+    }
+
+    fn runtime() -> Runtime {
+        let mut runtime = Runtime::new();
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+        runtime
+            .balances
+            .set_balance("bob".to_string(), 100);
+        runtime
+    }
+
+    fn order(extrinsics: &[types::Extrinsic]) -> Vec<(&str, types::Nonce)> {
+        extrinsics
+            .iter()
+            .map(|ext| (ext.caller.as_str(), ext.nonce))
+            .collect()
+    }
+
+    #[test]
+    fn submit_validates_extrinsics() {
+        let mut runtime = runtime();
+        let mut pool = TransactionPool::new();
+
+        assert_eq!(
+            pool.submit(&runtime, transfer("alice", 1, 0)),
+            Ok(TransactionStatus::Future)
+        );
+        assert_eq!(
+            pool.submit(&runtime, transfer("alice", 0, 0)),
+            Ok(TransactionStatus::Ready)
+        );
+        assert_eq!(
+            pool.submit(&runtime, transfer("alice", 0, 0)),
+            Err("an extrinsic with the same nonce and a higher priority is queued")
+        );
+        assert_eq!(
+            pool.submit(&runtime, transfer("alice", 0, 5)),
+            Ok(TransactionStatus::Ready)
+        );
+        assert_eq!(
+            pool.submit(&runtime, transfer("bob", 0, 1000)),
+            Err("insufficient balance to pay the fee")
+        );
+        assert_eq!(pool.len(), 2);
+
+        runtime
+            .system
+            .inc_nonce(&"alice".to_string());
+        assert_eq!(
+            pool.submit(&runtime, transfer("alice", 0, 10)),
+            Err("stale nonce")
+        );
+    }
+
+    #[test]
+    fn ready_orders_by_nonce_and_priority() {
+        let runtime = runtime();
+        let mut pool = TransactionPool::new();
+
+        // Alice's second extrinsic pays a lot, but must wait for her first one.
+        pool.submit(&runtime, transfer("alice", 1, 50))
+            .unwrap();
+        pool.submit(&runtime, transfer("alice", 0, 1))
+            .unwrap();
+        pool.submit(&runtime, transfer("bob", 0, 10))
+            .unwrap();
+        pool.submit(&runtime, transfer("bob", 1, 0))
+            .unwrap();
+        // A gap: bob's nonce 2 is missing.
+        pool.submit(&runtime, transfer("bob", 3, 80))
+            .unwrap();
+
+        assert_eq!(
+            order(&pool.ready(&runtime, 10)),
+            vec![("bob", 0), ("alice", 0), ("alice", 1), ("bob", 1)]
+        );
+        assert_eq!(
+            order(&pool.ready(&runtime, 2)),
+            vec![("bob", 0), ("alice", 0)]
+        );
+    }
+
+    #[test]
+    fn prune_removes_included_extrinsics() {
+        let mut runtime = runtime();
+        let mut pool = TransactionPool::new();
+        pool.submit(&runtime, transfer("alice", 0, 0))
+            .unwrap();
+        pool.submit(&runtime, transfer("alice", 1, 0))
+            .unwrap();
+
+        runtime
+            .system
+            .inc_nonce(&"alice".to_string());
+        pool.prune(&runtime);
+
+        assert_eq!(order(&pool.pending()), vec![("alice", 1)]);
+        assert_eq!(order(&pool.ready(&runtime, 10)), vec![("alice", 1)]);
+    }
+
+    #[test]
+    fn ready_skips_expired_extrinsics() {
+        let mut runtime = runtime();
+        let mut pool = TransactionPool::new();
+        let mut mortal = transfer("alice", 0, 0);
+        mortal.era = support::Era::Mortal {
+            birth: 1,
+            period: 1,
+        };
+        pool.submit(&runtime, mortal)
+            .unwrap();
+        pool.submit(&runtime, transfer("alice", 1, 0))
+            .unwrap();
+        pool.submit(&runtime, transfer("bob", 0, 0))
+            .unwrap();
+        assert_eq!(
+            order(&pool.ready(&runtime, 10)),
+            vec![("alice", 0), ("alice", 1), ("bob", 0)]
+        );
+
+        // Alice's first extrinsic can no longer be included, so neither can the next one.
+        runtime
+            .system
+            .inc_block_number();
+        assert_eq!(order(&pool.ready(&runtime, 10)), vec![("bob", 0)]);
+        pool.prune(&runtime);
+        assert_eq!(order(&pool.pending()), vec![("alice", 1), ("bob", 0)]);
+    }
+
+    #[test]
+    fn ready_fills_blocks_up_to_the_max_block_weight() {
+        let mut runtime = runtime();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 10_000_000);
+        let mut pool = TransactionPool::new();
+        // Two of these contract calls fit in a block, but not three.
+        for nonce in 0..3 {
+            let mut call = transfer("alice", nonce, 10);
+            call.call = RuntimeCall::contracts(crate::contracts::Call::call {
+                contract: "contract".to_string(),
+                value: 0,
+                gas_limit: 900_000_000,
+                data: vec![],
+            });
+            pool.submit(&runtime, call)
+                .unwrap();
+        }
+        pool.submit(&runtime, transfer("bob", 0, 0))
+            .unwrap();
+
+        let ready = pool.ready(&runtime, 10);
+        assert_eq!(order(&ready), vec![("alice", 0), ("alice", 1), ("bob", 0)]);
+        assert!(
+            ready
+                .iter()
+                .map(Runtime::weight)
+                .sum::<Weight>()
+                <= MAX_BLOCK_WEIGHT
+        );
+    }
+}
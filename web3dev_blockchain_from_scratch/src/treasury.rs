@@ -0,0 +1,727 @@
+//! The treasury pallet: funds owned by the chain, spent on the proposals approved by root.
+//!
+//! The treasury owns an account derived from its `PALLET_ID`, which receives a share of the
+//! transaction fees and the bonds of the rejected proposals. Anyone can propose to spend some of
+//! its funds for a beneficiary, by reserving a bond which is returned if the proposal is approved.
+//!
+//! The approved proposals are paid every `SPEND_PERIOD` blocks by the `on_initialize` hook, in the
+//! order of their approval, as long as the treasury has enough funds. A `BURN` fraction of the
+//! funds left after the payouts is burned, so the treasury doesn't grow forever if nothing is
+//! spent.
+//!
+//! The runtime lends the system pallet, to check the root calls, and the currency of the config,
+//! like the balances pallet with `#[lend(system, balances)]`.
+
+use codec::{
+    Decode,
+    Encode,
+};
+use num::{
+    CheckedAdd,
+    Integer,
+    Zero,
+};
+use serde::Serialize;
+
+use crate::{
+    support::{
+        benchmarking::{
+            ensure,
+            Benchmark,
+            Benchmarks,
+        },
+        storage::{
+            StorageMap,
+            StorageValue,
+        },
+        AsCurrency,
+        Borrower,
+        Currency,
+        DispatchResult,
+        Hooks,
+        Lent,
+        OnRuntimeUpgrade,
+        PalletId,
+        Percent,
+        ReservableCurrency,
+    },
+    system,
+};
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+pub type ProposalIndex = u32;
+
+/// The most proposals which can be approved and not yet paid.
+pub const MAX_APPROVALS: u32 = 100;
+
+pub trait Config: system::Config<AccountId: From<PalletId>> {
+    /// The currency holding the funds of the treasury and the bonds.
+    type Currency: ReservableCurrency<Self::AccountId, Balance: Integer + From<u8>>;
+    /// The id of the treasury, from which its account is derived.
+    const PALLET_ID: PalletId;
+    /// The bond reserved from the proposer of a spend.
+    const PROPOSAL_BOND: BalanceOf<Self>;
+    /// The number of blocks between two payouts of the approved proposals.
+    const SPEND_PERIOD: Self::BlockNumber;
+    /// The fraction of the funds left after a payout which is burned.
+    const BURN: Percent;
+    /// The weights of the calls, see the `weights` module.
+    type WeightInfo: WeightInfo;
+}
+
+/// The funds of the treasury and the bonds, in the currency of the runtime `T`.
+pub type BalanceOf<T> =
+    <<T as Config>::Currency as Currency<<T as system::Config>::AccountId>>::Balance;
+
+/// A proposal to pay `value` from the treasury to `beneficiary`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Encode, Decode, macros::TypeInfo)]
+pub struct Proposal<AccountId, Balance> {
+    pub proposer: AccountId,
+    pub value: Balance,
+    pub beneficiary: AccountId,
+    /// The bond reserved from the proposer, returned when the proposal is paid and given to the
+    /// treasury if it is rejected.
+    pub bond: Balance,
+}
+
+/// The proposals of the runtime `T`.
+pub type ProposalOf<T> = Proposal<<T as system::Config>::AccountId, BalanceOf<T>>;
+
+/// The events emitted by the treasury pallet.
+#[derive(
+    macros::CloneNoBound,
+    macros::DebugNoBound,
+    macros::PartialEqNoBound,
+    macros::EqNoBound,
+    macros::TypeInfo,
+    Serialize,
+)]
+// The bounds are given since serde can't see through the `BalanceOf<T>` alias.
+#[serde(bound(serialize = "T::AccountId: Serialize, BalanceOf<T>: Serialize"))]
+pub enum Event<T: Config> {
+    /// The proposal `index` was made.
+    Proposed { index: ProposalIndex },
+    /// The proposal `index` was approved, and will be paid at a next payout.
+    Approved { index: ProposalIndex },
+    /// The proposal `index` was rejected, and its bond `slashed` to the treasury.
+    Rejected {
+        index: ProposalIndex,
+        slashed: BalanceOf<T>,
+    },
+    /// The proposal `index` paid `award` to `beneficiary`.
+    Awarded {
+        index: ProposalIndex,
+        award: BalanceOf<T>,
+        beneficiary: T::AccountId,
+    },
+    /// The proposal `index` could not be paid, failing with `error`. It is no longer approved, so
+    /// root can approve it again or reject it.
+    PayoutFailed {
+        index: ProposalIndex,
+        error: &'static str,
+    },
+    /// `burnt_funds` of the treasury were burned after a payout.
+    Burnt { burnt_funds: BalanceOf<T> },
+    /// The treasury keeps `rollover_balance` for the next payouts.
+    Rollover { rollover_balance: BalanceOf<T> },
+}
+
+#[derive(Debug)]
+#[macros::storage]
+pub struct Pallet<T: Config> {
+    /// The index of the next proposal.
+    #[default(0)]
+    proposal_count: StorageValue<ProposalIndex>,
+    /// The proposals which were neither paid nor rejected.
+    proposals: StorageMap<ProposalIndex, ProposalOf<T>>,
+    /// The approved proposals which are not yet paid, in the order of their approval.
+    #[default(Vec::new())]
+    approvals: StorageValue<Vec<ProposalIndex>>,
+    /// The block of the last payout.
+    #[default(T::BlockNumber::zero())]
+    last_payout: StorageValue<T::BlockNumber>,
+    /// The system pallet, checking the root calls.
+    system: Lent<system::Pallet<T>>,
+    /// The currency, holding the funds of the treasury and the bonds.
+    currency: Lent<T::Currency>,
+    /// The events emitted since the runtime last collected them.
+    events: Vec<Event<T>>,
+}
+
+#[macros::call]
+#[errors(
+    "the spend is empty",
+    "no more proposal indices",
+    "unknown proposal",
+    "the proposal is approved",
+    "the proposal is already approved",
+    "too many approvals",
+    crate::system::NOT_ROOT,
+    crate::support::NOT_LENT,
+    crate::support::INSUFFICIENT_BALANCE,
+    crate::support::INSUFFICIENT_RESERVED_BALANCE,
+    crate::support::OVERFLOW
+)]
+impl<T: Config> Pallet<T> {
+    /// Propose to pay `value` from the treasury to `beneficiary`, reserving the `PROPOSAL_BOND`
+    /// from the `caller`.
+    #[call_index(0)]
+    #[weight(<T as Config>::WeightInfo::propose_spend())]
+    pub fn propose_spend(
+        &mut self,
+        caller: T::AccountId,
+        value: BalanceOf<T>,
+        beneficiary: T::AccountId,
+    ) -> DispatchResult {
+        if value.is_zero() {
+            return Err("the spend is empty");
+        }
+        let index = self.proposal_count.get();
+        let next = index
+            .checked_add(1)
+            .ok_or("no more proposal indices")?;
+        let bond = T::PROPOSAL_BOND;
+        self.currency()?
+            .reserve(caller.clone(), bond)?;
+        self.proposal_count.set(next);
+        self.proposals.insert(
+            &index,
+            Proposal {
+                proposer: caller,
+                value,
+                beneficiary,
+                bond,
+            },
+        );
+        self.deposit_event(Event::Proposed { index });
+        Ok(())
+    }
+
+    /// Reject a proposal which isn't approved, giving its bond to the treasury. Only root can call
+    /// it.
+    #[call_index(1)]
+    #[weight(<T as Config>::WeightInfo::reject_proposal())]
+    pub fn reject_proposal(
+        &mut self,
+        caller: T::AccountId,
+        index: ProposalIndex,
+    ) -> DispatchResult {
+        self.ensure_root(&caller)?;
+        let proposal = self
+            .proposal(index)
+            .ok_or("unknown proposal")?;
+        if self
+            .approvals()
+            .contains(&index)
+        {
+            return Err("the proposal is approved");
+        }
+        self.currency()?
+            .repatriate_reserved(proposal.proposer, Self::account_id(), proposal.bond)?;
+        self.proposals.remove(&index);
+        self.deposit_event(Event::Rejected {
+            index,
+            slashed: proposal.bond,
+        });
+        Ok(())
+    }
+
+    /// Approve a proposal, which is paid at a next payout. Only root can call it.
+    #[call_index(2)]
+    #[weight(<T as Config>::WeightInfo::approve_proposal())]
+    pub fn approve_proposal(
+        &mut self,
+        caller: T::AccountId,
+        index: ProposalIndex,
+    ) -> DispatchResult {
+        self.ensure_root(&caller)?;
+        if self.proposal(index).is_none() {
+            return Err("unknown proposal");
+        }
+        let mut approvals = self.approvals();
+        if approvals.contains(&index) {
+            return Err("the proposal is already approved");
+        }
+        if approvals.len() >= MAX_APPROVALS as usize {
+            return Err("too many approvals");
+        }
+        approvals.push(index);
+        self.approvals.set(approvals);
+        self.deposit_event(Event::Approved { index });
+        Ok(())
+    }
+}
+
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// The account of the treasury, derived from its `PALLET_ID`.
+    pub fn account_id() -> T::AccountId {
+        T::PALLET_ID.into()
+    }
+
+    pub fn proposal(&self, index: ProposalIndex) -> Option<ProposalOf<T>> {
+        self.proposals.try_get(&index)
+    }
+
+    /// The approved proposals which are not yet paid, in the order they are paid.
+    pub fn approvals(&self) -> Vec<ProposalIndex> {
+        self.approvals.get()
+    }
+
+    /// Pay the approved proposals while the treasury has enough funds, and burn the `BURN`
+    /// fraction of what is left.
+    ///
+    /// A proposal which can't be paid is reported with `Event::PayoutFailed`, instead of failing
+    /// the `on_initialize` hook and so every next block. It only fails if the currency is not lent.
+    fn spend_funds(&mut self) -> DispatchResult {
+        let account = Self::account_id();
+        let mut budget = self
+            .currency()?
+            .free_balance(account.clone());
+        let mut unpaid = Vec::new();
+        for index in self.approvals() {
+            let Some(proposal) = self.proposal(index) else {
+                continue;
+            };
+            if proposal.value > budget {
+                unpaid.push(index);
+                continue;
+            }
+            if let Err(error) = self.pay(&proposal) {
+                self.deposit_event(Event::PayoutFailed { index, error });
+                continue;
+            }
+            budget = budget - proposal.value;
+            self.proposals.remove(&index);
+            self.deposit_event(Event::Awarded {
+                index,
+                award: proposal.value,
+                beneficiary: proposal.beneficiary,
+            });
+        }
+        match unpaid.is_empty() {
+            true => self.approvals.kill(),
+            false => self.approvals.set(unpaid),
+        }
+
+        let burn = T::BURN.mul_floor(budget);
+        let burnt_funds = burn
+            - self
+                .currency()?
+                .slash(account, burn);
+        if !burnt_funds.is_zero() {
+            self.deposit_event(Event::Burnt { burnt_funds });
+        }
+        self.deposit_event(Event::Rollover {
+            rollover_balance: budget - burnt_funds,
+        });
+        Ok(())
+    }
+
+    /// Pay a proposal from the treasury and return its bond, or change nothing if it fails.
+    fn pay(&mut self, proposal: &ProposalOf<T>) -> DispatchResult {
+        let currency = self.currency()?;
+        currency.unreserve(proposal.proposer.clone(), proposal.bond)?;
+        let paid = currency.transfer(
+            Self::account_id(),
+            proposal.beneficiary.clone(),
+            proposal.value,
+        );
+        if paid.is_err() {
+            // The bond was just unreserved, so it can be reserved again.
+            currency.reserve(proposal.proposer.clone(), proposal.bond)?;
+        }
+        paid
+    }
+
+    fn ensure_root(&mut self, caller: &T::AccountId) -> DispatchResult {
+        self.system
+            .get_mut()?
+            .ensure_root(caller)
+    }
+
+    /// The currency lent by the runtime.
+    fn currency(&mut self) -> Result<&mut T::Currency, &'static str> {
+        self.currency.get_mut()
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    /// Take the events emitted since the last call, so the runtime can record them.
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<T: Config> Borrower<system::Pallet<T>> for Pallet<T> {
+    fn lent(&mut self) -> &mut Lent<system::Pallet<T>> {
+        &mut self.system
+    }
+}
+
+impl<T: Config> Borrower<T::Currency, AsCurrency> for Pallet<T> {
+    fn lent(&mut self) -> &mut Lent<T::Currency> {
+        &mut self.currency
+    }
+}
+
+impl<T: Config> OnRuntimeUpgrade for Pallet<T> {}
+
+/// The approved proposals are paid every `SPEND_PERIOD` blocks.
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+    fn on_initialize(&mut self, n: T::BlockNumber) -> DispatchResult {
+        let next_payout = self
+            .last_payout
+            .get()
+            .checked_add(&T::SPEND_PERIOD);
+        if next_payout.is_none_or(|next_payout| n < next_payout) {
+            return Ok(());
+        }
+        self.last_payout.set(n);
+        self.spend_funds()
+    }
+}
+
+/// The proposals are made by an account which can pay the bond, and approved by root with as many
+/// proposals already approved as possible.
+impl<T: Config> Benchmarks for Pallet<T>
+where
+    T::AccountId: From<&'static str>,
+    T::Currency: Default,
+    BalanceOf<T>: From<u32>,
+{
+    /// The number of proposals.
+    type Setup = u32;
+
+    /// A pallet with `proposals` proposals from the proposer, of which all but the last are
+    /// approved.
+    fn setup(proposals: u32) -> Self {
+        let mut system = system::Pallet::new();
+        system.set_root_key("root".into());
+        let mut currency = T::Currency::default();
+        currency
+            .deposit_creating("proposer".into(), u32::MAX.into())
+            .expect("the account is empty");
+        let mut pallet = Pallet::new();
+        Borrower::<system::Pallet<T>>::lent(&mut pallet).lend(system);
+        pallet.currency.lend(currency);
+        for index in 0..proposals {
+            pallet
+                .propose_spend("proposer".into(), 1000u32.into(), "beneficiary".into())
+                .expect("the proposer can pay the bond");
+            if index + 1 < proposals {
+                pallet
+                    .approve_proposal("root".into(), index)
+                    .expect("root approves the proposals");
+            }
+        }
+        pallet
+    }
+
+    fn benchmarks() -> Vec<Benchmark<Self>> {
+        vec![
+            Benchmark {
+                name: "propose_spend",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::propose_spend {
+                        value: 1000u32.into(),
+                        beneficiary: "beneficiary".into(),
+                    };
+                    (Self::setup(0), "proposer".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.proposal(0).is_some()),
+            },
+            Benchmark {
+                name: "reject_proposal",
+                components: vec![],
+                setup: |_| {
+                    (
+                        Self::setup(1),
+                        "root".into(),
+                        Call::reject_proposal { index: 0 },
+                    )
+                },
+                verify: |pallet, _| ensure(pallet.proposal(0).is_none()),
+            },
+            Benchmark {
+                name: "approve_proposal",
+                components: vec![],
+                setup: |_| {
+                    let call = Call::approve_proposal {
+                        index: MAX_APPROVALS - 1,
+                    };
+                    (Self::setup(MAX_APPROVALS), "root".into(), call)
+                },
+                verify: |pallet, _| ensure(pallet.approvals().len() == MAX_APPROVALS as usize),
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances,
+        support::benchmarking::{
+            self,
+            BenchmarkConfig,
+        },
+    };
+
+    struct TestConfig;
+
+    impl system::Config for TestConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type SystemWeightInfo = ();
+    }
+
+    impl balances::Config for TestConfig {
+        type Balance = u128;
+        type WeightInfo = ();
+    }
+
+    impl Config for TestConfig {
+        type Currency = balances::Pallet<TestConfig>;
+        const PALLET_ID: PalletId = PalletId(*b"py/trsry");
+        const PROPOSAL_BOND: u128 = 10;
+        const SPEND_PERIOD: u32 = 5;
+        const BURN: Percent = Percent::from_percent(10);
+        type WeightInfo = ();
+    }
+
+    fn alice() -> String {
+        "alice".to_string()
+    }
+
+    fn bob() -> String {
+        "bob".to_string()
+    }
+
+    fn root() -> String {
+        "root".to_string()
+    }
+
+    fn treasury() -> String {
+        Pallet::<TestConfig>::account_id()
+    }
+
+    /// The pallet with root, lent a balances pallet in which alice has 100 and the treasury 1000.
+    fn new_pallet() -> Pallet<TestConfig> {
+        let mut system = system::Pallet::new();
+        system.set_root_key(root());
+        let mut currency = balances::Pallet::new();
+        currency.set_balance(alice(), 100);
+        currency.set_balance(treasury(), 1000);
+        let mut pallet = Pallet::new();
+        pallet.system.lend(system);
+        pallet.currency.lend(currency);
+        pallet
+    }
+
+    /// The balance and reserved balance of `who`.
+    fn balances(pallet: &mut Pallet<TestConfig>, who: String) -> (u128, u128) {
+        let currency = pallet.currency().unwrap();
+        (
+            currency.balance(who.clone()),
+            currency.reserved_balance(who),
+        )
+    }
+
+    #[test]
+    fn the_account_is_derived_from_the_pallet_id() {
+        assert!(treasury().starts_with("0x"));
+        assert_ne!(treasury(), String::from(PalletId(*b"py/other")));
+    }
+
+    #[test]
+    fn proposals_are_approved_or_rejected_by_root() {
+        let mut pallet = new_pallet();
+        assert_eq!(
+            pallet.propose_spend(alice(), 0, bob()),
+            Err("the spend is empty")
+        );
+        assert_eq!(pallet.propose_spend(alice(), 500, bob()), Ok(()));
+        assert_eq!(pallet.propose_spend(alice(), 600, bob()), Ok(()));
+        assert_eq!(balances(&mut pallet, alice()), (80, 20));
+        assert_eq!(
+            pallet.proposal(0),
+            Some(Proposal {
+                proposer: alice(),
+                value: 500,
+                beneficiary: bob(),
+                bond: 10,
+            })
+        );
+
+        assert_eq!(
+            pallet.approve_proposal(alice(), 0),
+            Err("caller is not root")
+        );
+        assert_eq!(pallet.approve_proposal(root(), 2), Err("unknown proposal"));
+        assert_eq!(pallet.approve_proposal(root(), 0), Ok(()));
+        assert_eq!(
+            pallet.approve_proposal(root(), 0),
+            Err("the proposal is already approved")
+        );
+        assert_eq!(
+            pallet.reject_proposal(root(), 0),
+            Err("the proposal is approved")
+        );
+
+        // The bond of a rejected proposal goes to the treasury.
+        assert_eq!(pallet.reject_proposal(root(), 1), Ok(()));
+        assert_eq!(pallet.proposal(1), None);
+        assert_eq!(balances(&mut pallet, alice()), (80, 10));
+        assert_eq!(balances(&mut pallet, treasury()), (1010, 0));
+        assert_eq!(
+            pallet.take_events(),
+            vec![
+                Event::Proposed { index: 0 },
+                Event::Proposed { index: 1 },
+                Event::Approved { index: 0 },
+                Event::Rejected {
+                    index: 1,
+                    slashed: 10,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn approved_proposals_are_paid_every_spend_period() {
+        let mut pallet = new_pallet();
+        for value in [300, 800, 200] {
+            pallet
+                .propose_spend(alice(), value, bob())
+                .unwrap();
+        }
+        for index in 0..3 {
+            pallet
+                .approve_proposal(root(), index)
+                .unwrap();
+        }
+        pallet.take_events();
+
+        assert_eq!(pallet.on_initialize(4), Ok(()));
+        assert_eq!(pallet.take_events(), vec![]);
+
+        // The proposals which the treasury can't pay wait for the next payouts.
+        assert_eq!(pallet.on_initialize(5), Ok(()));
+        assert_eq!(balances(&mut pallet, bob()), (500, 0));
+        assert_eq!(balances(&mut pallet, alice()), (90, 10));
+        assert_eq!(pallet.approvals(), vec![1]);
+        assert_eq!(balances(&mut pallet, treasury()), (450, 0));
+        assert_eq!(
+            pallet.take_events(),
+            vec![
+                Event::Awarded {
+                    index: 0,
+                    award: 300,
+                    beneficiary: bob(),
+                },
+                Event::Awarded {
+                    index: 2,
+                    award: 200,
+                    beneficiary: bob(),
+                },
+                Event::Burnt { burnt_funds: 50 },
+                Event::Rollover {
+                    rollover_balance: 450,
+                },
+            ]
+        );
+
+        pallet
+            .currency()
+            .unwrap()
+            .deposit(treasury(), 400)
+            .unwrap();
+        assert_eq!(pallet.on_initialize(9), Ok(()));
+        assert_eq!(pallet.approvals(), vec![1]);
+        assert_eq!(pallet.on_initialize(10), Ok(()));
+        assert!(pallet.approvals().is_empty());
+        assert_eq!(balances(&mut pallet, bob()), (1300, 0));
+        assert_eq!(balances(&mut pallet, alice()), (100, 0));
+        assert_eq!(balances(&mut pallet, treasury()), (45, 0));
+    }
+
+    #[test]
+    fn failed_payouts_are_reported_without_failing_the_block() {
+        let mut pallet = new_pallet();
+        pallet
+            .currency()
+            .unwrap()
+            .set_balance("whale".to_string(), u128::MAX - 100);
+        pallet
+            .propose_spend(alice(), 300, "whale".to_string())
+            .unwrap();
+        pallet
+            .propose_spend(alice(), 200, bob())
+            .unwrap();
+        for index in 0..2 {
+            pallet
+                .approve_proposal(root(), index)
+                .unwrap();
+        }
+        pallet.take_events();
+
+        // The failed proposal is no longer approved, and keeps its bond reserved.
+        assert_eq!(pallet.on_initialize(5), Ok(()));
+        assert!(pallet.approvals().is_empty());
+        assert!(pallet.proposal(0).is_some());
+        assert_eq!(balances(&mut pallet, alice()), (90, 10));
+        assert_eq!(balances(&mut pallet, bob()), (200, 0));
+        assert_eq!(balances(&mut pallet, treasury()), (720, 0));
+        assert_eq!(
+            pallet.take_events(),
+            vec![
+                Event::PayoutFailed {
+                    index: 0,
+                    error: balances::OVERFLOW,
+                },
+                Event::Awarded {
+                    index: 1,
+                    award: 200,
+                    beneficiary: bob(),
+                },
+                Event::Burnt { burnt_funds: 80 },
+                Event::Rollover {
+                    rollover_balance: 720,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn calls_fail_without_the_lent_pallets() {
+        let mut pallet = Pallet::<TestConfig>::new();
+        assert_eq!(
+            pallet.propose_spend(alice(), 100, bob()),
+            Err("the pallet was not lent by the runtime")
+        );
+    }
+
+    #[test]
+    fn benchmarks_succeed() {
+        let config = BenchmarkConfig {
+            steps: 2,
+            repeat: 1,
+        };
+        let results = benchmarking::run::<Pallet<TestConfig>>(&config).unwrap();
+        assert_eq!(results.len(), 3);
+    }
+}
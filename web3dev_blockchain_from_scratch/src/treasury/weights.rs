@@ -0,0 +1,45 @@
+//! The weights of the `treasury` pallet, measured by its benchmarks.
+//!
+//! Generated by `cargo run --release --bin benchmark`, do not edit by hand. The unit of weight is
+//! one nanosecond of execution on the machine which ran the benchmarks.
+
+use crate::support::Weight;
+
+/// The weights of the callable functions of the pallet.
+pub trait WeightInfo {
+    fn propose_spend() -> Weight;
+    fn reject_proposal() -> Weight;
+    fn approve_proposal() -> Weight;
+}
+
+/// The weights measured by the benchmarks.
+pub struct BenchmarkedWeights;
+
+impl WeightInfo for BenchmarkedWeights {
+    fn propose_spend() -> Weight {
+        2090
+    }
+
+    fn reject_proposal() -> Weight {
+        3114
+    }
+
+    fn approve_proposal() -> Weight {
+        979
+    }
+}
+
+/// No weights, for tests.
+impl WeightInfo for () {
+    fn propose_spend() -> Weight {
+        0
+    }
+
+    fn reject_proposal() -> Weight {
+        0
+    }
+
+    fn approve_proposal() -> Weight {
+        0
+    }
+}
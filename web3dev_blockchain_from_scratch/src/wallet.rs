@@ -0,0 +1,340 @@
+//! An off-chain wallet: a keystore of the keys of the user, which sign the extrinsics of their
+//! accounts.
+//!
+//! Every key is stored in its own JSON file in the keystore directory, named after the key. The
+//! mnemonic phrase of the key is encrypted with ChaCha20-Poly1305, by a key derived from the
+//! password of the keystore with Argon2id. Only the name, scheme and account of the keys can be
+//! read without the password.
+
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::Aead,
+    ChaCha20Poly1305,
+    KeyInit,
+    Nonce,
+};
+use rand::RngCore;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    crypto::{
+        Pair,
+        Scheme,
+    },
+    runtime::types,
+};
+
+/// A key of the keystore, as stored on disk.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyFile {
+    pub name: String,
+    pub scheme: Scheme,
+    pub account: types::AccountId,
+    /// The salt of the password hash.
+    #[serde(with = "hex::serde")]
+    salt: [u8; 16],
+    #[serde(with = "hex::serde")]
+    nonce: [u8; 12],
+    /// The encrypted mnemonic phrase.
+    #[serde(with = "hex::serde")]
+    ciphertext: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+impl Keystore {
+    /// Open the keystore in `dir`, which is created if needed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("cannot create the keystore {}: {e}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// Add the key of the given scheme derived from `phrase`, encrypted with `password`.
+    pub fn add(
+        &self,
+        name: &str,
+        scheme: Scheme,
+        phrase: &str,
+        password: &str,
+    ) -> Result<KeyFile, String> {
+        let path = self.path(name)?;
+        if path.exists() {
+            return Err(format!("a key named {name} already exists"));
+        }
+        let pair = Pair::from_phrase(scheme, phrase, "")?;
+
+        let mut salt = [0; 16];
+        let mut nonce = [0; 12];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let ciphertext = cipher(password, &salt)?
+            .encrypt(Nonce::from_slice(&nonce), phrase.as_bytes())
+            .map_err(|_| "encryption failed")?;
+
+        let key = KeyFile {
+            name: name.to_string(),
+            scheme,
+            account: pair.account_id(),
+            salt,
+            nonce,
+            ciphertext,
+        };
+        let json = serde_json::to_string_pretty(&key).map_err(|e| e.to_string())?;
+        fs::write(&path, json).map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+        Ok(key)
+    }
+
+    /// The key named `name`, still encrypted.
+    pub fn get(&self, name: &str) -> Result<KeyFile, String> {
+        let path = self.path(name)?;
+        let json = fs::read_to_string(&path).map_err(|_| format!("no key named {name}"))?;
+        serde_json::from_str(&json).map_err(|e| format!("invalid key file {}: {e}", path.display()))
+    }
+
+    /// All the keys, by name.
+    pub fn list(&self) -> Result<Vec<KeyFile>, String> {
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| format!("cannot read the keystore {}: {e}", self.dir.display()))?;
+        let mut keys = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|e| e.to_string())?
+                .path();
+            if let Some(name) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".json"))
+            {
+                keys.push(self.get(name)?);
+            }
+        }
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(keys)
+    }
+
+    /// Decrypt the mnemonic phrase of the key named `name`.
+    pub fn phrase(&self, name: &str, password: &str) -> Result<String, String> {
+        let key = self.get(name)?;
+        let phrase = cipher(password, &key.salt)?
+            .decrypt(Nonce::from_slice(&key.nonce), key.ciphertext.as_slice())
+            .map_err(|_| "wrong password")?;
+        String::from_utf8(phrase).map_err(|_| "invalid key file".to_string())
+    }
+
+    /// Decrypt the key named `name`, to sign with it.
+    pub fn unlock(&self, name: &str, password: &str) -> Result<Pair, String> {
+        let key = self.get(name)?;
+        let pair = Pair::from_phrase(key.scheme, &self.phrase(name, password)?, "")?;
+        if pair.account_id() != key.account {
+            return Err(format!("the key {name} does not match its account"));
+        }
+        Ok(pair)
+    }
+
+    /// The file of the key named `name`. Names are restricted so they can't escape the keystore.
+    fn path(&self, name: &str) -> Result<PathBuf, String> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!(
+                "invalid key name {name:?}, use letters, digits, '-' and '_'"
+            ));
+        }
+        Ok(self
+            .dir
+            .join(format!("{name}.json")))
+    }
+}
+
+/// The cipher of the keys encrypted with `password`.
+fn cipher(password: &str, salt: &[u8; 16]) -> Result<ChaCha20Poly1305, String> {
+    let mut key = [0; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("cannot derive the encryption key: {e}"))?;
+    Ok(ChaCha20Poly1305::new(&key.into()))
+}
+
+/// Sign the extrinsic with `pair`, as the account of the key.
+pub fn sign(pair: &Pair, mut extrinsic: types::Extrinsic) -> types::Extrinsic {
+    extrinsic.caller = pair.account_id();
+    extrinsic.signature = Some(pair.sign(&extrinsic.signing_payload()));
+    extrinsic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balances,
+        runtime::{
+            Runtime,
+            RuntimeCall,
+            VERSION,
+        },
+        support::{
+            self,
+            ValidateTransaction,
+        },
+    };
+
+    const PHRASE: &str =
+        "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+    /// A keystore in a new temporary directory.
+    fn keystore(name: &str) -> Keystore {
+        let dir =
+            std::env::temp_dir().join(format!("web3dev-keystore-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Keystore::open(dir).unwrap()
+    }
+
+    #[test]
+    fn keys_are_encrypted() {
+        let keystore = keystore("encrypted");
+        let key = keystore
+            .add("alice", Scheme::Sr25519, PHRASE, "secret")
+            .unwrap();
+        keystore
+            .add(
+                "bob",
+                Scheme::Ed25519,
+                &crate::crypto::generate_phrase(),
+                "",
+            )
+            .unwrap();
+
+        let stored = fs::read_to_string(
+            keystore
+                .dir
+                .join("alice.json"),
+        )
+        .unwrap();
+        assert!(!stored.contains("legal"));
+        assert_eq!(keystore.get("alice"), Ok(key.clone()));
+        assert_eq!(
+            keystore
+                .list()
+                .unwrap()
+                .iter()
+                .map(|key| key.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["alice", "bob"]
+        );
+
+        assert_eq!(keystore.phrase("alice", "secret"), Ok(PHRASE.to_string()));
+        assert_eq!(
+            keystore
+                .unlock("alice", "secret")
+                .map(|pair| pair.account_id()),
+            Ok(key.account)
+        );
+        assert_eq!(
+            keystore
+                .unlock("alice", "wrong")
+                .err(),
+            Some("wrong password".to_string())
+        );
+        assert!(keystore
+            .add("alice", Scheme::Sr25519, PHRASE, "")
+            .is_err());
+        assert!(keystore
+            .add("../alice", Scheme::Sr25519, PHRASE, "")
+            .is_err());
+        assert!(keystore
+            .get("charlie")
+            .is_err());
+    }
+
+    #[test]
+    fn signed_extrinsics_are_checked_by_the_runtime() {
+        let pair = Pair::from_phrase(Scheme::Ed25519, PHRASE, "").unwrap();
+        let mut runtime = Runtime::new();
+        runtime
+            .balances
+            .set_balance(pair.account_id(), 100);
+
+        let unsigned = support::Extrinsic {
+            caller: pair.account_id(),
+            signature: None,
+            nonce: 0,
+            tip: 0,
+            era: support::Era::Immortal,
+            spec_version: VERSION.spec_version,
+            genesis_hash: [0; 32],
+            call: RuntimeCall::balances(balances::Call::transfer {
+                to: "bob".to_string(),
+                amount: 10,
+            }),
+        };
+        assert_eq!(
+            runtime
+                .validate_transaction(&unsigned)
+                .err(),
+            Some("extrinsic is not signed")
+        );
+
+        let signed = sign(&pair, unsigned);
+        assert!(runtime
+            .validate_transaction(&signed)
+            .is_ok());
+
+        // Changing anything signed invalidates the signature.
+        let mut tampered = signed.clone();
+        tampered.tip = 1;
+        assert_eq!(
+            runtime
+                .validate_transaction(&tampered)
+                .err(),
+            Some("invalid signature")
+        );
+
+        // The development accounts have no key to sign with.
+        let mut named = signed;
+        named.caller = "alice".to_string();
+        assert_eq!(
+            runtime
+                .validate_transaction(&named)
+                .err(),
+            Some("extrinsic signed for an account without a key")
+        );
+
+        // Their unsigned extrinsics are only accepted by development chains.
+        named.signature = None;
+        assert_eq!(
+            runtime
+                .validate_transaction(&named)
+                .err(),
+            Some("account has no key")
+        );
+        runtime
+            .system
+            .allow_keyless_accounts();
+        runtime
+            .balances
+            .set_balance("alice".to_string(), 100);
+        assert!(runtime
+            .validate_transaction(&named)
+            .is_ok());
+    }
+}